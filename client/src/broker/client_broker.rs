@@ -3,15 +3,18 @@
 
 use crate::net::{SocketUpdate, SystemInfo};
 use crate::{
-    eval_snippet, js_hooks, map_ranges, Apply, ArenaQuery, BrowserStorages, ClientContext,
-    ClientRequest, ClientUpdate, CommonRequest, CommonSettings, CommonUpdate, Escaping, FatalError,
-    FpsMonitor, GameClient, InvitationRequest, Key, KeyboardEvent as GameClientKeyboardEvent,
-    MouseButton, MouseEvent as GameClientMouseEvent, QuestEvent, VisibilityEvent,
+    eval_snippet, js_hooks, map_ranges, standard_gamepad, Apply, ArenaQuery, BrowserStorages,
+    ClientContext, ClientRequest, ClientUpdate, CommonRequest, CommonSettings, CommonUpdate,
+    Escaping, FatalError, FpsMonitor, GameClient, GamepadButton as GameClientGamepadButton,
+    GamepadEvent, GamepadState, GamepadStick, InvitationRequest, Key,
+    KeyboardEvent as GameClientKeyboardEvent, MouseButton, MouseEvent as GameClientMouseEvent,
+    QuestEvent, VisibilityEvent,
 };
 use kodiak_common::glam::{IVec2, Vec2};
 use wasm_bindgen::JsCast;
 use web_sys::{
-    Event, FocusEvent, HtmlInputElement, KeyboardEvent, MouseEvent, Touch, TouchEvent, WheelEvent,
+    Event, FocusEvent, GamepadButton, HtmlInputElement, KeyboardEvent, MouseEvent, Touch,
+    TouchEvent, WheelEvent,
 };
 use yew::Callback;
 
@@ -180,6 +183,11 @@ impl<G: GameClient> ClientBroker<G> {
             .socket
             .update(&mut self.context.state, time_seconds);
 
+        self.poll_gamepad();
+
+        #[cfg(feature = "renderer_post_process")]
+        crate::renderer::set_post_processing_setting(self.context.common_settings.post_processing);
+
         self.game.tick(elapsed_seconds, &mut self.context);
 
         if self.context.client_activity() != self.context.reported_activity
@@ -198,6 +206,65 @@ impl<G: GameClient> ClientBroker<G> {
         }
     }
 
+    /// The browser only has events for gamepads connecting and disconnecting, so buttons and
+    /// sticks are polled each frame and turned into events.
+    fn poll_gamepad(&mut self) {
+        let Some(gamepad) = standard_gamepad() else {
+            if self.context.gamepad.connected {
+                self.gamepad(GamepadEvent::Connected(false));
+            }
+            return;
+        };
+        if !self.context.gamepad.connected {
+            self.gamepad(GamepadEvent::Connected(true));
+        }
+
+        if self.context.client.escaping.is_escaping() {
+            self.context.gamepad.reset();
+            return;
+        }
+
+        let time = self.context.client.time_seconds;
+        for (index, button) in gamepad.buttons().iter().enumerate() {
+            let (Some(button), Ok(js_button)) = (
+                GameClientGamepadButton::try_from_index(index as u32),
+                button.dyn_into::<GamepadButton>(),
+            ) else {
+                continue;
+            };
+            let down = js_button.pressed();
+            let value = js_button.value() as f32;
+            if down != self.context.gamepad.is_down(button)
+                || value != self.context.gamepad.value(button)
+            {
+                self.gamepad(GamepadEvent::Button {
+                    button,
+                    down,
+                    value,
+                    time,
+                });
+            }
+        }
+
+        let axes = gamepad.axes();
+        let axis = |index: u32| axes.get(index).as_f64().unwrap_or(0.0) as f32;
+        for (stick, index) in [(GamepadStick::Left, 0), (GamepadStick::Right, 2)] {
+            // Standard mapping has positive y being down.
+            let position = GamepadState::apply_dead_zone(Vec2::new(axis(index), -axis(index + 1)));
+            if position != self.context.gamepad.stick(stick) {
+                self.gamepad(GamepadEvent::Stick { stick, position });
+            }
+        }
+    }
+
+    fn gamepad(&mut self, event: GamepadEvent) {
+        if !matches!(event, GamepadEvent::Connected(_)) {
+            self.context.cancel_afk();
+        }
+        self.game.peek_gamepad(&event, &mut self.context);
+        self.context.gamepad.apply(event);
+    }
+
     pub fn keyboard(&mut self, event: KeyboardEvent) {
         self.context.cancel_afk();
         let type_ = event.type_();
@@ -4,15 +4,16 @@
 use crate::js_hooks::{self, window};
 use crate::net::{ReconnSocket, SocketUpdate, SystemInfo};
 use crate::{
-    dedup_into_inner, get_real_referrer, host, is_https, is_mobile, owned_into_box,
-    owned_into_iter, post_message, timezone_offset, ws_protocol, AdEvent, Apply, ArenaQuery,
-    BrowserStorages, ChatUpdate, ClaimValue, ClientActivity, ClientRequest, ClientUpdate,
-    CommonRequest, CommonSettings, CommonUpdate, Escaping, GameClient, GameFence,
-    InstancePickerDto, InvitationId, InvitationUpdate, KeyboardState, LeaderboardCaveat,
-    LeaderboardScoreDto, LeaderboardUpdate, LiveboardDto, LiveboardUpdate, MessageDto,
-    MessageNumber, MouseState, NavigationMetricsDto, NexusPath, PeriodId, PlayerDto, PlayerId,
-    PlayerUpdate, QuestEvent, RankNumber, Referrer, SceneId, ScopeClaimKey, ServerId, SocketQuery,
-    SystemUpdate, TeamId, VisibilityState, YourScoreDto,
+    dedup_into_inner, get_real_referrer, host, is_https, is_mobile, network_impairment,
+    owned_into_box, owned_into_iter, post_message, timezone_offset, ws_protocol, AdEvent, Apply,
+    ArenaQuery, Binding, BrowserStorages, ChatCommandDto, ChatUpdate, ClaimValue, ClientActivity,
+    ClientRequest, ClientUpdate, CommonRequest, CommonSettings, CommonUpdate, Escaping, GameClient,
+    GameFence, GamepadState, InstancePickerDto, InvitationId, InvitationUpdate, Joystick,
+    KeyboardState, LeaderboardCaveat, LeaderboardScoreDto, LeaderboardUpdate, LiveboardDto,
+    LiveboardUpdate, LockstepDesync, MessageDto, MessageNumber, MouseState, NavigationMetricsDto,
+    NexusPath, PeriodId, PlayerDto, PlayerId, PlayerUpdate, QuestEvent, RankNumber, Referrer,
+    SceneId, ScopeClaimKey, ServerId, SocketQuery, SystemUpdate, TeamId, VisibilityState,
+    YourScoreDto,
 };
 use kodiak_common::arrayvec::ArrayString;
 use std::collections::{BTreeMap, HashMap, VecDeque};
@@ -42,6 +43,8 @@ pub struct ClientContext<G: GameClient + ?Sized> {
     pub keyboard: KeyboardState,
     /// Mouse input.
     pub mouse: MouseState,
+    /// Gamepad input.
+    pub gamepad: GamepadState,
     /// Whether the page is visible.
     pub visibility: VisibilityState,
     /// Settings.
@@ -103,6 +106,8 @@ pub struct CoreState {
     pub leaderboards: [Box<[LeaderboardScoreDto]>; std::mem::variant_count::<PeriodId>()],
     pub liveboard: Vec<LiveboardDto>,
     pub messages: VecDeque<(MessageNumber, MessageDto)>,
+    /// Chat commands available to the player, for autocomplete.
+    pub chat_commands: Box<[ChatCommandDto]>,
     pub players: HashMap<PlayerId, PlayerDto>,
     pub players_on_shard: u32,
     pub shard_per_scene: bool,
@@ -191,8 +196,8 @@ impl<G: GameClient> Apply<CommonUpdate<G::GameUpdate>> for ServerState<G> {
         let mut core = Rc::borrow_mut(&mut self.core);
 
         match update {
-            CommonUpdate::Chat(update) => {
-                if let ChatUpdate::Received(received) = update {
+            CommonUpdate::Chat(update) => match update {
+                ChatUpdate::Received(received) => {
                     let limit = if is_mobile() { 5 } else { 10 };
                     // Need to use into_vec since
                     // https://github.com/rust-lang/rust/issues/59878 is incomplete.
@@ -204,7 +209,11 @@ impl<G: GameClient> Apply<CommonUpdate<G::GameUpdate>> for ServerState<G> {
                         core.messages.push_back((number, dto));
                     }
                 }
-            }
+                ChatUpdate::Commands(commands) => {
+                    core.chat_commands = commands;
+                }
+                _ => {}
+            },
             CommonUpdate::Client(update) => match update {
                 ClientUpdate::SessionCreated { player_id, .. } => {
                     core.player_id = Some(player_id);
@@ -341,6 +350,7 @@ impl<G: GameClient> ClientContext<G> {
             socket,
             keyboard: KeyboardState::default(),
             mouse: MouseState::default(),
+            gamepad: GamepadState::default(),
             visibility: VisibilityState::default(),
             settings,
             common_settings,
@@ -355,6 +365,16 @@ impl<G: GameClient> ClientContext<G> {
         }
     }
 
+    /// Whether any input bound to an action, such as a [`Binding`] setting, is down.
+    pub fn is_action_down(&self, binding: Binding) -> bool {
+        binding.is_down(&self.keyboard, &self.mouse, &self.gamepad)
+    }
+
+    /// Joystick-style input from the keyboard or gamepad, if any.
+    pub fn joystick(&self) -> Option<Joystick> {
+        Joystick::try_from_input_state(self.client.time_seconds, &self.keyboard, &self.gamepad)
+    }
+
     pub(crate) fn cancel_afk(&mut self) {
         self.client.last_input = self.client.time_seconds;
     }
@@ -532,6 +552,8 @@ impl<G: GameClient> ClientContext<G> {
             tls: NAVIGATION_METRICS.tls,
             http: NAVIGATION_METRICS.http,
             dom: NAVIGATION_METRICS.dom,
+            spectate: common_settings.spectate,
+            impairment: network_impairment(),
         };
 
         // TODO to_string should take &impl Serialize.
@@ -584,6 +606,17 @@ impl<G: GameClient> ClientContext<G> {
         )));
     }
 
+    /// Report a lockstep desync, see `LockstepClient::take_desync`.
+    pub fn report_desync(&mut self, desync: &LockstepDesync) {
+        self.send_trace(format!("lockstep {desync}"));
+    }
+
+    /// As a spectator (see `CommonSettings::spectate`), switch which player the server sends the
+    /// view of. `None` means free camera.
+    pub fn spectate(&mut self, follow: Option<PlayerId>) {
+        self.send_to_server(CommonRequest::Client(ClientRequest::Spectate(follow)));
+    }
+
     /// Send a request on the socket.
     pub fn send_to_server(&mut self, request: CommonRequest<G::GameRequest>) {
         self.send_to_server_with_reliable(request, true)
@@ -2,8 +2,11 @@
 // SPDX-License-Identifier: LGPL-3.0-or-later
 
 use crate::js_hooks::{document, window};
-use crate::Referrer;
+use crate::{NetworkImpairment, Referrer};
+use serde::Deserialize;
 use std::cell::LazyCell;
+use wasm_bindgen::JsCast;
+use web_sys::{Gamepad, GamepadMappingType};
 
 pub fn browser_pathname() -> String {
     window()
@@ -105,6 +108,34 @@ pub fn referrer() -> Option<Referrer> {
     Referrer::new(&document().referrer())
 }
 
+/// Simulated network conditions from the `impair` query parameter, e.g.
+/// `?impair=latency%3D100,loss%3D0.05`. Only honored by debug builds.
+pub fn network_impairment() -> Option<NetworkImpairment> {
+    #[derive(Deserialize)]
+    struct Query {
+        impair: Option<NetworkImpairment>,
+    }
+
+    if !cfg!(debug_assertions) {
+        return None;
+    }
+    let search = window().location().search().ok()?;
+    serde_urlencoded::from_str::<Query>(search.trim_start_matches('?'))
+        .ok()?
+        .impair
+}
+
+/// The first connected gamepad with the standard mapping, the only kind supported.
+pub(crate) fn standard_gamepad() -> Option<Gamepad> {
+    window()
+        .navigator()
+        .get_gamepads()
+        .ok()?
+        .iter()
+        .filter_map(|gamepad| gamepad.dyn_into::<Gamepad>().ok())
+        .find(|gamepad| gamepad.connected() && gamepad.mapping() == GamepadMappingType::Standard)
+}
+
 pub fn ws_protocol(encrypted: bool) -> &'static str {
     if encrypted {
         "wss"
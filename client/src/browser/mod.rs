@@ -18,6 +18,7 @@ pub use self::js_util::{
     exit_pointer_lock_with_emulation, pointer_locked_with_emulation,
     request_pointer_lock_with_emulation,
 };
+pub(crate) use self::js_util::{network_impairment, standard_gamepad};
 pub use self::setting::{CommonSettings, LocalSettings, SettingCategory};
 // TODO: games only use VisibilityEvent
 pub use self::visibility::{VisibilityEvent, VisibilityState};
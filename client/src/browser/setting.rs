@@ -4,7 +4,7 @@
 use crate::browser::BrowserStorages;
 use crate::js_hooks::window;
 use crate::{
-    settings_prerequisites, translate, ArenaQuery, CohortId, DeepConnect, LanguageId,
+    settings_prerequisites, translate, ArenaQuery, Binding, CohortId, DeepConnect, LanguageId,
     NonZeroUnixMillis, PeriodId, PlayerAlias, ServerId, ServerKind, SessionId, SessionToken,
     Settings, Translator,
 };
@@ -28,6 +28,9 @@ pub trait LocalSettings: Sized {
     fn synchronize(&self, known: &HashMap<String, String>, browser_storages: &mut BrowserStorages);
 
     /// Renders GUI widgets for certain settings.
+    ///
+    /// Implemented by `#[derive(Settings)]`. Manual implementations must accept the `binding`
+    /// parameter, for [`Binding`] settings, even if they have none.
     fn display(
         &self,
         t: &Translator,
@@ -46,6 +49,12 @@ pub trait LocalSettings: Sized {
             RangeInclusive<f32>,
             fn(&mut Self, f32, &mut BrowserStorages),
         ),
+        binding: impl FnMut(
+            SettingCategory,
+            String,
+            Binding,
+            fn(&mut Self, Binding, &mut BrowserStorages),
+        ),
     );
 }
 
@@ -55,6 +64,7 @@ pub enum SettingCategory {
     General,
     #[cfg(feature = "audio")]
     Audio,
+    Controls,
     Graphics,
     Privacy,
 }
@@ -96,6 +106,7 @@ impl LocalSettings for () {
             RangeInclusive<f32>,
             fn(&mut Self, f32, &mut BrowserStorages),
         ),
+        _: impl FnMut(SettingCategory, String, Binding, fn(&mut Self, Binding, &mut BrowserStorages)),
     ) {
     }
 }
@@ -148,6 +159,9 @@ pub struct CommonSettings {
     pub user_name: Option<String>,
     #[setting(volatile)]
     pub store_enabled: bool,
+    /// Watch without playing.
+    #[setting(volatile)]
+    pub spectate: bool,
     /// Pending chat message.
     #[setting(volatile)]
     pub chat_message: String,
@@ -180,6 +194,10 @@ pub struct CommonSettings {
         post
     )]
     pub mouse_sensitivity: f32,
+    /// Whether to run post-processing effects, which may be slow on low-end devices.
+    #[cfg(feature = "renderer_post_process")]
+    #[setting(preference, checkbox = "Graphics/Post-processing")]
+    pub post_processing: bool,
 }
 
 impl Default for CommonSettings {
@@ -202,6 +220,15 @@ impl Default for CommonSettings {
             let _ = translate!(t, "Mouse sensitivity");
         }
 
+        #[cfg(not(feature = "renderer_post_process"))]
+        #[allow(unreachable_code)]
+        if false {
+            // Ensure this translation always happens regardless of feature flags.
+            #[allow(unused)]
+            let t: Translator = unimplemented!();
+            let _ = translate!(t, "Post-processing");
+        }
+
         let deep_connect = crate::deep_connect();
         Self {
             alias: None,
@@ -242,6 +269,7 @@ impl Default for CommonSettings {
             user: false,
             user_name: None,
             store_enabled: false,
+            spectate: false,
             date_created: None,
             chat_message: String::new(),
             #[cfg(feature = "high_contrast_setting")]
@@ -254,6 +282,8 @@ impl Default for CommonSettings {
             statistic_cookies: false,
             #[cfg(feature = "pointer_lock")]
             mouse_sensitivity: 1.0,
+            #[cfg(feature = "renderer_post_process")]
+            post_processing: true,
         }
     }
 }
@@ -2,8 +2,8 @@
 // SPDX-License-Identifier: LGPL-3.0-or-later
 
 use super::{
-    entry_point, Apply, ClientContext, KeyboardEvent, LocalSettings, MouseEvent, PropertiesWrapper,
-    RoutableExt, TranslateFn, Translator, VisibilityEvent,
+    entry_point, Apply, ClientContext, GamepadEvent, KeyboardEvent, LocalSettings, MouseEvent,
+    PropertiesWrapper, RoutableExt, TranslateFn, Translator, VisibilityEvent,
 };
 use kodiak_common::bitcode::*;
 use kodiak_common::{
@@ -112,6 +112,11 @@ pub trait GameClient: Sized + 'static {
         let _ = event;
     }
 
+    /// Peek at a gamepad event before it is applied to `GamepadState`.
+    fn peek_gamepad(&mut self, event: &GamepadEvent, _context: &mut ClientContext<Self>) {
+        let _ = event;
+    }
+
     /// Peek at a visibility event before it is applied to `VisibilityState`.
     fn peek_visibility(&mut self, event: &VisibilityEvent, _context: &mut ClientContext<Self>) {
         let _ = event;
@@ -0,0 +1,152 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+use crate::io::{GamepadButton, GamepadState, Key, KeyboardState, MouseButton, MouseState};
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+/// Inputs bound to a game action (e.g. "Fire"), any of which perform the action. Declared as a
+/// `#[setting(preference, binding = "Fire")]` field of `GameSettings`, so players may rebind it.
+///
+/// Written like `key=Space,mouse=Left,gamepad=South`, where every part is optional.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Binding {
+    pub key: Option<Key>,
+    pub mouse: Option<MouseButton>,
+    pub gamepad: Option<GamepadButton>,
+}
+
+impl Binding {
+    /// No inputs.
+    pub const NONE: Self = Self {
+        key: None,
+        mouse: None,
+        gamepad: None,
+    };
+
+    /// Bound to only `key`.
+    pub const fn key(key: Key) -> Self {
+        Self {
+            key: Some(key),
+            ..Self::NONE
+        }
+    }
+
+    /// Bound to only `button`.
+    pub const fn mouse(button: MouseButton) -> Self {
+        Self {
+            mouse: Some(button),
+            ..Self::NONE
+        }
+    }
+
+    /// Bound to only `button`.
+    pub const fn gamepad(button: GamepadButton) -> Self {
+        Self {
+            gamepad: Some(button),
+            ..Self::NONE
+        }
+    }
+
+    /// Also bound to `button`.
+    pub const fn with_mouse(mut self, button: MouseButton) -> Self {
+        self.mouse = Some(button);
+        self
+    }
+
+    /// Also bound to `button`.
+    pub const fn with_gamepad(mut self, button: GamepadButton) -> Self {
+        self.gamepad = Some(button);
+        self
+    }
+
+    /// Whether any bound input is down.
+    pub fn is_down(
+        &self,
+        keyboard: &KeyboardState,
+        mouse: &MouseState,
+        gamepad: &GamepadState,
+    ) -> bool {
+        self.key.is_some_and(|key| keyboard.is_down(key))
+            || self.mouse.is_some_and(|button| mouse.is_down(button))
+            || self.gamepad.is_some_and(|button| gamepad.is_down(button))
+    }
+
+    /// Human readable, for the settings dialog.
+    pub fn label(&self) -> String {
+        let mut parts = Vec::with_capacity(3);
+        if let Some(key) = self.key {
+            parts.push(key.to_string());
+        }
+        if let Some(button) = self.mouse {
+            parts.push(format!("{button} Mouse"));
+        }
+        if let Some(button) = self.gamepad {
+            parts.push(format!("{button} Gamepad"));
+        }
+        if parts.is_empty() {
+            String::from("-")
+        } else {
+            parts.join(" / ")
+        }
+    }
+}
+
+impl Display for Binding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut separator = "";
+        if let Some(key) = self.key {
+            write!(f, "key={key}")?;
+            separator = ",";
+        }
+        if let Some(button) = self.mouse {
+            write!(f, "{separator}mouse={button}")?;
+            separator = ",";
+        }
+        if let Some(button) = self.gamepad {
+            write!(f, "{separator}gamepad={button}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Binding {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut ret = Self::NONE;
+        for part in s.split(',').filter(|p| !p.is_empty()) {
+            let (input, value) = part.split_once('=').ok_or("missing =")?;
+            match input {
+                "key" => ret.key = Some(value.parse().map_err(|_| "invalid key")?),
+                "mouse" => ret.mouse = Some(value.parse().map_err(|_| "invalid mouse button")?),
+                "gamepad" => {
+                    ret.gamepad = Some(value.parse().map_err(|_| "invalid gamepad button")?)
+                }
+                _ => return Err("unknown input"),
+            }
+        }
+        Ok(ret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        for binding in [
+            Binding::NONE,
+            Binding::key(Key::Space),
+            Binding::mouse(MouseButton::Right),
+            Binding::key(Key::E)
+                .with_mouse(MouseButton::Left)
+                .with_gamepad(GamepadButton::South),
+        ] {
+            assert_eq!(binding.to_string().parse::<Binding>(), Ok(binding));
+        }
+        assert_eq!("mouse=Middle".parse(), Ok(Binding::mouse(MouseButton::Middle)));
+        assert!("key=Nope".parse::<Binding>().is_err());
+    }
+}
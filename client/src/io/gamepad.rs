@@ -0,0 +1,315 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+use crate::Apply;
+use kodiak_common::glam::Vec2;
+use strum::EnumIter;
+use strum_macros::{Display, EnumString};
+
+/// Identifies a gamepad button by its position in the
+/// [standard mapping](https://w3c.github.io/gamepad/#remapping), so games don't depend on the
+/// labels of any particular controller.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Display, EnumIter, EnumString)]
+pub enum GamepadButton {
+    /// Bottom face button (A on Xbox, Cross on PlayStation).
+    South,
+    /// Right face button (B on Xbox, Circle on PlayStation).
+    East,
+    /// Left face button (X on Xbox, Square on PlayStation).
+    West,
+    /// Top face button (Y on Xbox, Triangle on PlayStation).
+    North,
+    LeftBumper,
+    RightBumper,
+    /// Analog, see `GamepadState::value`.
+    LeftTrigger,
+    /// Analog, see `GamepadState::value`.
+    RightTrigger,
+    Select,
+    Start,
+    /// Pressing the left stick.
+    LeftStick,
+    /// Pressing the right stick.
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+    Home,
+}
+
+impl GamepadButton {
+    /// Converts from a JS standard mapping button index, if possible.
+    pub fn try_from_index(index: u32) -> Option<Self> {
+        Some(match index {
+            0 => Self::South,
+            1 => Self::East,
+            2 => Self::West,
+            3 => Self::North,
+            4 => Self::LeftBumper,
+            5 => Self::RightBumper,
+            6 => Self::LeftTrigger,
+            7 => Self::RightTrigger,
+            8 => Self::Select,
+            9 => Self::Start,
+            10 => Self::LeftStick,
+            11 => Self::RightStick,
+            12 => Self::DPadUp,
+            13 => Self::DPadDown,
+            14 => Self::DPadLeft,
+            15 => Self::DPadRight,
+            16 => Self::Home,
+            _ => return None,
+        })
+    }
+}
+
+/// Identifies an analog stick of a gamepad.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, EnumIter)]
+pub enum GamepadStick {
+    Left,
+    Right,
+}
+
+/// The state of one gamepad button.
+#[derive(Default, Copy, Clone)]
+pub enum GamepadButtonState {
+    /// The button was pressed and the press hasn't been taken yet.
+    ///
+    /// This state will persist until the button is released or manually
+    /// cleared (see `GamepadState::take_press`).
+    ///
+    /// Stores the time the button was pressed.
+    Press(f32),
+    /// Stores the time the button was pressed.
+    Down(f32),
+    #[default]
+    Up,
+}
+
+impl GamepadButtonState {
+    /// Whether the button was pressed (and the press wasn't taken yet).
+    pub fn is_press(&self) -> bool {
+        matches!(self, Self::Press(_))
+    }
+
+    /// Whether the button was pressed. Resets state back to down (no press).
+    pub fn take_press(&mut self) -> bool {
+        if let &mut Self::Press(time) = self {
+            *self = Self::Down(time);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Whether the button is down (including an untaken press).
+    pub fn is_down(&self) -> bool {
+        matches!(self, Self::Press(_) | Self::Down(_))
+    }
+
+    /// Whether the button is down for a certain amount of time.
+    pub fn is_down_for(&self, down_time: f32, time: f32) -> bool {
+        if let &Self::Press(t) | &Self::Down(t) = self {
+            time > t + down_time
+        } else {
+            false
+        }
+    }
+
+    /// Whether the button is up.
+    pub fn is_up(&self) -> bool {
+        matches!(self, Self::Up)
+    }
+}
+
+/// Any type of gamepad event, emulated by polling the browser each frame.
+#[derive(Debug)]
+pub enum GamepadEvent {
+    Button {
+        button: GamepadButton,
+        down: bool,
+        /// Analog value (0..1). Only triggers are likely to have values between 0 and 1.
+        value: f32,
+        time: f32,
+    },
+    /// Position with dead-zone applied (-1..1), with positive y being up.
+    Stick { stick: GamepadStick, position: Vec2 },
+    /// A gamepad was connected (true) or the last one was disconnected (false).
+    Connected(bool),
+}
+
+/// The state of the gamepad i.e. buttons and sticks. Only one gamepad is supported.
+#[derive(Default)]
+pub struct GamepadState {
+    states: [GamepadButtonState; std::mem::variant_count::<GamepadButton>()],
+    values: [f32; std::mem::variant_count::<GamepadButton>()],
+    sticks: [Vec2; std::mem::variant_count::<GamepadStick>()],
+    /// Whether a gamepad is connected.
+    pub connected: bool,
+}
+
+impl Apply<GamepadEvent> for GamepadState {
+    fn apply(&mut self, event: GamepadEvent) {
+        match event {
+            GamepadEvent::Button {
+                button,
+                down,
+                value,
+                time,
+            } => {
+                self.values[button as usize] = value;
+                if down {
+                    if !self.state(button).is_down() {
+                        *self.state_mut(button) = GamepadButtonState::Press(time);
+                    }
+                } else {
+                    *self.state_mut(button) = GamepadButtonState::Up;
+                }
+            }
+            GamepadEvent::Stick { stick, position } => {
+                self.sticks[stick as usize] = position;
+            }
+            GamepadEvent::Connected(connected) => {
+                *self = Self {
+                    connected,
+                    ..Self::default()
+                };
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        // Stays connected.
+        *self = Self {
+            connected: self.connected,
+            ..Self::default()
+        };
+    }
+}
+
+impl GamepadState {
+    /// Stick positions with a smaller magnitude are considered centered, to ignore drift.
+    pub const DEAD_ZONE: f32 = 0.15;
+
+    /// Applies a radial dead-zone to a raw stick position, rescaling the remainder to 0..1 so
+    /// that small movements are still possible.
+    pub fn apply_dead_zone(raw: Vec2) -> Vec2 {
+        let length = raw.length();
+        if length <= Self::DEAD_ZONE {
+            Vec2::ZERO
+        } else {
+            let scaled = ((length - Self::DEAD_ZONE) / (1.0 - Self::DEAD_ZONE)).min(1.0);
+            raw * (scaled / length)
+        }
+    }
+
+    /// Immutable reference to the state of a particular button.
+    pub fn state(&self, button: GamepadButton) -> &GamepadButtonState {
+        &self.states[button as usize]
+    }
+
+    /// Mutable reference to the state of a particular button.
+    pub(crate) fn state_mut(&mut self, button: GamepadButton) -> &mut GamepadButtonState {
+        &mut self.states[button as usize]
+    }
+
+    /// Analog value of a particular button (0..1).
+    pub fn value(&self, button: GamepadButton) -> f32 {
+        self.values[button as usize]
+    }
+
+    /// Position of a particular stick with dead-zone applied (-1..1), with positive y being up.
+    pub fn stick(&self, stick: GamepadStick) -> Vec2 {
+        self.sticks[stick as usize]
+    }
+
+    /// See `GamepadButtonState::is_press`.
+    pub fn is_press(&self, button: GamepadButton) -> bool {
+        self.state(button).is_press()
+    }
+
+    /// See `GamepadButtonState::take_press`.
+    pub fn take_press(&mut self, button: GamepadButton) -> bool {
+        self.state_mut(button).take_press()
+    }
+
+    /// See `GamepadButtonState::is_down`.
+    pub fn is_down(&self, button: GamepadButton) -> bool {
+        self.state(button).is_down()
+    }
+
+    /// See `GamepadButtonState::is_down_for`.
+    pub fn is_down_for(&self, button: GamepadButton, down_time: f32, time: f32) -> bool {
+        self.state(button).is_down_for(down_time, time)
+    }
+
+    /// See `GamepadButtonState::is_up`.
+    pub fn is_up(&self, button: GamepadButton) -> bool {
+        self.state(button).is_up()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_dead_zone() {
+        let dead_zone = GamepadState::DEAD_ZONE;
+        assert_eq!(GamepadState::apply_dead_zone(Vec2::ZERO), Vec2::ZERO);
+        assert_eq!(
+            GamepadState::apply_dead_zone(Vec2::new(dead_zone, 0.0)),
+            Vec2::ZERO
+        );
+        assert_eq!(GamepadState::apply_dead_zone(Vec2::NEG_Y), Vec2::NEG_Y);
+
+        // Rescaled to start from 0 just outside the dead-zone, keeping the direction.
+        let halfway = GamepadState::apply_dead_zone(Vec2::new(0.0, (1.0 + dead_zone) * 0.5));
+        assert!(
+            (halfway - Vec2::new(0.0, 0.5)).length() < 0.0001,
+            "{halfway}"
+        );
+
+        // Corners of square gates are clamped to the unit circle.
+        let corner = GamepadState::apply_dead_zone(Vec2::ONE);
+        assert!((corner.length() - 1.0).abs() < 0.0001, "{corner}");
+        assert_eq!(corner.x, corner.y);
+    }
+
+    #[test]
+    fn test_press() {
+        let button = |down, time| GamepadEvent::Button {
+            button: GamepadButton::South,
+            down,
+            value: down as u8 as f32,
+            time,
+        };
+        let mut state = GamepadState::default();
+        assert!(state.is_up(GamepadButton::South));
+
+        state.apply(button(true, 1.0));
+        assert!(state.is_press(GamepadButton::South));
+        assert!(state.is_down_for(GamepadButton::South, 0.5, 2.0));
+        assert!(state.take_press(GamepadButton::South));
+        assert!(!state.take_press(GamepadButton::South));
+        assert!(state.is_down(GamepadButton::South));
+
+        // Still being down isn't another press.
+        state.apply(button(true, 2.0));
+        assert!(!state.is_press(GamepadButton::South));
+        assert!(!state.is_down_for(GamepadButton::South, 1.5, 2.0));
+
+        state.apply(button(false, 3.0));
+        assert!(state.is_up(GamepadButton::South));
+        state.apply(button(true, 4.0));
+        assert!(state.is_press(GamepadButton::South));
+
+        // Resetting releases buttons but stays connected.
+        state.apply(GamepadEvent::Connected(true));
+        state.apply(button(true, 5.0));
+        state.reset();
+        assert!(state.is_up(GamepadButton::South));
+        assert!(state.connected);
+    }
+}
@@ -1,11 +1,11 @@
 // SPDX-FileCopyrightText: 2024 Softbear, Inc.
 // SPDX-License-Identifier: LGPL-3.0-or-later
 
-use crate::io::{Key, KeyState, KeyboardState};
+use crate::io::{GamepadButton, GamepadState, GamepadStick, Key, KeyState, KeyboardState};
 use crate::map_ranges;
 use kodiak_common::glam::Vec2;
 
-/// Helper for taking joystick input. For now, the only joystick input is emulated from keyboard input.
+/// Helper for taking joystick input, emulated from keyboard or gamepad input.
 #[derive(Debug, Default)]
 pub struct Joystick {
     /// Turning (x axis) is interpolated.
@@ -16,9 +16,23 @@ pub struct Joystick {
 }
 
 impl Joystick {
+    /// Returns Some if the keyboard or, failing that, the gamepad state can reasonably interpreted
+    /// as a joystick-style input. See [`Self::try_from_keyboard_state`] and
+    /// [`Self::try_from_gamepad_state`].
+    pub fn try_from_input_state(
+        time_seconds: f32,
+        keyboard_state: &KeyboardState,
+        gamepad_state: &GamepadState,
+    ) -> Option<Self> {
+        Self::try_from_keyboard_state(time_seconds, keyboard_state)
+            .or_else(|| Self::try_from_gamepad_state(gamepad_state))
+    }
+
     /// Returns Some if the keyboard state can reasonably interpreted as a joystick-style input.
     ///  - WASD/Arrow keys for movement.
     ///  - X for stop.
+    ///
+    /// Prefer [`Self::try_from_input_state`] to support gamepads too.
     pub fn try_from_keyboard_state(
         time_seconds: f32,
         keyboard_state: &KeyboardState,
@@ -68,4 +82,71 @@ impl Joystick {
             })
         }
     }
+
+    /// Returns Some if the gamepad state can reasonably interpreted as a joystick-style input.
+    ///  - Left stick or D-pad for movement.
+    ///  - Pressing the left stick for stop.
+    pub fn try_from_gamepad_state(gamepad_state: &GamepadState) -> Option<Self> {
+        let mut stick = gamepad_state.stick(GamepadStick::Left);
+        if stick == Vec2::ZERO {
+            if gamepad_state.is_down(GamepadButton::DPadUp) {
+                stick.y += 1.0;
+            }
+            if gamepad_state.is_down(GamepadButton::DPadDown) {
+                stick.y -= 1.0;
+            }
+            if gamepad_state.is_down(GamepadButton::DPadLeft) {
+                stick.x -= 1.0;
+            }
+            if gamepad_state.is_down(GamepadButton::DPadRight) {
+                stick.x += 1.0;
+            }
+        }
+        let stop = gamepad_state.is_down(GamepadButton::LeftStick);
+
+        if !stop && stick == Vec2::ZERO {
+            None
+        } else {
+            Some(Self {
+                // Analog sticks don't need turning to be interpolated, and turning right is
+                // negative (like the keyboard).
+                position: Vec2::new(-stick.x, stick.y),
+                translation_2d: stick,
+                stop,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::GamepadEvent;
+    use crate::Apply;
+
+    #[test]
+    fn test_gamepad_fallback() {
+        let keyboard = KeyboardState::default();
+        let mut gamepad = GamepadState::default();
+        assert!(Joystick::try_from_input_state(0.0, &keyboard, &gamepad).is_none());
+
+        gamepad.apply(GamepadEvent::Button {
+            button: GamepadButton::DPadUp,
+            down: true,
+            value: 1.0,
+            time: 0.0,
+        });
+        let joystick = Joystick::try_from_input_state(0.0, &keyboard, &gamepad).unwrap();
+        assert_eq!(joystick.translation_2d, Vec2::Y);
+        assert!(!joystick.stop);
+
+        // The stick takes precedence over the D-pad.
+        gamepad.apply(GamepadEvent::Stick {
+            stick: GamepadStick::Left,
+            position: Vec2::new(0.5, 0.0),
+        });
+        let joystick = Joystick::try_from_input_state(0.0, &keyboard, &gamepad).unwrap();
+        assert_eq!(joystick.translation_2d, Vec2::new(0.5, 0.0));
+        assert_eq!(joystick.position, Vec2::new(-0.5, 0.0));
+    }
 }
@@ -4,10 +4,10 @@
 use crate::broker::Apply;
 use std::num::NonZeroU8;
 use strum::EnumIter;
-use strum_macros::Display;
+use strum_macros::{Display, EnumString};
 
 /// Each variant is a possible key. Not guaranteed to support all keys.
-#[derive(Copy, Clone, Eq, PartialEq, Display, EnumIter)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Display, EnumIter, EnumString)]
 pub enum Key {
     A,
     B,
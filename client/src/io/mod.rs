@@ -4,6 +4,8 @@
 #[cfg(feature = "audio")]
 mod audio;
 
+mod binding;
+mod gamepad;
 mod joystick;
 mod keyboard;
 mod mouse;
@@ -12,6 +14,10 @@ mod pan_zoom;
 #[cfg(feature = "audio")]
 pub use self::audio::{Audio, AudioBufferHandle, AudioPlayer, AudioToneHandle};
 
+pub use self::binding::Binding;
+pub use self::gamepad::{
+    GamepadButton, GamepadButtonState, GamepadEvent, GamepadState, GamepadStick,
+};
 pub use self::joystick::Joystick;
 pub use self::keyboard::{Key, KeyState, KeyboardEvent, KeyboardState};
 pub use self::mouse::{MouseButton, MouseEvent, MouseState};
@@ -4,9 +4,10 @@
 use crate::Apply;
 use kodiak_common::glam::Vec2;
 use strum::EnumIter;
+use strum_macros::{Display, EnumString};
 
 /// Identifies a mouse button (left, middle, or right).
-#[derive(Copy, Clone, Debug, Eq, PartialEq, EnumIter)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Display, EnumIter, EnumString)]
 pub enum MouseButton {
     Left,
     Middle,
@@ -58,6 +58,7 @@ pub use sprite_sheet::*;
 // Export symbols used by settings macros.
 pub mod settings_prerequisites {
     pub use super::translation::Translator;
+    pub use super::Binding;
     pub use super::LocalSettings;
     pub use kodiak_common::translate;
 }
@@ -4,16 +4,16 @@
 use crate::bitcode::*;
 use crate::broker::Apply;
 use crate::js_hooks;
-use crate::net::{ProtoSocket, State};
+use crate::net::State;
 use std::marker::PhantomData;
 use yew::Callback;
 
-use super::socket::SocketUpdate;
+use super::socket::{ImpairedProtoSocket, SocketUpdate};
 
 /// Reconnectable Socket (generic over inbound, outbound, state, and transport).
 /// Old state is preserved after closing, but cleared when a new connection is reopened.
 pub struct ReconnSocket<I, O, S> {
-    inner: ProtoSocket<I, O>,
+    inner: ImpairedProtoSocket<I, O>,
     /// For when we need to retry.
     socket_inbound: Callback<SocketUpdate<I>>,
     host: String,
@@ -41,7 +41,7 @@ where
         socket_inbound: Callback<SocketUpdate<I>>,
     ) -> Self {
         Self {
-            inner: ProtoSocket::new(&host, try_web_transport, socket_inbound.clone()),
+            inner: ImpairedProtoSocket::new(&host, try_web_transport, socket_inbound.clone()),
             socket_inbound,
             host,
             tries: 0,
@@ -82,6 +82,7 @@ where
         if self.inner.take_updated() {
             self.last_progress = time_seconds;
         }
+        self.inner.update();
         self.reconnect_if_necessary(state, time_seconds);
     }
 
@@ -136,7 +137,7 @@ where
             // Wait...
         } else if self.inner.is_error() && self.tries < Self::MAX_TRIES {
             // Try again.
-            self.inner = ProtoSocket::new(&self.host, false, self.socket_inbound.clone());
+            self.inner = ImpairedProtoSocket::new(&self.host, false, self.socket_inbound.clone());
             self.next_try = time_seconds + Self::SECONDS_PER_TRY * 1.8f32.powi(self.tries as i32);
             self.tries += 1;
         } else if self.is_terminated() {
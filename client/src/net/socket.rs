@@ -4,6 +4,8 @@
 use super::web_socket::ProtoWebSocket;
 use super::web_transport::ProtoWebTransport;
 use crate::bitcode::*;
+use crate::js_hooks::window;
+use crate::{encode_buffer, network_impairment, ImpairedQueue};
 use yew::Callback;
 
 /// The state of a socket.
@@ -146,3 +148,99 @@ impl<I, O> ProtoSocket<I, O> {
         }
     }
 }
+
+/// A [`ProtoSocket`] that may hold back outbound messages to simulate poor network conditions,
+/// see [`network_impairment`]. Like the connection, held back messages don't survive reconnecting.
+pub(crate) struct ImpairedProtoSocket<I, O> {
+    inner: ProtoSocket<I, O>,
+    /// Outbound messages and whether they are reliable.
+    impaired: Option<ImpairedQueue<(O, bool)>>,
+}
+
+impl<I, O> ImpairedProtoSocket<I, O>
+where
+    I: 'static + DecodeOwned,
+    O: 'static + Encode,
+{
+    pub(crate) fn new(
+        host: &str,
+        web_transport: bool,
+        socket_inbound: Callback<SocketUpdate<I>>,
+    ) -> Self {
+        Self {
+            inner: ProtoSocket::new(host, web_transport, socket_inbound),
+            impaired: network_impairment().map(ImpairedQueue::new),
+        }
+    }
+
+    /// Sends held back messages that are due.
+    pub(crate) fn update(&mut self) {
+        if let Some(impaired) = &mut self.impaired {
+            let now = impairment_now();
+            while let Some((msg, reliable)) = impaired.pop_due(now) {
+                self.inner.send(msg, reliable);
+            }
+        }
+    }
+
+    pub(crate) fn supports_unreliable(&self) -> bool {
+        self.inner.supports_unreliable()
+    }
+
+    pub(crate) fn take_updated(&self) -> bool {
+        self.inner.take_updated()
+    }
+
+    /// Gets current (cached) socket state.
+    pub(crate) fn state(&self) -> State {
+        self.inner.state()
+    }
+
+    /// How many items + bytes are queued to send, not counting held back messages.
+    pub(crate) fn outbound_backlog(&self) -> usize {
+        self.inner.outbound_backlog()
+    }
+
+    /// Returns whether closed for any reason (error or not).
+    pub(crate) fn is_closed(&self) -> bool {
+        self.inner.is_closed()
+    }
+
+    /// Returns whether closed in error.
+    pub(crate) fn is_error(&self) -> bool {
+        self.inner.is_error()
+    }
+
+    /// Returns whether socket is open.
+    pub(crate) fn is_open(&self) -> bool {
+        self.inner.is_open()
+    }
+
+    /// Send a message, or hold it back if impaired, or buffer reliable messages if the socket is
+    /// still opening.
+    pub(crate) fn send(&mut self, msg: O, reliable: bool) {
+        if let Some(impaired) = &mut self.impaired {
+            // Uncompressed size.
+            let len = encode_buffer(&msg).len();
+            impaired.push(impairment_now(), len, reliable, (msg, reliable));
+        } else {
+            self.inner.send(msg, reliable);
+        }
+    }
+}
+
+impl<I, O> ImpairedProtoSocket<I, O> {
+    pub(crate) fn close(&mut self) {
+        self.inner.close();
+    }
+
+    /// Close the connection as if it had an error.
+    pub(crate) fn error(&mut self) {
+        self.inner.error();
+    }
+}
+
+/// Seconds, for [`ImpairedQueue`].
+fn impairment_now() -> f64 {
+    window().performance().map_or(0.0, |p| p.now()) * 0.001
+}
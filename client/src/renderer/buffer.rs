@@ -2,16 +2,19 @@
 // SPDX-License-Identifier: LGPL-3.0-or-later
 
 use super::attribs::Attribs;
+use super::context::Restore;
 use super::gl::*;
 use super::index::Index;
 use super::renderer::Renderer;
 use super::vertex::Vertex;
 use super::DefaultRender;
 use bytemuck::Pod;
+use std::cell::{Cell, RefCell};
 use std::convert::TryInto;
 use std::marker::PhantomData;
 use std::mem::size_of;
 use std::ops::Range;
+use std::rc::Rc;
 use web_sys::{WebGlBuffer, WebGlVertexArrayObject};
 
 /// Vertex indices of a traingle, in counter-clockwise order.
@@ -147,38 +150,69 @@ impl GpuBufferType {
 /// TODO find a way to keep this private and implement Deque.
 #[doc(hidden)]
 pub struct GpuBuffer<E, const B: bool> {
-    elements: WebGlBuffer,
-    length: usize,   // The amount of valid elements in the buffer.
-    capacity: usize, // The amount of capacity (in elements) that is available in the buffer.
-    element: PhantomData<E>,
+    inner: Rc<GpuBufferInner<E, B>>,
+    length: usize, // The amount of valid elements in the buffer.
+}
+
+/// Shared with the [`Vao`]s that use the [`GpuBuffer`].
+pub(crate) struct GpuBufferInner<E, const B: bool> {
+    elements: RefCell<WebGlBuffer>,
+    capacity: Cell<usize>, // The amount of capacity (in elements) that is available in the buffer.
+    /// Copy of the valid elements, to upload again after the context is restored. Only kept for
+    /// static buffers, since dynamic buffers are buffered again anyway.
+    shadow: Option<RefCell<Vec<E>>>,
+    /// True if the context was restored without a `shadow`, until the buffer is buffered again.
+    lost: Cell<bool>,
 }
 
 impl<E: Pod, const B: bool> GpuBuffer<E, B> {
-    pub fn new(gl: &Gl) -> Self {
-        Self {
-            elements: gl.create_buffer().unwrap(),
-            length: 0,
-            capacity: 0,
-            element: PhantomData,
-        }
+    pub fn new(renderer: &Renderer) -> Self {
+        Self::new_inner(renderer, false)
+    }
+
+    /// Like [`Self::new`] but keeps a copy of the elements to restore them after the context is
+    /// restored.
+    pub(crate) fn new_static(renderer: &Renderer) -> Self {
+        Self::new_inner(renderer, true)
+    }
+
+    fn new_inner(renderer: &Renderer, shadow: bool) -> Self {
+        let inner = Rc::new(GpuBufferInner {
+            elements: RefCell::new(created(renderer.gl.create_buffer())),
+            capacity: Cell::new(0),
+            shadow: shadow.then(Default::default),
+            lost: Cell::new(false),
+        });
+        renderer.context_loss.track(&inner);
+        Self { inner, length: 0 }
     }
 
     #[must_use]
     pub(crate) fn bind<'a>(&'a self, gl: &'a Gl) -> GpuBufferBinding<'a, E, B> {
-        GpuBufferBinding::new(gl, self)
+        GpuBufferBinding::new(gl, &self.inner)
     }
 
+    /// Returns 0 if the contents were lost with the context, until buffered again.
     pub(crate) fn len(&self) -> usize {
-        self.length
+        if self.is_lost() {
+            0
+        } else {
+            self.length
+        }
     }
 
     pub(crate) fn is_empty(&self) -> bool {
-        self.length == 0
+        self.len() == 0
+    }
+
+    /// Returns true if the contents were lost with the context and must be buffered again.
+    pub(crate) fn is_lost(&self) -> bool {
+        self.inner.lost.get()
     }
 
     /// Only used once for an optimization in instance.rs.
-    pub(crate) fn _elements(&self) -> &WebGlBuffer {
-        &self.elements
+    pub(crate) fn _elements(&self) -> WebGlBuffer {
+        self.inner.elements.borrow().clone()
     }
 
     pub(crate) fn buffer(&mut self, gl: &Gl, elements: &[E]) {
@@ -186,7 +220,7 @@ impl<E: Pod, const B: bool> GpuBuffer<E, B> {
     }
 
     /// Overwrites part of the [`GpuBuffer`] leaving the rest untouched. Cannot expand the
-    /// [`GpuBuffer`].
+    /// [`GpuBuffer`]. If [`Self::is_lost`], the rest is zeroed.
     pub(crate) fn buffer_sub_data(&mut self, gl: &Gl, elements: &[E], start: usize) {
         self.buffer_inner(gl, self.length, elements, start);
     }
@@ -199,7 +233,35 @@ impl<E: Pod, const B: bool> GpuBuffer<E, B> {
     fn buffer_inner(&mut self, gl: &Gl, length: usize, elements: &[E], start: usize) {
         assert!(elements.len() + start <= length, "out of bounds");
         self.length = length;
+        self.inner.lost.set(false);
 
+        if let Some(shadow) = &self.inner.shadow {
+            let mut shadow = shadow.borrow_mut();
+            let reallocate = length.next_power_of_two() > self.inner.capacity.get();
+            mirror(&mut shadow, reallocate, length, elements, start);
+        }
+        self.inner.upload(gl, length, elements, start);
+    }
+}
+
+/// Applies an upload of `elements` at `start` to a copy of a buffer of `length`, which is zeroed
+/// if `reallocate`.
+fn mirror<E: Pod>(
+    shadow: &mut Vec<E>,
+    reallocate: bool,
+    length: usize,
+    elements: &[E],
+    start: usize,
+) {
+    if reallocate {
+        shadow.clear();
+    }
+    shadow.resize(length, E::zeroed());
+    shadow[start..start + elements.len()].copy_from_slice(elements);
+}
+
+impl<E: Pod, const B: bool> GpuBufferInner<E, B> {
+    fn upload(&self, gl: &Gl, length: usize, elements: &[E], start: usize) {
         // This can easily mess up the bind_buffer calls.
         debug_assert!(gl
             .get_parameter(Ovao::VERTEX_ARRAY_BINDING_OES)
@@ -223,13 +285,13 @@ impl<E: Pod, const B: bool> GpuBuffer<E, B> {
         if copy_data || grow_buffer {
             // Buffer elements.
             let target = GpuBufferType::fr(B).target();
-            gl.bind_buffer(target, Some(&self.elements));
+            gl.bind_buffer(target, Some(&self.elements.borrow()));
 
             // Allocate buffer to nearest power of 2 (never shrinks).
             let new_cap = length.next_power_of_two();
-            if new_cap > self.capacity {
+            if new_cap > self.capacity.get() {
                 gl.buffer_data_with_i32(target, elements_to_bytes::<E>(new_cap), Gl::DYNAMIC_DRAW);
-                self.capacity = new_cap;
+                self.capacity.set(new_cap);
             }
 
             if copy_data {
@@ -263,13 +325,27 @@ impl<E: Pod, const B: bool> GpuBuffer<E, B> {
     }
 }
 
+impl<E: Pod, const B: bool> Restore for GpuBufferInner<E, B> {
+    fn restore(self: Rc<Self>, renderer: &Renderer) {
+        *self.elements.borrow_mut() = created(renderer.gl.create_buffer());
+        self.capacity.set(0);
+        if let Some(shadow) = &self.shadow {
+            let shadow = shadow.borrow();
+            self.upload(&renderer.gl, shadow.len(), &shadow, 0);
+        } else {
+            // Dynamic buffers are buffered again by their owners, before they are drawn.
+            self.lost.set(true);
+        }
+    }
+}
+
 pub(crate) struct GpuBufferBinding<'a, E: Pod, const B: bool> {
     gl: &'a Gl,
     element: PhantomData<E>,
 }
 
 impl<'a, E: Pod, const B: bool> GpuBufferBinding<'a, E, B> {
-    fn new(gl: &'a Gl, buffer: &GpuBuffer<E, B>) -> Self {
+    fn new(gl: &'a Gl, buffer: &GpuBufferInner<E, B>) -> Self {
         // Make sure buffer element's binding was cleared.
         debug_assert!(gl
             .get_parameter(GpuBufferType::fr(B).parameter())
@@ -277,7 +353,10 @@ impl<'a, E: Pod, const B: bool> GpuBufferBinding<'a, E, B> {
             .is_null());
 
         // Bind buffer's elements.
-        gl.bind_buffer(GpuBufferType::fr(B).target(), Some(&buffer.elements));
+        gl.bind_buffer(
+            GpuBufferType::fr(B).target(),
+            Some(&buffer.elements.borrow()),
+        );
 
         Self {
             gl,
@@ -306,51 +385,128 @@ impl<'a, E: Pod, const B: bool> Drop for GpuBufferBinding<'a, E, B> {
     }
 }
 
-/// [`TriangleBuffer`] facilitates drawing a triangle mesh.
-pub struct TriangleBuffer<V, I = u16> {
-    pub(crate) vertices: GpuBuffer<V, { GpuBufferType::Array.to() }>,
-    pub(crate) indices: GpuBuffer<I, { GpuBufferType::Element.to() }>,
-    vao: WebGlVertexArrayObject,
+/// A vertex array object, which is set up again after the context is restored.
+pub(crate) struct Vao {
+    inner: Rc<VaoInner>,
 }
 
-impl<V: Vertex, I: Index> DefaultRender for TriangleBuffer<V, I> {
-    fn new(renderer: &Renderer) -> Self {
-        let gl = &renderer.gl;
-        let ovao = &renderer.ovao;
-
-        let buffer = Self {
-            vertices: GpuBuffer::new(gl),
-            indices: GpuBuffer::new(gl),
-            vao: ovao.create_vertex_array_oes().unwrap(),
-        };
+struct VaoInner {
+    vao: RefCell<WebGlVertexArrayObject>,
+    /// Binds buffers and attributes to the vertex array object.
+    setup: Box<dyn Fn(&Renderer, &WebGlVertexArrayObject)>,
+}
 
-        // Make sure VAO was unbound.
-        debug_assert!(gl
-            .get_parameter(Ovao::VERTEX_ARRAY_BINDING_OES)
-            .unwrap()
-            .is_null());
+impl Vao {
+    pub(crate) fn new(
+        renderer: &Renderer,
+        setup: impl Fn(&Renderer, &WebGlVertexArrayObject) + 'static,
+    ) -> Self {
+        let vao = created(renderer.ovao.create_vertex_array_oes());
+        setup(renderer, &vao);
+        let inner = Rc::new(VaoInner {
+            vao: RefCell::new(vao),
+            setup: Box::new(setup),
+        });
+        renderer.context_loss.track(&inner);
+        Self { inner }
+    }
 
-        ovao.bind_vertex_array_oes(Some(&buffer.vao));
+    /// Like [`Self::new`] but with attributes that are set up as needed by the caller.
+    pub(crate) fn new_empty(renderer: &Renderer) -> Self {
+        Self::new(renderer, |_, _| {})
+    }
 
-        // Bind array buffer.
-        let array_binding = buffer.vertices.bind(gl);
-        array_binding.bind_attribs();
+    /// Unbinding is ALWAYS required (unlike all other render unbinds).
+    pub(crate) fn bind(&self, ovao: &Ovao) {
+        ovao.bind_vertex_array_oes(Some(&self.inner.vao.borrow()));
+    }
+}
 
-        // Bind element buffer.
-        let element_binding = buffer.indices.bind(gl);
+impl Restore for VaoInner {
+    fn restore(self: Rc<Self>, renderer: &Renderer) {
+        let vao = created(renderer.ovao.create_vertex_array_oes());
+        (self.setup)(renderer, &vao);
+        *self.vao.borrow_mut() = vao;
+    }
+}
 
-        // Unbinding VAO is ALWAYS required (unlike all other render unbinds).
-        ovao.bind_vertex_array_oes(None);
+/// Binds the attributes of `vertices` and, optionally, `indices` to `vao`.
+fn setup_vao<V: Vertex, I: Index>(
+    renderer: &Renderer,
+    vao: &WebGlVertexArrayObject,
+    vertices: &GpuBufferInner<V, { GpuBufferType::Array.to() }>,
+    indices: Option<&GpuBufferInner<I, { GpuBufferType::Element.to() }>>,
+) {
+    let gl = &renderer.gl;
+    let ovao = &renderer.ovao;
+
+    // Make sure VAO was unbound.
+    debug_assert!(gl
+        .get_parameter(Ovao::VERTEX_ARRAY_BINDING_OES)
+        .unwrap()
+        .is_null());
+
+    ovao.bind_vertex_array_oes(Some(vao));
+
+    // Bind array buffer.
+    let array_binding = GpuBufferBinding::new(gl, vertices);
+    array_binding.bind_attribs();
+
+    // Bind element buffer.
+    let element_binding = indices.map(|indices| GpuBufferBinding::new(gl, indices));
+
+    // Unbinding VAO is ALWAYS required (unlike all other render unbinds).
+    ovao.bind_vertex_array_oes(None);
+
+    // Unbind both buffers.
+    drop(array_binding);
+    drop(element_binding);
+}
 
-        // Unbind both buffers.
-        drop(array_binding);
-        drop(element_binding);
+/// [`TriangleBuffer`] facilitates drawing a triangle mesh.
+pub struct TriangleBuffer<V, I = u16> {
+    pub(crate) vertices: GpuBuffer<V, { GpuBufferType::Array.to() }>,
+    pub(crate) indices: GpuBuffer<I, { GpuBufferType::Element.to() }>,
+    vao: Vao,
+}
 
-        buffer
+impl<V: Vertex, I: Index> DefaultRender for TriangleBuffer<V, I> {
+    fn new(renderer: &Renderer) -> Self {
+        Self::new_inner(GpuBuffer::new(renderer), GpuBuffer::new(renderer), renderer)
     }
 }
 
 impl<V: Vertex, I: Index> TriangleBuffer<V, I> {
+    /// Like [`DefaultRender::new`] but keeps a copy of what is buffered, so that it can be drawn
+    /// again after the WebGL context is restored. Use for triangles that are buffered once. Other
+    /// [`TriangleBuffer`]s are empty after the context is restored, until they are buffered again
+    /// (e.g. on the next frame or in
+    /// [`Layer::context_restored`][`super::Layer::context_restored`]).
+    pub fn new_static(renderer: &Renderer) -> Self {
+        Self::new_inner(
+            GpuBuffer::new_static(renderer),
+            GpuBuffer::new_static(renderer),
+            renderer,
+        )
+    }
+
+    fn new_inner(
+        vertices: GpuBuffer<V, { GpuBufferType::Array.to() }>,
+        indices: GpuBuffer<I, { GpuBufferType::Element.to() }>,
+        renderer: &Renderer,
+    ) -> Self {
+        let (v, i) = (Rc::clone(&vertices.inner), Rc::clone(&indices.inner));
+        let vao = Vao::new(renderer, move |renderer, vao| {
+            setup_vao(renderer, vao, &v, Some(&i))
+        });
+
+        Self {
+            vertices,
+            indices,
+            vao,
+        }
+    }
+
     /// Returns true if the [`TriangleBuffer`] has no triangles to draw.
     pub fn is_empty(&self) -> bool {
         self.vertices.is_empty() // Indices can't be empty if vertices isn't empty.
@@ -398,7 +554,7 @@ impl<'a, V: Vertex, I: Index> TriangleBufferBinding<'a, V, I> {
             .unwrap()
             .is_null());
 
-        ovao.bind_vertex_array_oes(Some(&buffer.vao));
+        buffer.vao.bind(ovao);
         Self { gl, ovao, buffer }
     }
 
@@ -430,42 +586,32 @@ impl<'a, V: Vertex, I: Index> Drop for TriangleBufferBinding<'a, V, I> {
 /// [`PointDeque`][`crate::PointDeque`] instead.
 pub struct PointBuffer<V> {
     pub(crate) points: GpuBuffer<V, { GpuBufferType::Array.to() }>,
-    vao: WebGlVertexArrayObject,
+    vao: Vao,
 }
 
 impl<P: Vertex> DefaultRender for PointBuffer<P> {
     fn new(renderer: &Renderer) -> Self {
-        let gl = &renderer.gl;
-        let ovao = &renderer.ovao;
-
-        let buffer = Self {
-            points: GpuBuffer::new(gl),
-            vao: ovao.create_vertex_array_oes().unwrap(),
-        };
-
-        // Make sure VAO was unbound.
-        debug_assert!(gl
-            .get_parameter(Ovao::VERTEX_ARRAY_BINDING_OES)
-            .unwrap()
-            .is_null());
-
-        ovao.bind_vertex_array_oes(Some(&buffer.vao));
-
-        // Bind array buffer.
-        let array_binding = buffer.points.bind(gl);
-        array_binding.bind_attribs();
+        Self::new_inner(GpuBuffer::new(renderer), renderer)
+    }
+}
 
-        // Unbinding VAO is ALWAYS required (unlike all other render unbinds).
-        ovao.bind_vertex_array_oes(None);
+impl<P: Vertex> PointBuffer<P> {
+    /// Like [`DefaultRender::new`] but keeps a copy of what is buffered, so that it can be drawn
+    /// again after the WebGL context is restored. Use for points that are buffered once. Other
+    /// [`PointBuffer`]s are empty after the context is restored, until they are buffered again.
+    pub fn new_static(renderer: &Renderer) -> Self {
+        Self::new_inner(GpuBuffer::new_static(renderer), renderer)
+    }
 
-        // Unbind both buffers.
-        drop(array_binding);
+    fn new_inner(points: GpuBuffer<P, { GpuBufferType::Array.to() }>, renderer: &Renderer) -> Self {
+        let p = Rc::clone(&points.inner);
+        let vao = Vao::new(renderer, move |renderer, vao| {
+            setup_vao::<_, u16>(renderer, vao, &p, None)
+        });
 
-        buffer
+        Self { points, vao }
     }
-}
 
-impl<P: Vertex> PointBuffer<P> {
     /// Returns true if the [`PointBuffer`] has no points to draw.
     pub fn is_empty(&self) -> bool {
         self.points.is_empty()
@@ -498,7 +644,7 @@ impl<'a, P: Vertex> PointBufferBinding<'a, P> {
             .unwrap()
             .is_null());
 
-        ovao.bind_vertex_array_oes(Some(&buffer.vao));
+        buffer.vao.bind(ovao);
         Self { gl, ovao, buffer }
     }
 
@@ -536,47 +682,44 @@ impl<'a, P: Vertex> Drop for PointBufferBinding<'a, P> {
 pub struct LineBuffer<V, I = u16> {
     vertices: GpuBuffer<V, { GpuBufferType::Array.to() }>,
     indices: GpuBuffer<I, { GpuBufferType::Element.to() }>,
-    vao: WebGlVertexArrayObject,
+    vao: Vao,
 }
 
 impl<V: Vertex, I: Index> DefaultRender for LineBuffer<V, I> {
     fn new(renderer: &Renderer) -> Self {
-        let gl = &renderer.gl;
-        let ovao = &renderer.ovao;
-
-        let buffer = Self {
-            vertices: GpuBuffer::new(gl),
-            indices: GpuBuffer::new(gl),
-            vao: ovao.create_vertex_array_oes().unwrap(),
-        };
-
-        // Make sure VAO was unbound.
-        debug_assert!(gl
-            .get_parameter(Ovao::VERTEX_ARRAY_BINDING_OES)
-            .unwrap()
-            .is_null());
-
-        ovao.bind_vertex_array_oes(Some(&buffer.vao));
-
-        // Bind array buffer.
-        let array_binding = buffer.vertices.bind(gl);
-        array_binding.bind_attribs();
-
-        // Bind element buffer.
-        let element_binding = buffer.indices.bind(gl);
+        Self::new_inner(GpuBuffer::new(renderer), GpuBuffer::new(renderer), renderer)
+    }
+}
 
-        // Unbinding VAO is ALWAYS required (unlike all other render unbinds).
-        ovao.bind_vertex_array_oes(None);
+impl<V: Vertex, I: Index> LineBuffer<V, I> {
+    /// Like [`DefaultRender::new`] but keeps a copy of what is buffered, so that it can be drawn
+    /// again after the WebGL context is restored. Use for lines that are buffered once. Other
+    /// [`LineBuffer`]s are empty after the context is restored, until they are buffered again.
+    pub fn new_static(renderer: &Renderer) -> Self {
+        Self::new_inner(
+            GpuBuffer::new_static(renderer),
+            GpuBuffer::new_static(renderer),
+            renderer,
+        )
+    }
 
-        // Unbind both buffers.
-        drop(array_binding);
-        drop(element_binding);
+    fn new_inner(
+        vertices: GpuBuffer<V, { GpuBufferType::Array.to() }>,
+        indices: GpuBuffer<I, { GpuBufferType::Element.to() }>,
+        renderer: &Renderer,
+    ) -> Self {
+        let (v, i) = (Rc::clone(&vertices.inner), Rc::clone(&indices.inner));
+        let vao = Vao::new(renderer, move |renderer, vao| {
+            setup_vao(renderer, vao, &v, Some(&i))
+        });
 
-        buffer
+        Self {
+            vertices,
+            indices,
+            vao,
+        }
     }
-}
 
-impl<V: Vertex, I: Index> LineBuffer<V, I> {
     /// Returns true if the [`LineBuffer`] has no lines to draw.
     pub fn is_empty(&self) -> bool {
         self.vertices.is_empty() // Indices can't be empty if vertices isn't empty.
@@ -613,7 +756,7 @@ impl<'a, V: Vertex, I: Index> LineBufferBinding<'a, V, I> {
             .unwrap()
             .is_null());
 
-        ovao.bind_vertex_array_oes(Some(&buffer.vao));
+        buffer.vao.bind(ovao);
         Self { gl, ovao, buffer }
     }
 
@@ -640,3 +783,31 @@ impl<'a, V: Vertex, I: Index> Drop for LineBufferBinding<'a, V, I> {
         self.ovao.bind_vertex_array_oes(None);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::mirror;
+
+    #[test]
+    fn test_mirror() {
+        let mut shadow = Vec::<u16>::new();
+        mirror(&mut shadow, true, 3, &[1, 2, 3], 0);
+        assert_eq!(shadow, [1, 2, 3]);
+
+        // Sub data leaves the rest untouched.
+        mirror(&mut shadow, false, 3, &[4], 1);
+        assert_eq!(shadow, [1, 4, 3]);
+
+        // Growing within capacity keeps the contents.
+        mirror(&mut shadow, false, 4, &[], 0);
+        assert_eq!(shadow, [1, 4, 3, 0]);
+
+        // Shrinking truncates.
+        mirror(&mut shadow, false, 2, &[5, 6], 0);
+        assert_eq!(shadow, [5, 6]);
+
+        // Reallocating zeroes the contents.
+        mirror(&mut shadow, true, 8, &[7], 2);
+        assert_eq!(shadow, [0, 0, 7, 0, 0, 0, 0, 0]);
+    }
+}
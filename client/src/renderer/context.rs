@@ -0,0 +1,105 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+use super::renderer::Renderer;
+use crate::js_hooks::console_log;
+use gloo_events::{EventListener, EventListenerOptions};
+use std::cell::{Cell, RefCell};
+use std::rc::{Rc, Weak};
+use web_sys::HtmlCanvasElement;
+
+/// A GPU resource that is recreated, with its contents, after the WebGL context is restored.
+pub(crate) trait Restore {
+    /// Recreates the resource's GL objects on the restored context.
+    fn restore(self: Rc<Self>, renderer: &Renderer);
+}
+
+/// Tracks the loss and restoration of the WebGL context, and which resources must be restored.
+pub(crate) struct ContextLoss {
+    lost: Rc<Cell<bool>>,
+    restored: Rc<Cell<bool>>,
+    /// Incremented each time the context is restored, so resources can restore lazily.
+    generation: Cell<u32>,
+    /// In creation order, so that dependencies are restored first.
+    resources: RefCell<Vec<Weak<dyn Restore>>>,
+    _listeners: [EventListener; 2],
+}
+
+impl ContextLoss {
+    pub(crate) fn new(canvas: &HtmlCanvasElement) -> Self {
+        let lost = Rc::new(Cell::new(false));
+        let restored = Rc::new(Cell::new(false));
+
+        let lost_clone = Rc::clone(&lost);
+        let lost_listener = EventListener::new_with_options(
+            canvas,
+            "webglcontextlost",
+            EventListenerOptions::enable_prevent_default(),
+            move |event| {
+                // Otherwise, the context will never be restored.
+                event.prevent_default();
+                console_log!("context lost");
+                lost_clone.set(true);
+            },
+        );
+
+        let lost_clone = Rc::clone(&lost);
+        let restored_clone = Rc::clone(&restored);
+        let restored_listener = EventListener::new(canvas, "webglcontextrestored", move |_| {
+            console_log!("context restored");
+            lost_clone.set(false);
+            restored_clone.set(true);
+        });
+
+        Self {
+            lost,
+            restored,
+            generation: Cell::new(0),
+            resources: Default::default(),
+            _listeners: [lost_listener, restored_listener],
+        }
+    }
+
+    pub(crate) fn is_lost(&self) -> bool {
+        self.lost.get()
+    }
+
+    pub(crate) fn generation(&self) -> u32 {
+        self.generation.get()
+    }
+
+    /// Returns true once after the context is restored.
+    pub(crate) fn take_restored(&self) -> bool {
+        self.restored.replace(false)
+    }
+
+    /// Registers a resource to be restored by [`Self::restore`].
+    pub(crate) fn track<R: Restore + 'static>(&self, resource: &Rc<R>) {
+        let mut resources = self.resources.borrow_mut();
+        // Amortize removing dropped resources.
+        if resources.len() == resources.capacity() {
+            resources.retain(|r| r.strong_count() != 0);
+        }
+        let resource: Weak<dyn Restore> = Rc::downgrade(resource);
+        resources.push(resource);
+    }
+
+    /// Restores all live resources, in creation order.
+    pub(crate) fn restore(&self, renderer: &Renderer) {
+        self.generation.set(self.generation.get().wrapping_add(1));
+
+        // Don't hold the borrow while restoring, in case a resource is created.
+        let mut resources = self.resources.take();
+        resources.retain(|r| {
+            if let Some(resource) = r.upgrade() {
+                resource.restore(renderer);
+                true
+            } else {
+                false
+            }
+        });
+        let mut tracked = self.resources.borrow_mut();
+        resources.append(&mut tracked);
+        *tracked = resources;
+    }
+}
@@ -159,7 +159,10 @@ impl<V: Vertex, B: Buffer<V>> Deque<V, B> {
 
     /// Called by bind.
     fn buffer(&mut self, renderer: &Renderer) {
-        if self.pushed == 0 && self.popped == 0 {
+        if self.backing_buffer.gpu_buffer_mut().is_lost() {
+            // The context was restored, so upload everything again as if growing.
+            self.capacity = 0;
+        } else if self.pushed == 0 && self.popped == 0 {
             return;
         }
         let gl = &renderer.gl;
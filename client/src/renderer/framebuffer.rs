@@ -5,6 +5,7 @@ use super::gl::*;
 use super::renderer::Renderer;
 use super::texture::{Texture, TextureFormat};
 use kodiak_common::glam::{UVec2, Vec4};
+use std::mem;
 use std::rc::Rc;
 use web_sys::{WebGlFramebuffer, WebGlRenderbuffer};
 
@@ -26,6 +27,8 @@ pub struct Framebuffer {
     depth_stencil: Option<DepthStencilBuffer>,
     dimensions: UVec2,
     framebuffer: Rc<WebGlFramebuffer>, // For cheap clones for restoring previous.
+    /// Of the context that created the GL objects (see [`Self::restore_if_stale`]).
+    generation: u32,
     #[cfg(feature = "renderer_srgb")]
     srgb: bool,
 }
@@ -118,12 +121,12 @@ impl Framebuffer {
         let gl = &renderer.gl;
 
         // Create framebuffer but don't bind it yet.
-        let framebuffer = gl.create_framebuffer().unwrap();
+        let framebuffer = created(gl.create_framebuffer());
 
         let color = if let Some(texture) = texture {
             ColorBuffer::Texture(texture)
         } else {
-            ColorBuffer::Renderbuffer(gl.create_renderbuffer().unwrap())
+            ColorBuffer::Renderbuffer(created(gl.create_renderbuffer()))
         };
 
         let depth_stencil = depth_stencil.then(|| {
@@ -137,7 +140,7 @@ impl Framebuffer {
                     cfg!(feature = "renderer_webgl2"),
                 ))
             } else {
-                DepthStencilBuffer::Renderbuffer(gl.create_renderbuffer().unwrap())
+                DepthStencilBuffer::Renderbuffer(created(gl.create_renderbuffer()))
             }
         });
 
@@ -152,21 +155,28 @@ impl Framebuffer {
             depth_stencil,
             dimensions: UVec2::ZERO,
             framebuffer: Rc::new(framebuffer),
+            generation: renderer.context_loss.generation(),
             #[cfg(feature = "renderer_srgb")]
             srgb,
         };
 
         // Android WebGL can, contrary to the spec, silently error without the initial allocation.
         ret.set_viewport(renderer, UVec2::splat(1));
+        ret.attach(renderer);
+        ret
+    }
 
-        let binding = FramebufferBinding::new(renderer, &ret);
-        match &ret.color {
+    /// Attaches the buffers to the framebuffer.
+    fn attach(&self, renderer: &Renderer) {
+        let gl = &renderer.gl;
+        let binding = FramebufferBinding::new(renderer, self);
+        match &self.color {
             ColorBuffer::Texture(texture) => {
                 gl.framebuffer_texture_2d(
                     Gl::FRAMEBUFFER,
                     Gl::COLOR_ATTACHMENT0,
                     Gl::TEXTURE_2D,
-                    Some(texture.inner()),
+                    Some(&texture.inner()),
                     0,
                 );
             }
@@ -180,14 +190,14 @@ impl Framebuffer {
             }
         }
 
-        match &ret.depth_stencil {
+        match &self.depth_stencil {
             #[cfg(feature = "renderer_depth_texture")]
             Some(DepthStencilBuffer::Texture(texture)) => {
                 gl.framebuffer_texture_2d(
                     Gl::FRAMEBUFFER,
                     Gl::DEPTH_ATTACHMENT, // TODO support stencil textures.
                     Gl::TEXTURE_2D,
-                    Some(texture.inner()),
+                    Some(&texture.inner()),
                     0,
                 );
             }
@@ -219,7 +229,30 @@ impl Framebuffer {
         );
 
         drop(binding);
-        ret
+    }
+
+    /// Recreates the framebuffer and renderbuffers if the context was restored since they were
+    /// created. The [`Texture`]s restore themselves.
+    fn restore_if_stale(&mut self, renderer: &Renderer) {
+        let generation = renderer.context_loss.generation();
+        if self.generation == generation {
+            return;
+        }
+        self.generation = generation;
+
+        let gl = &renderer.gl;
+        self.framebuffer = Rc::new(created(gl.create_framebuffer()));
+        if let ColorBuffer::Renderbuffer(r) = &mut self.color {
+            *r = created(gl.create_renderbuffer());
+        }
+        if let Some(DepthStencilBuffer::Renderbuffer(r)) = &mut self.depth_stencil {
+            *r = created(gl.create_renderbuffer());
+        }
+
+        // Allocate the renderbuffers again.
+        let dimensions = mem::take(&mut self.dimensions);
+        self.set_viewport(renderer, dimensions);
+        self.attach(renderer);
     }
 
     /// Sets the dimensions of the [`Framebuffer`]. If you want to render a whole screen,
@@ -230,6 +263,7 @@ impl Framebuffer {
     ///
     /// NOTE: this clears the [`Framebuffer`] if the viewport changes between calls.
     pub fn set_viewport(&mut self, renderer: &Renderer, viewport: UVec2) {
+        self.restore_if_stale(renderer);
         if viewport != self.dimensions {
             // TODO is this required?
             let restore = RestoreFramebuffer::new(renderer);
@@ -327,6 +361,7 @@ impl Framebuffer {
     /// NOTE: Does not get cleared between frames.
     #[must_use]
     pub fn bind<'a>(&'a mut self, renderer: &'a Renderer) -> FramebufferBinding<'a> {
+        self.restore_if_stale(renderer);
         FramebufferBinding::new(renderer, self)
     }
 
@@ -2,6 +2,14 @@
 // SPDX-License-Identifier: LGPL-3.0-or-later
 
 pub(crate) use gl::*;
+use wasm_bindgen::{JsCast, JsValue};
+
+/// Unwraps the result of a `create_*` call, which is `None` while the context is lost. The null
+/// placeholder is only valid where WebGL accepts null, and is replaced once the context is
+/// restored.
+pub(crate) fn created<T: JsCast>(object: Option<T>) -> T {
+    object.unwrap_or_else(|| JsValue::NULL.unchecked_into())
+}
 
 /// This module provides utilities to write code that is compatible with WebGL and WebGL2.
 /// It acomplishes this by aliasing either WebGlRenderingContext or WebGl2RenderingContext to Gl and
@@ -10,7 +10,7 @@ use super::{DefaultRender, RenderLayer, ShaderBinding};
 use kodiak_common::fxhash::FxHashMap;
 use std::cell::RefCell;
 use std::hash::Hash;
-use web_sys::{WebGlBuffer, WebGlVertexArrayObject};
+use web_sys::WebGlBuffer;
 
 /// Differentiates [`MeshBuilder`]s that are being instanced by an [`InstanceLayer`]. The
 /// [`Ord`] impl specifies the draw order.
@@ -41,7 +41,7 @@ impl<V: Vertex, I: Index, M: Vertex> Buffers<V, I, M> {
     fn pre_render(&mut self, renderer: &Renderer) {
         match &mut self.inner {
             InnerBuffers::Mesh(mesh) => {
-                let mut render_buffer = TriangleBuffer::new(renderer);
+                let mut render_buffer = TriangleBuffer::new_static(renderer);
                 render_buffer.buffer_mesh(renderer, mesh);
                 let instance_buffer = InstanceBuffer::new(renderer);
 
@@ -141,15 +141,17 @@ impl<V: Vertex, I: Index, M: Vertex, ID: MeshId> RenderLayer<&ShaderBinding<'_>>
 /// [`InstanceBuffer`] facilitates drawing a [`TriangleBuffer`] multiple times.
 pub struct InstanceBuffer<M> {
     instances: GpuBuffer<M, { GpuBufferType::Array.to() }>,
-    vao: WebGlVertexArrayObject,
+    /// Set up in [`Self::bind`]. After the context is restored, the vertex buffer is different so
+    /// it will be set up again.
+    vao: Vao,
     last_vertex_buffer: RefCell<Option<WebGlBuffer>>,
 }
 
 impl<M: Vertex> DefaultRender for InstanceBuffer<M> {
     fn new(renderer: &Renderer) -> Self {
         Self {
-            instances: GpuBuffer::new(&renderer.gl),
-            vao: renderer.ovao.create_vertex_array_oes().unwrap(),
+            instances: GpuBuffer::new(renderer),
+            vao: Vao::new_empty(renderer),
             last_vertex_buffer: Default::default(),
         }
     }
@@ -183,8 +185,8 @@ impl<M: Vertex> InstanceBuffer<M> {
         // Don't redo attribs if buffer doesn't change.
         let mut last_vertex_buffer = self.last_vertex_buffer.borrow_mut();
         let vertex_buffer = triangle_buffer.vertices._elements();
-        if last_vertex_buffer.as_ref() != Some(vertex_buffer) {
-            *last_vertex_buffer = Some(vertex_buffer.clone());
+        if last_vertex_buffer.as_ref() != Some(&vertex_buffer) {
+            *last_vertex_buffer = Some(vertex_buffer);
 
             // Make sure VAO was unbound.
             debug_assert!(gl
@@ -192,7 +194,7 @@ impl<M: Vertex> InstanceBuffer<M> {
                 .unwrap()
                 .is_null());
 
-            ovao.bind_vertex_array_oes(Some(&self.vao));
+            self.vao.bind(ovao);
 
             // Bind array buffer.
             let array_binding = triangle_buffer.vertices.bind(gl);
@@ -247,7 +249,7 @@ impl<'a, V: Vertex, I: Index, M: Vertex> InstanceBufferBinding<'a, V, I, M> {
             .unwrap()
             .is_null());
 
-        ovao.bind_vertex_array_oes(Some(&buffer.vao));
+        buffer.vao.bind(ovao);
 
         Self {
             aia,
@@ -13,6 +13,8 @@
 #[macro_use]
 mod gl;
 
+#[cfg(feature = "renderer_post_process")]
+mod post_process;
 #[cfg(feature = "renderer_query")]
 mod query;
 #[cfg(feature = "renderer_srgb")]
@@ -21,6 +23,7 @@ mod srgb_layer;
 mod antialiasing;
 mod attribs;
 mod buffer;
+mod context;
 mod deque;
 mod framebuffer;
 mod index;
@@ -38,6 +41,8 @@ mod vertex;
 pub use attribs::*;
 
 // Re-export to provide a simpler api.
+#[cfg(feature = "renderer_post_process")]
+pub use post_process::*;
 #[cfg(feature = "renderer_query")]
 pub use query::*;
 
@@ -0,0 +1,668 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+use super::{
+    include_shader, DefaultRender, Framebuffer, Layer, RenderLayer, Renderer, Shader,
+    ShaderBinding, Texture, TextureFormat, TriangleBuffer, TriangleBufferBinding,
+};
+use kodiak_common::glam::{vec2, UVec2, Vec2};
+use std::cell::Cell;
+use std::mem;
+
+thread_local! {
+    /// Copied from [`CommonSettings::post_processing`][`crate::CommonSettings::post_processing`]
+    /// every frame.
+    static POST_PROCESSING_SETTING: Cell<bool> = const { Cell::new(true) };
+}
+
+/// Called every frame so that all [`PostProcessLayer`]s follow the player's setting.
+pub(crate) fn set_post_processing_setting(enabled: bool) {
+    POST_PROCESSING_SETTING.set(enabled);
+}
+
+/// A full-screen pass of a [`PostProcessLayer`]. Implemented by a small [`Shader`] whose uniforms
+/// are the fields of the implementing type.
+pub trait PostProcessPass {
+    /// Called before rendering with the size of the [`PostProcessLayer`]. Useful for resizing
+    /// intermediate [`Framebuffer`]s.
+    fn pre_render(&mut self, renderer: &Renderer, viewport: UVec2) {
+        let _ = (renderer, viewport);
+    }
+
+    /// If `false`, the pass is skipped e.g. because it would have no effect.
+    fn is_active(&self) -> bool {
+        true
+    }
+
+    /// Draws `input` with the effect applied to the bound [`Framebuffer`] (or screen).
+    /// `triangle` covers the whole screen.
+    fn render(
+        &mut self,
+        renderer: &Renderer,
+        input: &Texture,
+        triangle: &TriangleBufferBinding<Vec2, u16>,
+    );
+}
+
+/// The uniforms of a [`PostProcessPass`]'s [`Shader`], so that they are all set together with the
+/// right names and types.
+pub trait PostProcessUniforms {
+    /// Sets the uniforms of a bound `shader`.
+    fn prepare(&self, shader: &ShaderBinding);
+
+    /// Binds `shader`, sets the uniforms and draws `triangle` (unless `shader` isn't ready).
+    fn draw(
+        &self,
+        renderer: &Renderer,
+        shader: &Shader,
+        triangle: &TriangleBufferBinding<Vec2, u16>,
+    ) {
+        if let Some(shader) = shader.bind(renderer) {
+            self.prepare(&shader);
+            triangle.draw();
+        }
+    }
+}
+
+/// The passes of a [`PostProcessLayer`], which run in the order of the fields (all `None` by
+/// default).
+#[derive(Default)]
+pub struct PostProcessPasses {
+    /// See [`ScreenShake`].
+    pub screen_shake: Option<ScreenShake>,
+    /// See [`Bloom`].
+    pub bloom: Option<Bloom>,
+    /// See [`ChromaticAberration`].
+    pub chromatic_aberration: Option<ChromaticAberration>,
+    /// See [`ColorGrading`].
+    pub color_grading: Option<ColorGrading>,
+    /// See [`Vignette`].
+    pub vignette: Option<Vignette>,
+    /// Game specific passes, which run last.
+    pub custom: Vec<Box<dyn PostProcessPass>>,
+}
+
+impl PostProcessPasses {
+    /// Passes that aren't `None` and are [active][`PostProcessPass::is_active`], in order.
+    pub fn active_mut(&mut self) -> impl Iterator<Item = &mut dyn PostProcessPass> {
+        let builtin: [Option<&mut dyn PostProcessPass>; 5] = [
+            self.screen_shake
+                .as_mut()
+                .map(|p| p as &mut dyn PostProcessPass),
+            self.bloom.as_mut().map(|p| p as &mut dyn PostProcessPass),
+            self.chromatic_aberration
+                .as_mut()
+                .map(|p| p as &mut dyn PostProcessPass),
+            self.color_grading
+                .as_mut()
+                .map(|p| p as &mut dyn PostProcessPass),
+            self.vignette
+                .as_mut()
+                .map(|p| p as &mut dyn PostProcessPass),
+        ];
+        builtin
+            .into_iter()
+            .flatten()
+            .chain(
+                self.custom
+                    .iter_mut()
+                    .map(|p| &mut **p as &mut dyn PostProcessPass),
+            )
+            .filter(|p| p.is_active())
+    }
+}
+
+/// Draws its inner [`Layer`] to a [`Framebuffer`] and then runs full-screen
+/// [passes][`PostProcessPasses`] on it, ping-ponging between two [`Framebuffer`]s. The last pass
+/// draws to wherever the [`PostProcessLayer`] would have drawn.
+pub struct PostProcessLayer<I> {
+    /// The inner [`Layer`] passed to [`with_inner`][`Self::with_inner`].
+    pub inner: I,
+    /// If `false`, the inner [`Layer`] is drawn directly (`true` by default). Regardless, passes
+    /// are skipped while
+    /// [`CommonSettings::post_processing`][`crate::CommonSettings::post_processing`] is off, so
+    /// players can turn them off on low-end devices.
+    pub enabled: bool,
+    /// The passes to run.
+    pub passes: PostProcessPasses,
+    /// The inner [`Layer`] is drawn to the first.
+    ping_pong: [Framebuffer; 2],
+    buffer: TriangleBuffer<Vec2>,
+}
+
+impl<I: Layer + DefaultRender> DefaultRender for PostProcessLayer<I> {
+    fn new(renderer: &Renderer) -> Self {
+        Self::with_inner(renderer, DefaultRender::new(renderer))
+    }
+}
+
+impl<I> PostProcessLayer<I> {
+    /// Whether passes run, considering [`enabled`][`Self::enabled`] and the player's setting.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled && POST_PROCESSING_SETTING.get()
+    }
+}
+
+impl<I: Layer> PostProcessLayer<I> {
+    /// Creates a new [`PostProcessLayer`] without any passes.
+    pub fn with_inner(renderer: &Renderer, inner: I) -> Self {
+        let ping_pong = [I::DEPTH || I::STENCIL, false].map(|depth_stencil| {
+            Framebuffer::new2(
+                renderer,
+                renderer.background_color,
+                true,
+                TextureFormat::COLOR_RGBA_STRAIGHT,
+                depth_stencil,
+            )
+        });
+
+        Self {
+            inner,
+            enabled: true,
+            passes: PostProcessPasses::default(),
+            ping_pong,
+            buffer: full_screen_triangle(renderer),
+        }
+    }
+}
+
+impl<I: Layer> Layer for PostProcessLayer<I> {
+    const ALPHA: bool = I::ALPHA;
+    const DEPTH: bool = I::DEPTH;
+    const STENCIL: bool = I::STENCIL;
+
+    fn pre_prepare(&mut self, renderer: &Renderer) {
+        self.inner.pre_prepare(renderer);
+    }
+
+    fn context_restored(&mut self, renderer: &Renderer) {
+        self.inner.context_restored(renderer);
+    }
+
+    fn pre_render(&mut self, renderer: &Renderer) {
+        self.inner.pre_render(renderer);
+        if !self.is_enabled() {
+            return;
+        }
+        let viewport = renderer.canvas_size();
+        for framebuffer in &mut self.ping_pong {
+            framebuffer.set_viewport(renderer, viewport);
+        }
+        for pass in self.passes.active_mut() {
+            pass.pre_render(renderer, viewport);
+        }
+    }
+}
+
+impl<I: RenderLayer<P>, P> RenderLayer<P> for PostProcessLayer<I> {
+    fn render(&mut self, renderer: &Renderer, params: P) {
+        let count = if self.is_enabled() {
+            self.passes.active_mut().count()
+        } else {
+            0
+        };
+        if count == 0 {
+            self.inner.render(renderer, params);
+            return;
+        }
+
+        let [read, write] = &mut self.ping_pong;
+        let (mut read, mut write) = (read, write);
+
+        let fb = read.bind(renderer);
+        fb.clear();
+        self.inner.render(renderer, params);
+        drop(fb);
+
+        let triangle = self.buffer.bind(renderer);
+        let last = count - 1;
+        for (i, pass) in self.passes.active_mut().enumerate() {
+            if i == last {
+                pass.render(renderer, read.as_texture(), &triangle);
+            } else {
+                let fb = write.bind(renderer);
+                fb.clear();
+                pass.render(renderer, read.as_texture(), &triangle);
+                drop(fb);
+                mem::swap(&mut read, &mut write);
+            }
+        }
+    }
+}
+
+/// Offsets the whole screen, e.g. to shake it after an explosion.
+pub struct ScreenShake {
+    /// Offset in view space (-1..1). The pass is skipped if zero.
+    pub offset: Vec2,
+    shader: Shader,
+}
+
+impl DefaultRender for ScreenShake {
+    fn new(renderer: &Renderer) -> Self {
+        Self {
+            offset: Vec2::ZERO,
+            shader: include_shader!(renderer, "post_process", "screen_shake"),
+        }
+    }
+}
+
+impl PostProcessPass for ScreenShake {
+    fn is_active(&self) -> bool {
+        self.offset != Vec2::ZERO
+    }
+
+    fn render(
+        &mut self,
+        renderer: &Renderer,
+        input: &Texture,
+        triangle: &TriangleBufferBinding<Vec2, u16>,
+    ) {
+        ScreenShakeUniforms {
+            sampler: input,
+            // View space to uv space, and sample opposite of the offset.
+            offset: self.offset * -0.5,
+        }
+        .draw(renderer, &self.shader, triangle);
+    }
+}
+
+struct ScreenShakeUniforms<'a> {
+    sampler: &'a Texture,
+    /// In uv space.
+    offset: Vec2,
+}
+
+impl PostProcessUniforms for ScreenShakeUniforms<'_> {
+    fn prepare(&self, shader: &ShaderBinding) {
+        shader.uniform("uSampler", self.sampler);
+        shader.uniform("uOffset", self.offset);
+    }
+}
+
+/// Makes bright colors glow, by blurring them with a chain of successively downsampled and then
+/// upsampled [`Framebuffer`]s.
+pub struct Bloom {
+    /// Brightness (0..1) above which colors glow.
+    pub threshold: f32,
+    /// Softens the transition at the threshold (0..1).
+    pub knee: f32,
+    /// Multiplier of the glow.
+    pub intensity: f32,
+    /// Each half the dimensions of the previous, and the first half of the viewport.
+    mips: Vec<(Framebuffer, UVec2)>,
+    viewport: UVec2,
+    prefilter: Shader,
+    downsample: Shader,
+    upsample: Shader,
+    composite: Shader,
+}
+
+impl DefaultRender for Bloom {
+    fn new(renderer: &Renderer) -> Self {
+        Self::with_levels(renderer, 5)
+    }
+}
+
+impl Bloom {
+    /// Creates a new [`Bloom`] with `levels` (at least 1) of downsampling. More levels make the
+    /// glow spread further.
+    pub fn with_levels(renderer: &Renderer, levels: usize) -> Self {
+        assert!(levels >= 1, "bloom requires at least 1 level");
+        let mips = (0..levels)
+            .map(|_| {
+                let framebuffer = Framebuffer::new2(
+                    renderer,
+                    [0; 4],
+                    true,
+                    TextureFormat::COLOR_RGBA_STRAIGHT,
+                    false,
+                );
+                (framebuffer, UVec2::ZERO)
+            })
+            .collect();
+
+        Self {
+            threshold: 0.8,
+            knee: 0.5,
+            intensity: 0.6,
+            mips,
+            viewport: UVec2::ZERO,
+            prefilter: include_shader!(renderer, "post_process", "bloom_prefilter"),
+            downsample: include_shader!(renderer, "post_process", "bloom_downsample"),
+            upsample: include_shader!(renderer, "post_process", "bloom_upsample"),
+            composite: include_shader!(renderer, "post_process", "bloom"),
+        }
+    }
+}
+
+impl PostProcessPass for Bloom {
+    fn pre_render(&mut self, renderer: &Renderer, viewport: UVec2) {
+        self.viewport = viewport;
+        let mut dimensions = viewport;
+        for (framebuffer, mip_dimensions) in &mut self.mips {
+            dimensions = (dimensions / 2).max(UVec2::ONE);
+            framebuffer.set_viewport(renderer, dimensions);
+            *mip_dimensions = dimensions;
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        self.intensity > 0.0
+    }
+
+    fn render(
+        &mut self,
+        renderer: &Renderer,
+        input: &Texture,
+        triangle: &TriangleBufferBinding<Vec2, u16>,
+    ) {
+        // Extract bright colors into the first mip.
+        {
+            let fb = self.mips[0].0.bind(renderer);
+            BloomPrefilterUniforms {
+                sampler: input,
+                inverse_size: self.viewport.as_vec2().recip(),
+                threshold: self.threshold,
+                knee: self.knee,
+            }
+            .draw(renderer, &self.prefilter, triangle);
+            drop(fb);
+        }
+
+        // Downsample each mip into the next.
+        for i in 1..self.mips.len() {
+            let (previous, next) = self.mips.split_at_mut(i);
+            let (source, source_dimensions) = &previous[i - 1];
+            let fb = next[0].0.bind(renderer);
+            BloomSampleUniforms {
+                sampler: source.as_texture(),
+                inverse_size: source_dimensions.as_vec2().recip(),
+            }
+            .draw(renderer, &self.downsample, triangle);
+            drop(fb);
+        }
+
+        // Upsample each mip, adding it to the previous, back to the first.
+        for i in (1..self.mips.len()).rev() {
+            let (previous, next) = self.mips.split_at_mut(i);
+            let (source, source_dimensions) = &next[0];
+            let fb = previous[i - 1].0.bind(renderer);
+            BloomSampleUniforms {
+                sampler: source.as_texture(),
+                inverse_size: source_dimensions.as_vec2().recip(),
+            }
+            .draw(renderer, &self.upsample, triangle);
+            drop(fb);
+        }
+
+        BloomUniforms {
+            sampler: input,
+            bloom: self.mips[0].0.as_texture(),
+            intensity: self.intensity,
+        }
+        .draw(renderer, &self.composite, triangle);
+    }
+}
+
+struct BloomPrefilterUniforms<'a> {
+    sampler: &'a Texture,
+    inverse_size: Vec2,
+    threshold: f32,
+    knee: f32,
+}
+
+impl PostProcessUniforms for BloomPrefilterUniforms<'_> {
+    fn prepare(&self, shader: &ShaderBinding) {
+        shader.uniform("uSampler", self.sampler);
+        shader.uniform("uInverseSize", self.inverse_size);
+        shader.uniform("uThreshold", self.threshold);
+        shader.uniform("uKnee", self.knee);
+    }
+}
+
+/// Of both downsampling and upsampling.
+struct BloomSampleUniforms<'a> {
+    sampler: &'a Texture,
+    inverse_size: Vec2,
+}
+
+impl PostProcessUniforms for BloomSampleUniforms<'_> {
+    fn prepare(&self, shader: &ShaderBinding) {
+        shader.uniform("uSampler", self.sampler);
+        shader.uniform("uInverseSize", self.inverse_size);
+    }
+}
+
+struct BloomUniforms<'a> {
+    sampler: &'a Texture,
+    bloom: &'a Texture,
+    intensity: f32,
+}
+
+impl PostProcessUniforms for BloomUniforms<'_> {
+    fn prepare(&self, shader: &ShaderBinding) {
+        shader.uniform("uSampler", self.sampler);
+        shader.uniform("uBloom", self.bloom);
+        shader.uniform("uIntensity", self.intensity);
+    }
+}
+
+/// Separates the red and blue channels towards the edges of the screen, like a cheap lens.
+pub struct ChromaticAberration {
+    /// Offset of the channels, as a fraction of the distance from the center. The pass is skipped
+    /// if zero.
+    pub strength: f32,
+    shader: Shader,
+}
+
+impl DefaultRender for ChromaticAberration {
+    fn new(renderer: &Renderer) -> Self {
+        Self {
+            strength: 0.01,
+            shader: include_shader!(renderer, "post_process", "chromatic_aberration"),
+        }
+    }
+}
+
+impl PostProcessPass for ChromaticAberration {
+    fn is_active(&self) -> bool {
+        self.strength != 0.0
+    }
+
+    fn render(
+        &mut self,
+        renderer: &Renderer,
+        input: &Texture,
+        triangle: &TriangleBufferBinding<Vec2, u16>,
+    ) {
+        ChromaticAberrationUniforms {
+            sampler: input,
+            strength: self.strength,
+        }
+        .draw(renderer, &self.shader, triangle);
+    }
+}
+
+struct ChromaticAberrationUniforms<'a> {
+    sampler: &'a Texture,
+    strength: f32,
+}
+
+impl PostProcessUniforms for ChromaticAberrationUniforms<'_> {
+    fn prepare(&self, shader: &ShaderBinding) {
+        shader.uniform("uSampler", self.sampler);
+        shader.uniform("uStrength", self.strength);
+    }
+}
+
+/// Remaps colors with a lookup table (LUT) [`Texture`].
+pub struct ColorGrading {
+    /// A horizontal strip of [`lut_size`][`Self::lut_size`] slices, one per blue value, each
+    /// [`lut_size`][`Self::lut_size`] squared with red increasing to the right and green
+    /// increasing downwards. Should use linear filtering.
+    pub lut: Texture,
+    /// Usually 16 or 32.
+    pub lut_size: u32,
+    /// How much of the graded color to use (0..1). The pass is skipped if zero.
+    pub intensity: f32,
+    shader: Shader,
+}
+
+impl ColorGrading {
+    /// Creates a new [`ColorGrading`] with a `lut` (see [`lut`][`Self::lut`]).
+    pub fn new(renderer: &Renderer, lut: Texture, lut_size: u32) -> Self {
+        Self {
+            lut,
+            lut_size,
+            intensity: 1.0,
+            shader: include_shader!(renderer, "post_process", "color_grading"),
+        }
+    }
+}
+
+impl PostProcessPass for ColorGrading {
+    fn is_active(&self) -> bool {
+        self.intensity > 0.0
+    }
+
+    fn render(
+        &mut self,
+        renderer: &Renderer,
+        input: &Texture,
+        triangle: &TriangleBufferBinding<Vec2, u16>,
+    ) {
+        ColorGradingUniforms {
+            sampler: input,
+            lut: &self.lut,
+            lut_size: self.lut_size as f32,
+            intensity: self.intensity,
+        }
+        .draw(renderer, &self.shader, triangle);
+    }
+}
+
+struct ColorGradingUniforms<'a> {
+    sampler: &'a Texture,
+    lut: &'a Texture,
+    lut_size: f32,
+    intensity: f32,
+}
+
+impl PostProcessUniforms for ColorGradingUniforms<'_> {
+    fn prepare(&self, shader: &ShaderBinding) {
+        shader.uniform("uSampler", self.sampler);
+        shader.uniform("uLut", self.lut);
+        shader.uniform("uLutSize", self.lut_size);
+        shader.uniform("uIntensity", self.intensity);
+    }
+}
+
+/// Darkens the corners of the screen.
+pub struct Vignette {
+    /// How dark the corners get (0..1). The pass is skipped if zero.
+    pub intensity: f32,
+    /// Distance from the center (0..1, where 1 is a corner) at which darkening starts.
+    pub radius: f32,
+    /// Distance over which darkening reaches full intensity.
+    pub smoothness: f32,
+    shader: Shader,
+}
+
+impl DefaultRender for Vignette {
+    fn new(renderer: &Renderer) -> Self {
+        Self {
+            intensity: 0.5,
+            radius: 0.5,
+            smoothness: 0.5,
+            shader: include_shader!(renderer, "post_process", "vignette"),
+        }
+    }
+}
+
+impl PostProcessPass for Vignette {
+    fn is_active(&self) -> bool {
+        self.intensity > 0.0
+    }
+
+    fn render(
+        &mut self,
+        renderer: &Renderer,
+        input: &Texture,
+        triangle: &TriangleBufferBinding<Vec2, u16>,
+    ) {
+        VignetteUniforms {
+            sampler: input,
+            intensity: self.intensity,
+            radius: self.radius,
+            smoothness: self.smoothness,
+        }
+        .draw(renderer, &self.shader, triangle);
+    }
+}
+
+struct VignetteUniforms<'a> {
+    sampler: &'a Texture,
+    intensity: f32,
+    radius: f32,
+    smoothness: f32,
+}
+
+impl PostProcessUniforms for VignetteUniforms<'_> {
+    fn prepare(&self, shader: &ShaderBinding) {
+        shader.uniform("uSampler", self.sampler);
+        shader.uniform("uIntensity", self.intensity);
+        shader.uniform("uRadius", self.radius);
+        shader.uniform("uSmoothness", self.smoothness);
+    }
+}
+
+/// Creates a buffer that has 1 triangle covering the whole screen.
+fn full_screen_triangle(renderer: &Renderer) -> TriangleBuffer<Vec2> {
+    let mut buffer = TriangleBuffer::new_static(renderer);
+    buffer.buffer(
+        renderer,
+        &[vec2(-1.0, 3.0), vec2(-1.0, -1.0), vec2(3.0, -1.0)],
+        &[],
+    );
+    buffer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Custom {
+        active: bool,
+    }
+
+    impl PostProcessPass for Custom {
+        fn is_active(&self) -> bool {
+            self.active
+        }
+
+        fn render(&mut self, _: &Renderer, _: &Texture, _: &TriangleBufferBinding<Vec2, u16>) {
+            unreachable!();
+        }
+    }
+
+    #[test]
+    fn test_active_mut() {
+        let mut passes = PostProcessPasses::default();
+        assert_eq!(passes.active_mut().count(), 0);
+
+        for active in [true, false, true] {
+            passes.custom.push(Box::new(Custom { active }));
+        }
+        assert_eq!(passes.active_mut().count(), 2);
+        assert!(passes.active_mut().all(|p| p.is_active()));
+    }
+
+    #[test]
+    fn test_setting() {
+        assert!(POST_PROCESSING_SETTING.get());
+        set_post_processing_setting(false);
+        assert!(!POST_PROCESSING_SETTING.get());
+        set_post_processing_setting(true);
+        assert!(POST_PROCESSING_SETTING.get());
+    }
+}
@@ -1,7 +1,7 @@
 // SPDX-FileCopyrightText: 2024 Softbear, Inc.
 // SPDX-License-Identifier: LGPL-3.0-or-later
 
-use super::gl::Gl;
+use super::gl::{created, Gl};
 use super::{DefaultRender, Renderer};
 use web_sys::WebGlQuery;
 
@@ -10,6 +10,8 @@ use web_sys::WebGlQuery;
 pub struct OcclusionQuery {
     in_progress: bool,
     query: WebGlQuery,
+    /// Of the context that created `query`.
+    generation: u32,
     visible: Option<bool>,
 }
 
@@ -19,16 +21,29 @@ impl DefaultRender for OcclusionQuery {
     fn new(renderer: &Renderer) -> Self {
         Self {
             in_progress: false,
-            query: renderer.gl.create_query().unwrap(),
+            query: created(renderer.gl.create_query()),
+            generation: renderer.context_loss.generation(),
             visible: None,
         }
     }
 }
 
 impl OcclusionQuery {
-    /// Binds the [`OcclusionQuery`] to record draws.
+    /// Binds the [`OcclusionQuery`] to record draws. Returns [`None`] if the last query is still
+    /// in progress or the context is lost.
     pub fn bind<'a>(&'a mut self, renderer: &'a Renderer) -> Option<OcclusionQueryBinding<'a>> {
         let gl = &renderer.gl;
+        if renderer.is_context_lost() {
+            return None;
+        }
+
+        // Recreate the query, which was lost with the context.
+        let generation = renderer.context_loss.generation();
+        if self.generation != generation {
+            self.generation = generation;
+            self.query = created(gl.create_query());
+            self.in_progress = false;
+        }
 
         if self.in_progress
             && gl
@@ -1,6 +1,7 @@
 // SPDX-FileCopyrightText: 2024 Softbear, Inc.
 // SPDX-License-Identifier: LGPL-3.0-or-later
 
+use super::context::ContextLoss;
 use super::gl::*;
 use super::shader::Shader;
 use super::{Antialiasing, OwnedFramebufferBinding};
@@ -84,6 +85,10 @@ impl<L: Layer> RenderChain<L> {
     /// Begins rendering a frame. Must call [`end`][`RenderFrame::end`] on result.
     #[must_use]
     pub fn begin(&mut self, time_seconds: f32) -> RenderFrame<'_, L> {
+        if self.renderer.context_loss.take_restored() {
+            self.renderer.restore();
+            self.layer.context_restored(&self.renderer);
+        }
         self.renderer.pre_prepare(&mut self.layer, time_seconds);
         RenderFrame {
             layer: &mut self.layer,
@@ -199,6 +204,13 @@ pub trait Layer {
     fn pre_render(&mut self, renderer: &Renderer) {
         let _ = renderer;
     }
+
+    /// Called after the WebGL context is restored, before the next frame. [`Shader`]s,
+    /// [`Texture`][`super::Texture`]s, buffers, and [`Framebuffer`][`super::Framebuffer`]s restore
+    /// themselves, so this is only needed for other state derived from the GPU.
+    fn context_restored(&mut self, renderer: &Renderer) {
+        let _ = renderer;
+    }
 }
 
 /// Allows a [`Layer`] to be renderered with `params`. Layers can be rendered with different sets
@@ -222,6 +234,10 @@ pub struct Renderer {
     pub(crate) aia: Option<Aia>,
     pub(crate) khr: Option<Khr>,
     pub(crate) ovao: Ovao,
+    /// Extensions that only need to be enabled, to re-enable after the context is restored.
+    enabled_extensions: RefCell<Vec<&'static str>>,
+    /// Recovers from the context being lost.
+    pub(crate) context_loss: ContextLoss,
     /// Save this in case we need to transform it later, for sRGB purposes.
     pub background_color: [u8; 4],
     /// Current time in seconds since start.
@@ -246,6 +262,13 @@ pub struct Renderer {
     /// To allow binding [`Framebuffer`][`super::Framebuffer`]s recursively.
     pub(crate) bound_framebuffers: Cell<Vec<OwnedFramebufferBinding>>,
     pub(crate) current_clear_color: Cell<Vec4>,
+    /// To save reading `Gl::BLEND` back.
+    blend_enabled: Cell<bool>,
+    /// To save reading the source and destination factors back from `Gl`.
+    blend_func: Cell<[u32; 2]>,
+    /// Whether blending was enabled and the blend function, before
+    /// [`set_blend_multiply`][`Self::set_blend_multiply`].
+    previous_blend: Cell<Option<(bool, [u32; 2])>>,
 }
 
 impl Renderer {
@@ -293,17 +316,23 @@ impl Renderer {
 
         let ovao = gl.get_extension_ovao();
 
+        #[allow(unused_mut)]
+        let mut enabled_extensions = Vec::new();
         // WebGL2 has these built in by default. In WebGL we only need to enable it, not save it.
         #[cfg(all(not(feature = "renderer_webgl2"), feature = "renderer_srgb"))]
-        gl.get_extension("EXT_sRGB").unwrap().expect("no EXT_sRGB");
+        {
+            gl.get_extension("EXT_sRGB").unwrap().expect("no EXT_sRGB");
+            enabled_extensions.push("EXT_sRGB");
+        }
         #[cfg(all(not(feature = "renderer_webgl2"), feature = "renderer_depth_texture"))]
-        gl.get_extension("WEBGL_depth_texture")
-            .expect("no WEBGL_depth_texture");
-
-        gl.enable(Gl::BLEND);
+        {
+            gl.get_extension("WEBGL_depth_texture")
+                .expect("no WEBGL_depth_texture");
+            enabled_extensions.push("WEBGL_depth_texture");
+        }
 
-        // First argument is Gl::SRC_ALPHA if not premultiplied alpha, Gl::ONE if premultiplied(?).
-        gl.blend_func(Gl::ONE, Gl::ONE_MINUS_SRC_ALPHA);
+        init_state(&gl);
+        let context_loss = ContextLoss::new(&canvas);
 
         Ok(Self {
             canvas,
@@ -312,6 +341,8 @@ impl Renderer {
             aia: None,
             khr,
             ovao,
+            enabled_extensions: RefCell::new(enabled_extensions),
+            context_loss,
             background_color,
             time: 0.0,
             time_delta: 0.0,
@@ -327,10 +358,25 @@ impl Renderer {
             active_texture: Default::default(),
             bound_framebuffers: Default::default(),
             current_clear_color: Default::default(),
+            blend_enabled: Cell::new(true),
+            blend_func: Cell::new(BLEND_FUNC),
+            previous_blend: Default::default(),
         })
     }
 }
 
+/// Initial blend function, set by [`init_state`].
+///
+/// First factor is Gl::SRC_ALPHA if not premultiplied alpha, Gl::ONE if premultiplied(?).
+const BLEND_FUNC: [u32; 2] = [Gl::ONE, Gl::ONE_MINUS_SRC_ALPHA];
+
+/// Sets the state that [`Renderer`] depends on, which is reset if the context is restored.
+fn init_state(gl: &Gl) {
+    gl.enable(Gl::BLEND);
+    let [src, dst] = BLEND_FUNC;
+    gl.blend_func(src, dst);
+}
+
 impl Renderer {
     #[cfg(feature = "renderer_webgl2")]
     pub(crate) fn max_array_texture_layers(&self) -> u32 {
@@ -393,22 +439,11 @@ impl Renderer {
         #[cfg(not(feature = "renderer_webgl2"))]
         {
             // TODO could split up these 2 extensions into separate features.
-            self.gl
-                .get_extension("EXT_color_buffer_half_float")
-                .unwrap()
-                .unwrap();
-            self.gl
-                .get_extension("WEBGL_color_buffer_float")
-                .unwrap()
-                .unwrap();
+            self.enable_extension("EXT_color_buffer_half_float");
+            self.enable_extension("WEBGL_color_buffer_float");
         }
         #[cfg(feature = "renderer_webgl2")]
-        {
-            self.gl
-                .get_extension("EXT_color_buffer_float")
-                .unwrap()
-                .unwrap();
-        }
+        self.enable_extension("EXT_color_buffer_float");
     }
 
     /// Call early on if any custom shaders need OES standard derivatives.
@@ -418,20 +453,30 @@ impl Renderer {
     #[cfg(not(feature = "renderer_webgl2"))]
     pub fn enable_oes_standard_derivatives(&self) {
         // We only need to enable it, not save it.
-        self.gl
-            .get_extension("OES_standard_derivatives")
-            .unwrap()
-            .unwrap();
+        self.enable_extension("OES_standard_derivatives");
     }
 
     /// Call early on if using [`prim@u32`] as [`Index`][`super::index::Index`].
     pub fn enable_oes_element_index_uint(&self) {
         // WebGL2 has this built in by default. In WebGL we only need to enable it, not save it.
         #[cfg(not(feature = "renderer_webgl2"))]
-        self.gl
-            .get_extension("OES_element_index_uint")
-            .unwrap()
-            .unwrap();
+        self.enable_extension("OES_element_index_uint");
+    }
+
+    /// Enables an extension that doesn't need to be saved, remembering to enable it again after
+    /// the context is restored.
+    #[allow(unused)]
+    fn enable_extension(&self, name: &'static str) {
+        self.gl.get_extension(name).unwrap().unwrap();
+        self.remember_extension(name);
+    }
+
+    #[allow(unused)]
+    fn remember_extension(&self, name: &'static str) {
+        let mut enabled_extensions = self.enabled_extensions.borrow_mut();
+        if !enabled_extensions.contains(&name) {
+            enabled_extensions.push(name);
+        }
     }
 
     /// Call early on if you want textures to be sampled with anisotropy. `anisotropy_limit` limits
@@ -442,6 +487,7 @@ impl Renderer {
         let ext = self.gl.get_extension("EXT_texture_filter_anisotropic");
 
         if ext.map_or(false, |v| v.is_some()) {
+            self.remember_extension("EXT_texture_filter_anisotropic");
             self.anisotropy = Some(
                 (self
                     .gl
@@ -454,6 +500,38 @@ impl Renderer {
         }
     }
 
+    /// Returns true if the WebGL context is lost, in which case nothing will be drawn until it is
+    /// restored.
+    pub fn is_context_lost(&self) -> bool {
+        self.context_loss.is_lost()
+    }
+
+    /// Re-enables extensions, resets state, and restores resources after the context is restored.
+    fn restore(&mut self) {
+        let gl = &self.gl;
+        if self.khr.is_some() {
+            self.khr = gl
+                .get_extension("KHR_parallel_shader_compile")
+                .unwrap()
+                .map(|_| KhrParallelShaderCompile);
+        }
+        if self.aia.is_some() {
+            self.aia = Some(gl.get_extension_aia());
+        }
+        self.ovao = gl.get_extension_ovao();
+        for &name in self.enabled_extensions.borrow().iter() {
+            gl.get_extension(name).unwrap();
+        }
+        init_state(gl);
+
+        self.active_texture.set(0);
+        self.current_clear_color.set(Vec4::ZERO);
+        self.blend_enabled.set(true);
+        self.blend_func.set(BLEND_FUNC);
+        self.previous_blend.set(None);
+        self.context_loss.restore(self);
+    }
+
     /// Returns the aspect ratio (width / height) of the canvas.
     pub fn aspect_ratio(&self) -> f32 {
         viewport_to_aspect(self.canvas_size())
@@ -595,6 +673,7 @@ impl Renderer {
     /// Enables or disables blending.
     #[doc(hidden)]
     pub fn set_blend(&self, enabled: bool) {
+        self.blend_enabled.set(enabled);
         if enabled {
             self.gl.enable(Gl::BLEND);
         } else {
@@ -604,7 +683,31 @@ impl Renderer {
 
     #[doc(hidden)]
     pub fn blend(&self) -> bool {
-        self.gl.is_enabled(Gl::BLEND)
+        self.blend_enabled.get()
+    }
+
+    /// Multiplies drawn colors with existing colors (if `true`), until called again with `false`,
+    /// which restores the previous blending.
+    pub(crate) fn set_blend_multiply(&self, multiply: bool) {
+        if multiply {
+            let previous = self
+                .previous_blend
+                .replace(Some((self.blend(), self.blend_func.get())));
+            debug_assert!(previous.is_none(), "already multiplying");
+
+            self.set_blend(true);
+            self.set_blend_func([Gl::DST_COLOR, Gl::ZERO]);
+        } else if let Some((enabled, func)) = self.previous_blend.take() {
+            self.set_blend_func(func);
+            self.set_blend(enabled);
+        }
+    }
+
+    /// Sets the source and destination blend factors.
+    fn set_blend_func(&self, func: [u32; 2]) {
+        self.blend_func.set(func);
+        let [src, dst] = func;
+        self.gl.blend_func(src, dst);
     }
 
     pub(crate) fn clear(&self, color: Vec4) {
@@ -1,6 +1,7 @@
 // SPDX-FileCopyrightText: 2024 Softbear, Inc.
 // SPDX-License-Identifier: LGPL-3.0-or-later
 
+use super::context::Restore;
 use super::gl::*;
 use super::renderer::Renderer;
 use super::texture::{Texture, TextureBinding};
@@ -23,9 +24,10 @@ struct CachedUniform {
 pub struct Shader(Rc<ShaderInner>);
 
 struct ShaderInner {
-    program: WebGlProgram,
-    vert_shader: WebGlShader,
-    frag_shader: WebGlShader,
+    /// Sources, to compile again after the context is restored.
+    vertex: Box<str>,
+    fragment: Box<str>,
+    program: RefCell<ShaderProgram>,
     link_done: Cell<bool>,
     // Use a LinearMap because there are relatively few uniforms.
     // TODO make a macro to convert uniform names to indices in a vec.
@@ -33,31 +35,61 @@ struct ShaderInner {
     texture_index_alloc: Cell<usize>,
 }
 
-impl Shader {
-    /// Compiles a new glsl shader from sources. Attribute locations are indexed exactly according
-    /// to their index in the input.
-    pub fn new(renderer: &Renderer, vertex: &str, fragment: &str) -> Self {
-        let gl = &renderer.gl;
+struct ShaderProgram {
+    program: WebGlProgram,
+    vert_shader: WebGlShader,
+    frag_shader: WebGlShader,
+}
+
+impl ShaderProgram {
+    fn new(gl: &Gl, vertex: &str, fragment: &str) -> Self {
+        // Null placeholders until the context is restored.
+        if gl.is_context_lost() {
+            return Self {
+                program: created(None),
+                vert_shader: created(None),
+                frag_shader: created(None),
+            };
+        }
+
         let vert_shader = compile_shader(gl, Gl::VERTEX_SHADER, vertex);
         let frag_shader = compile_shader(gl, Gl::FRAGMENT_SHADER, fragment);
 
         // Defers failing to shader bind.
         let program = link_program(gl, &vert_shader, &frag_shader, parse_attributes(vertex));
 
-        Self(Rc::new(ShaderInner {
+        Self {
             program,
             vert_shader,
             frag_shader,
+        }
+    }
+}
+
+impl Shader {
+    /// Compiles a new glsl shader from sources. Attribute locations are indexed exactly according
+    /// to their index in the input.
+    pub fn new(renderer: &Renderer, vertex: &str, fragment: &str) -> Self {
+        let program = ShaderProgram::new(&renderer.gl, vertex, fragment);
+        let inner = Rc::new(ShaderInner {
+            vertex: vertex.into(),
+            fragment: fragment.into(),
+            program: RefCell::new(program),
             link_done: Default::default(),
             uniform_cache: Default::default(),
             texture_index_alloc: Cell::new(1), // 0 is reserved for creating textures.
-        }))
+        });
+        renderer.context_loss.track(&inner);
+        Self(inner)
     }
 
     /// Binds the [`Shader`] for handling subsequent draw calls. Returns `None` if the [`Shader`] is
-    /// still compiling asynchronously.
+    /// still compiling asynchronously or the context is lost.
     #[must_use]
     pub fn bind<'a>(&'a self, renderer: &'a Renderer) -> Option<ShaderBinding<'a>> {
+        if renderer.is_context_lost() {
+            return None;
+        }
         let khr = renderer.khr.as_ref();
         if !self.0.link_done.get() {
             if self
@@ -82,13 +114,13 @@ impl ShaderInner {
         texture: bool,
     ) -> RefMut<'a, CachedUniform> {
         // Pre-borrow because using self in closure borrows all of self.
-        let program = &self.program;
+        let program = self.program.borrow();
         let r = self.uniform_cache.borrow_mut();
 
         // Map mutable ref to avoid indexing again.
         RefMut::map(r, |r| {
             r.entry(name).or_insert_with(|| {
-                let location = gl.get_uniform_location(program, name);
+                let location = gl.get_uniform_location(&program.program, name);
                 let texture_index = if location.is_some() {
                     if texture {
                         let i = self.texture_index_alloc.get();
@@ -114,23 +146,28 @@ impl ShaderInner {
 
     /// Returns either Ok with a bool of if its done compiling or and Err with a compile error.
     fn query_link_status(&self, gl: &Gl, khr: Option<&Khr>) -> Result<bool, String> {
-        // return Ok(false) if async compile not complete.
+        let program = self.program.borrow();
+
+        // return Ok(false) if async compile not complete (parameters are null if context lost).
         if !cfg!(feature = "renderer_blocking")
             && khr.is_some()
-            && !gl
-                .get_program_parameter(&self.program, Khr::COMPLETION_STATUS_KHR)
+            && gl
+                .get_program_parameter(&program.program, Khr::COMPLETION_STATUS_KHR)
                 .as_bool()
-                .unwrap()
+                != Some(true)
         {
             return Ok(false);
         }
 
         if gl
-            .get_program_parameter(&self.program, Gl::LINK_STATUS)
+            .get_program_parameter(&program.program, Gl::LINK_STATUS)
             .as_bool()
             .unwrap_or(false)
         {
             Ok(true)
+        } else if gl.is_context_lost() {
+            // Try again after the context is restored.
+            Ok(false)
         } else {
             fn fmt_err(e: Option<String>, prefix: &str) -> String {
                 let mut e = e.unwrap_or_default();
@@ -140,14 +177,23 @@ impl ShaderInner {
                 e
             }
 
-            let mut error = fmt_err(gl.get_program_info_log(&self.program), "\n");
-            error += &fmt_err(gl.get_shader_info_log(&self.vert_shader), "vs: ");
-            error += &fmt_err(gl.get_shader_info_log(&self.frag_shader), "fs: ");
+            let mut error = fmt_err(gl.get_program_info_log(&program.program), "\n");
+            error += &fmt_err(gl.get_shader_info_log(&program.vert_shader), "vs: ");
+            error += &fmt_err(gl.get_shader_info_log(&program.frag_shader), "fs: ");
             Err(error)
         }
     }
 }
 
+impl Restore for ShaderInner {
+    fn restore(self: Rc<Self>, renderer: &Renderer) {
+        *self.program.borrow_mut() = ShaderProgram::new(&renderer.gl, &self.vertex, &self.fragment);
+        self.link_done.set(false);
+        self.uniform_cache.borrow_mut().clear();
+        self.texture_index_alloc.set(1);
+    }
+}
+
 /// A type which can be set as a uniform in a [`ShaderBinding`].
 pub trait Uniform {
     #[doc(hidden)]
@@ -481,7 +527,7 @@ impl<'a> ShaderBinding<'a> {
         // Make sure binding was cleared.
         debug_assert!(gl.get_parameter(Gl::CURRENT_PROGRAM).unwrap().is_null());
 
-        gl.use_program(Some(&shader.program));
+        gl.use_program(Some(&shader.program.borrow().program));
         Self {
             renderer,
             gl: &renderer.gl,
@@ -0,0 +1,11 @@
+precision mediump float;
+
+varying vec2 vUv;
+uniform sampler2D uSampler;
+uniform sampler2D uBloom;
+uniform float uIntensity;
+
+void main() {
+    vec4 color = texture2D(uSampler, vUv);
+    gl_FragColor = vec4(color.rgb + texture2D(uBloom, vUv).rgb * uIntensity, color.a);
+}
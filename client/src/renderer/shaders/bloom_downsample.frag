@@ -0,0 +1,13 @@
+precision mediump float;
+
+varying vec2 vUv;
+uniform sampler2D uSampler;
+uniform vec2 uInverseSize;
+
+void main() {
+    // 4 bilinear taps cover a 4x4 texel area.
+    vec4 o = uInverseSize.xyxy * vec4(-1.0, -1.0, 1.0, 1.0);
+    vec3 color = (texture2D(uSampler, vUv + o.xy).rgb + texture2D(uSampler, vUv + o.zy).rgb
+        + texture2D(uSampler, vUv + o.xw).rgb + texture2D(uSampler, vUv + o.zw).rgb) * 0.25;
+    gl_FragColor = vec4(color, 1.0);
+}
@@ -0,0 +1,21 @@
+precision mediump float;
+
+varying vec2 vUv;
+uniform sampler2D uSampler;
+uniform vec2 uInverseSize;
+uniform float uThreshold;
+uniform float uKnee;
+
+void main() {
+    // 4 bilinear taps cover a 4x4 texel area.
+    vec4 o = uInverseSize.xyxy * vec4(-1.0, -1.0, 1.0, 1.0);
+    vec3 color = (texture2D(uSampler, vUv + o.xy).rgb + texture2D(uSampler, vUv + o.zy).rgb
+        + texture2D(uSampler, vUv + o.xw).rgb + texture2D(uSampler, vUv + o.zw).rgb) * 0.25;
+
+    // Soft threshold, see https://catlikecoding.com/unity/tutorials/advanced-rendering/bloom/
+    float brightness = max(color.r, max(color.g, color.b));
+    float soft = clamp(brightness - uThreshold + uKnee, 0.0, 2.0 * uKnee);
+    soft = soft * soft / (4.0 * uKnee + 0.00001);
+    float contribution = max(soft, brightness - uThreshold) / max(brightness, 0.00001);
+    gl_FragColor = vec4(color * contribution, 1.0);
+}
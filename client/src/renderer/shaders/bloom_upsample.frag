@@ -0,0 +1,22 @@
+precision mediump float;
+
+varying vec2 vUv;
+uniform sampler2D uSampler;
+uniform vec2 uInverseSize;
+
+void main() {
+    // 3x3 tent filter.
+    vec4 o = uInverseSize.xyxy * vec4(1.0, 1.0, -1.0, 0.0);
+    vec3 color = texture2D(uSampler, vUv - o.xy).rgb;
+    color += texture2D(uSampler, vUv - o.wy).rgb * 2.0;
+    color += texture2D(uSampler, vUv - o.zy).rgb;
+    color += texture2D(uSampler, vUv + o.zw).rgb * 2.0;
+    color += texture2D(uSampler, vUv).rgb * 4.0;
+    color += texture2D(uSampler, vUv + o.xw).rgb * 2.0;
+    color += texture2D(uSampler, vUv + o.zy).rgb;
+    color += texture2D(uSampler, vUv + o.wy).rgb * 2.0;
+    color += texture2D(uSampler, vUv + o.xy).rgb;
+
+    // Alpha of 0 with premultiplied blending adds to the existing mip.
+    gl_FragColor = vec4(color * (1.0 / 16.0), 0.0);
+}
@@ -0,0 +1,14 @@
+precision mediump float;
+
+varying vec2 vUv;
+uniform sampler2D uSampler;
+uniform float uStrength;
+
+void main() {
+    // Increases towards the edges of the screen.
+    vec2 offset = (vUv - 0.5) * uStrength;
+    vec4 color = texture2D(uSampler, vUv);
+    float r = texture2D(uSampler, vUv - offset).r;
+    float b = texture2D(uSampler, vUv + offset).b;
+    gl_FragColor = vec4(r, color.g, b, color.a);
+}
@@ -0,0 +1,28 @@
+precision mediump float;
+
+varying vec2 vUv;
+uniform sampler2D uSampler;
+uniform sampler2D uLut;
+uniform float uLutSize;
+uniform float uIntensity;
+
+// The LUT is a horizontal strip of uLutSize slices (one per blue value), each uLutSize square.
+vec3 lookup(vec3 color) {
+    float maxIndex = uLutSize - 1.0;
+    float blue = color.b * maxIndex;
+    float slice0 = floor(blue);
+    float slice1 = min(slice0 + 1.0, maxIndex);
+
+    // Sample texel centers to avoid bleeding between slices.
+    vec2 texel = vec2(1.0 / (uLutSize * uLutSize), 1.0 / uLutSize);
+    vec2 uv = color.rg * maxIndex * texel + texel * 0.5;
+    vec3 color0 = texture2D(uLut, uv + vec2(slice0 / uLutSize, 0.0)).rgb;
+    vec3 color1 = texture2D(uLut, uv + vec2(slice1 / uLutSize, 0.0)).rgb;
+    return mix(color0, color1, blue - slice0);
+}
+
+void main() {
+    vec4 color = texture2D(uSampler, vUv);
+    vec3 graded = lookup(clamp(color.rgb, 0.0, 1.0));
+    gl_FragColor = vec4(mix(color.rgb, graded, uIntensity), color.a);
+}
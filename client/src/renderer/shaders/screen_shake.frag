@@ -0,0 +1,9 @@
+precision mediump float;
+
+varying vec2 vUv;
+uniform sampler2D uSampler;
+uniform vec2 uOffset;
+
+void main() {
+    gl_FragColor = texture2D(uSampler, clamp(vUv + uOffset, 0.0, 1.0));
+}
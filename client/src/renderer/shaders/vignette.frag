@@ -0,0 +1,14 @@
+precision mediump float;
+
+varying vec2 vUv;
+uniform sampler2D uSampler;
+uniform float uIntensity;
+uniform float uRadius;
+uniform float uSmoothness;
+
+void main() {
+    vec4 color = texture2D(uSampler, vUv);
+    float distance = length(vUv - 0.5) * 1.41421356;
+    float darken = smoothstep(uRadius, uRadius + uSmoothness, distance) * uIntensity;
+    gl_FragColor = vec4(color.rgb * (1.0 - darken), color.a);
+}
@@ -119,7 +119,7 @@ impl<I: Layer> SrgbLayer<I> {
         };
 
         // Create a buffer that has 1 triangle covering the whole screen.
-        let mut buffer = TriangleBuffer::new(renderer);
+        let mut buffer = TriangleBuffer::new_static(renderer);
         buffer.buffer(
             renderer,
             &[vec2(-1.0, 3.0), vec2(-1.0, -1.0), vec2(3.0, -1.0)],
@@ -151,6 +151,10 @@ impl<I: Layer> Layer for SrgbLayer<I> {
         self.inner.pre_prepare(renderer);
     }
 
+    fn context_restored(&mut self, renderer: &Renderer) {
+        self.inner.context_restored(renderer);
+    }
+
     fn pre_render(&mut self, renderer: &Renderer) {
         self.inner.pre_render(renderer);
         let viewport = renderer.canvas_size();
@@ -73,7 +73,7 @@ impl<M: TextInstance> GenTextLayer<M> {
     /// Creates a new [`GenTextLayer`] given a `renderer` and a `shader` that takes `in vec2 position`
     /// and any uniforms set in [`TextInstance::prepare`].
     pub fn new(renderer: &Renderer, shader: Shader) -> Self {
-        let mut geometry = TriangleBuffer::new(renderer);
+        let mut geometry = TriangleBuffer::new_static(renderer);
         geometry.buffer(
             renderer,
             &[
@@ -1,13 +1,14 @@
 // SPDX-FileCopyrightText: 2024 Softbear, Inc.
 // SPDX-License-Identifier: LGPL-3.0-or-later
 
+use super::context::Restore;
 use super::gl::*;
 use super::renderer::Renderer;
 use super::rgb::rgba_array_to_css;
 use super::TextStyle;
 use crate::js_hooks::{document, window};
 use kodiak_common::glam::UVec2;
-use std::cell::Cell;
+use std::cell::{Cell, Ref, RefCell};
 use std::rc::Rc;
 use wasm_bindgen::closure::Closure;
 use wasm_bindgen::JsCast;
@@ -15,8 +16,42 @@ use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, HtmlImageElement, Web
 
 /// Required for [`Texture::load`]'s callback.
 struct TextureInner {
-    texture: WebGlTexture,
+    texture: RefCell<WebGlTexture>,
     dimensions: Cell<UVec2>,
+    format: TextureFormat,
+    typ: TextureType,
+    source: RefCell<TextureSource>,
+}
+
+/// How to recreate a [`Texture`] after the context is restored.
+enum TextureSource {
+    /// From [`Texture::new_empty_inner`].
+    Empty {
+        linear_filter: bool,
+        /// From [`Texture::new_empty_static`].
+        keep_bytes: bool,
+        /// The bytes of the last [`Texture::realloc_with_opt_bytes`], if `keep_bytes`.
+        bytes: Option<Vec<u8>>,
+    },
+    /// From [`Texture::from_text`].
+    Text {
+        text: String,
+        color: [u8; 4],
+        style: TextStyle,
+    },
+    /// From [`Texture::load_inner`].
+    Load(TextureLoadOptions),
+}
+
+/// Options of [`Texture::load_inner`] other than format and type.
+struct TextureLoadOptions {
+    img_url: String,
+    placeholder: Option<[u8; 3]>,
+    repeating: bool,
+    nearest: bool,
+    disable_mipmap: bool,
+    #[cfg(feature = "renderer_anisotropy")]
+    disable_anisotropy: bool,
 }
 
 /// A 2d array of pixels that you can sample in a [`Shader`][`super::shader::Shader`]. There
@@ -25,8 +60,6 @@ struct TextureInner {
 #[derive(Clone)]
 pub struct Texture {
     inner: Rc<TextureInner>,
-    format: TextureFormat,
-    typ: TextureType,
 }
 
 /// A format of a [`Texture`]. Describes `bytes` in [`Texture::realloc_with_opt_bytes`] or the image
@@ -385,19 +418,26 @@ impl TextureFace {
 }
 
 impl Texture {
-    pub(crate) fn new(gl: &Gl, dimensions: UVec2, format: TextureFormat, typ: TextureType) -> Self {
-        Self {
-            inner: Rc::new(TextureInner {
-                texture: gl.create_texture().unwrap(),
-                dimensions: Cell::new(dimensions),
-            }),
+    fn new(
+        renderer: &Renderer,
+        dimensions: UVec2,
+        format: TextureFormat,
+        typ: TextureType,
+        source: TextureSource,
+    ) -> Self {
+        let inner = Rc::new(TextureInner {
+            texture: RefCell::new(created(renderer.gl.create_texture())),
+            dimensions: Cell::new(dimensions),
             format,
             typ,
-        }
+            source: RefCell::new(source),
+        });
+        renderer.context_loss.track(&inner);
+        Self { inner }
     }
 
-    pub(crate) fn inner(&self) -> &WebGlTexture {
-        &self.inner.texture
+    pub(crate) fn inner(&self) -> Ref<'_, WebGlTexture> {
+        self.inner.texture.borrow()
     }
 
     /// Gets aspect ratio (width / height).
@@ -413,13 +453,26 @@ impl Texture {
 
     /// Gets the [`TextureType`] of the [`Texture`].
     pub fn typ(&self) -> TextureType {
-        self.typ
+        self.inner.typ
     }
 
     /// Creates a new empty [`Texture`] with the given `format` and `linear_filter`. Mipmaps and repeating
     /// cannot be used.
     pub fn new_empty(renderer: &Renderer, format: TextureFormat, linear_filter: bool) -> Self {
-        Self::new_empty_inner(renderer, format, linear_filter, None)
+        Self::new_empty_inner(renderer, format, linear_filter, None, false)
+    }
+
+    /// Like [`Self::new_empty`] but keeps a copy of the bytes passed to
+    /// [`Self::realloc_with_opt_bytes`], so that they can be uploaded again after the WebGL
+    /// context is restored. Use for textures that are uploaded once. Other textures are zeroed
+    /// after the context is restored, until they are uploaded again (e.g. in
+    /// [`Layer::context_restored`][`super::Layer::context_restored`]).
+    pub fn new_empty_static(
+        renderer: &Renderer,
+        format: TextureFormat,
+        linear_filter: bool,
+    ) -> Self {
+        Self::new_empty_inner(renderer, format, linear_filter, None, true)
     }
 
     /// Creates a new empty 3D [`Texture`] with the given `format` and `linear_filter`. Mipmaps and repeating
@@ -436,6 +489,7 @@ impl Texture {
             format,
             linear_filter,
             Some(dimension3.try_into().expect("dimension3 too large")),
+            false,
         )
     }
 
@@ -446,8 +500,8 @@ impl Texture {
         format: TextureFormat,
         linear_filter: bool,
         dimension3: Option<u16>,
+        keep_bytes: bool,
     ) -> Self {
-        let gl = &renderer.gl;
         let typ = if let Some(_dimension3) = dimension3 {
             #[cfg(not(feature = "renderer_webgl2"))]
             unreachable!();
@@ -457,15 +511,29 @@ impl Texture {
             TextureType::D2
         };
 
-        let texture = Self::new(gl, UVec2::ZERO, format, typ);
+        let source = TextureSource::Empty {
+            linear_filter,
+            keep_bytes,
+            bytes: None,
+        };
+        let texture = Self::new(renderer, UVec2::ZERO, format, typ, source);
+        texture.init_empty(renderer, linear_filter);
+        texture
+    }
+
+    /// Sets the parameters of a [`Texture`] from [`Self::new_empty_inner`].
+    fn init_empty(&self, renderer: &Renderer, linear_filter: bool) {
+        let gl = &renderer.gl;
+        let format = self.inner.format;
+        let typ = self.inner.typ;
         let target = typ.target();
-        let binding = texture.bind(renderer, 0);
+        let binding = self.bind(renderer, 0);
 
         // Can't be repeating because size isn't known yet.
         gl.tex_parameteri(target, Gl::TEXTURE_WRAP_S, Gl::CLAMP_TO_EDGE as i32);
         gl.tex_parameteri(target, Gl::TEXTURE_WRAP_T, Gl::CLAMP_TO_EDGE as i32);
         #[cfg(feature = "renderer_webgl2")]
-        if dimension3.is_some() {
+        if typ.depth_if_3d().is_some() {
             gl.tex_parameteri(target, Gl::TEXTURE_WRAP_R, Gl::CLAMP_TO_EDGE as i32);
         }
 
@@ -494,7 +562,6 @@ impl Texture {
         }
 
         drop(binding);
-        texture
     }
 
     /// Copies the `bytes` to the [`Texture`], resizing to `dimensions` if necessary. The
@@ -505,7 +572,13 @@ impl Texture {
         dimensions: UVec2,
         bytes: Option<&[u8]>,
     ) {
-        let typ = self.typ;
+        if bytes.is_none() && self.dimensions() == dimensions {
+            // Nothing to copy, and texSubImage without pixels is an INVALID_VALUE error.
+            return;
+        }
+
+        let typ = self.inner.typ;
+        let format = self.inner.format;
         assert_ne!(typ, TextureType::Cube);
         let target = typ.target();
         let gl = &renderer.gl;
@@ -513,13 +586,13 @@ impl Texture {
 
         // No mipmaps.
         let level = 0;
-        let src_format = self.format.src_format();
-        let src_type = self.format.src_type();
+        let src_format = format.src_format();
+        let src_type = format.src_type();
         let [width, height] = dimensions.to_array();
-        let depth = self.typ.depth();
+        let depth = typ.depth();
 
         if let Some(bytes) = bytes {
-            let pixel_size = self.format.pixel_size();
+            let pixel_size = format.pixel_size();
             assert_eq!(
                 width * height * depth * pixel_size,
                 bytes.len() as u32,
@@ -531,15 +604,31 @@ impl Texture {
             );
         }
 
+        // Keep the bytes to upload again after the context is restored.
+        if let TextureSource::Empty {
+            keep_bytes: true,
+            bytes: saved,
+            ..
+        } = &mut *self.inner.source.borrow_mut()
+        {
+            if let Some(bytes) = bytes {
+                let saved = saved.get_or_insert_default();
+                saved.clear();
+                saved.extend_from_slice(bytes);
+            } else {
+                *saved = None;
+            }
+        }
+
         // Set alignment if it's not the default.
-        let align = self.format.pixel_align();
+        let align = format.pixel_align();
         if align != 4 {
             gl.pixel_storei(Gl::UNPACK_ALIGNMENT, align as i32);
         }
 
         // Don't reallocate if dimensions haven't changed.
         if self.dimensions() == dimensions {
-            if let Some(_depth) = self.typ.depth_if_3d() {
+            if let Some(_depth) = typ.depth_if_3d() {
                 #[cfg(not(feature = "renderer_webgl2"))]
                 unreachable!();
                 #[cfg(feature = "renderer_webgl2")]
@@ -574,10 +663,10 @@ impl Texture {
         } else {
             self.inner.dimensions.set(dimensions);
 
-            let internal_format = self.format.internal_format();
+            let internal_format = format.internal_format();
             let border = 0;
 
-            if let Some(_depth) = self.typ.depth_if_3d() {
+            if let Some(_depth) = typ.depth_if_3d() {
                 #[cfg(not(feature = "renderer_webgl2"))]
                 unreachable!();
                 #[cfg(feature = "renderer_webgl2")]
@@ -622,6 +711,24 @@ impl Texture {
     /// will be `TextureFormat::COLOR_RGBA`. Pass `color` to this function instead of coloring in a
     /// [`Shader`][`super::shader::Shader`] so emoji colors are preserved.
     pub fn from_text(renderer: &Renderer, text: &str, color: [u8; 4], style: TextStyle) -> Self {
+        let source = TextureSource::Text {
+            text: text.to_owned(),
+            color,
+            style,
+        };
+        let texture = Self::new(
+            renderer,
+            UVec2::ZERO,
+            TextureFormat::COLOR_RGBA,
+            TextureType::D2,
+            source,
+        );
+        texture.draw_text(renderer, text, color, style);
+        texture
+    }
+
+    /// Draws the contents of a [`Texture`] from [`Self::from_text`].
+    fn draw_text(&self, renderer: &Renderer, text: &str, color: [u8; 4], style: TextStyle) {
         let (canvas, context) = create_canvas();
 
         let font = match style {
@@ -649,13 +756,12 @@ impl Texture {
             .fill_text(text, 1.0, (HEIGHT - 1) as f64)
             .expect("could not fill text on canvas");
 
-        let format = TextureFormat::COLOR_RGBA;
-        let dimensions = UVec2::new(canvas_width, HEIGHT);
+        let format = self.inner.format;
+        self.inner.dimensions.set(UVec2::new(canvas_width, HEIGHT));
 
         let gl = &renderer.gl;
-        let texture = Self::new(gl, dimensions, format, TextureType::D2);
-        let target = texture.typ.target();
-        let binding = texture.bind(renderer, 0);
+        let target = self.inner.typ.target();
+        let binding = self.bind(renderer, 0);
 
         // No mipmaps since not always a power of 2.
         let level = 0;
@@ -690,7 +796,6 @@ impl Texture {
         gl.tex_parameteri(target, Gl::TEXTURE_MAG_FILTER, Gl::LINEAR as i32);
 
         drop(binding);
-        texture
     }
 
     /// Loads a [`Texture`] from `img_url`. You may specify a `placeholder` color for use before
@@ -798,10 +903,39 @@ impl Texture {
     ) -> Self {
         assert!(!matches!(format, TextureFormat::Alpha), "not supported");
 
+        let options = TextureLoadOptions {
+            img_url: img_url.to_owned(),
+            placeholder,
+            repeating,
+            nearest,
+            disable_mipmap,
+            #[cfg(feature = "renderer_anisotropy")]
+            disable_anisotropy,
+        };
+        let source = TextureSource::Load(options);
+        let texture = Self::new(renderer, UVec2::ONE, format, typ, source);
+        if let TextureSource::Load(options) = &*texture.inner.source.borrow() {
+            texture.load_images(renderer, options);
+        }
+        texture
+    }
+
+    /// Uploads a placeholder and starts loading the images of a [`Texture`] from
+    /// [`Self::load_inner`].
+    fn load_images(&self, renderer: &Renderer, options: &TextureLoadOptions) {
+        let img_url = options.img_url.as_str();
+        let placeholder = options.placeholder;
+        let repeating = options.repeating;
+        let nearest = options.nearest;
+        let disable_mipmap = options.disable_mipmap;
+        #[cfg(feature = "renderer_anisotropy")]
+        let disable_anisotropy = options.disable_anisotropy;
+        let format = self.inner.format;
+        let typ = self.inner.typ;
+
         let gl = &renderer.gl;
-        let texture = Self::new(gl, UVec2::ONE, format, typ);
         let target = typ.target();
-        let binding = texture.bind(renderer, 0);
+        let binding = self.bind(renderer, 0);
 
         let internal_format = format.internal_format();
         let src_format = format.src_format();
@@ -894,7 +1028,7 @@ impl Texture {
 
         for (i, (img, face)) in images.iter().zip(typ.faces()).enumerate() {
             let gl = Rc::clone(&gl);
-            let inner = texture.inner.clone();
+            let inner = self.inner.clone();
             let retry_images = Rc::clone(&images);
             let images = Rc::clone(&images);
 
@@ -906,7 +1040,7 @@ impl Texture {
                     return;
                 }
 
-                bind_texture_checked(&gl, typ, &inner.texture);
+                bind_texture_checked(&gl, typ, &inner.texture.borrow());
                 let premultiply = format.premultiply_alpha();
                 if premultiply {
                     gl.pixel_storei(Gl::UNPACK_PREMULTIPLY_ALPHA_WEBGL, 1);
@@ -1138,8 +1272,6 @@ impl Texture {
             // Cube maps have multiple faces so "foo.png" would map to "foo_px.png", "foo_nx.png"...
             img.set_src(&face.url(img_url));
         }
-
-        texture
     }
 
     /// Bind a texture for affecting subsequent draw calls.
@@ -1149,6 +1281,40 @@ impl Texture {
     }
 }
 
+impl Restore for TextureInner {
+    fn restore(self: Rc<Self>, renderer: &Renderer) {
+        *self.texture.borrow_mut() = created(renderer.gl.create_texture());
+        let mut texture = Texture { inner: self };
+
+        let empty = match &mut *texture.inner.source.borrow_mut() {
+            TextureSource::Empty {
+                linear_filter,
+                bytes,
+                ..
+            } => Some((*linear_filter, bytes.take())),
+            _ => None,
+        };
+        if let Some((linear_filter, bytes)) = empty {
+            texture.init_empty(renderer, linear_filter);
+            let dimensions = texture.dimensions();
+            if dimensions != UVec2::ZERO {
+                // Allocate again, which also saves the bytes again. Without bytes, the texture is
+                // zeroed until its owner uploads to it again.
+                texture.inner.dimensions.set(UVec2::ZERO);
+                texture.realloc_with_opt_bytes(renderer, dimensions, bytes.as_deref());
+            }
+        } else {
+            match &*texture.inner.source.borrow() {
+                TextureSource::Text { text, color, style } => {
+                    texture.draw_text(renderer, text, *color, *style)
+                }
+                TextureSource::Load(options) => texture.load_images(renderer, options),
+                TextureSource::Empty { .. } => unreachable!(),
+            }
+        }
+    }
+}
+
 /// Loads a single 2D texture.
 pub struct TextureLoader<'a> {
     renderer: &'a Renderer,
@@ -1258,12 +1424,16 @@ pub(crate) struct TextureBinding<'a> {
 impl<'a> TextureBinding<'a> {
     fn new(renderer: &'a Renderer, index: usize, texture: &Texture) -> Self {
         renderer.active_texture(index);
-        bind_texture_checked(&renderer.gl, texture.typ, &texture.inner.texture);
+        bind_texture_checked(
+            &renderer.gl,
+            texture.inner.typ,
+            &texture.inner.texture.borrow(),
+        );
 
         Self {
             renderer,
             index,
-            texture_type: texture.typ,
+            texture_type: texture.inner.typ,
         }
     }
 
@@ -32,6 +32,10 @@ impl<I: Layer> Layer for ToggleLayer<I> {
         self.inner.pre_prepare(renderer);
     }
 
+    fn context_restored(&mut self, renderer: &Renderer) {
+        self.inner.context_restored(renderer);
+    }
+
     fn pre_render(&mut self, renderer: &Renderer) {
         self.inner.pre_render(renderer);
     }
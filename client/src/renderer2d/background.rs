@@ -44,7 +44,7 @@ pub struct BackgroundLayer {
 
 impl DefaultRender for BackgroundLayer {
     fn new(renderer: &Renderer) -> Self {
-        let mut buffer = TriangleBuffer::new(renderer);
+        let mut buffer = TriangleBuffer::new_static(renderer);
         buffer_viewport(renderer, &mut buffer);
         Self {
             buffer,
@@ -0,0 +1,347 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+use super::camera_2d::Camera2d;
+use crate::renderer::{
+    include_shader, DefaultRender, Framebuffer, Layer, MeshBuilder, RenderLayer, Renderer, Shader,
+    TextureFormat, TriangleBuffer,
+};
+use kodiak_common::glam::{vec2, UVec2, Vec2, Vec3};
+use kodiak_common::{polygon_signed_area_2x, RotatedRectangle};
+use std::f32::consts::PI;
+
+/// A light added to a [`LightingLayer`] for one frame.
+struct Light {
+    center: Vec2,
+    radius: f32,
+    color: Vec3,
+    /// Unit vector, only used if `cone` is narrower than a full circle.
+    direction: Vec2,
+    /// Cosines of the outer and inner angles of the cone.
+    cone: Vec2,
+}
+
+/// A polygon that casts shadows, added to a [`LightingLayer`] for one frame.
+struct Occluder {
+    /// Range of [`LightingLayer::occluder_vertices`], in counter-clockwise order.
+    start: usize,
+    end: usize,
+    /// Bounding circle, for skipping far away lights.
+    center: Vec2,
+    radius: f32,
+}
+
+/// Lights the scene drawn before it, by multiplying it with a light map. The light map is the
+/// [`ambient`][`Self::ambient`] color plus point and spot lights, which are blocked by occluders to
+/// cast soft shadows. Lights and occluders are in world space and must be added every frame, like
+/// a [`GraphicLayer`][`super::GraphicLayer`]. All its methods take angles in radians.
+pub struct LightingLayer {
+    /// Color of areas that aren't lit by any light (black by default).
+    pub ambient: Vec3,
+    /// Resolution of the light map relative to the canvas (0..1). Lower is faster and softer
+    /// (0.5 by default).
+    pub resolution: f32,
+    /// Blur of shadow edges, in light map pixels (1.5 by default).
+    pub softness: f32,
+    lights: Vec<Light>,
+    occluders: Vec<Occluder>,
+    occluder_vertices: Vec<Vec2>,
+    light_map: Framebuffer,
+    /// White where the current light reaches and black where it is shadowed.
+    shadow_mask: Framebuffer,
+    light_shader: Shader,
+    shadow_shader: Shader,
+    composite_shader: Shader,
+    light_buffer: TriangleBuffer<Vec2>,
+    shadow_mesh: MeshBuilder<Vec2>,
+    shadow_buffer: TriangleBuffer<Vec2>,
+    /// 1 triangle covering the whole screen.
+    composite_buffer: TriangleBuffer<Vec2>,
+}
+
+impl DefaultRender for LightingLayer {
+    fn new(renderer: &Renderer) -> Self {
+        // Light is linear, even if the scene isn't.
+        let format = TextureFormat::Rgba { premultiply: false };
+        let light_map = Framebuffer::new2(renderer, [0; 4], true, format, false);
+        let shadow_mask = Framebuffer::new2(renderer, [255; 4], true, format, false);
+
+        let mut composite_buffer = TriangleBuffer::new_static(renderer);
+        composite_buffer.buffer(
+            renderer,
+            &[vec2(-1.0, 3.0), vec2(-1.0, -1.0), vec2(3.0, -1.0)],
+            &[],
+        );
+
+        Self {
+            ambient: Vec3::ZERO,
+            resolution: 0.5,
+            softness: 1.5,
+            lights: Vec::new(),
+            occluders: Vec::new(),
+            occluder_vertices: Vec::new(),
+            light_map,
+            shadow_mask,
+            light_shader: include_shader!(renderer, "light"),
+            shadow_shader: include_shader!(renderer, "shadow"),
+            composite_shader: include_shader!(renderer, "lighting"),
+            light_buffer: TriangleBuffer::new(renderer),
+            shadow_mesh: MeshBuilder::new(),
+            shadow_buffer: TriangleBuffer::new(renderer),
+            composite_buffer,
+        }
+    }
+}
+
+impl LightingLayer {
+    /// Adds a light at `center` that shines in all directions, fading out at `radius`. `color` and
+    /// overlapping lights add up to at most 1, which shows the scene's original color, since
+    /// lighting can only darken the scene.
+    pub fn add_point_light(&mut self, center: Vec2, radius: f32, color: Vec3) {
+        self.add_spot_light(center, radius, color, 0.0, PI);
+    }
+
+    /// Like [`add_point_light`][`Self::add_point_light`] but only shines in a cone centered on
+    /// `angle`, extending `spread` on each side. An `angle` of 0 is pointing right.
+    pub fn add_spot_light(
+        &mut self,
+        center: Vec2,
+        radius: f32,
+        color: Vec3,
+        angle: f32,
+        spread: f32,
+    ) {
+        if radius <= 0.0 || color == Vec3::ZERO || spread <= 0.0 {
+            return;
+        }
+        let cone = if spread >= PI {
+            // Always lit, since cosines are at least -1.
+            vec2(-3.0, -2.0)
+        } else {
+            // Soften the outermost 20% of the cone.
+            vec2(spread.cos(), (spread * 0.8).cos())
+        };
+        self.lights.push(Light {
+            center,
+            radius,
+            color,
+            direction: vec2(angle.cos(), angle.sin()),
+            cone,
+        });
+    }
+
+    /// Adds a rectangular occluder that casts shadows.
+    pub fn add_occluder(&mut self, rectangle: &RotatedRectangle) {
+        self.add_occluder_polygon(rectangle.corners());
+    }
+
+    /// Adds a polygon occluder that casts shadows. Must be convex, but may be in either winding
+    /// order.
+    pub fn add_occluder_polygon(&mut self, vertices: impl IntoIterator<Item = Vec2>) {
+        let start = self.occluder_vertices.len();
+        self.occluder_vertices.extend(vertices);
+        let polygon = &mut self.occluder_vertices[start..];
+        if polygon.len() < 3 {
+            self.occluder_vertices.truncate(start);
+            return;
+        }
+
+        if polygon_signed_area_2x(polygon) < 0.0 {
+            polygon.reverse();
+        }
+
+        let center = polygon.iter().sum::<Vec2>() / polygon.len() as f32;
+        let radius = polygon
+            .iter()
+            .map(|v| v.distance_squared(center))
+            .fold(0.0, f32::max)
+            .sqrt();
+        self.occluders.push(Occluder {
+            start,
+            end: self.occluder_vertices.len(),
+            center,
+            radius,
+        });
+    }
+
+    /// Builds the shadow volumes of all occluders near `light` into `shadow_mesh`.
+    fn build_shadows(&mut self, light: &Light) {
+        self.shadow_mesh.clear();
+        for occluder in &self.occluders {
+            if occluder.center.distance(light.center) > occluder.radius + light.radius {
+                continue;
+            }
+            let polygon = &self.occluder_vertices[occluder.start..occluder.end];
+            build_shadow(&mut self.shadow_mesh, light, polygon);
+        }
+    }
+}
+
+/// Builds the shadow volume of a counter-clockwise `polygon` lit by `light` into `mesh`.
+fn build_shadow(mesh: &mut MeshBuilder<Vec2>, light: &Light, polygon: &[Vec2]) {
+    // Far enough that the edge between projected vertices stays outside the light.
+    let far = light.radius * 2.0;
+
+    for (i, &a) in polygon.iter().enumerate() {
+        let b = polygon[(i + 1) % polygon.len()];
+
+        // Only edges facing away from the light cast shadows, so the occluder itself stays lit.
+        let outward = -(b - a).perp();
+        if outward.dot(light.center - a) >= 0.0 {
+            continue;
+        }
+
+        let a_direction = (a - light.center).normalize_or_zero();
+        let b_direction = (b - light.center).normalize_or_zero();
+        let middle_direction = (a_direction + b_direction).normalize_or_zero();
+        if middle_direction == Vec2::ZERO {
+            // The light is on the edge.
+            continue;
+        }
+
+        let index = mesh.vertices.len() as u16;
+        mesh.vertices.extend_from_slice(&[
+            a,
+            b,
+            b + b_direction * far,
+            light.center + middle_direction * (far * 2.0),
+            a + a_direction * far,
+        ]);
+        mesh.push_triangle([index, index + 1, index + 2]);
+        mesh.push_triangle([index, index + 2, index + 3]);
+        mesh.push_triangle([index, index + 3, index + 4]);
+    }
+}
+
+impl Layer for LightingLayer {
+    fn pre_render(&mut self, renderer: &Renderer) {
+        let viewport = (renderer.canvas_size().as_vec2() * self.resolution.clamp(0.05, 1.0))
+            .ceil()
+            .as_uvec2()
+            .max(UVec2::ONE);
+        self.light_map.set_viewport(renderer, viewport);
+        self.shadow_mask.set_viewport(renderer, viewport);
+    }
+}
+
+impl RenderLayer<&Camera2d> for LightingLayer {
+    fn render(&mut self, renderer: &Renderer, camera: &Camera2d) {
+        let lights = std::mem::take(&mut self.lights);
+        let inverse_size = (renderer.canvas_size().as_vec2() * self.resolution.clamp(0.05, 1.0))
+            .ceil()
+            .max(Vec2::ONE)
+            .recip();
+
+        // Ambient light.
+        let fb = self.light_map.bind(renderer);
+        renderer.clear(self.ambient.extend(1.0));
+        drop(fb);
+
+        for light in &lights {
+            // Skip lights that are off screen.
+            let view_center = camera.to_view_position(light.center);
+            let view_radius = camera.to_view_vector(Vec2::splat(light.radius)).abs();
+            if (view_center.abs() - view_radius).cmpgt(Vec2::ONE).any() {
+                continue;
+            }
+
+            self.build_shadows(light);
+            let fb = self.shadow_mask.bind(renderer);
+            fb.clear();
+            if !self.shadow_mesh.is_empty()
+                && let Some(shader) = self.shadow_shader.bind(renderer)
+            {
+                camera.prepare(&shader);
+                self.shadow_buffer.buffer_mesh(renderer, &self.shadow_mesh);
+                self.shadow_buffer.bind(renderer).draw();
+            }
+            drop(fb);
+
+            let fb = self.light_map.bind(renderer);
+            if let Some(shader) = self.light_shader.bind(renderer) {
+                camera.prepare(&shader);
+                shader.uniform("uCenter", light.center);
+                shader.uniform("uRadius", light.radius);
+                shader.uniform("uColor", light.color);
+                shader.uniform("uDirection", light.direction);
+                shader.uniform("uCone", light.cone);
+                shader.uniform("uShadow", self.shadow_mask.as_texture());
+                shader.uniform("uInverseSize", inverse_size);
+                shader.uniform("uSoftness", self.softness);
+
+                let r = light.radius;
+                let c = light.center;
+                self.light_buffer.buffer(
+                    renderer,
+                    &[
+                        c + vec2(-r, -r),
+                        c + vec2(r, -r),
+                        c + vec2(r, r),
+                        c + vec2(-r, r),
+                    ],
+                    &[0, 1, 2, 2, 3, 0],
+                );
+                self.light_buffer.bind(renderer).draw();
+            }
+            drop(fb);
+        }
+
+        // Multiply the light map over the scene.
+        if let Some(shader) = self.composite_shader.bind(renderer) {
+            shader.uniform("uSampler", self.light_map.as_texture());
+            renderer.set_blend_multiply(true);
+            self.composite_buffer.bind(renderer).draw();
+            renderer.set_blend_multiply(false);
+        }
+
+        // Reuse allocations.
+        self.lights = lights;
+        self.lights.clear();
+        self.occluders.clear();
+        self.occluder_vertices.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_shadow() {
+        let light = Light {
+            center: vec2(-5.0, 0.0),
+            radius: 10.0,
+            color: Vec3::ONE,
+            direction: Vec2::X,
+            cone: vec2(-3.0, -2.0),
+        };
+        let square = [
+            vec2(-1.0, -1.0),
+            vec2(1.0, -1.0),
+            vec2(1.0, 1.0),
+            vec2(-1.0, 1.0),
+        ];
+
+        // The left edge faces the light, so only the other 3 edges cast shadows.
+        let mut mesh = MeshBuilder::new();
+        build_shadow(&mut mesh, &light, &square);
+        assert_eq!(mesh.vertices.len(), 3 * 5);
+        assert_eq!(mesh.indices.len(), 3 * 9);
+        let left_edge = [vec2(-1.0, 1.0), vec2(-1.0, -1.0)];
+        assert!(mesh.vertices.chunks(5).all(|v| v[..2] != left_edge));
+
+        // Shadows extend away from the light.
+        assert!(mesh
+            .vertices
+            .chunks(5)
+            .all(|v| v[2].x > 1.0 && v[4].x > -1.0));
+
+        // A clockwise polygon would only shadow the edge facing the light.
+        let mut clockwise = square;
+        clockwise.reverse();
+        let mut mesh = MeshBuilder::new();
+        build_shadow(&mut mesh, &light, &clockwise);
+        assert_eq!(mesh.vertices.len(), 5);
+        assert_eq!(mesh.vertices[..2], [vec2(-1.0, -1.0), vec2(-1.0, 1.0)]);
+    }
+}
@@ -11,6 +11,7 @@ mod background;
 mod basic_sprite;
 mod camera_2d;
 mod graphic;
+mod lighting;
 mod particle;
 mod text;
 
@@ -18,5 +19,6 @@ pub use self::background::*;
 pub use self::basic_sprite::*;
 pub use self::camera_2d::*;
 pub use self::graphic::*;
+pub use self::lighting::*;
 pub use self::particle::*;
 pub use self::text::*;
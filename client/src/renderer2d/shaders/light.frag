@@ -0,0 +1,36 @@
+precision mediump float;
+
+varying vec2 vPosition;
+uniform vec2 uCenter;
+uniform float uRadius;
+uniform vec3 uColor;
+uniform vec2 uDirection;
+// Cosines of the outer and inner angles of a spot light's cone.
+uniform vec2 uCone;
+uniform sampler2D uShadow;
+uniform vec2 uInverseSize;
+uniform float uSoftness;
+
+void main() {
+    vec2 delta = vPosition - uCenter;
+    float distance = length(delta);
+    float attenuation = clamp(1.0 - distance / uRadius, 0.0, 1.0);
+    attenuation *= attenuation;
+    attenuation *= smoothstep(uCone.x, uCone.y, dot(delta / max(distance, 0.0001), uDirection));
+
+    // Blur the shadow mask for soft shadows.
+    vec2 uv = gl_FragCoord.xy * uInverseSize;
+    vec2 o = uInverseSize * uSoftness;
+    float lit = texture2D(uShadow, uv).r * 4.0;
+    lit += texture2D(uShadow, uv + vec2(o.x, 0.0)).r * 2.0;
+    lit += texture2D(uShadow, uv - vec2(o.x, 0.0)).r * 2.0;
+    lit += texture2D(uShadow, uv + vec2(0.0, o.y)).r * 2.0;
+    lit += texture2D(uShadow, uv - vec2(0.0, o.y)).r * 2.0;
+    lit += texture2D(uShadow, uv + o).r;
+    lit += texture2D(uShadow, uv - o).r;
+    lit += texture2D(uShadow, uv + vec2(o.x, -o.y)).r;
+    lit += texture2D(uShadow, uv + vec2(-o.x, o.y)).r;
+
+    // Alpha of 0 with pre-multiplied blending adds to the light map.
+    gl_FragColor = vec4(uColor * (attenuation * lit * (1.0 / 16.0)), 0.0);
+}
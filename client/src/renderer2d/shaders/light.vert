@@ -0,0 +1,8 @@
+attribute vec2 position;
+uniform mat3 uView;
+varying vec2 vPosition;
+
+void main() {
+    gl_Position = vec4(uView * vec3(position.x, position.y, 1.0), 1.0);
+    vPosition = position;
+}
@@ -0,0 +1,9 @@
+precision mediump float;
+
+varying vec2 vUv;
+uniform sampler2D uSampler;
+
+void main() {
+    // Multiplied over the scene.
+    gl_FragColor = vec4(texture2D(uSampler, vUv).rgb, 1.0);
+}
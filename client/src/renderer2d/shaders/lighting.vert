@@ -0,0 +1,7 @@
+attribute vec4 position;
+varying vec2 vUv;
+
+void main() {
+    gl_Position = position;
+    vUv = vec2(position * 0.5 + 0.5);
+}
@@ -0,0 +1,6 @@
+precision mediump float;
+
+void main() {
+    // Opaque black covers the white shadow mask.
+    gl_FragColor = vec4(0.0, 0.0, 0.0, 1.0);
+}
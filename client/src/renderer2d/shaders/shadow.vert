@@ -0,0 +1,6 @@
+attribute vec2 position;
+uniform mat3 uView;
+
+void main() {
+    gl_Position = vec4(uView * vec3(position.x, position.y, 1.0), 1.0);
+}
@@ -20,7 +20,7 @@ pub struct CrosshairLayer {
 
 impl DefaultRender for CrosshairLayer {
     fn new(renderer: &Renderer) -> Self {
-        let mut buffer = TriangleBuffer::new(renderer);
+        let mut buffer = TriangleBuffer::new_static(renderer);
 
         let t = (THICKNESS / RADIUS) * 0.5;
         let vertices = [
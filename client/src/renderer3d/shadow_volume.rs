@@ -33,7 +33,7 @@ pub struct ShadowVolumeLayer<L> {
 impl<L> ShadowVolumeLayer<L> {
     /// Creates a new [`ShadowVolumeLayer`].
     pub fn new(renderer: &Renderer, inner: L, color: Vec4) -> Self {
-        let mut buffer = TriangleBuffer::new(renderer);
+        let mut buffer = TriangleBuffer::new_static(renderer);
         buffer.buffer(
             renderer,
             &[vec2(-1.0, 3.0), vec2(-1.0, -1.0), vec2(3.0, -1.0)],
@@ -127,7 +127,7 @@ impl<S: Sky> SkyLayer<S> {
     /// Creates a new [`SkyboxLayer`].
     pub fn new(renderer: &Renderer, skybox: S) -> Self {
         // Create a buffer that has 1 triangle covering the whole screen.
-        let mut buffer = TriangleBuffer::new(renderer);
+        let mut buffer = TriangleBuffer::new_static(renderer);
         buffer.buffer(
             renderer,
             &[vec2(-1.0, 3.0), vec2(-1.0, -1.0), vec2(3.0, -1.0)],
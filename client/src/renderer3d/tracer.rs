@@ -26,7 +26,7 @@ pub struct TracerLayer {
 
 impl DefaultRender for TracerLayer {
     fn new(renderer: &Renderer) -> Self {
-        let mut triangles = TriangleBuffer::new(renderer);
+        let mut triangles = TriangleBuffer::new_static(renderer);
         triangles.buffer(
             renderer,
             &[
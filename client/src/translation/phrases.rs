@@ -310,6 +310,7 @@ impl Translator {
         let audio = || translate!(self, "Audio");
         match setting_category {
             SettingCategory::General => translate!(self, "General"),
+            SettingCategory::Controls => translate!(self, "Controls"),
             SettingCategory::Graphics => translate!(self, "Graphics"),
             #[cfg(feature = "audio")]
             SettingCategory::Audio => audio(),
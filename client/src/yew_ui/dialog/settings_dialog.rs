@@ -4,10 +4,12 @@
 #![allow(clippy::type_complexity)]
 
 use crate::{
-    post_message, translate, use_core_state, use_ctw, use_gctw, ArenaId, ArenaQuery,
-    BrowserStorages, EngineNexus, GameClient, InstancePickerDto, LanguagePicker, LocalSettings,
+    post_message, standard_gamepad, translate, use_core_state, use_ctw, use_gctw, ArenaId,
+    ArenaQuery, Binding, BrowserStorages, EngineNexus, GameClient, GamepadButton,
+    GlobalEventListener, InstancePickerDto, Key, LanguagePicker, LocalSettings, MouseButton,
     NexusDialog, RealmId, RouteLink, SceneId, ServerId, SettingCategory,
 };
+use gloo::timers::callback::Interval;
 use std::borrow::Cow;
 use std::collections::BTreeMap;
 use std::fmt::Write;
@@ -15,8 +17,12 @@ use std::ops::RangeInclusive;
 use std::str::FromStr;
 use stylist::yew::styled_component;
 use stylist::StyleSource;
-use web_sys::{HtmlInputElement, HtmlSelectElement, InputEvent};
-use yew::{classes, html, html_nested, Callback, Html, MouseEvent, TargetCast};
+use wasm_bindgen::JsCast;
+use web_sys::{HtmlInputElement, HtmlSelectElement, InputEvent, KeyboardEvent};
+use yew::{
+    classes, function_component, html, html_nested, use_effect_with, use_state, Callback, Html,
+    MouseEvent, Properties, TargetCast,
+};
 
 #[styled_component(SettingsDialog)]
 pub fn settings_dialog<G: GameClient>() -> Html {
@@ -138,6 +144,25 @@ pub fn settings_dialog<G: GameClient>() -> Html {
         }
     }
 
+    fn binding<S: 'static>(
+        label: String,
+        binding: Binding,
+        callback: fn(&mut S, Binding, &mut BrowserStorages),
+        change_settings: &Callback<Box<dyn FnOnce(&mut S, &mut BrowserStorages)>>,
+        style: &StyleSource,
+    ) -> Html {
+        let onchange = change_settings.reform(move |binding: Binding| {
+            Box::new(
+                move |settings: &mut S, browser_storages: &mut BrowserStorages| {
+                    callback(settings, binding, browser_storages);
+                },
+            )
+        });
+        html! {
+            <BindingPicker {label} {binding} {onchange} style={style.clone()}/>
+        }
+    }
+
     let core_state = use_core_state();
     let selected_server_id = ctw
         .setting_cache
@@ -279,6 +304,12 @@ pub fn settings_dialog<G: GameClient>() -> Html {
                 slider(b, c, d, e, &gctw.change_settings_callback, &slider_style),
             );
         },
+        |a, b, c, d| {
+            categories.borrow_mut().entry(a).or_default().insert(
+                Cow::Owned(b.clone()),
+                binding(b, c, d, &gctw.change_settings_callback, &select_style),
+            );
+        },
     );
     ctw.setting_cache.display(
         &t,
@@ -326,6 +357,12 @@ pub fn settings_dialog<G: GameClient>() -> Html {
                 ),
             );
         },
+        |a, b, c, d| {
+            categories.borrow_mut().entry(a).or_default().insert(
+                Cow::Owned(b.clone()),
+                binding(b, c, d, &ctw.change_common_settings_callback, &select_style),
+            );
+        },
     );
 
     html! {
@@ -347,3 +384,124 @@ pub fn settings_dialog<G: GameClient>() -> Html {
         </NexusDialog>
     }
 }
+
+#[derive(PartialEq, Properties)]
+struct BindingPickerProps {
+    label: String,
+    binding: Binding,
+    onchange: Callback<Binding>,
+    style: StyleSource,
+}
+
+/// Rebinds the keyboard, mouse or gamepad input of a [`Binding`] to the next key, mouse button or
+/// gamepad button pressed after clicking it. Escape cancels, and Backspace unbinds.
+#[function_component(BindingPicker)]
+fn binding_picker(props: &BindingPickerProps) -> Html {
+    let capturing = use_state(|| false);
+
+    {
+        let capturing = capturing.clone();
+        let binding = props.binding;
+        let onchange = props.onchange.clone();
+        use_effect_with(*capturing, move |&is_capturing| {
+            let listeners = is_capturing.then(|| {
+                let keydown = {
+                    let capturing = capturing.clone();
+                    let onchange = onchange.clone();
+                    GlobalEventListener::new_window(
+                        "keydown",
+                        move |e: &KeyboardEvent| {
+                            let Some(key) = Key::try_from_key_code(e.key_code()) else {
+                                return;
+                            };
+                            e.prevent_default();
+                            match key {
+                                Key::Escape => {}
+                                Key::Backspace => onchange.emit(Binding::NONE),
+                                _ => onchange.emit(Binding {
+                                    key: Some(key),
+                                    ..binding
+                                }),
+                            }
+                            capturing.set(false);
+                        },
+                        true,
+                    )
+                };
+                let gamepad = {
+                    let capturing = capturing.clone();
+                    let onchange = onchange.clone();
+                    // The browser has no gamepad button events, so poll. Buttons already down
+                    // when capturing starts don't count as presses.
+                    let mut was_down = Vec::new();
+                    Interval::new(50, move || {
+                        let Some(gamepad) = standard_gamepad() else {
+                            return;
+                        };
+                        let down = gamepad
+                            .buttons()
+                            .iter()
+                            .map(|button| {
+                                button
+                                    .dyn_into::<web_sys::GamepadButton>()
+                                    .is_ok_and(|button| button.pressed())
+                            })
+                            .collect::<Vec<_>>();
+                        let pressed = down.iter().enumerate().find_map(|(index, &is_down)| {
+                            (is_down && !was_down.get(index).copied().unwrap_or(true))
+                                .then(|| GamepadButton::try_from_index(index as u32))
+                                .flatten()
+                        });
+                        was_down = down;
+                        if let Some(button) = pressed {
+                            onchange.emit(Binding {
+                                gamepad: Some(button),
+                                ..binding
+                            });
+                            capturing.set(false);
+                        }
+                    })
+                };
+                let mousedown = GlobalEventListener::new_window(
+                    "mousedown",
+                    move |e: &MouseEvent| {
+                        if let Some(button) = MouseButton::try_from_button(e.button()) {
+                            e.prevent_default();
+                            onchange.emit(Binding {
+                                mouse: Some(button),
+                                ..binding
+                            });
+                        }
+                        capturing.set(false);
+                    },
+                    true,
+                );
+                (keydown, mousedown, gamepad)
+            });
+            || drop(listeners)
+        });
+    }
+
+    let onclick = {
+        let capturing = capturing.clone();
+        Callback::from(move |e: MouseEvent| {
+            e.prevent_default();
+            capturing.set(true);
+        })
+    };
+
+    html! {
+        <label
+            style="display: block; user-select: none; margin-bottom: 0.6rem;"
+        >
+            <button {onclick} class={props.style.clone()}>
+                if *capturing {
+                    {"..."}
+                } else {
+                    {props.binding.label()}
+                }
+            </button>
+            {props.label.clone()}
+        </label>
+    }
+}
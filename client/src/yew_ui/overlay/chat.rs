@@ -4,8 +4,9 @@
 use crate::{
     event_target, high_contrast_class, profile_factory, translate, use_chat_request_callback,
     use_core_state, use_ctw, use_set_context_menu_callback, use_translator, ArenaId,
-    BrowserStorages, ChatMessage, ChatRequest, CommonSettings, ContextMenu, ContextMenuButton,
-    GlobalEventListener, Position, Positioner, RealmId, ServerNumber, Translator,
+    BrowserStorages, ChatCommandDto, ChatMessage, ChatRequest, CommonSettings, ContextMenu,
+    ContextMenuButton, DirectRecipient, GlobalEventListener, PlayerAlias, PlayerDto, PlayerId,
+    Position, Positioner, RealmId, ServerNumber, Translator,
 };
 use js_sys::JsString;
 use std::str::pattern::Pattern;
@@ -172,10 +173,19 @@ pub fn chat_overlay(props: &ChatProps) -> Html {
     let input_ref = use_node_ref();
     let help_hint = use_state_eq::<Option<&'static str>, _>(|| None);
     let is_command = use_state_eq(|| false);
+    // The input, if it is a command.
+    let command_text = use_state_eq(String::new);
+    // Who messages are sent to privately, if anyone.
+    let direct = use_state_eq::<Option<(DirectRecipient, PlayerAlias)>, _>(|| None);
+    let chat_request_callback = use_chat_request_callback();
+    let core_state = use_core_state();
+    let command_suggestions = command_suggestions(&core_state.chat_commands, &command_text);
 
     let oninput = {
         let help_hint = help_hint.clone();
         let is_command = is_command.clone();
+        let command_text = command_text.clone();
+        let chat_request_callback = chat_request_callback.clone();
         let hints = props.hints;
         let on_save_chat_message = on_save_chat_message.clone();
 
@@ -183,21 +193,48 @@ pub fn chat_overlay(props: &ChatProps) -> Html {
             let input: HtmlInputElement = event_target(&event);
             let string = input.value();
             help_hint.set(help_hint_of(hints, &string));
-            is_command.set(string.starts_with('/'));
+            let command = string.starts_with('/');
+            if command && !*is_command {
+                // Permissions may have changed since last time.
+                chat_request_callback.emit(ChatRequest::Commands);
+            }
+            is_command.set(command);
+            command_text.set(if command {
+                string.clone()
+            } else {
+                String::new()
+            });
             on_save_chat_message.emit(string);
         }
     };
 
     const ENTER: u32 = 13;
-
-    let chat_request_callback = use_chat_request_callback();
+    const TAB: u32 = 9;
+    const ESCAPE: u32 = 27;
 
     let onkeydown = {
         let help_hint = help_hint.clone();
         let is_command = is_command.clone();
+        let command_text = command_text.clone();
+        let direct = direct.clone();
         let chat_request_callback = chat_request_callback.clone();
+        let completion = command_completion(&command_suggestions, &command_text);
+        let state = ctw.state.clone();
 
         move |event: KeyboardEvent| {
+            if event.key_code() == ESCAPE && direct.is_some() {
+                direct.set(None);
+                return;
+            }
+            if event.key_code() == TAB
+                && let Some(completion) = &completion
+            {
+                event.prevent_default();
+                let input: HtmlInputElement = event_target(&event);
+                input.set_value(completion);
+                command_text.set(completion.clone());
+                return;
+            }
             if event.key_code() != ENTER {
                 return;
             }
@@ -214,10 +251,29 @@ pub fn chat_overlay(props: &ChatProps) -> Html {
             if message.is_empty() {
                 return;
             }
-            chat_request_callback.emit(ChatRequest::Send { message, whisper });
+            let core_state = state.as_strong();
+            if let Some((Some((player_id, alias)), text)) =
+                parse_direct(&message, core_state.players.values(), core_state.player_id)
+            {
+                let recipient = DirectRecipient::Player(player_id);
+                direct.set(Some((recipient, alias)));
+                if !text.is_empty() {
+                    chat_request_callback.emit(ChatRequest::SendDirect {
+                        recipient,
+                        message: text.to_owned(),
+                    });
+                }
+            } else if let Some((recipient, _)) = *direct
+                && !message.starts_with('/')
+            {
+                chat_request_callback.emit(ChatRequest::SendDirect { recipient, message });
+            } else {
+                chat_request_callback.emit(ChatRequest::Send { message, whisper });
+            }
             on_save_chat_message.emit(String::new());
             help_hint.set(None);
             is_command.set(false);
+            command_text.set(String::new());
         }
     };
 
@@ -263,21 +319,38 @@ pub fn chat_overlay(props: &ChatProps) -> Html {
         });
     }
 
-    let core_state = use_core_state();
     let set_context_menu_callback = use_set_context_menu_callback();
     let profile_factory = profile_factory(&ctw);
     let (mention_string, moderator) = core_state
         .player()
         .map(|p| (format!("@{}", p.alias), p.moderator))
         .unwrap_or((String::from("PLACEHOLDER"), false));
+    let own_player_id = core_state.player_id;
 
     let items = core_state.messages.iter().map(|(message_number, dto)| {
         let message_number = *message_number;
+        // Aliases aren't unique.
+        let own = dto.player_id.is_some() && dto.player_id == own_player_id;
+        let direct_label = dto.recipient.map(|recipient| {
+            if own {
+                let recipient = recipient.as_str();
+                translate!(t, "[→ {recipient}]")
+            } else {
+                translate!(t, "[DM]")
+            }
+        });
         let onclick_reply = {
             let input_ref_clone = input_ref.clone();
             let alias = dto.alias;
+            let direct = direct.clone();
+            let reply_directly = dto.recipient.is_some() && !own;
             move || {
-                if let Some(input) = input_ref_clone.cast::<HtmlInputElement>() {
+                if reply_directly {
+                    direct.set(Some((DirectRecipient::Reply(message_number), alias)));
+                    if let Some(input) = input_ref_clone.cast::<HtmlInputElement>() {
+                        focus(&input);
+                    }
+                } else if let Some(input) = input_ref_clone.cast::<HtmlInputElement>() {
                     let mut message = input.value();
                     let mention = format!("@{} ", alias.as_str());
                     if !message.ends_with(&mention) {
@@ -300,6 +373,10 @@ pub fn chat_overlay(props: &ChatProps) -> Html {
             let chat_restrict_5m_label = AttrValue::from(translate!(t, "Restrict (5m+)"));
             let chat_report_label = AttrValue::from(translate!(t, "Report"));
             let chat_mute_label = AttrValue::from(translate!(t, "Mute"));
+            let chat_direct_label = AttrValue::from(translate!(t, "Message privately"));
+            let direct = direct.clone();
+            let input_ref = input_ref.clone();
+            let alias = dto.alias;
 
             let oncontextmenu = Some(move |e: MouseEvent| {
                 e.prevent_default();
@@ -319,12 +396,25 @@ pub fn chat_overlay(props: &ChatProps) -> Html {
                         chat_request_callback.emit(ChatRequest::Report(message_number));
                     })
                 };
+                let onclick_direct = {
+                    let direct = direct.clone();
+                    let input_ref = input_ref.clone();
+                    Callback::from(move |_: MouseEvent| {
+                        direct.set(Some((DirectRecipient::Reply(message_number), alias)));
+                        if let Some(input) = input_ref.cast::<HtmlInputElement>() {
+                            focus(&input);
+                        }
+                    })
+                };
 
                 let html = html!{
                     <ContextMenu position={&e}>
                         if visitor_id.is_some() {
                             <ContextMenuButton onclick={profile_factory(visitor_id)}>{AttrValue::from(t.profile_label())}</ContextMenuButton>
                         }
+                        if !own {
+                            <ContextMenuButton onclick={onclick_direct}>{chat_direct_label.clone()}</ContextMenuButton>
+                        }
                         <ContextMenuButton onclick={onclick_mute}>{chat_mute_label.clone()}</ContextMenuButton>
                         <ContextMenuButton onclick={onclick_report}>{
                             if moderator {
@@ -358,11 +448,14 @@ pub fn chat_overlay(props: &ChatProps) -> Html {
                     classes!(
                         "message",
                         message_css_class.clone(),
-                        dto.whisper.then(|| whisper_style.clone()),
+                        (dto.whisper || dto.recipient.is_some()).then(|| whisper_style.clone()),
                     )
                 }
                 {oncontextmenu}
             >
+                if let Some(direct_label) = direct_label {
+                    <span class={name_css_class.clone()}>{direct_label}{" "}</span>
+                }
                 if let Some(team_name) = dto.team_name {
                     <span class={name_css_class.clone()}>
                         {"["}
@@ -454,6 +547,14 @@ pub fn chat_overlay(props: &ChatProps) -> Html {
                 if let Some(help_hint) = *help_hint {
                     <p><b>{"Automated help: "}{help_hint}</b></p>
                 }
+                {command_suggestions.iter().take(MAX_COMMAND_SUGGESTIONS).map(|command| html_nested!{
+                    <p><b>{command.usage.clone()}</b>{" "}{command.description.clone()}</p>
+                }).collect::<Html>()}
+                if let Some((_, alias)) = *direct {
+                    <p>
+                        <b>{translate!(t, "Messaging {alias} privately (Esc to stop)")}</b>
+                    </p>
+                }
                 <input
                     type="text"
                     name="message"
@@ -498,6 +599,72 @@ fn help_hint_of(
     }
 }
 
+/// If `text` is `/dm <alias> [message]`, returns another player with that (optionally `@`-prefixed)
+/// alias, if any, and the message, which may be empty.
+fn parse_direct<'a, 'b>(
+    text: &'a str,
+    players: impl IntoIterator<Item = &'b PlayerDto>,
+    own_player_id: Option<PlayerId>,
+) -> Option<(Option<(PlayerId, PlayerAlias)>, &'a str)> {
+    let rest = text.strip_prefix("/dm ")?.trim_start();
+    let (alias, message) = rest.split_once(' ').unwrap_or((rest, ""));
+    let alias = alias.strip_prefix('@').unwrap_or(alias);
+    let player = players
+        .into_iter()
+        .find(|p| {
+            Some(p.player_id) != own_player_id && p.alias.as_str().eq_ignore_ascii_case(alias)
+        })
+        .map(|p| (p.player_id, p.alias));
+    Some((player, message.trim()))
+}
+
+/// How many of [`command_suggestions`] to display.
+const MAX_COMMAND_SUGGESTIONS: usize = 5;
+
+/// Commands matching partially typed `text`.
+fn command_suggestions<'a>(commands: &'a [ChatCommandDto], text: &str) -> Vec<&'a ChatCommandDto> {
+    let Some(command) = text.strip_prefix('/') else {
+        return Vec::new();
+    };
+    let command = command.to_ascii_lowercase();
+    commands
+        .iter()
+        .filter(|c| {
+            if let Some((name, _)) = command.split_once(' ') {
+                // Typing arguments.
+                c.name == name
+            } else {
+                c.name.starts_with(&command)
+            }
+        })
+        .collect()
+}
+
+/// What `text` would become if the command name were completed with Tab.
+fn command_completion(suggestions: &[&ChatCommandDto], text: &str) -> Option<String> {
+    if text.contains(' ') {
+        return None;
+    }
+    let (first, rest) = suggestions.split_first()?;
+    let completion = if rest.is_empty() {
+        format!("/{} ", first.name)
+    } else {
+        // Longest common prefix.
+        let mut prefix = first.name.as_str();
+        for other in rest {
+            let len = prefix
+                .char_indices()
+                .zip(other.name.chars())
+                .find(|((_, a), b)| a != b)
+                .map(|((i, _), _)| i)
+                .unwrap_or(prefix.len().min(other.name.len()));
+            prefix = &prefix[..len];
+        }
+        format!("/{prefix}")
+    };
+    (completion.len() > text.len()).then_some(completion)
+}
+
 #[derive(Debug)]
 struct Segment<'a> {
     pub contents: &'a str,
@@ -557,6 +724,10 @@ impl<'a, P: Pattern<'a> + Clone> Iterator for Segments<'a, P> {
 
 #[cfg(test)]
 mod tests {
+    use super::{
+        command_completion, command_suggestions, parse_direct, ChatCommandDto, PlayerAlias,
+        PlayerDto, PlayerId,
+    };
     use crate::{segments, Segment};
     use kodiak_common::rand::prelude::SliceRandom;
     use kodiak_common::rand::{thread_rng, Rng};
@@ -590,4 +761,57 @@ mod tests {
             debug_assert_eq!(message.len(), total);
         }
     }
+
+    #[test]
+    fn command_completion_considers_all_matches() {
+        let commands = [
+            "bots",
+            "bot_aggression",
+            "bot_a",
+            "bot_b",
+            "bot_c",
+            "ban",
+            "help",
+        ]
+        .map(|name| ChatCommandDto {
+            name: name.to_owned(),
+            usage: format!("/{name}"),
+            description: String::new(),
+        });
+        let complete = |text: &str| command_completion(&command_suggestions(&commands, text), text);
+
+        assert_eq!(command_suggestions(&commands, "/bo").len(), 5);
+        assert_eq!(complete("/bo").as_deref(), Some("/bot"));
+        // More matches than are displayed, not all of which start with `/bot`.
+        assert_eq!(command_suggestions(&commands, "/b").len(), 6);
+        assert_eq!(complete("/b").as_deref(), None);
+        assert_eq!(complete("/bot_ag").as_deref(), Some("/bot_aggression "));
+        assert_eq!(complete("/he").as_deref(), Some("/help "));
+        assert_eq!(complete("/help ").as_deref(), None);
+        assert_eq!(complete("/x").as_deref(), None);
+        assert_eq!(complete("hello").as_deref(), None);
+    }
+
+    #[test]
+    fn parse_direct_resolves_alias() {
+        let players = ["Alice", "Bob"].map(|alias| PlayerDto {
+            alias: PlayerAlias::new_unsanitized(alias),
+            admin: false,
+            moderator: false,
+            player_id: PlayerId::nth_client(alias.len()).unwrap(),
+            team_id: None,
+            authentic: false,
+        });
+        let alice = (players[0].player_id, players[0].alias);
+        let bob = players[1].player_id;
+        let parse = |text| parse_direct(text, &players, Some(bob));
+
+        assert_eq!(parse("/dm alice hi there"), Some((Some(alice), "hi there")));
+        assert_eq!(parse("/dm @Alice"), Some((Some(alice), "")));
+        // Not to self.
+        assert_eq!(parse("/dm bob hi"), Some((None, "hi")));
+        assert_eq!(parse("/dm carol hi"), Some((None, "hi")));
+        assert_eq!(parse("alice hi"), None);
+        assert_eq!(parse("/dmalice"), None);
+    }
 }
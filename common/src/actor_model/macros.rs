@@ -323,6 +323,27 @@ macro_rules! define_world {
                     update
                 }
 
+                /// Like [`Self::get_update`] but for a spectator, who sees the actors that the
+                /// player it's following knows about. Call after getting the followed player's
+                /// update so `following` is up to date.
+                pub fn get_spectator_update(
+                    &self,
+                    knowledge: &mut Knowledge,
+                    following: &Knowledge,
+                ) -> ActorUpdate {
+                    self.get_update(
+                        knowledge,
+                        Visibility {
+                            $(
+                                [<$actor:snake>]: move |_: &Knowledge| {
+                                    Map::keys(&following.[<$actor:snake>])
+                                        .filter(move |&actor_id| Map::contains(&self.[<$actor:snake>], actor_id))
+                                }
+                            ),+
+                        },
+                    )
+                }
+
                 /// Checks if `self == Self::default()` without requiring `PartialEq`.
                 #[allow(unused)]
                 pub fn is_default(&self) -> bool {
@@ -224,6 +224,53 @@ mod singleton_tests {
             println!("client: {client_world:?}");
         }
     }
+
+    #[test]
+    fn spectator() {
+        let mut world = World::default();
+        Map::insert(
+            &mut world.singleton,
+            SingletonId,
+            Singleton::default().into(),
+        );
+        for id in [VISIBLE_ID, OTHER_ID] {
+            Map::insert(&mut world.sector, id, Sector { data: vec![] }.into());
+        }
+
+        let mut client = Knowledge::default();
+        let mut client_world = World::default();
+        let mut spectator = Knowledge::default();
+        let mut spectator_world = World::default();
+
+        for tick in 0..20u32 {
+            world.tick_before_inputs(&mut ());
+            world.tick_after_inputs(&mut ());
+
+            let update = world.get_update(
+                &mut client,
+                Visibility {
+                    singleton: |_: &_| Some(SingletonId),
+                    sector: |_: &_| (tick < 3).then_some(VISIBLE_ID),
+                },
+            );
+            client_world.apply_owned(update, &mut ());
+            let update = world.get_spectator_update(&mut spectator, &client);
+            spectator_world.apply_owned(update, &mut ());
+            world.post_update();
+
+            // The spectator's own keepalive may keep actors a bit longer than the client.
+            if Map::contains(&client_world.sector, VISIBLE_ID) {
+                assert!(Map::contains(&spectator_world.sector, VISIBLE_ID));
+            }
+            assert!(!Map::contains(&spectator_world.sector, OTHER_ID));
+            assert_eq!(
+                singleton!(spectator_world).unwrap().tick,
+                singleton!(client_world).unwrap().tick
+            );
+        }
+        assert!(Map::is_empty(&client_world.sector));
+        assert!(Map::is_empty(&spectator_world.sector));
+    }
 }
 
 #[cfg(test)]
@@ -2,13 +2,39 @@
 // SPDX-License-Identifier: LGPL-3.0-or-later
 
 use super::phase::LockstepPhaseInner;
+use super::transfer::LockstepIncomingTransfer;
 use super::{
     Lockstep, LockstepInputId, LockstepInputQueue, LockstepInputWindow, LockstepRequest,
     LockstepTick, LockstepUpdate, LockstepWorld,
 };
+use crate::bitcode::DecodeOwned;
 use crate::lockstep::phase::LockstepPhase;
 use crate::{ArenaMap, PlayerId};
 use heapless::HistoryBuffer;
+use std::collections::VecDeque;
+use std::fmt::{self, Display, Formatter};
+
+/// A desync between client and server, found by comparing the client's `real` state to a server
+/// snapshot. Games should report it via `ClientContext::report_desync`, see
+/// [`LockstepClient::take_desync`].
+#[derive(Clone, Debug)]
+pub struct LockstepDesync {
+    /// Tick at which the snapshot was compared.
+    pub tick_id: u32,
+    /// Paths of differing fields, e.g. `.world.enemies[3].position`.
+    pub diffs: Vec<String>,
+}
+
+impl Display for LockstepDesync {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "desync at tick {}: ", self.tick_id)?;
+        if self.diffs.is_empty() {
+            write!(f, "no differing fields")
+        } else {
+            write!(f, "{}", self.diffs.join(", "))
+        }
+    }
+}
 
 /// Implements lockstep model on game client.
 pub struct LockstepClient<W: LockstepWorld>
@@ -45,8 +71,23 @@ where
     pub ping_latencies: HistoryBuffer<u32, { W::TPS }>,
     /// Diagnostic.
     pub total_latencies: HistoryBuffer<u32, { W::TPS }>,
+    /// Diagnostic. Number of times `predicted` was re-simulated from `real`.
+    pub repredictions: u32,
+    /// Past `predicted` states, oldest first, one per input in `input_queue`. Only for
+    /// [`LockstepWorld::ROLLBACK`].
+    predicted_history: VecDeque<Lockstep<W>>,
     /// A buffer of `Info` (e.g. sound) events that game hasn't yet consumed.
     pub(crate) info: Vec<W::Info>,
+    /// Checksum mismatched, so request a snapshot in the next [`LockstepRequest`].
+    pub(crate) request_snapshot: bool,
+    /// Updates received since a snapshot was requested, cleared when it arrives.
+    awaiting_snapshot: Option<u32>,
+    /// A keyframe transfer failed, so initialize from a snapshot instead.
+    failed_transfer: Option<PlayerId>,
+    /// Desync that the game hasn't yet consumed.
+    pub(crate) desync: Option<LockstepDesync>,
+    /// Keyframe transfer in progress, see [`LockstepWorld::KEYFRAME_PERIOD`].
+    transfer: Option<LockstepIncomingTransfer<W>>,
 }
 
 impl<W: LockstepWorld + Default> Default for LockstepClient<W>
@@ -69,7 +110,14 @@ where
             server_buffered_inputs: 0,
             ping_latencies: Default::default(),
             total_latencies: Default::default(),
+            repredictions: 0,
+            predicted_history: Default::default(),
             info: Default::default(),
+            request_snapshot: false,
+            awaiting_snapshot: None,
+            failed_transfer: None,
+            desync: None,
+            transfer: None,
         }
     }
 }
@@ -82,20 +130,57 @@ where
     [(); W::TPS]:,
 {
     /// Returns ping latency and total latency.
-    pub fn receive(&mut self, update: LockstepUpdate<W>) -> (Option<u32>, Option<u32>) {
-        if let Some((player_id, initialization)) = update.initialization {
-            self.player_id = Some(player_id);
-            self.real.clone_from(&initialization);
-            self.predicted.clone_from(&initialization);
-            self.predicted_next.clone_from(&initialization);
-            self.interpolated = initialization;
-            // Kludge.
-            //self.input_queue = Default::default();
-
-            if let Some(info) = W::on_complete() {
-                self.info.push(info);
+    pub fn receive(&mut self, update: LockstepUpdate<W>) -> (Option<u32>, Option<u32>)
+    where
+        Lockstep<W>: DecodeOwned,
+        LockstepTick<W>: DecodeOwned,
+    {
+        if let Some(updates) = &mut self.awaiting_snapshot {
+            *updates += 1;
+        }
+        let initialization = if let Some(transfer) = update.transfer {
+            let incoming = self.transfer.get_or_insert_default();
+            let result = match incoming.receive(&transfer) {
+                Ok(false) => {
+                    // Can't apply ticks until we have the keyframe.
+                    incoming.buffer(update.tick);
+                    return (None, None);
+                }
+                Ok(true) => self.transfer.take().unwrap().finish(transfer.keyframe_len),
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(lockstep) => Some((transfer.player_id, lockstep)),
+                Err(e) => {
+                    self.transfer_failed(transfer.player_id, e);
+                    return (None, None);
+                }
+            }
+        } else if let Some(incoming) = self.transfer.take()
+            && update.initialization.is_none()
+            && let Some(player_id) = incoming.player_id
+        {
+            self.transfer_failed(player_id, "missed last chunk");
+            return (None, None);
+        } else {
+            update.initialization
+        };
+        if let Some((player_id, initialization)) = initialization {
+            self.initialize(player_id, initialization);
+        }
+        if let Some(snapshot) = update.snapshot {
+            if let Some(player_id) = self.failed_transfer.take() {
+                self.request_snapshot = false;
+                self.awaiting_snapshot = None;
+                self.initialize(player_id, snapshot);
+            } else {
+                self.resync(snapshot);
             }
         }
+        if !self.loaded() {
+            // Waiting for a snapshot after a failed transfer.
+            return (None, None);
+        }
         self.server_buffered_inputs = update.buffered_inputs;
         let (ping_latency, total_latency) = self.tick(
             update.tick,
@@ -113,6 +198,46 @@ where
         (ping_latency, total_latency)
     }
 
+    /// Adopts a complete state from the server.
+    fn initialize(&mut self, player_id: PlayerId, initialization: Lockstep<W>) {
+        self.player_id = Some(player_id);
+        self.real.clone_from(&initialization);
+        self.predicted.clone_from(&initialization);
+        self.predicted_next.clone_from(&initialization);
+        self.interpolated = initialization;
+        self.predicted_history.clear();
+        self.failed_transfer = None;
+        // Kludge.
+        //self.input_queue = Default::default();
+
+        if let Some(info) = W::on_complete() {
+            self.info.push(info);
+        }
+    }
+
+    /// Gives up on a keyframe transfer and initializes from a snapshot instead.
+    fn transfer_failed(&mut self, player_id: PlayerId, _error: &'static str) {
+        #[cfg(feature = "log")]
+        log::error!("lockstep transfer failed: {_error}");
+        self.transfer = None;
+        // Don't apply ticks to a stale state, e.g. from before reconnecting.
+        self.player_id = None;
+        self.failed_transfer = Some(player_id);
+        self.want_snapshot();
+    }
+
+    /// Requests a snapshot in the next [`LockstepRequest`], unless one was requested recently.
+    fn want_snapshot(&mut self) {
+        // Ask again in case the request was lost.
+        if self
+            .awaiting_snapshot
+            .map_or(true, |updates| updates > W::TPS as u32)
+        {
+            self.awaiting_snapshot = Some(0);
+            self.request_snapshot = true;
+        }
+    }
+
     /// Advances the real world by one `tick` from the server. Also corrects any miss predictions
     /// we made with [`Self::tick_predicted`]. Called by `receive`
     ///
@@ -131,21 +256,29 @@ where
             self.input_queue.end
         );
 
+        #[allow(unused_mut)]
+        let mut tick = tick;
         if let Some(checksum) = tick.checksum {
             let real = self.real.checksum();
             if real != checksum {
-                #[cfg(feature = "desync")]
-                {
-                    log::warn!("client = {:?}", self.real);
-                    log::warn!("server = {:?}", tick.complete);
-                }
                 #[cfg(feature = "log")]
                 log::error!("desync {real} {checksum}");
-                panic!("desync {real} {checksum}");
+                // Server state is already here, so no need to wait for a snapshot.
+                #[cfg(feature = "desync")]
+                let complete = tick.complete.take();
+                #[cfg(not(feature = "desync"))]
+                let complete: Option<Lockstep<W>> = None;
+                if let Some(complete) = complete {
+                    self.awaiting_snapshot = Some(0);
+                    self.resync(complete);
+                } else {
+                    self.want_snapshot();
+                }
             }
-            assert_eq!(real, checksum, "desync");
         }
+        let unacknowledged = self.input_queue.len();
         self.input_queue.acknowledged(last_applied_id);
+        let acknowledged = unacknowledged - self.input_queue.len();
         self.heard_from_server = true;
         self.smoothed_normalized_ticks_since_real -= 1.0;
 
@@ -165,6 +298,56 @@ where
         );
 
         // Prediction.
+        if !self.prediction_confirmed(acknowledged) {
+            self.repredict();
+        }
+
+        // Prediction next.
+        self.predicted_next = self.predicted.clone();
+        let _old_predicted_next =
+            std::mem::replace(&mut self.predicted_next, self.predicted.clone());
+        Self::predict(
+            &mut self.predicted_next,
+            self.player_id,
+            true,
+            None,
+            &mut |_| {},
+        );
+
+        // Interpolation.
+        // TODO: need to recalculate time since prediction.
+        self.update_interpolated();
+
+        // 0 is used when server is missing a command, may be too high if we sent to the old server
+        // while on the new server during server switching.
+        (
+            (last_received_id != 0).then(|| self.input_queue.latency(last_received_id)),
+            (last_applied_id != 0).then(|| self.input_queue.latency(last_applied_id)),
+        )
+    }
+
+    /// If [`LockstepWorld::ROLLBACK`], returns whether the server's tick, now applied to `real`,
+    /// matches the oldest predicted tick. If so, the newer predicted ticks are still valid.
+    ///
+    /// Otherwise, the first mispredicted tick is the one just applied, because older ticks were
+    /// confirmed by earlier calls, so `real` is the most recent state to roll back to.
+    fn prediction_confirmed(&mut self, acknowledged: usize) -> bool {
+        if !W::ROLLBACK {
+            return false;
+        }
+        // The server must have applied exactly our oldest predicted input.
+        if acknowledged != 1 || self.predicted_history.len() != self.input_queue.len() + 1 {
+            return false;
+        }
+        let oldest = self.predicted_history.pop_front().unwrap();
+        oldest.context.tick_id == self.real.context.tick_id
+            && oldest.checksum() == self.real.checksum()
+    }
+
+    /// Rolls back `predicted` to `real` and re-simulates the `input_queue`.
+    fn repredict(&mut self) {
+        self.repredictions = self.repredictions.wrapping_add(1);
+        self.predicted_history.clear();
         let old_predicted = std::mem::replace(&mut self.predicted, self.real.clone());
         for c in self.input_queue.iter() {
             Self::predict(
@@ -174,6 +357,9 @@ where
                 Some(c),
                 &mut |_| {},
             );
+            if W::ROLLBACK {
+                self.predicted_history.push_back(self.predicted.clone());
+            }
         }
         if W::INTERPOLATE_PREDICTION {
             let old_predicted =
@@ -190,29 +376,28 @@ where
                 },
             );
         }
+    }
 
-        // Prediction next.
-        self.predicted_next = self.predicted.clone();
-        let _old_predicted_next =
-            std::mem::replace(&mut self.predicted_next, self.predicted.clone());
-        Self::predict(
-            &mut self.predicted_next,
-            self.player_id,
-            true,
-            None,
-            &mut |_| {},
-        );
-
-        // Interpolation.
-        // TODO: need to recalculate time since prediction.
-        self.update_interpolated();
+    /// Diffs `real` against the server's `snapshot` if a desync was detected, then adopts the
+    /// snapshot. The prediction is fixed by the next tick.
+    fn resync(&mut self, snapshot: Lockstep<W>) {
+        self.request_snapshot = false;
+        if self.awaiting_snapshot.take().is_some() {
+            let desync = LockstepDesync {
+                tick_id: snapshot.context.tick_id,
+                diffs: self.real.diff(&snapshot),
+            };
+            #[cfg(feature = "log")]
+            log::error!("{desync}");
+            self.desync = Some(desync);
+        }
+        self.real = snapshot;
+    }
 
-        // 0 is used when server is missing a command, may be too high if we sent to the old server
-        // while on the new server during server switching.
-        (
-            (last_received_id != 0).then(|| self.input_queue.latency(last_received_id)),
-            (last_applied_id != 0).then(|| self.input_queue.latency(last_applied_id)),
-        )
+    /// Returns the most recent desync, if any, so the game can report it. Call every frame, e.g.
+    /// `if let Some(desync) = lockstep.take_desync() { context.report_desync(&desync) }`.
+    pub fn take_desync(&mut self) -> Option<LockstepDesync> {
+        self.desync.take()
     }
 
     fn update_interpolated(&mut self) {
@@ -243,6 +428,20 @@ where
         W: std::fmt::Debug,
     {
         if !self.loaded() {
+            if std::mem::take(&mut self.request_snapshot) {
+                // No inputs yet, so only ask for a snapshot.
+                let inputs = LockstepInputWindow {
+                    sliding_window: Default::default(),
+                    last_input_id: 0,
+                };
+                send_with_reliable(
+                    LockstepRequest {
+                        inputs,
+                        snapshot: true,
+                    },
+                    true,
+                );
+            }
             return self.info.drain(..);
         }
         let server_buffer_usage = self.server_buffer_usage();
@@ -266,7 +465,9 @@ where
             let input = input(true);
 
             if let Ok(inputs) = self.tick_predicted(input, supports_unreliable) {
-                send_with_reliable(LockstepRequest { inputs }, false);
+                // Only ask once, the server sends one snapshot per request.
+                let snapshot = std::mem::take(&mut self.request_snapshot);
+                send_with_reliable(LockstepRequest { inputs, snapshot }, false);
             } else {
                 //#[cfg(feature = "log")]
                 //log::warn!("unable to predict");
@@ -384,6 +585,8 @@ where
                 // command instead, the buffer may never empty causing a deadlock.
                 // The oldest command is most likely to have been dropped, so we delete it.
                 self.input_queue.pop_front();
+                // Newer predictions assumed the server would apply it.
+                self.predicted_history.clear();
                 #[cfg(feature = "log")]
                 log::warn!("full queue, popped front");
             } else {
@@ -407,6 +610,9 @@ where
                 }
             },
         );
+        if W::ROLLBACK {
+            self.predicted_history.push_back(self.predicted.clone());
+        }
         Ok(self.input_queue.push_back(input, unreliable))
     }
 
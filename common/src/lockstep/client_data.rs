@@ -1,6 +1,7 @@
 // SPDX-FileCopyrightText: 2024 Softbear, Inc.
 // SPDX-License-Identifier: LGPL-3.0-or-later
 
+use super::transfer::LockstepOutgoingTransfer;
 use super::{LockstepInput, LockstepInputId, LockstepWorld};
 use arrayvec::ArrayVec;
 use std::fmt::{self, Debug, Formatter};
@@ -18,6 +19,10 @@ where
     pub last_received_command_id: LockstepInputId,
     /// Client inputs received by server but not yet applied.
     pub receive_buffer: ArrayVec<LockstepInput<W::Input>, { W::BUFFERED_TICKS }>,
+    /// Client requested a snapshot to diagnose a desync.
+    pub snapshot_requested: bool,
+    /// Keyframe transfer in progress.
+    pub(crate) transfer: Option<LockstepOutgoingTransfer>,
 }
 
 impl<W: LockstepWorld> Default for LockstepClientData<W>
@@ -30,6 +35,8 @@ where
             last_applied_command_id: Default::default(),
             last_received_command_id: Default::default(),
             receive_buffer: Default::default(),
+            snapshot_requested: false,
+            transfer: None,
         }
     }
 }
@@ -45,12 +52,16 @@ where
             last_applied_command_id,
             last_received_command_id,
             receive_buffer,
+            snapshot_requested,
+            transfer,
         } = self;
         f.debug_struct("LockstepClientData")
             .field("initialized", initialized)
             .field("last_applied_command_id", last_applied_command_id)
             .field("last_received_command_id", last_received_command_id)
             .field("receive_buffer", receive_buffer)
+            .field("snapshot_requested", snapshot_requested)
+            .field("transfer", transfer)
             .finish()
     }
 }
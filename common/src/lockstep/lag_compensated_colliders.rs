@@ -0,0 +1,172 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+use super::{LagCompensation, LockstepWorld};
+use crate::actor_model::{Map, SectorId2d, SectorMap2d};
+use crate::{Circle, Collider2d, Ray};
+use glam::{Vec2, Vec3Swizzles};
+
+/// Colliders of one tick, indexed by the sector containing their center.
+#[derive(Clone)]
+struct ColliderSnapshot<K, const WIDTH: usize, const HEIGHT: usize, const SCALE: u16> {
+    tick_id: u32,
+    /// Largest [`Collider2d::bounding_radius`], by which queries are expanded.
+    max_radius: f32,
+    sectors: SectorMap2d<Vec<(K, Collider2d)>, WIDTH, HEIGHT, SCALE>,
+}
+
+impl<K: Copy, const WIDTH: usize, const HEIGHT: usize, const SCALE: u16>
+    ColliderSnapshot<K, WIDTH, HEIGHT, SCALE>
+{
+    fn iter_rect(
+        &self,
+        bottom_left: Vec2,
+        top_right: Vec2,
+    ) -> impl Iterator<Item = (K, &Collider2d)> + '_ {
+        let start =
+            SectorId2d::<WIDTH, HEIGHT, SCALE>::saturating_from(bottom_left - self.max_radius);
+        let end = SectorId2d::<WIDTH, HEIGHT, SCALE>::saturating_from(top_right + self.max_radius);
+        SectorId2d::iter(start, end)
+            .filter_map(|sector_id| self.sectors.get(sector_id))
+            .flatten()
+            .map(|(key, collider)| (*key, collider))
+    }
+}
+
+/// Remembers where actors (e.g. players) were over the last [`LockstepWorld::LAG_COMPENSATION`]
+/// ticks, so the server can answer hit queries as seen by a player at their latency, instead of
+/// every game rewinding positions itself.
+///
+/// Record every actor's collider each tick with [`Self::record`], then query with the tick and
+/// latency of the shooter.
+#[derive(Clone)]
+pub struct LagCompensatedColliders<
+    K,
+    W: LockstepWorld,
+    const WIDTH: usize,
+    const HEIGHT: usize,
+    const SCALE: u16,
+> where
+    [(); W::LAG_COMPENSATION]:,
+{
+    snapshots: LagCompensation<ColliderSnapshot<K, WIDTH, HEIGHT, SCALE>, W>,
+}
+
+impl<K, W: LockstepWorld, const WIDTH: usize, const HEIGHT: usize, const SCALE: u16> Default
+    for LagCompensatedColliders<K, W, WIDTH, HEIGHT, SCALE>
+where
+    [(); W::LAG_COMPENSATION]:,
+{
+    fn default() -> Self {
+        Self {
+            snapshots: Default::default(),
+        }
+    }
+}
+
+impl<K: Copy, W: LockstepWorld, const WIDTH: usize, const HEIGHT: usize, const SCALE: u16>
+    LagCompensatedColliders<K, W, WIDTH, HEIGHT, SCALE>
+where
+    [(); W::LAG_COMPENSATION]:,
+{
+    /// Records the colliders of all actors at `tick_id`, replacing those recorded
+    /// [`LockstepWorld::LAG_COMPENSATION`] ticks ago. Colliders outside the map are clamped to the
+    /// nearest sector.
+    pub fn record(&mut self, tick_id: u32, colliders: impl IntoIterator<Item = (K, Collider2d)>) {
+        let snapshot = self
+            .snapshots
+            .slot_mut(tick_id)
+            .get_or_insert_with(|| ColliderSnapshot {
+                tick_id,
+                max_radius: 0.0,
+                sectors: Default::default(),
+            });
+        snapshot.tick_id = tick_id;
+        snapshot.max_radius = 0.0;
+        // Keep allocations.
+        for (_, sector) in snapshot.sectors.iter_mut() {
+            sector.clear();
+        }
+        for (key, collider) in colliders {
+            snapshot.max_radius = snapshot.max_radius.max(collider.bounding_radius());
+            snapshot
+                .sectors
+                .or_default(SectorId2d::<WIDTH, HEIGHT, SCALE>::saturating_from(
+                    collider.center(),
+                ))
+                .push((key, collider));
+        }
+    }
+
+    fn snapshot(
+        &self,
+        tick_id: u32,
+        latency: u8,
+    ) -> Option<&ColliderSnapshot<K, WIDTH, HEIGHT, SCALE>> {
+        self.snapshots
+            .read(tick_id, latency)
+            .filter(|snapshot| snapshot.tick_id == tick_id.wrapping_sub(latency as u32))
+    }
+
+    /// Returns the colliders that were at `tick_id - latency`, or `None` if that tick wasn't
+    /// recorded (e.g. `latency` exceeds [`LockstepWorld::MAX_LATENCY`]).
+    pub fn iter(
+        &self,
+        tick_id: u32,
+        latency: u8,
+    ) -> Option<impl Iterator<Item = (K, &Collider2d)> + '_> {
+        Some(
+            self.snapshot(tick_id, latency)?
+                .sectors
+                .iter()
+                .flat_map(|(_, sector)| sector.iter().map(|(key, collider)| (*key, collider))),
+        )
+    }
+
+    /// Returns the actors that a `circle` (e.g. an explosion) hit, as seen by a player with
+    /// `latency` at `tick_id`.
+    pub fn query_circle(
+        &self,
+        tick_id: u32,
+        latency: u8,
+        circle: Circle,
+    ) -> impl Iterator<Item = K> + '_ {
+        let query = Collider2d::Circle(circle);
+        self.snapshot(tick_id, latency)
+            .into_iter()
+            .flat_map(move |snapshot| {
+                snapshot.iter_rect(circle.center - circle.radius, circle.center + circle.radius)
+            })
+            .filter(move |(_, collider)| collider.collides(&query))
+            .map(|(key, _)| key)
+    }
+
+    /// Returns the first actor that a `ray` (e.g. a hit-scan bullet) hit within `max_t`, as seen
+    /// by a player with `latency` at `tick_id`, and the `t` at which it was hit (see
+    /// [`Ray::position`]). The ray is projected onto the XY plane.
+    ///
+    /// Optionally `filter` out actors (e.g. the shooter).
+    pub fn raycast(
+        &self,
+        tick_id: u32,
+        latency: u8,
+        ray: Ray,
+        max_t: f32,
+        mut filter: impl FnMut(K) -> bool,
+    ) -> Option<(K, f32)> {
+        let snapshot = self.snapshot(tick_id, latency)?;
+        let origin = ray.origin.xy();
+        let direction = ray.direction.xy();
+        let end = origin + direction * max_t;
+        snapshot
+            .iter_rect(origin.min(end), origin.max(end))
+            .filter(|&(key, _)| filter(key))
+            .filter_map(|(key, collider)| {
+                collider
+                    .raycast(origin, direction)
+                    .filter(|&t| t <= max_t)
+                    .map(|t| (key, t))
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+    }
+}
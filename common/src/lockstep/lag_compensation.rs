@@ -53,6 +53,13 @@ where
         self.lag_compensation[self.index(tick_id)] = Some(value);
     }
 
+    /// The slot for `tick_id`, which may still contain the value written
+    /// [`LockstepWorld::LAG_COMPENSATION`] ticks ago (e.g. to reuse its allocations).
+    pub fn slot_mut(&mut self, tick_id: u32) -> &mut Option<T> {
+        let index = self.index(tick_id);
+        &mut self.lag_compensation[index]
+    }
+
     pub fn read(&self, tick_id: u32, latency: u8) -> Option<&T> {
         if latency > W::MAX_LATENCY {
             return None;
@@ -4,7 +4,7 @@
 use super::phase::LockstepPhase;
 use super::{LockstepContext, LockstepPlayer, LockstepTick};
 use crate::bitcode::{self, *};
-use crate::{ArenaEntry, CompatHasher, PlayerId};
+use crate::{hb_diff_hash, with_path_segment, ArenaEntry, CompatHasher, PlayerId};
 use std::fmt::{self, Debug, Formatter};
 use std::hash::{Hash, Hasher};
 
@@ -80,6 +80,27 @@ pub trait LockstepWorld: Hash + Debug + Clone + Sized {
     ///
     /// Do not use if there are physics discontinuities.
     const INTERPOLATE_PREDICTION: bool = false;
+    /// Server sends a checksum every tick so that clients can detect desyncs, request a snapshot,
+    /// and report which fields differ.
+    const CHECKSUM: bool = true;
+    /// Instead of re-simulating all predicted inputs on every server tick, keep the past
+    /// predicted states and only roll back when the server's state differs from what was
+    /// predicted for that tick.
+    ///
+    /// Costs a clone per predicted tick and a checksum per server tick, which pays off if ticks
+    /// are expensive. Do not use if other players' inputs are usually mispredicted, since they
+    /// would always trigger rollback, or with [`Self::INTERPOLATE_PREDICTION`].
+    const ROLLBACK: bool = false;
+    /// If nonzero, the server keeps a keyframe every this many ticks plus the ticks applied since,
+    /// and transfers them to joining clients over several updates instead of sending a whole
+    /// state at once. Helps large worlds.
+    const KEYFRAME_PERIOD: u32 = 0;
+    /// Maximum bytes of keyframe transfer per update.
+    const TRANSFER_CHUNK: usize = 16384;
+    /// If `Some`, the server keeps a copy of the state this many ticks in the past for
+    /// spectators, see [`LockstepServer::spectator_update`](super::LockstepServer::spectator_update).
+    /// A delay stops spectators from relaying information to players.
+    const SPECTATOR_DELAY: Option<u32> = None;
 
     type Player: Hash + Debug + Clone + Encode + DecodeOwned;
     type Input: Copy + Debug + Hash + Default + Encode + DecodeOwned;
@@ -128,6 +149,12 @@ pub trait LockstepWorld: Hash + Debug + Clone + Sized {
     fn is_predicted(_info: &Self::Info, _my_id: PlayerId) -> bool {
         false
     }
+
+    /// Reports which parts of the world differ, for diagnosing desyncs. If the world derives
+    /// [`HbDiff`](crate::HbDiff), override with it for more detail.
+    fn diff(&self, other: &Self, path: &mut String, on_diff: &mut dyn FnMut(&str)) {
+        hb_diff_hash(self, other, path, on_diff);
+    }
 }
 
 impl<W: LockstepWorld> Lockstep<W>
@@ -149,6 +176,42 @@ where
         h.finish() as u32
     }
 
+    /// Returns the paths of all fields that differ between `self` and `other`.
+    pub(crate) fn diff(&self, other: &Self) -> Vec<String> {
+        let mut diffs = Vec::new();
+        let mut on_diff = |path: &str| diffs.push(path.to_owned());
+        let mut path = String::new();
+        if self.context.tick_id != other.context.tick_id {
+            on_diff(".context.tick_id");
+        }
+        with_path_segment(&mut path, ".context.players", |path| {
+            let (a, b) = (&self.context.players, &other.context.players);
+            for (player_id, a) in a.iter() {
+                with_path_segment(path, format_args!("[{player_id:?}]"), |path| {
+                    let Some(b) = b.get(player_id) else {
+                        on_diff(path);
+                        return;
+                    };
+                    with_path_segment(path, ".input", |path| {
+                        hb_diff_hash(&a.input, &b.input, path, &mut on_diff)
+                    });
+                    with_path_segment(path, ".inner", |path| {
+                        hb_diff_hash(&a.inner, &b.inner, path, &mut on_diff)
+                    });
+                });
+            }
+            for (player_id, _) in b.iter() {
+                if !a.contains(player_id) {
+                    with_path_segment(path, format_args!("[{player_id:?}]"), |path| on_diff(path));
+                }
+            }
+        });
+        with_path_segment(&mut path, ".world", |path| {
+            self.world.diff(&other.world, path, &mut on_diff)
+        });
+        diffs
+    }
+
     pub(crate) fn tick(
         &mut self,
         tick: LockstepTick<W>,
@@ -7,30 +7,38 @@ mod context;
 mod input;
 mod input_queue;
 mod input_window;
+mod lag_compensated_colliders;
 mod lag_compensation;
 mod lockstep;
 mod phase;
 mod player;
 mod player_peers;
+mod replay;
 mod request;
 mod server;
+mod spectator;
 #[cfg(test)]
 mod tests;
 mod tick;
+mod transfer;
 mod update;
 
-pub use client::LockstepClient;
+pub use client::{LockstepClient, LockstepDesync};
 pub use client_data::LockstepClientData;
 pub use context::LockstepContext;
 pub use input::{LockstepInput, LockstepInputId};
 pub use input_queue::LockstepInputQueue;
 pub use input_window::LockstepInputWindow;
+pub use lag_compensated_colliders::LagCompensatedColliders;
 pub use lag_compensation::LagCompensation;
 pub use lockstep::{Lockstep, LockstepWorld};
 pub use phase::LockstepPhase;
 pub use player::LockstepPlayer;
 pub use player_peers::LockstepPeers;
+pub use replay::{LockstepRecorder, LockstepReplay, LockstepReplayError};
 pub use request::LockstepRequest;
 pub use server::{lockstep_get, lockstep_mut, LockstepServer};
+pub use spectator::{LockstepSpectator, LockstepSpectatorData, LockstepSpectatorUpdate};
 pub use tick::LockstepTick;
+pub use transfer::LockstepTransfer;
 pub use update::LockstepUpdate;
@@ -0,0 +1,206 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+use super::phase::{LockstepPhase, LockstepPhaseInner};
+use super::{Lockstep, LockstepServer, LockstepTick, LockstepWorld};
+use crate::bitcode::{self, *};
+use std::io::{self, Read, Write};
+use std::marker::PhantomData;
+
+/// Length of the little-endian frame length prefix.
+const FRAME_HEADER: usize = std::mem::size_of::<u32>();
+/// Largest frame that will be written or read, so a corrupt length can't exhaust memory.
+const MAX_FRAME: usize = 1 << 28;
+
+/// Records a [`LockstepServer`] to an output (e.g. a file) so that it can be played back
+/// offline by [`LockstepReplay`].
+///
+/// Format is a sequence of length-prefixed bitcode frames. The first frame is the initial
+/// [`Lockstep`], and each subsequent frame is a [`LockstepTick`] applied to it.
+pub struct LockstepRecorder<W: LockstepWorld, O: Write>
+where
+    [(); W::LAG_COMPENSATION]:,
+{
+    output: O,
+    ticks: u32,
+    _spooky: PhantomData<W>,
+}
+
+impl<W: LockstepWorld, O: Write> LockstepRecorder<W, O>
+where
+    [(); W::LAG_COMPENSATION]:,
+    Lockstep<W>: Encode,
+    LockstepTick<W>: Encode,
+{
+    /// Starts recording from the `initial` state, which must be the state the first recorded tick
+    /// applies to.
+    pub fn new(mut output: O, initial: &Lockstep<W>) -> io::Result<Self> {
+        write_frame(&mut output, &bitcode::encode(initial))?;
+        Ok(Self {
+            output,
+            ticks: 0,
+            _spooky: PhantomData,
+        })
+    }
+
+    /// Records the pending tick of `server`. Call after [`LockstepServer::update`] and before
+    /// [`LockstepServer::post_update`], so the tick contains its checksum.
+    pub fn record_server(&mut self, server: &LockstepServer<W>) -> io::Result<()>
+    where
+        [(); W::MAX_PREDICTION]:,
+        [(); W::INPUTS_PER_EFFICIENT_PACKET]:,
+        [(); W::BUFFERED_TICKS]:,
+    {
+        self.record(&server.current)
+    }
+
+    /// Records a tick that is about to be applied.
+    pub fn record(&mut self, tick: &LockstepTick<W>) -> io::Result<()> {
+        write_frame(&mut self.output, &bitcode::encode(tick))?;
+        self.ticks += 1;
+        Ok(())
+    }
+
+    /// Number of ticks recorded so far.
+    pub fn ticks(&self) -> u32 {
+        self.ticks
+    }
+
+    /// Flushes and returns the output.
+    pub fn finish(mut self) -> io::Result<O> {
+        self.output.flush()?;
+        Ok(self.output)
+    }
+}
+
+#[derive(Debug, strum::Display)]
+pub enum LockstepReplayError {
+    #[strum(to_string = "io error: {0}")]
+    Io(io::Error),
+    #[strum(to_string = "decode error: {0}")]
+    Decode(bitcode::Error),
+    #[strum(to_string = "desync at tick {tick_id}: replayed {replayed} recorded {recorded}")]
+    Desync {
+        tick_id: u32,
+        replayed: u32,
+        recorded: u32,
+    },
+}
+
+impl From<io::Error> for LockstepReplayError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<bitcode::Error> for LockstepReplayError {
+    fn from(e: bitcode::Error) -> Self {
+        Self::Decode(e)
+    }
+}
+
+/// Plays back a recording made by [`LockstepRecorder`], re-running [`LockstepWorld::tick`] and
+/// verifying the checksum of every tick that has one.
+pub struct LockstepReplay<W: LockstepWorld, I: Read>
+where
+    [(); W::LAG_COMPENSATION]:,
+{
+    /// State after the most recently replayed tick.
+    pub real: Lockstep<W>,
+    input: I,
+    buffer: Vec<u8>,
+}
+
+impl<W: LockstepWorld, I: Read> LockstepReplay<W, I>
+where
+    [(); W::LAG_COMPENSATION]:,
+    Lockstep<W>: DecodeOwned,
+    LockstepTick<W>: DecodeOwned,
+{
+    /// Reads the initial state from `input`.
+    pub fn new(mut input: I) -> Result<Self, LockstepReplayError> {
+        let mut buffer = Vec::new();
+        if !read_frame(&mut input, &mut buffer)? {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        let real = bitcode::decode(&buffer)?;
+        Ok(Self {
+            real,
+            input,
+            buffer,
+        })
+    }
+
+    /// Replays one tick, returning `Ok(false)` at the end of the recording.
+    pub fn step(&mut self, on_info: &mut dyn FnMut(W::Info)) -> Result<bool, LockstepReplayError> {
+        if !read_frame(&mut self.input, &mut self.buffer)? {
+            return Ok(false);
+        }
+        let tick: LockstepTick<W> = bitcode::decode(&self.buffer)?;
+        if let Some(recorded) = tick.checksum {
+            let replayed = self.real.checksum();
+            if replayed != recorded {
+                return Err(LockstepReplayError::Desync {
+                    tick_id: self.real.context.tick_id,
+                    replayed,
+                    recorded,
+                });
+            }
+        }
+        self.real.tick(
+            tick,
+            &LockstepPhase {
+                inner: LockstepPhaseInner::GroundTruth,
+            },
+            on_info,
+        );
+        Ok(true)
+    }
+
+    /// Replays all remaining ticks, returning how many were replayed.
+    pub fn play(&mut self, on_info: &mut dyn FnMut(W::Info)) -> Result<u32, LockstepReplayError> {
+        let mut ticks = 0;
+        while self.step(on_info)? {
+            ticks += 1;
+        }
+        Ok(ticks)
+    }
+}
+
+fn write_frame(output: &mut impl Write, frame: &[u8]) -> io::Result<()> {
+    if frame.len() > MAX_FRAME {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "frame too large",
+        ));
+    }
+    let len = frame.len() as u32;
+    output.write_all(&len.to_le_bytes())?;
+    output.write_all(frame)
+}
+
+/// Returns `Ok(false)` if the input ended cleanly between frames.
+fn read_frame(input: &mut impl Read, buffer: &mut Vec<u8>) -> io::Result<bool> {
+    let mut header = [0u8; FRAME_HEADER];
+    let mut read = 0;
+    while read < FRAME_HEADER {
+        match input.read(&mut header[read..]) {
+            Ok(0) if read == 0 => return Ok(false),
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(n) => read += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    let len = u32::from_le_bytes(header) as usize;
+    if len > MAX_FRAME {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "frame too large",
+        ));
+    }
+    buffer.clear();
+    buffer.resize(len, 0);
+    input.read_exact(buffer)?;
+    Ok(true)
+}
@@ -12,6 +12,8 @@ where
     [(); W::INPUTS_PER_EFFICIENT_PACKET]:,
 {
     pub inputs: LockstepInputWindow<W>,
+    /// Client detected a desync and wants a snapshot of the server's state to diagnose it.
+    pub snapshot: bool,
 }
 
 impl<W: LockstepWorld> LockstepRequest<W>
@@ -24,6 +26,7 @@ where
                 inner: input,
                 input_id: 0,
             }),
+            snapshot: false,
         }
     }
 }
@@ -34,9 +37,10 @@ where
     W::Input: Debug,
 {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        let Self { inputs } = self;
+        let Self { inputs, snapshot } = self;
         f.debug_struct("LockstepRequest")
             .field("inputs", &inputs)
+            .field("snapshot", snapshot)
             .finish()
     }
 }
@@ -2,10 +2,13 @@
 // SPDX-License-Identifier: LGPL-3.0-or-later
 
 use super::phase::{LockstepPhase, LockstepPhaseInner};
+use super::spectator::LockstepDelayed;
+use super::transfer::LockstepKeyframe;
 use super::{
-    Lockstep, LockstepClientData, LockstepInput, LockstepRequest, LockstepTick, LockstepUpdate,
-    LockstepWorld,
+    Lockstep, LockstepClientData, LockstepInput, LockstepRequest, LockstepSpectatorData,
+    LockstepSpectatorUpdate, LockstepTick, LockstepUpdate, LockstepWorld,
 };
+use crate::bitcode::Encode;
 use crate::{ArenaKey, ArenaMap, PlayerId};
 use std::collections::btree_map::Entry;
 use std::collections::BTreeMap;
@@ -21,6 +24,10 @@ where
     pub real: Lockstep<W>,
     /// Pending inputs not yet applied to `real`
     pub current: LockstepTick<W>,
+    /// See [`LockstepWorld::KEYFRAME_PERIOD`].
+    keyframe: Option<LockstepKeyframe<W>>,
+    /// See [`LockstepWorld::SPECTATOR_DELAY`].
+    delayed: Option<LockstepDelayed<W>>,
 }
 
 impl<W: LockstepWorld + Default> Default for LockstepServer<W>
@@ -54,7 +61,12 @@ where
     W::Tick: Debug,
 {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        let Self { real, current } = self;
+        let Self {
+            real,
+            current,
+            keyframe: _,
+            delayed: _,
+        } = self;
         f.debug_struct("LockstepServer")
             .field("real", real)
             .field("current", current)
@@ -95,9 +107,12 @@ where
     [(); W::BUFFERED_TICKS]:,
 {
     pub fn new(world: W) -> Self {
+        let real = Lockstep::new(world);
         Self {
-            real: Lockstep::new(world),
+            delayed: W::SPECTATOR_DELAY.map(|_| LockstepDelayed::new(&real)),
+            real,
             current: Default::default(),
+            keyframe: None,
         }
     }
 
@@ -150,6 +165,7 @@ where
                 // client already.
                 return;
             }
+            client.snapshot_requested |= request.snapshot;
             for input in request.inputs.into_input_iter() {
                 if !W::is_valid(&input.inner) {
                     debug_assert!(
@@ -223,7 +239,7 @@ where
             self.current.inputs.insert(player_id, inner);
         }
 
-        self.current.checksum = Some(self.checksum());
+        self.current.checksum = W::CHECKSUM.then(|| self.checksum());
         #[cfg(feature = "desync")]
         {
             self.current.complete = Some(self.real.clone());
@@ -234,16 +250,40 @@ where
         &self,
         player_id: PlayerId,
         client_data: &mut LockstepClientData<W>,
-    ) -> LockstepUpdate<W> {
+    ) -> LockstepUpdate<W>
+    where
+        LockstepTick<W>: Encode,
+    {
         assert!(player_id.is_client());
         let initialize = !std::mem::replace(&mut client_data.initialized, true);
+        let mut initialization = None;
+        if initialize {
+            client_data.last_applied_command_id = 0;
+            // New.
+            client_data.last_received_command_id = 0;
+            if let Some(keyframe) = &self.keyframe {
+                client_data.transfer = Some(keyframe.outgoing());
+            } else {
+                initialization = Some((player_id, self.real.clone()));
+            }
+        }
+        let transfer = if let Some(outgoing) = &mut client_data.transfer {
+            let (chunk, done) = outgoing.next_chunk(player_id, W::TRANSFER_CHUNK);
+            if done {
+                client_data.transfer = None;
+            }
+            Some(chunk)
+        } else {
+            None
+        };
         LockstepUpdate {
-            initialization: initialize.then(|| {
-                client_data.last_applied_command_id = 0;
-                // New.
-                client_data.last_received_command_id = 0;
-                (player_id, self.real.clone())
-            }),
+            // Keep the request until a snapshot can be sent.
+            snapshot: (initialization.is_none()
+                && transfer.is_none()
+                && std::mem::take(&mut client_data.snapshot_requested))
+            .then(|| self.real.clone()),
+            initialization,
+            transfer,
             last_applied_input_id: client_data.last_applied_command_id,
             last_received_input_id: client_data.last_received_command_id,
             tick: self.current.clone(),
@@ -251,7 +291,42 @@ where
         }
     }
 
-    pub fn post_update(&mut self, on_info: &mut dyn FnMut(W::Info)) {
+    /// Like [`Self::client_update`] but for a spectator, who has no player and is
+    /// [`LockstepWorld::SPECTATOR_DELAY`] ticks behind. Returns `None` if spectating is disabled
+    /// or there is nothing to send.
+    pub fn spectator_update(
+        &self,
+        spectator_data: &mut LockstepSpectatorData,
+    ) -> Option<LockstepSpectatorUpdate<W>> {
+        let delay = W::SPECTATOR_DELAY?;
+        let delayed = self.delayed.as_ref()?;
+        let initialization = (!std::mem::replace(&mut spectator_data.initialized, true))
+            .then(|| delayed.real.clone());
+        let tick = delayed.next(&self.current, delay).cloned();
+        (initialization.is_some() || tick.is_some()).then_some(LockstepSpectatorUpdate {
+            initialization,
+            tick,
+        })
+    }
+
+    pub fn post_update(&mut self, on_info: &mut dyn FnMut(W::Info))
+    where
+        Lockstep<W>: Encode,
+        LockstepTick<W>: Encode,
+    {
+        if W::KEYFRAME_PERIOD != 0 {
+            if self.keyframe.is_none() || self.real.context.tick_id % W::KEYFRAME_PERIOD == 0 {
+                self.keyframe = Some(LockstepKeyframe::new(&self.real));
+            }
+            if let Some(keyframe) = &mut self.keyframe {
+                keyframe.push(&self.current);
+            }
+        }
+        if let Some(delay) = W::SPECTATOR_DELAY
+            && let Some(delayed) = &mut self.delayed
+        {
+            delayed.push(&self.current, delay);
+        }
         self.real.tick(
             std::mem::take(&mut self.current),
             &LockstepPhase {
@@ -0,0 +1,212 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+use super::phase::{LockstepPhase, LockstepPhaseInner};
+use super::{Lockstep, LockstepPlayer, LockstepTick, LockstepWorld};
+use crate::bitcode::{self, *};
+use crate::PlayerId;
+use std::collections::VecDeque;
+use std::fmt::{self, Debug, Formatter};
+
+/// Message sent from lockstep model on game server to a spectator, see
+/// [`LockstepWorld::SPECTATOR_DELAY`].
+#[derive(Clone, Encode, Decode)]
+pub struct LockstepSpectatorUpdate<W: LockstepWorld>
+where
+    [(); W::LAG_COMPENSATION]:,
+{
+    /// Delayed state, sent when the spectator starts watching.
+    pub initialization: Option<Lockstep<W>>,
+    /// Tick to apply to the delayed state. `None` until the delay has elapsed.
+    pub tick: Option<LockstepTick<W>>,
+}
+
+impl<W: LockstepWorld + Debug> Debug for LockstepSpectatorUpdate<W>
+where
+    [(); W::LAG_COMPENSATION]:,
+    W::Player: Debug,
+    W::Input: Debug,
+    W::Tick: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let Self {
+            initialization,
+            tick,
+        } = self;
+        f.debug_struct("LockstepSpectatorUpdate")
+            .field("initialization", &initialization)
+            .field("tick", &tick)
+            .finish()
+    }
+}
+
+/// The data about a spectator that is known by the server.
+#[derive(Debug, Default)]
+pub struct LockstepSpectatorData {
+    pub initialized: bool,
+}
+
+/// The server state [`LockstepWorld::SPECTATOR_DELAY`] ticks ago, and the ticks applied since.
+pub(crate) struct LockstepDelayed<W: LockstepWorld>
+where
+    [(); W::LAG_COMPENSATION]:,
+{
+    pub(crate) real: Lockstep<W>,
+    ticks: VecDeque<LockstepTick<W>>,
+}
+
+impl<W: LockstepWorld> LockstepDelayed<W>
+where
+    [(); W::LAG_COMPENSATION]:,
+{
+    pub(crate) fn new(real: &Lockstep<W>) -> Self {
+        Self {
+            real: real.clone(),
+            ticks: VecDeque::new(),
+        }
+    }
+
+    /// The tick that the next [`Self::push`] of `current` will apply to `real`, if any.
+    pub(crate) fn next<'a>(
+        &'a self,
+        current: &'a LockstepTick<W>,
+        delay: u32,
+    ) -> Option<&'a LockstepTick<W>> {
+        (self.ticks.len() >= delay as usize).then(|| self.ticks.front().unwrap_or(current))
+    }
+
+    /// Buffers a tick applied to the server state, applying any that are old enough.
+    pub(crate) fn push(&mut self, tick: &LockstepTick<W>, delay: u32) {
+        #[allow(unused_mut)]
+        let mut tick = tick.clone();
+        // Spectators don't diagnose desyncs.
+        #[cfg(feature = "desync")]
+        {
+            tick.complete = None;
+        }
+        self.ticks.push_back(tick);
+        while self.ticks.len() > delay as usize {
+            let tick = self.ticks.pop_front().unwrap();
+            self.real.tick(
+                tick,
+                &LockstepPhase {
+                    inner: LockstepPhaseInner::GroundTruth,
+                },
+                &mut |_| {},
+            );
+        }
+    }
+}
+
+/// Implements lockstep model on a spectating game client, which has no player and sends no
+/// inputs. Since there is nothing to predict, it simply applies delayed ticks from the server.
+pub struct LockstepSpectator<W: LockstepWorld>
+where
+    [(); W::LAG_COMPENSATION]:,
+{
+    /// A delayed server state. `None` before initialization is received.
+    pub real: Option<Lockstep<W>>,
+    /// The player the camera follows.
+    follow: Option<PlayerId>,
+    /// A buffer of `Info` (e.g. sound) events that game hasn't yet consumed.
+    info: Vec<W::Info>,
+}
+
+impl<W: LockstepWorld> Default for LockstepSpectator<W>
+where
+    [(); W::LAG_COMPENSATION]:,
+{
+    fn default() -> Self {
+        Self {
+            real: None,
+            follow: None,
+            info: Vec::new(),
+        }
+    }
+}
+
+impl<W: LockstepWorld> LockstepSpectator<W>
+where
+    [(); W::LAG_COMPENSATION]:,
+{
+    pub fn receive(&mut self, update: LockstepSpectatorUpdate<W>) {
+        if let Some(initialization) = update.initialization {
+            self.real = Some(initialization);
+            if let Some(info) = W::on_complete() {
+                self.info.push(info);
+            }
+        }
+        if let Some(real) = &mut self.real
+            && let Some(tick) = update.tick
+        {
+            if let Some(checksum) = tick.checksum
+                && checksum != real.checksum()
+            {
+                // No inputs, so there is nothing to diagnose on the server's end.
+                #[cfg(feature = "log")]
+                log::error!("spectator desync {} {checksum}", real.checksum());
+            }
+            let info = &mut self.info;
+            real.tick(
+                tick,
+                &LockstepPhase {
+                    inner: LockstepPhaseInner::GroundTruth,
+                },
+                &mut |i| info.push(i),
+            );
+        }
+        if self.follow.is_some() && self.followed().is_none() {
+            // Followed player left.
+            self.follow(None);
+        }
+    }
+
+    /// Returns `Info` (e.g. sound) events since the last call.
+    pub fn update(&mut self) -> impl Iterator<Item = W::Info> + '_ {
+        self.info.drain(..)
+    }
+
+    /// The player the camera follows, if any.
+    pub fn following(&self) -> Option<PlayerId> {
+        self.follow
+    }
+
+    pub fn followed(&self) -> Option<&LockstepPlayer<W>> {
+        self.real.as_ref()?.context.players.get(self.follow?)
+    }
+
+    /// Switches which player the camera follows. `None` means free camera.
+    pub fn follow(&mut self, player_id: Option<PlayerId>) {
+        self.follow = player_id.filter(|&player_id| {
+            self.real
+                .as_ref()
+                .is_some_and(|real| real.context.players.contains(player_id))
+        });
+    }
+
+    /// Follows the next (or previous) player, wrapping around.
+    pub fn follow_next(&mut self, forward: bool) {
+        let Some(real) = &self.real else {
+            return;
+        };
+        let player_ids = real
+            .context
+            .players
+            .iter()
+            .map(|(player_id, _)| player_id)
+            .collect::<Vec<_>>();
+        let next = if forward {
+            player_ids
+                .iter()
+                .find(|&&player_id| self.follow.map_or(true, |follow| player_id > follow))
+                .or(player_ids.first())
+        } else {
+            player_ids
+                .iter()
+                .rev()
+                .find(|&&player_id| self.follow.map_or(true, |follow| player_id < follow))
+                .or(player_ids.last())
+        };
+        self.follow = next.copied();
+    }
+}
@@ -39,8 +39,7 @@ fn lockstep() {
             &mut self,
             _tick: Self::Tick,
             context: &mut crate::lockstep::LockstepContext<Self>,
-            _predicting: Option<kodiak_common::PlayerId>,
-            _interpolation_prediction: bool,
+            _phase: &crate::LockstepPhase,
             _on_info: &mut dyn FnMut(Self::Info),
         ) where
             [(); Self::LAG_COMPENSATION]:,
@@ -52,7 +51,13 @@ fn lockstep() {
             }
         }
 
-        fn lerp_player(player: &Self::Player, next: &Self::Player, t: f32) -> Self::Player {
+        fn lerp_player(
+            _player_id: PlayerId,
+            player: &Self::Player,
+            next: &Self::Player,
+            t: f32,
+            _phase: &crate::LockstepPhase,
+        ) -> Self::Player {
             Player {
                 number: player.number + (next.number - player.number) * t,
                 velocity: player.velocity + (next.velocity - player.velocity) * t,
@@ -82,7 +87,7 @@ fn lockstep() {
             }
             server.update(std::iter::once((player_id, &mut client_data)));
             let client_update = server.client_update(player_id, &mut client_data);
-            server.post_update();
+            server.post_update(&mut |_| {});
             if send_to_client.send(client_update).is_err() {
                 break;
             }
@@ -165,3 +170,540 @@ fn lockstep() {
     server.join().unwrap();
     client.join().unwrap();
 }
+
+#[test]
+fn replay() {
+    use crate::bitcode::{self, *};
+    use crate::{
+        LockstepContext, LockstepPhase, LockstepRecorder, LockstepReplay, LockstepReplayError,
+        LockstepServer, LockstepWorld, PlayerId,
+    };
+    use std::hash::Hash;
+    use std::io::{self, Read};
+
+    #[derive(Clone, Default, Debug, Hash, Encode, Decode)]
+    struct World {
+        sum: u32,
+    }
+    impl LockstepWorld for World {
+        type Input = u8;
+        type Player = u32;
+
+        fn tick(
+            &mut self,
+            _tick: Self::Tick,
+            context: &mut LockstepContext<Self>,
+            _phase: &LockstepPhase,
+            _on_info: &mut dyn FnMut(Self::Info),
+        ) where
+            [(); Self::LAG_COMPENSATION]:,
+        {
+            for (_, player) in context.players.iter_mut() {
+                player.inner += player.input as u32;
+                self.sum += player.inner;
+            }
+        }
+    }
+
+    let player_id = PlayerId::nth_client(0).unwrap();
+    let mut server = LockstepServer::<World>::default();
+    *server.player_mut(player_id) = Some(0);
+    server.post_update(&mut |_| {});
+
+    let mut recorder = LockstepRecorder::new(Vec::new(), &server.real).unwrap();
+    for i in 0..50u8 {
+        server.update(std::iter::empty());
+        server.current.inputs.insert(player_id, i % 3);
+        recorder.record_server(&server).unwrap();
+        server.post_update(&mut |_| {});
+    }
+    assert_eq!(recorder.ticks(), 50);
+    let recording = recorder.finish().unwrap();
+
+    let mut replay = LockstepReplay::<World, _>::new(recording.as_slice()).unwrap();
+    assert_eq!(replay.play(&mut |_| {}).unwrap(), 50);
+    assert_eq!(replay.real.checksum(), server.real.checksum());
+    assert_eq!(replay.real.world.sum, server.real.world.sum);
+
+    // Tamper with the initial state so the first checksum no longer matches.
+    let mut tampered = LockstepServer::<World>::default();
+    *tampered.player_mut(player_id) = Some(1);
+    tampered.post_update(&mut |_| {});
+    let mut corrupt = Vec::new();
+    let initial = bitcode::encode(&tampered.real);
+    corrupt.extend_from_slice(&(initial.len() as u32).to_le_bytes());
+    corrupt.extend_from_slice(&initial);
+    let initial_len = u32::from_le_bytes(recording[..4].try_into().unwrap()) as usize;
+    corrupt.extend_from_slice(&recording[4 + initial_len..]);
+    let mut replay = LockstepReplay::<World, _>::new(corrupt.as_slice()).unwrap();
+    assert!(matches!(
+        replay.play(&mut |_| {}),
+        Err(LockstepReplayError::Desync { tick_id: 1, .. })
+    ));
+
+    // A corrupt length is rejected instead of allocated.
+    let mut huge = recording[..4 + initial_len].to_vec();
+    huge.extend_from_slice(&u32::MAX.to_le_bytes());
+    let mut replay = LockstepReplay::<World, _>::new(huge.as_slice()).unwrap();
+    assert!(matches!(
+        replay.play(&mut |_| {}),
+        Err(LockstepReplayError::Io(e)) if e.kind() == io::ErrorKind::InvalidData
+    ));
+
+    // Interrupted reads are retried.
+    struct Interrupting<'a> {
+        inner: &'a [u8],
+        interrupt: bool,
+    }
+    impl Read for Interrupting<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.interrupt = !self.interrupt;
+            if self.interrupt {
+                return Err(io::ErrorKind::Interrupted.into());
+            }
+            // One byte at a time, to interrupt the header.
+            let len = buf.len().min(1);
+            self.inner.read(&mut buf[..len])
+        }
+    }
+    let mut replay = LockstepReplay::<World, _>::new(Interrupting {
+        inner: &recording,
+        interrupt: false,
+    })
+    .unwrap();
+    assert_eq!(replay.play(&mut |_| {}).unwrap(), 50);
+}
+
+#[test]
+fn desync() {
+    use crate::bitcode::{self, *};
+    use crate::{
+        LockstepClient, LockstepClientData, LockstepContext, LockstepPhase, LockstepServer,
+        LockstepWorld, PlayerId,
+    };
+    use std::collections::VecDeque;
+    use std::hash::Hash;
+
+    #[derive(Clone, Default, Debug, Hash, Encode, Decode)]
+    struct World {
+        value: u32,
+    }
+    impl LockstepWorld for World {
+        type Input = u8;
+        type Player = u32;
+
+        fn tick(
+            &mut self,
+            _tick: Self::Tick,
+            context: &mut LockstepContext<Self>,
+            _phase: &LockstepPhase,
+            _on_info: &mut dyn FnMut(Self::Info),
+        ) where
+            [(); Self::LAG_COMPENSATION]:,
+        {
+            self.value += 1;
+            for (_, player) in context.players.iter_mut() {
+                player.inner += player.input as u32;
+            }
+        }
+    }
+
+    let player_id = PlayerId::nth_client(0).unwrap();
+    let mut server = LockstepServer::<World>::default();
+    let mut client_data = LockstepClientData::<World>::default();
+    *server.player_mut(player_id) = Some(0);
+    server.post_update(&mut |_| {});
+
+    let mut client = LockstepClient::<World>::default();
+    let mut in_flight = VecDeque::new();
+    let mut snapshot_requests = 0;
+    let mut desyncs = Vec::new();
+    for frame in 0..60u32 {
+        for request in in_flight.drain(..) {
+            server.request(player_id, request, Some(&mut client_data), false);
+        }
+        server.update(std::iter::once((player_id, &mut client_data)));
+        let update = server.client_update(player_id, &mut client_data);
+        server.post_update(&mut |_| {});
+        client.receive(update);
+        if frame == 10 {
+            // Simulate non-deterministic game logic.
+            client.real.world.value += 100;
+        }
+
+        let _ = client.update(
+            World::TICK_PERIOD_SECS,
+            false,
+            |_| 1,
+            |request, _| {
+                snapshot_requests += request.snapshot as u32;
+                in_flight.push_back(request);
+            },
+        );
+        desyncs.extend(client.take_desync());
+    }
+
+    // Asked once, even though the checksum mismatched until the snapshot arrived.
+    assert_eq!(
+        snapshot_requests,
+        if cfg!(feature = "desync") { 0 } else { 1 }
+    );
+    assert_eq!(desyncs.len(), 1, "{desyncs:?}");
+    assert_eq!(desyncs[0].diffs, [".world"]);
+    assert_eq!(client.real.checksum(), server.real.checksum());
+}
+
+#[test]
+fn rollback() {
+    use crate::bitcode::{self, *};
+    use crate::{
+        LockstepClient, LockstepClientData, LockstepContext, LockstepPhase, LockstepServer,
+        LockstepWorld, PlayerId,
+    };
+    use std::collections::VecDeque;
+    use std::hash::Hash;
+
+    #[derive(Clone, Default, Debug, Hash, Encode, Decode)]
+    struct World<const ROLLBACK: bool> {
+        ticks: u32,
+    }
+    impl<const ROLLBACK: bool> LockstepWorld for World<ROLLBACK> {
+        type Input = i8;
+        type Player = i32;
+
+        const ROLLBACK: bool = ROLLBACK;
+
+        fn tick(
+            &mut self,
+            _tick: Self::Tick,
+            context: &mut LockstepContext<Self>,
+            _phase: &LockstepPhase,
+            _on_info: &mut dyn FnMut(Self::Info),
+        ) where
+            [(); Self::LAG_COMPENSATION]:,
+        {
+            self.ticks += 1;
+            for (_, player) in context.players.iter_mut() {
+                player.inner += player.input as i32;
+            }
+        }
+    }
+
+    let player_id = PlayerId::nth_client(0).unwrap();
+    let mut server = LockstepServer::<World<false>>::default();
+    let mut client_data = LockstepClientData::<World<false>>::default();
+    *server.player_mut(player_id) = Some(0);
+    server.post_update(&mut |_| {});
+
+    let mut full = LockstepClient::<World<false>>::default();
+    let mut rollback = LockstepClient::<World<true>>::default();
+    let mut in_flight = VecDeque::new();
+    for frame in 0..200u32 {
+        // Inputs arrive late every so often, causing mispredictions.
+        if frame % 7 != 3 {
+            for request in in_flight.drain(..) {
+                server.request(player_id, request, Some(&mut client_data), false);
+            }
+        }
+        server.update(std::iter::once((player_id, &mut client_data)));
+        let update = server.client_update(player_id, &mut client_data);
+        server.post_update(&mut |_| {});
+        rollback.receive(bitcode::decode(&bitcode::encode(&update)).unwrap());
+        full.receive(update);
+
+        let input = ((frame / 5) % 3) as i8 - 1;
+        let _ = full.update(
+            World::<false>::TICK_PERIOD_SECS,
+            false,
+            |_| input,
+            |request, _| in_flight.push_back(request),
+        );
+        let _ = rollback.update(World::<true>::TICK_PERIOD_SECS, false, |_| input, |_, _| {});
+        assert_eq!(full.predicted.checksum(), rollback.predicted.checksum());
+    }
+
+    // Correct predictions were confirmed instead of re-simulated.
+    assert_eq!(full.repredictions, 200);
+    assert!(
+        rollback.repredictions < full.repredictions,
+        "{}",
+        rollback.repredictions
+    );
+}
+
+#[test]
+fn keyframe_transfer() {
+    use crate::bitcode::{self, *};
+    use crate::{
+        LockstepClient, LockstepClientData, LockstepContext, LockstepPhase, LockstepServer,
+        LockstepWorld, PlayerId,
+    };
+    use std::hash::Hash;
+
+    #[derive(Clone, Default, Debug, Hash, Encode, Decode)]
+    struct World {
+        history: Vec<u32>,
+    }
+    impl LockstepWorld for World {
+        type Input = u8;
+        type Player = u32;
+
+        const KEYFRAME_PERIOD: u32 = 4;
+        const TRANSFER_CHUNK: usize = 8;
+
+        fn tick(
+            &mut self,
+            _tick: Self::Tick,
+            context: &mut LockstepContext<Self>,
+            _phase: &LockstepPhase,
+            _on_info: &mut dyn FnMut(Self::Info),
+        ) where
+            [(); Self::LAG_COMPENSATION]:,
+        {
+            for (_, player) in context.players.iter_mut() {
+                player.inner += player.input as u32;
+                self.history.push(player.inner);
+            }
+        }
+    }
+
+    let bot_id = PlayerId::nth_bot(0).unwrap();
+    let player_id = PlayerId::nth_client(0).unwrap();
+    let mut server = LockstepServer::<World>::default();
+    *server.player_mut(bot_id) = Some(0);
+    *server.player_mut(player_id) = Some(0);
+    for i in 0..10 {
+        server.update(std::iter::empty());
+        server.current.inputs.insert(bot_id, i % 3);
+        server.post_update(&mut |_| {});
+    }
+
+    let mut client_data = LockstepClientData::<World>::default();
+    let mut client = LockstepClient::<World>::default();
+    let mut updates = 0;
+    while !client.loaded() {
+        server.update(std::iter::once((player_id, &mut client_data)));
+        server.current.inputs.insert(bot_id, 1);
+        let update = server.client_update(player_id, &mut client_data);
+        assert!(update.initialization.is_none());
+        assert!(update.transfer.as_ref().unwrap().chunk.len() <= World::TRANSFER_CHUNK);
+        server.post_update(&mut |_| {});
+        client.receive(update);
+        updates += 1;
+    }
+    assert!(updates > 1);
+    assert_eq!(client.real.checksum(), server.real.checksum());
+    assert_eq!(client.real.world.history, server.real.world.history);
+
+    fn step(
+        server: &mut LockstepServer<World>,
+        client_data: &mut LockstepClientData<World>,
+        client: &mut LockstepClient<World>,
+        [bot_id, player_id]: [PlayerId; 2],
+        lost: bool,
+    ) {
+        server.update(std::iter::once((player_id, &mut *client_data)));
+        server.current.inputs.insert(bot_id, 1);
+        let update = server.client_update(player_id, client_data);
+        server.post_update(&mut |_| {});
+        if !lost {
+            client.receive(update);
+        }
+        let mut requests = Vec::new();
+        let _ = client.update(
+            World::TICK_PERIOD_SECS,
+            false,
+            |_| 0,
+            |request, _| requests.push(request),
+        );
+        for request in requests {
+            server.request(player_id, request, Some(client_data), false);
+        }
+    }
+    let ids = [bot_id, player_id];
+
+    // Reconnecting restarts the transfer, discarding chunks of the previous one.
+    let mut client = LockstepClient::<World>::default();
+    step(
+        &mut server,
+        &mut LockstepClientData::default(),
+        &mut client,
+        ids,
+        false,
+    );
+    assert!(!client.loaded());
+    let mut client_data = LockstepClientData::default();
+    while !client.loaded() {
+        step(&mut server, &mut client_data, &mut client, ids, false);
+    }
+    assert_eq!(client.real.checksum(), server.real.checksum());
+
+    // Missing a chunk falls back to a snapshot instead of panicking.
+    for lost in 0..2 {
+        let mut client = LockstepClient::<World>::default();
+        let mut client_data = LockstepClientData::default();
+        for i in 0..=lost {
+            step(&mut server, &mut client_data, &mut client, ids, i == lost);
+        }
+        // Waits for the rest of the transfer before sending the snapshot.
+        for _ in 0..updates * 2 {
+            step(&mut server, &mut client_data, &mut client, ids, false);
+        }
+        assert!(client.loaded());
+        assert!(client.take_desync().is_none());
+        assert_eq!(client.real.checksum(), server.real.checksum());
+    }
+}
+
+#[test]
+fn spectator() {
+    use crate::bitcode::{self, *};
+    use crate::{
+        LockstepContext, LockstepPhase, LockstepServer, LockstepSpectator, LockstepSpectatorData,
+        LockstepWorld, PlayerId,
+    };
+    use std::collections::HashMap;
+    use std::hash::Hash;
+
+    #[derive(Clone, Default, Debug, Hash, Encode, Decode)]
+    struct World {
+        total: u32,
+    }
+    impl LockstepWorld for World {
+        type Input = u8;
+        type Player = u32;
+
+        const SPECTATOR_DELAY: Option<u32> = Some(3);
+
+        fn tick(
+            &mut self,
+            _tick: Self::Tick,
+            context: &mut LockstepContext<Self>,
+            _phase: &LockstepPhase,
+            _on_info: &mut dyn FnMut(Self::Info),
+        ) where
+            [(); Self::LAG_COMPENSATION]:,
+        {
+            for (_, player) in context.players.iter_mut() {
+                player.inner += player.input as u32;
+                self.total += player.inner;
+            }
+        }
+    }
+
+    let bot_ids = [PlayerId::nth_bot(0).unwrap(), PlayerId::nth_bot(1).unwrap()];
+    let mut server = LockstepServer::<World>::default();
+    for bot_id in bot_ids {
+        *server.player_mut(bot_id) = Some(0);
+    }
+    let mut checksums = HashMap::new();
+    let mut spectator_data = LockstepSpectatorData::default();
+    let mut spectator = LockstepSpectator::<World>::default();
+    for i in 0..20u8 {
+        server.update(std::iter::empty());
+        for bot_id in bot_ids {
+            server.current.inputs.insert(bot_id, i % 4);
+        }
+        let update = (i >= 5).then(|| server.spectator_update(&mut spectator_data).unwrap());
+        server.post_update(&mut |_| {});
+        checksums.insert(server.real.context.tick_id, server.real.checksum());
+        if let Some(update) = update {
+            spectator.receive(update);
+            let real = spectator.real.as_ref().unwrap();
+            assert_eq!(real.context.tick_id + 3, server.real.context.tick_id);
+            assert_eq!(checksums[&real.context.tick_id], real.checksum());
+        }
+    }
+
+    assert_eq!(spectator.following(), None);
+    spectator.follow_next(true);
+    assert_eq!(spectator.following(), Some(bot_ids[0]));
+    spectator.follow_next(true);
+    assert_eq!(spectator.following(), Some(bot_ids[1]));
+    spectator.follow_next(true);
+    assert_eq!(spectator.following(), Some(bot_ids[0]));
+    spectator.follow_next(false);
+    assert_eq!(spectator.following(), Some(bot_ids[1]));
+    spectator.follow(Some(PlayerId::nth_client(0).unwrap()));
+    assert_eq!(spectator.following(), None);
+}
+
+#[test]
+fn lag_compensated_colliders() {
+    use crate::{
+        Angle, Circle, Collider2d, LagCompensatedColliders, LockstepContext, LockstepPhase,
+        LockstepWorld, Ray, RotatedRectangle,
+    };
+    use glam::{Vec2, Vec3};
+
+    #[derive(Clone, Default, Debug, Hash)]
+    struct World;
+    impl LockstepWorld for World {
+        type Input = ();
+        type Player = ();
+
+        fn tick(
+            &mut self,
+            _tick: Self::Tick,
+            _context: &mut LockstepContext<Self>,
+            _phase: &LockstepPhase,
+            _on_info: &mut dyn FnMut(Self::Info),
+        ) where
+            [(); Self::LAG_COMPENSATION]:,
+        {
+        }
+    }
+
+    let mut colliders = LagCompensatedColliders::<u8, World, 16, 16, 10>::default();
+    // Actor 0 moves right 1 unit per tick, actor 1 is a stationary box.
+    for tick_id in 0..20u32 {
+        colliders.record(
+            tick_id,
+            [
+                (
+                    0,
+                    Collider2d::Circle(Circle::new(Vec2::new(tick_id as f32, 0.0), 0.5)),
+                ),
+                (
+                    1,
+                    Collider2d::RotatedRectangle(RotatedRectangle::new(
+                        Vec2::new(0.0, 20.0),
+                        Vec2::splat(2.0),
+                        Angle::ZERO,
+                    )),
+                ),
+            ],
+        );
+    }
+
+    let ray = Ray {
+        origin: Vec3::new(15.0, -10.0, 0.0),
+        direction: Vec3::Y,
+    };
+    // Now, actor 0 is at x = 19, so the ray would miss it and hit the box.
+    assert_eq!(colliders.raycast(19, 0, ray, 100.0, |_| true), None);
+    // Player with 4 ticks of latency saw actor 0 at x = 15.
+    let (key, t) = colliders.raycast(19, 4, ray, 100.0, |_| true).unwrap();
+    assert_eq!(key, 0);
+    assert!((t - 9.5).abs() < 0.001, "{t}");
+    assert_eq!(colliders.raycast(19, 4, ray, 5.0, |_| true), None);
+
+    let box_ray = Ray {
+        origin: Vec3::new(0.0, 0.5, 0.0),
+        direction: Vec3::Y,
+    };
+    let (key, t) = colliders
+        .raycast(19, 0, box_ray, 100.0, |k| k != 0)
+        .unwrap();
+    assert_eq!(key, 1);
+    assert!((t - 18.5).abs() < 0.001, "{t}");
+
+    let explosion = Circle::new(Vec2::new(10.0, 1.0), 1.0);
+    assert_eq!(colliders.query_circle(19, 0, explosion).count(), 0);
+    assert_eq!(
+        colliders.query_circle(19, 9, explosion).collect::<Vec<_>>(),
+        [0]
+    );
+    // Too old.
+    assert!(colliders.iter(19, World::MAX_LATENCY + 1).is_none());
+}
@@ -0,0 +1,196 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+use super::phase::{LockstepPhase, LockstepPhaseInner};
+use super::{Lockstep, LockstepTick, LockstepWorld};
+use crate::bitcode::{self, *};
+use crate::PlayerId;
+use std::fmt::{self, Debug, Formatter};
+use std::sync::Arc;
+
+/// Part of a late-join transfer, see [`LockstepWorld::KEYFRAME_PERIOD`].
+///
+/// The transferred bytes are a bitcode-encoded [`Lockstep`] keyframe followed by the
+/// bitcode-encoded [`LockstepTick`]s applied since.
+#[derive(Clone, Debug, Encode, Decode)]
+pub struct LockstepTransfer {
+    pub player_id: PlayerId,
+    /// Length of the encoded keyframe.
+    pub keyframe_len: u32,
+    /// Length of the encoded keyframe and ticks.
+    pub total_len: u32,
+    /// Position of `chunk` in the encoded keyframe and ticks. Zero starts a new transfer, e.g.
+    /// after reconnecting.
+    pub offset: u32,
+    /// The next bytes.
+    pub chunk: Vec<u8>,
+}
+
+/// The most recent keyframe on the server and the ticks applied since.
+pub(crate) struct LockstepKeyframe<W: LockstepWorld>
+where
+    [(); W::LAG_COMPENSATION]:,
+{
+    /// Shared by all outgoing transfers.
+    pub(crate) encoded: Arc<[u8]>,
+    pub(crate) ticks: Vec<LockstepTick<W>>,
+}
+
+impl<W: LockstepWorld> LockstepKeyframe<W>
+where
+    [(); W::LAG_COMPENSATION]:,
+    Lockstep<W>: Encode,
+    LockstepTick<W>: Encode,
+{
+    pub(crate) fn new(lockstep: &Lockstep<W>) -> Self {
+        Self {
+            encoded: bitcode::encode(lockstep).into(),
+            ticks: Vec::new(),
+        }
+    }
+
+    pub(crate) fn push(&mut self, tick: &LockstepTick<W>) {
+        #[allow(unused_mut)]
+        let mut tick = tick.clone();
+        // Don't need to keep complete states around.
+        #[cfg(feature = "desync")]
+        {
+            tick.complete = None;
+        }
+        self.ticks.push(tick);
+    }
+
+    /// Starts transferring the keyframe and ticks so far to a client.
+    pub(crate) fn outgoing(&self) -> LockstepOutgoingTransfer {
+        LockstepOutgoingTransfer {
+            keyframe: Arc::clone(&self.encoded),
+            ticks: bitcode::encode(&self.ticks),
+            sent: 0,
+        }
+    }
+}
+
+/// Progress of a transfer to a client.
+pub(crate) struct LockstepOutgoingTransfer {
+    keyframe: Arc<[u8]>,
+    ticks: Vec<u8>,
+    sent: usize,
+}
+
+impl LockstepOutgoingTransfer {
+    fn total_len(&self) -> usize {
+        self.keyframe.len() + self.ticks.len()
+    }
+
+    /// Returns the next chunk of at most `max` bytes, and whether it is the last.
+    pub(crate) fn next_chunk(
+        &mut self,
+        player_id: PlayerId,
+        max: usize,
+    ) -> (LockstepTransfer, bool) {
+        let offset = self.sent;
+        let end = (offset + max.max(1)).min(self.total_len());
+        let keyframe_len = self.keyframe.len();
+        let mut chunk = Vec::with_capacity(end - self.sent);
+        if self.sent < keyframe_len {
+            chunk.extend_from_slice(&self.keyframe[self.sent..end.min(keyframe_len)]);
+        }
+        if end > keyframe_len {
+            chunk.extend_from_slice(
+                &self.ticks[self.sent.max(keyframe_len) - keyframe_len..end - keyframe_len],
+            );
+        }
+        self.sent = end;
+        (
+            LockstepTransfer {
+                player_id,
+                keyframe_len: keyframe_len as u32,
+                total_len: self.total_len() as u32,
+                offset: offset as u32,
+                chunk,
+            },
+            end == self.total_len(),
+        )
+    }
+}
+
+impl Debug for LockstepOutgoingTransfer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LockstepOutgoingTransfer")
+            .field("sent", &self.sent)
+            .field("total_len", &self.total_len())
+            .finish()
+    }
+}
+
+/// Progress of a transfer from the server.
+pub(crate) struct LockstepIncomingTransfer<W: LockstepWorld>
+where
+    [(); W::LAG_COMPENSATION]:,
+{
+    /// Who the transfer is for, known once a chunk is received.
+    pub(crate) player_id: Option<PlayerId>,
+    bytes: Vec<u8>,
+    /// Ticks received during the transfer, to apply after the ticks in the transfer.
+    live_ticks: Vec<LockstepTick<W>>,
+}
+
+impl<W: LockstepWorld> Default for LockstepIncomingTransfer<W>
+where
+    [(); W::LAG_COMPENSATION]:,
+{
+    fn default() -> Self {
+        Self {
+            player_id: None,
+            bytes: Vec::new(),
+            live_ticks: Vec::new(),
+        }
+    }
+}
+
+impl<W: LockstepWorld> LockstepIncomingTransfer<W>
+where
+    [(); W::LAG_COMPENSATION]:,
+    Lockstep<W>: DecodeOwned,
+    LockstepTick<W>: DecodeOwned,
+{
+    /// Returns whether the transfer is complete, or an error if a chunk was missed.
+    pub(crate) fn receive(&mut self, transfer: &LockstepTransfer) -> Result<bool, &'static str> {
+        if transfer.offset == 0 {
+            // Forget any stale chunks of a previous transfer.
+            self.bytes.clear();
+            self.live_ticks.clear();
+        } else if transfer.offset as usize != self.bytes.len() {
+            return Err("missed chunk");
+        }
+        self.player_id = Some(transfer.player_id);
+        self.bytes.extend_from_slice(&transfer.chunk);
+        Ok(self.bytes.len() >= transfer.total_len as usize)
+    }
+
+    /// Buffers a tick that is newer than the transfer.
+    pub(crate) fn buffer(&mut self, tick: LockstepTick<W>) {
+        self.live_ticks.push(tick);
+    }
+
+    /// Decodes the keyframe and catches up to the most recent buffered tick.
+    pub(crate) fn finish(self, keyframe_len: u32) -> Result<Lockstep<W>, &'static str> {
+        if keyframe_len as usize > self.bytes.len() {
+            return Err("keyframe too long");
+        }
+        let (keyframe, ticks) = self.bytes.split_at(keyframe_len as usize);
+        let mut lockstep: Lockstep<W> =
+            bitcode::decode(keyframe).map_err(|_| "invalid keyframe")?;
+        let ticks: Vec<LockstepTick<W>> = bitcode::decode(ticks).map_err(|_| "invalid ticks")?;
+        for tick in ticks.into_iter().chain(self.live_ticks) {
+            lockstep.tick(
+                tick,
+                &LockstepPhase {
+                    inner: LockstepPhaseInner::GroundTruth,
+                },
+                &mut |_| {},
+            );
+        }
+        Ok(lockstep)
+    }
+}
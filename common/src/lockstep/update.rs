@@ -1,7 +1,7 @@
 // SPDX-FileCopyrightText: 2024 Softbear, Inc.
 // SPDX-License-Identifier: LGPL-3.0-or-later
 
-use super::{Lockstep, LockstepInputId, LockstepTick, LockstepWorld};
+use super::{Lockstep, LockstepInputId, LockstepTick, LockstepTransfer, LockstepWorld};
 use crate::bitcode::{self, *};
 use crate::PlayerId;
 use std::fmt::{self, Debug, Formatter};
@@ -12,6 +12,10 @@ where
     [(); W::LAG_COMPENSATION]:,
 {
     pub initialization: Option<(PlayerId, Lockstep<W>)>,
+    /// Alternative to `initialization`, see [`LockstepWorld::KEYFRAME_PERIOD`].
+    pub transfer: Option<LockstepTransfer>,
+    /// Server state before `tick`, sent if the client requested it to diagnose a desync.
+    pub snapshot: Option<Lockstep<W>>,
     pub last_applied_input_id: LockstepInputId,
     pub last_received_input_id: LockstepInputId,
     pub tick: LockstepTick<W>,
@@ -28,6 +32,8 @@ where
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         let Self {
             initialization,
+            transfer,
+            snapshot,
             last_applied_input_id,
             last_received_input_id,
             tick,
@@ -35,6 +41,8 @@ where
         } = self;
         f.debug_struct("LockstepUpdate")
             .field("initialization", &initialization)
+            .field("transfer", &transfer)
+            .field("snapshot", &snapshot)
             .field("last_applied_input_id", last_applied_input_id)
             .field("last_received_input_id", last_received_input_id)
             .field("tick", &tick)
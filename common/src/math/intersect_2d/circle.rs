@@ -23,6 +23,24 @@ impl Circle {
         self.center.distance_squared(point) <= self.radius.powi(2)
     }
 
+    /// Returns the smallest `t >= 0` such that `origin + direction * t` is within the circle.
+    pub fn raycast(&self, origin: Vec2, direction: Vec2) -> Option<f32> {
+        let offset = origin - self.center;
+        let c = offset.length_squared() - self.radius.powi(2);
+        if c <= 0.0 {
+            // Starts inside.
+            return Some(0.0);
+        }
+        let a = direction.length_squared();
+        let b = offset.dot(direction);
+        let discriminant = b.powi(2) - a * c;
+        if a == 0.0 || discriminant < 0.0 {
+            return None;
+        }
+        let t = (-b - discriminant.sqrt()) / a;
+        (t >= 0.0).then_some(t)
+    }
+
     pub fn rotated_rectangle(&self, rect: &RotatedRectangle) -> bool {
         let Vec2 { x: cos, y: sin } = rect.normal;
         let matrix = Mat2::from_cols(Vec2::new(cos, sin), Vec2::new(-sin, cos)).inverse();
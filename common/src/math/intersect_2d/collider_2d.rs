@@ -28,6 +28,22 @@ impl Collider2d {
         }
     }
 
+    /// Distance from the center to the farthest point.
+    pub fn bounding_radius(&self) -> f32 {
+        match self {
+            Self::Circle(circle) => circle.radius,
+            Self::RotatedRectangle(rectangle) => rectangle.half_size.length(),
+        }
+    }
+
+    /// Returns the smallest `t >= 0` such that `origin + direction * t` is within the collider.
+    pub fn raycast(&self, origin: Vec2, direction: Vec2) -> Option<f32> {
+        match self {
+            Self::Circle(circle) => circle.raycast(origin, direction),
+            Self::RotatedRectangle(rectangle) => rectangle.raycast(origin, direction),
+        }
+    }
+
     pub fn collides(&self, other: &Self) -> bool {
         match (self, other) {
             (Self::Circle(s), Self::Circle(o)) => s.collides(o),
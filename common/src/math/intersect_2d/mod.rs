@@ -4,10 +4,12 @@
 mod circle;
 mod collider_2d;
 mod origin_aabb_2d;
+mod polygon;
 mod rotated_rectangle;
 mod tests;
 
 pub use circle::Circle;
 pub use collider_2d::Collider2d;
 pub use origin_aabb_2d::OriginAabb2d;
+pub use polygon::polygon_signed_area_2x;
 pub use rotated_rectangle::{RotatedRectangle, SatRect};
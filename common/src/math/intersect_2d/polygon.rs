@@ -0,0 +1,12 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+use glam::Vec2;
+
+/// Returns twice the signed area of a simple polygon (shoelace formula), which is positive if its
+/// `vertices` are in counter-clockwise order and negative if clockwise.
+pub fn polygon_signed_area_2x(vertices: &[Vec2]) -> f32 {
+    (0..vertices.len())
+        .map(|i| vertices[i].perp_dot(vertices[(i + 1) % vertices.len()]))
+        .sum()
+}
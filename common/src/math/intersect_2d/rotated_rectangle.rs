@@ -110,10 +110,51 @@ impl RotatedRectangle {
         Some(min_penetration)
     }
 
+    /// Returns the smallest `t >= 0` such that `origin + direction * t` is within the rectangle.
+    pub fn raycast(&self, origin: Vec2, direction: Vec2) -> Option<f32> {
+        // Slab test in the rectangle's space.
+        let relative = origin - self.center;
+        let perp = self.normal.perp();
+        let origin = Vec2::new(relative.dot(self.normal), relative.dot(perp));
+        let direction = Vec2::new(direction.dot(self.normal), direction.dot(perp));
+        let mut t_min = 0f32;
+        let mut t_max = f32::INFINITY;
+        for ((origin, direction), half_size) in origin
+            .to_array()
+            .into_iter()
+            .zip(direction.to_array())
+            .zip(self.half_size.to_array())
+        {
+            if direction == 0.0 {
+                if origin.abs() > half_size {
+                    return None;
+                }
+            } else {
+                let a = (-half_size - origin) / direction;
+                let b = (half_size - origin) / direction;
+                t_min = t_min.max(a.min(b));
+                t_max = t_max.min(a.max(b));
+            }
+        }
+        (t_min <= t_max).then_some(t_min)
+    }
+
     pub fn size(&self) -> Vec2 {
         self.half_size * 2.0
     }
 
+    /// Returns the corners in counter-clockwise order.
+    pub fn corners(&self) -> [Vec2; 4] {
+        let x = self.normal * self.half_size.x;
+        let y = self.normal.perp() * self.half_size.y;
+        [
+            self.center - x - y,
+            self.center + x - y,
+            self.center + x + y,
+            self.center - x + y,
+        ]
+    }
+
     pub fn with_normal(center: Vec2, size: Vec2, normal: Vec2) -> Self {
         debug_assert!(normal.is_normalized());
         Self {
@@ -5,6 +5,7 @@
 mod intersect_2d_tests {
     use crate::angle::Angle;
     use crate::collision::SatRect;
+    use crate::{polygon_signed_area_2x, Circle, RotatedRectangle};
     use glam::{vec2, Vec2};
     use test::bench::{black_box, Bencher};
 
@@ -31,4 +32,72 @@ mod intersect_2d_tests {
 
         bencher.iter(|| black_box(black_box(&a).collides_with(black_box(&b))))
     }
+
+    #[test]
+    fn test_circle_raycast() {
+        let circle = Circle::new(vec2(5.0, 0.0), 1.0);
+        assert_eq!(circle.raycast(Vec2::ZERO, Vec2::X), Some(4.0));
+        // `t` is in units of `direction`.
+        assert_eq!(circle.raycast(Vec2::ZERO, Vec2::X * 2.0), Some(2.0));
+        assert_eq!(circle.raycast(vec2(5.0, 0.5), Vec2::X), Some(0.0));
+        assert_eq!(circle.raycast(Vec2::ZERO, Vec2::Y), None);
+        assert_eq!(circle.raycast(Vec2::ZERO, -Vec2::X), None);
+        assert_eq!(circle.raycast(Vec2::ZERO, Vec2::ZERO), None);
+    }
+
+    #[test]
+    fn test_rotated_rectangle_raycast() {
+        let rectangle =
+            RotatedRectangle::new(vec2(5.0, 0.0), Vec2::splat(2.0), Angle::from_degrees(45.0));
+        // Hits the corner, which is sqrt(2) from the center.
+        let t = rectangle.raycast(Vec2::ZERO, Vec2::X).unwrap();
+        assert!((t - (5.0 - 2f32.sqrt())).abs() < 0.001, "{t}");
+        assert_eq!(rectangle.raycast(vec2(5.0, 0.5), Vec2::X), Some(0.0));
+        assert_eq!(rectangle.raycast(vec2(0.0, 5.0), Vec2::X), None);
+        assert_eq!(rectangle.raycast(Vec2::ZERO, -Vec2::X), None);
+        assert_eq!(rectangle.raycast(vec2(0.0, 1.0), Vec2::ZERO), None);
+    }
+
+    #[test]
+    fn test_polygon_signed_area_2x() {
+        let mut square = [
+            vec2(0.0, 0.0),
+            vec2(2.0, 0.0),
+            vec2(2.0, 2.0),
+            vec2(0.0, 2.0),
+        ];
+        assert_eq!(polygon_signed_area_2x(&square), 8.0);
+        square.reverse();
+        assert_eq!(polygon_signed_area_2x(&square), -8.0);
+
+        // Doesn't depend on the position.
+        let triangle = [vec2(10.0, 10.0), vec2(13.0, 10.0), vec2(10.0, 14.0)];
+        assert_eq!(polygon_signed_area_2x(&triangle), 12.0);
+
+        // Degenerate.
+        assert_eq!(polygon_signed_area_2x(&[]), 0.0);
+        assert_eq!(polygon_signed_area_2x(&[Vec2::ONE, Vec2::X]), 0.0);
+    }
+
+    #[test]
+    fn test_rotated_rectangle_corners() {
+        for degrees in [0.0, 30.0, 90.0, 135.0, 180.0, -45.0] {
+            let rectangle = RotatedRectangle::new(
+                vec2(5.0, -3.0),
+                vec2(4.0, 2.0),
+                Angle::from_degrees(degrees),
+            );
+            let corners = rectangle.corners();
+
+            // Counter-clockwise, with the rectangle's area.
+            let area = polygon_signed_area_2x(&corners) * 0.5;
+            assert!((area - 8.0).abs() < 0.001, "{degrees} {area}");
+
+            let center = corners.iter().sum::<Vec2>() * 0.25;
+            assert!(
+                center.distance(rectangle.center) < 0.001,
+                "{degrees} {center}"
+            );
+        }
+    }
 }
@@ -4,7 +4,7 @@
 use crate::{
     ClientHash, EngineMetricsDataPointDto, GameId, MetricFilter, MetricsSummaryDto,
     NonZeroUnixMillis, Owned, PlayerAlias, PlayerId, Referrer, RegionId, ServerId, ServerNumber,
-    SessionToken, TeamId, UserAgentId,
+    SessionToken, TeamId, UserAgentId, VisitorId,
 };
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
@@ -18,6 +18,20 @@ use std::net::IpAddr;
     rtype(result = "Result<AdminUpdate, &'static str>")
 )]
 pub enum AdminRequest {
+    /// Ban an IP or visitor for a number of minutes, kicking matching players.
+    Ban {
+        target: BanTarget,
+        minutes: u32,
+    },
+    /// Gracefully shut down: close to new players, move existing players to other servers, and
+    /// stop once empty or after a number of seconds.
+    Drain {
+        seconds: u16,
+    },
+    /// Disconnect a player from the main arena.
+    KickPlayer {
+        player_id: PlayerId,
+    },
     OverridePlayerAlias {
         player_id: PlayerId,
         alias: PlayerAlias,
@@ -29,6 +43,9 @@ pub enum AdminRequest {
     RequestDay {
         filter: Option<MetricFilter>,
     },
+    /// Bytes sent since startup, per kind of update.
+    RequestBandwidthBreakdown,
+    RequestBans,
     RequestGames,
     RequestPlayers,
     /// Request an n-second CPU profile.
@@ -52,16 +69,25 @@ pub enum AdminRequest {
         filter: Option<MetricFilter>,
     },
     RequestUserAgents,
+    Unban {
+        target: BanTarget,
+    },
 }
 
 /// Admin related responses from the server.
 #[derive(Clone, Debug, Serialize)]
 pub enum AdminUpdate {
+    BandwidthBreakdownRequested(Box<[AdminBandwidthDto]>),
+    /// How many players were kicked.
+    Banned(usize),
+    BansRequested(Box<[AdminBanDto]>),
     ChatSent,
     DayRequested(Owned<[(NonZeroUnixMillis, EngineMetricsDataPointDto)]>),
+    Draining,
     GamesRequested(Box<[(GameId, f32)]>),
     HttpServerRestarting,
     PlayerAliasOverridden(PlayerAlias),
+    PlayerKicked,
     PlayerModeratorOverridden(bool),
     PlayerMuted(usize),
     PlayersRequested(Box<[AdminPlayerDto]>),
@@ -73,6 +99,34 @@ pub enum AdminUpdate {
     ServerIdRequested(ServerId),
     SummaryRequested(Box<MetricsSummaryDto>),
     UserAgentsRequested(Box<[(UserAgentId, f32)]>),
+    Unbanned,
+}
+
+/// Who is banned.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Deserialize, Serialize)]
+pub enum BanTarget {
+    Ip(IpAddr),
+    Visitor(VisitorId),
+}
+
+/// An active ban.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct AdminBanDto {
+    pub target: BanTarget,
+    pub expiry: NonZeroUnixMillis,
+}
+
+/// Bytes sent for one kind of update.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct AdminBandwidthDto {
+    /// `CommonUpdate` variant, optionally followed by `::` and a game update label.
+    pub update: String,
+    /// Whether the reliable transport was used, which unreliable updates may fall back to.
+    pub reliable: bool,
+    pub messages: u64,
+    /// Before compression.
+    pub encoded_bytes: u64,
+    pub compressed_bytes: u64,
 }
 
 /// The Player Admin Data Transfer Object (DTO) binds player ID to admin player data (for real players, not bots).
@@ -0,0 +1,109 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+use super::hash::{CompatHasher, HbHash};
+use crate::{ArenaKey, ArenaMap};
+use std::fmt::{Debug, Write};
+use std::hash::{Hash, Hasher};
+
+/// Finds which fields differ between two values, to help diagnose desyncs.
+///
+/// Can be derived. Fields are compared by [`Hash`], or by [`HbHash`] if marked `#[hb_hash]`, unless
+/// marked `#[hb_diff]` in which case they are recursively diffed.
+pub trait HbDiff {
+    /// Calls `on_diff` with the path (e.g. `.players[3].position`) of each differing field,
+    /// relative to `path`.
+    fn diff(&self, other: &Self, path: &mut String, on_diff: &mut dyn FnMut(&str));
+}
+
+/// Reports `path` if `a` and `b` have different [`Hash`]es.
+pub fn hb_diff_hash<T: Hash + ?Sized>(
+    a: &T,
+    b: &T,
+    path: &mut String,
+    on_diff: &mut dyn FnMut(&str),
+) {
+    let mut a_hasher = CompatHasher::default();
+    let mut b_hasher = CompatHasher::default();
+    a.hash(&mut a_hasher);
+    b.hash(&mut b_hasher);
+    if a_hasher.finish() != b_hasher.finish() {
+        on_diff(path);
+    }
+}
+
+/// Reports `path` if `a` and `b` have different [`HbHash`]es.
+pub fn hb_diff_hb_hash<T: HbHash + ?Sized>(
+    a: &T,
+    b: &T,
+    path: &mut String,
+    on_diff: &mut dyn FnMut(&str),
+) {
+    if a.hash_to_u64() != b.hash_to_u64() {
+        on_diff(path);
+    }
+}
+
+/// Appends `segment` to `path` for the duration of `f`.
+pub(crate) fn with_path_segment(
+    path: &mut String,
+    segment: impl std::fmt::Display,
+    f: impl FnOnce(&mut String),
+) {
+    let len = path.len();
+    let _ = write!(path, "{segment}");
+    f(path);
+    path.truncate(len);
+}
+
+impl<T: HbDiff> HbDiff for Option<T> {
+    fn diff(&self, other: &Self, path: &mut String, on_diff: &mut dyn FnMut(&str)) {
+        match (self, other) {
+            (Some(a), Some(b)) => a.diff(b, path, on_diff),
+            (None, None) => {}
+            _ => on_diff(path),
+        }
+    }
+}
+
+impl<T: HbDiff> HbDiff for [T] {
+    fn diff(&self, other: &Self, path: &mut String, on_diff: &mut dyn FnMut(&str)) {
+        if self.len() != other.len() {
+            with_path_segment(path, ".len()", |path| on_diff(path));
+        }
+        for (i, (a, b)) in self.iter().zip(other).enumerate() {
+            with_path_segment(path, format_args!("[{i}]"), |path| a.diff(b, path, on_diff));
+        }
+    }
+}
+
+impl<T: HbDiff, const N: usize> HbDiff for [T; N] {
+    fn diff(&self, other: &Self, path: &mut String, on_diff: &mut dyn FnMut(&str)) {
+        self.as_slice().diff(other.as_slice(), path, on_diff);
+    }
+}
+
+impl<T: HbDiff> HbDiff for Vec<T> {
+    fn diff(&self, other: &Self, path: &mut String, on_diff: &mut dyn FnMut(&str)) {
+        self.as_slice().diff(other.as_slice(), path, on_diff);
+    }
+}
+
+impl<K: ArenaKey + Debug, V: HbDiff> HbDiff for ArenaMap<K, V> {
+    fn diff(&self, other: &Self, path: &mut String, on_diff: &mut dyn FnMut(&str)) {
+        for (key, a) in self.iter() {
+            with_path_segment(path, format_args!("[{key:?}]"), |path| {
+                if let Some(b) = other.get(key) {
+                    a.diff(b, path, on_diff);
+                } else {
+                    on_diff(path);
+                }
+            });
+        }
+        for (key, _) in other.iter() {
+            if !self.contains(key) {
+                with_path_segment(path, format_args!("[{key:?}]"), |path| on_diff(path));
+            }
+        }
+    }
+}
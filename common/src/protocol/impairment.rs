@@ -0,0 +1,134 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+use crate::{serde_str, FromStrVisitor};
+use std::collections::VecDeque;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+/// Simulated network conditions, applied to outbound messages, for testing prediction, jitter
+/// compensation, and reconnection.
+///
+/// Written like `latency=100,jitter=20,loss=0.05,reorder=0.01,kbps=1000`, where every part is
+/// optional.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct NetworkImpairment {
+    /// Added one-way delay, in milliseconds.
+    pub latency: u16,
+    /// Maximum additional random delay, in milliseconds.
+    pub jitter: u16,
+    /// Probability, from 0 to 1, that an unreliable message is dropped.
+    pub loss: f32,
+    /// Probability, from 0 to 1, that an unreliable message is delayed enough to arrive out of
+    /// order.
+    pub reorder: f32,
+    /// Bandwidth cap, in kilobits per second. 0 is unlimited.
+    pub kbps: u32,
+}
+
+impl Display for NetworkImpairment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "latency={},jitter={},loss={},reorder={},kbps={}",
+            self.latency, self.jitter, self.loss, self.reorder, self.kbps
+        )
+    }
+}
+
+impl FromStr for NetworkImpairment {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut ret = Self::default();
+        for part in s.split(',').filter(|p| !p.is_empty()) {
+            let (key, value) = part.split_once('=').ok_or("missing =")?;
+            match key {
+                "latency" => ret.latency = value.parse().map_err(|_| "invalid latency")?,
+                "jitter" => ret.jitter = value.parse().map_err(|_| "invalid jitter")?,
+                "loss" => ret.loss = parse_probability(value).ok_or("invalid loss")?,
+                "reorder" => ret.reorder = parse_probability(value).ok_or("invalid reorder")?,
+                "kbps" => ret.kbps = value.parse().map_err(|_| "invalid kbps")?,
+                _ => return Err("unknown impairment"),
+            }
+        }
+        Ok(ret)
+    }
+}
+
+serde_str!(NetworkImpairment);
+
+fn parse_probability(s: &str) -> Option<f32> {
+    s.parse::<f32>().ok().filter(|p| (0.0..=1.0).contains(p))
+}
+
+/// Holds back outbound messages according to a [`NetworkImpairment`]. Times are in seconds, from
+/// any consistent epoch.
+#[derive(Debug)]
+pub struct ImpairedQueue<T> {
+    impairment: NetworkImpairment,
+    /// When the simulated link is done transmitting already-queued messages.
+    link_free: f64,
+    /// Delivery time of the last reliable message, which later ones may not precede.
+    last_reliable: f64,
+    /// Sorted by delivery time.
+    pending: VecDeque<(f64, T)>,
+}
+
+impl<T> ImpairedQueue<T> {
+    pub fn new(impairment: NetworkImpairment) -> Self {
+        Self {
+            impairment,
+            link_free: 0.0,
+            last_reliable: 0.0,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Queues a message of `len` bytes, unless it is lost. Returns `false` if lost.
+    pub fn push(&mut self, now: f64, len: usize, reliable: bool, message: T) -> bool {
+        let impairment = &self.impairment;
+        if !reliable && rand::random::<f32>() < impairment.loss {
+            return false;
+        }
+        let start = self.link_free.max(now);
+        self.link_free = if impairment.kbps == 0 {
+            start
+        } else {
+            start + len as f64 * 8.0 / (impairment.kbps as f64 * 1000.0)
+        };
+        let max_delay = (impairment.latency as f64 + impairment.jitter as f64) * 0.001;
+        let mut delay =
+            (impairment.latency as f64 + rand::random::<f64>() * impairment.jitter as f64) * 0.001;
+        if !reliable && rand::random::<f32>() < impairment.reorder {
+            delay += max_delay.max(0.001);
+        }
+        let mut due = self.link_free + delay;
+        if reliable {
+            due = due.max(self.last_reliable);
+            self.last_reliable = due;
+        }
+        let index = self.pending.partition_point(|&(d, _)| d <= due);
+        self.pending.insert(index, (due, message));
+        true
+    }
+
+    /// When the next message is due, if any.
+    pub fn next_due(&self) -> Option<f64> {
+        self.pending.front().map(|&(due, _)| due)
+    }
+
+    /// Returns the next message that is due at `now`.
+    pub fn pop_due(&mut self, now: f64) -> Option<T> {
+        if self.next_due()? <= now {
+            self.pending.pop_front().map(|(_, message)| message)
+        } else {
+            None
+        }
+    }
+
+    /// Returns all pending messages, regardless of when they are due.
+    pub fn drain(&mut self) -> impl Iterator<Item = T> + '_ {
+        self.pending.drain(..).map(|(_, message)| message)
+    }
+}
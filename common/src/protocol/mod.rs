@@ -6,8 +6,10 @@ mod actix_macro;
 #[cfg(feature = "admin")]
 mod admin;
 mod compression;
+mod diff;
 mod fence;
 mod hash;
+mod impairment;
 mod invitations;
 mod leaderboard;
 mod owned;
@@ -21,8 +23,11 @@ mod updates;
 pub use admin::*;
 // Contains much use of conditional compilation.
 pub use self::compression::*;
+pub(crate) use self::diff::with_path_segment;
+pub use self::diff::{hb_diff_hash, hb_diff_hb_hash, HbDiff};
 pub use self::fence::GameFence;
 pub use self::hash::{hash_f32, hash_f32_ref, hash_f32s, CompatHasher, Hashable, HbHash};
+pub use self::impairment::{ImpairedQueue, NetworkImpairment};
 pub use self::invitations::{
     DeepConnect, DeepConnectError, InstancePickerDto, InvitationDto, InvitationRequest,
     InvitationUpdate, SystemUpdate,
@@ -37,6 +42,6 @@ pub use self::system::{
 };
 pub use self::teams::{TeamRequest, TeamUpdate};
 pub use self::updates::{
-    ChatRequest, ChatUpdate, ClientRequest, ClientUpdate, CommonRequest, CommonUpdate, MessageDto,
-    PlayerDto, PlayerUpdate,
+    ChatCommandDto, ChatRequest, ChatUpdate, ClientRequest, ClientUpdate, CommonRequest,
+    CommonUpdate, DirectRecipient, MessageDto, PlayerDto, PlayerUpdate,
 };
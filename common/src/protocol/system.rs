@@ -3,7 +3,7 @@
 
 use crate::{
     actix_response, is_default, ArenaQuery, CohortId, DomainName, LanguageDto, LanguageId,
-    NonZeroUnixMillis, Owned, Referrer, ServerId, SessionToken,
+    NetworkImpairment, NonZeroUnixMillis, Owned, Referrer, ServerId, SessionToken,
 };
 use arrayvec::ArrayString;
 use serde::{Deserialize, Serialize};
@@ -89,6 +89,12 @@ pub struct SocketQuery {
     /// DOM loading latency, after HTTP response.
     #[serde(default, skip_serializing_if = "is_default")]
     pub dom: u16,
+    /// Watch without playing, see [`ClientRequest::Spectate`](crate::ClientRequest::Spectate).
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub spectate: bool,
+    /// Simulated network conditions for messages from the server. Only honored by debug builds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub impairment: Option<NetworkImpairment>,
 }
 
 /// Pass the following query parameters to the system endpoint to inform server routing.
@@ -17,3 +17,103 @@ mod hash_tests {
         assert_eq!(a.finish(), b.finish());
     }
 }
+
+#[cfg(test)]
+mod diff_tests {
+    use crate::HbDiff;
+    extern crate self as kodiak_common;
+    use kodiak_macros::{HbDiff, HbHash};
+
+    #[derive(Clone, HbHash, HbDiff)]
+    struct Unit {
+        id: u32,
+        #[hb_hash]
+        position: glam::Vec2,
+    }
+
+    #[derive(Clone, Hash, HbDiff)]
+    enum State {
+        Idle,
+        Moving(u8),
+    }
+
+    #[derive(Clone, HbDiff)]
+    struct World {
+        #[hb_diff]
+        units: Vec<Unit>,
+        #[hb_diff]
+        state: State,
+    }
+
+    fn diff(a: &World, b: &World) -> Vec<String> {
+        let mut diffs = Vec::new();
+        a.diff(b, &mut String::new(), &mut |path| {
+            diffs.push(path.to_owned())
+        });
+        diffs
+    }
+
+    #[test]
+    fn hb_diff() {
+        let a = World {
+            units: vec![
+                Unit {
+                    id: 1,
+                    position: glam::Vec2::ZERO,
+                },
+                Unit {
+                    id: 2,
+                    position: glam::Vec2::ONE,
+                },
+            ],
+            state: State::Moving(3),
+        };
+        assert!(diff(&a, &a.clone()).is_empty());
+
+        let mut b = a.clone();
+        b.units[1].position.x = 0.5;
+        b.state = State::Moving(4);
+        assert_eq!(diff(&a, &b), [".units[1].position", ".state.0"]);
+
+        b.units.pop();
+        b.state = State::Idle;
+        assert_eq!(diff(&a, &b), [".units.len()", ".state"]);
+    }
+}
+
+#[cfg(test)]
+mod impairment_tests {
+    use crate::{ImpairedQueue, NetworkImpairment};
+
+    #[test]
+    fn parse() {
+        let impairment: NetworkImpairment = "latency=100,loss=0.5".parse().unwrap();
+        assert_eq!(impairment.latency, 100);
+        assert_eq!(impairment.loss, 0.5);
+        assert_eq!(impairment.to_string().parse(), Ok(impairment));
+        assert!("loss=2".parse::<NetworkImpairment>().is_err());
+        assert!("speed=1".parse::<NetworkImpairment>().is_err());
+    }
+
+    #[test]
+    fn reliable_in_order() {
+        let mut queue = ImpairedQueue::new("latency=50,jitter=50,kbps=8".parse().unwrap());
+        for i in 0..10 {
+            queue.push(0.0, 10, true, i);
+        }
+        // 10 bytes at 8 kbps is 10ms each.
+        assert!(queue.next_due().unwrap() >= 0.06);
+        assert_eq!(queue.pop_due(0.0), None);
+        let received = std::iter::from_fn(|| queue.pop_due(10.0)).collect::<Vec<_>>();
+        assert_eq!(received, (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn loss() {
+        let mut queue = ImpairedQueue::new("loss=1".parse().unwrap());
+        assert!(!queue.push(0.0, 10, false, 0));
+        // Reliable messages are never lost.
+        assert!(queue.push(0.0, 10, true, 1));
+        assert_eq!(queue.drain().collect::<Vec<_>>(), [1]);
+    }
+}
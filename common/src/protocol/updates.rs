@@ -26,12 +26,27 @@ pub enum ChatRequest {
         /// Whether messages should only be visible to sender's team.
         whisper: bool,
     },
+    /// Send a private message to one player in the same arena.
+    SendDirect {
+        recipient: DirectRecipient,
+        message: String,
+    },
     /// Chat will be in safe mode for this many more minutes. For moderators only.
     SetSafeMode(u32),
     /// Chat will be in slow mode for this many more minutes. For moderators only.
     SetSlowMode(u32),
     /// Resume seeing this player's messages.
     Unmute(MessageNumber),
+    /// Request the chat commands available to this player, for autocomplete.
+    Commands,
+}
+
+/// Who a direct message is for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Encode, Decode)]
+pub enum DirectRecipient {
+    Player(PlayerId),
+    /// The sender of a received message.
+    Reply(MessageNumber),
 }
 
 /// Chat related update from server to client.
@@ -45,6 +60,7 @@ pub enum ChatUpdate {
     Sent,
     Unmuted(MessageNumber),
     Reported(MessageNumber),
+    Commands(Box<[ChatCommandDto]>),
 }
 
 /// General request from client to server.
@@ -67,6 +83,8 @@ pub enum ClientRequest {
     },
     /// Configure join announcement.
     AnnouncementPreference(bool),
+    /// As a spectator, switch which player the camera follows. `None` means free camera.
+    Spectate(Option<PlayerId>),
 }
 
 /// General update from server to client.
@@ -150,10 +168,24 @@ pub struct MessageDto {
     pub authority: bool,
     /// Whether message is directed to team/player only.
     pub whisper: bool,
+    /// The sender, if a player on the same server as the recipient.
+    pub player_id: Option<PlayerId>,
+    /// If a direct message, the recipient.
+    pub recipient: Option<PlayerAlias>,
     /// Content of the message.
     pub message: ChatMessage,
 }
 
+/// A chat command the player may use.
+#[derive(Clone, Debug, PartialEq, Encode, Decode)]
+pub struct ChatCommandDto {
+    /// Without the leading `/`.
+    pub name: String,
+    /// E.g. `/bots [count|default]`.
+    pub usage: String,
+    pub description: String,
+}
+
 /// The Player Data Transfer Object (DTO) binds player ID to player data.
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Encode, Decode)]
 pub struct PlayerDto {
@@ -0,0 +1,240 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+use crate::bitcode::{DecodeOwned, Encode};
+use crate::policy::{LoadTestAction, LoadTestPolicy, LoadTestSession, LoadTestState};
+use crate::stats::SharedStats;
+use crate::{
+    decode_buffer, encode_buffer, ClientActivity, ClientRequest, ClientUpdate, CommonRequest,
+    CommonUpdate, Compression, CompressionImpl, Decompressor,
+};
+use bytes::Bytes;
+use futures::{SinkExt, StreamExt};
+use http::header::ORIGIN;
+use http::{HeaderValue, Uri};
+use std::collections::VecDeque;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio_websockets::{ClientBuilder, Limits, MaybeTlsStream, Message, WebSocketStream};
+
+/// Same as the browser client.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_millis(4500);
+/// A ping without a pong for this long is counted as dropped.
+const PING_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Everything a client needs to know to connect.
+pub(crate) struct ConnectionConfig {
+    pub(crate) url: String,
+    pub(crate) origin: String,
+    pub(crate) update_interval: Duration,
+    pub(crate) ping_interval: Duration,
+}
+
+/// One headless client, which speaks [`CommonRequest`] and [`CommonUpdate`] with the same framing
+/// as the browser client: requests are uncompressed and updates are compressed with
+/// [`CompressionImpl`].
+pub(crate) struct Connection<GR, GU, P> {
+    socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    decompressor: <CompressionImpl as Compression>::Decompressor,
+    policy: P,
+    state: LoadTestState,
+    stats: Arc<SharedStats>,
+    /// Ping payloads are microseconds since this.
+    epoch: Instant,
+    /// Payloads of pings that haven't been answered yet, oldest first.
+    pending_pings: VecDeque<u64>,
+    _spooky: std::marker::PhantomData<(GR, GU)>,
+}
+
+impl<GR, GU, P> Connection<GR, GU, P>
+where
+    GR: Encode + Send + 'static,
+    GU: DecodeOwned + Send + 'static,
+    P: LoadTestPolicy<GR, GU>,
+{
+    pub(crate) async fn connect(
+        index: usize,
+        config: &ConnectionConfig,
+        stats: Arc<SharedStats>,
+    ) -> Result<Self, ConnectError> {
+        let uri = Uri::from_str(&config.url).map_err(|_| ConnectError::InvalidUrl)?;
+        let origin = HeaderValue::from_str(&config.origin).map_err(|_| ConnectError::InvalidUrl)?;
+        let (socket, _) = tokio::time::timeout(
+            Duration::from_secs(12),
+            ClientBuilder::from_uri(uri)
+                .add_header(ORIGIN, origin)
+                .map_err(ConnectError::Other)?
+                .limits(Limits::default().max_payload_len(Some(2usize.pow(24))))
+                .connect(),
+        )
+        .await
+        .map_err(|_| ConnectError::Timeout)?
+        .map_err(ConnectError::Other)?;
+        Ok(Self {
+            socket,
+            decompressor: Default::default(),
+            policy: P::default(),
+            state: LoadTestState {
+                index,
+                ..Default::default()
+            },
+            stats,
+            epoch: Instant::now(),
+            pending_pings: VecDeque::new(),
+            _spooky: std::marker::PhantomData,
+        })
+    }
+
+    /// Runs until the policy quits, the server closes the connection, or `deadline`.
+    pub(crate) async fn run(mut self, config: &ConnectionConfig, deadline: Option<Instant>) {
+        let mut update = tokio::time::interval(config.update_interval);
+        let mut ping = tokio::time::interval(config.ping_interval);
+        let mut heartbeat = tokio::time::interval(HEARTBEAT_INTERVAL);
+        let mut deadline = std::pin::pin!(async move {
+            if let Some(deadline) = deadline {
+                tokio::time::sleep_until(deadline.into()).await;
+            } else {
+                std::future::pending::<()>().await;
+            }
+        });
+
+        loop {
+            let result = tokio::select! {
+                message = self.socket.next() => {
+                    match message {
+                        Some(Ok(message)) => self.receive(message),
+                        _ => Err(()),
+                    }
+                }
+                _ = update.tick() => {
+                    match self.policy.update(&self.state) {
+                        LoadTestAction::Some(request) => {
+                            let game_fence = self.state.game_fence;
+                            self.send(CommonRequest::Game(request, game_fence)).await
+                        }
+                        LoadTestAction::None(_) => Ok(()),
+                        LoadTestAction::Quit => {
+                            let _ = self.send(CommonRequest::Client(ClientRequest::Quit)).await;
+                            Err(())
+                        }
+                    }
+                }
+                _ = heartbeat.tick() => {
+                    self.send(CommonRequest::Client(ClientRequest::Heartbeat(
+                        ClientActivity::Active,
+                    )))
+                    .await
+                }
+                _ = ping.tick() => {
+                    let micros = self.epoch.elapsed().as_micros() as u64;
+                    let timeout = PING_TIMEOUT.as_micros() as u64;
+                    while let Some(&sent) = self.pending_pings.front()
+                        && micros.saturating_sub(sent) > timeout
+                    {
+                        self.pending_pings.pop_front();
+                        self.stats.dropped();
+                    }
+                    self.pending_pings.push_back(micros);
+                    self.socket
+                        .send(Message::ping(micros.to_ne_bytes().to_vec()))
+                        .await
+                        .map_err(|_| ())
+                }
+                _ = deadline.as_mut() => Err(()),
+            };
+            if result.is_err() {
+                break;
+            }
+        }
+        let _ = self.socket.close().await;
+        self.stats.disconnected();
+    }
+
+    async fn send(&mut self, request: CommonRequest<GR>) -> Result<(), ()> {
+        let bytes = encode_buffer(&request);
+        self.stats.sent(bytes.len());
+        self.socket
+            .send(Message::binary(bytes))
+            .await
+            .map_err(|_| ())
+    }
+
+    fn receive(&mut self, message: Message) -> Result<(), ()> {
+        if message.is_binary() {
+            let bytes: Bytes = message.into_payload().into();
+            self.stats.received(bytes.len());
+            // The decompressor is stateful, so a failure means all subsequent updates are lost.
+            let Ok(decompressed) = self.decompressor.decompress(&bytes) else {
+                self.stats.dropped();
+                return Err(());
+            };
+            match decode_buffer::<CommonUpdate<GU>>(&decompressed) {
+                Ok(update) => self.apply(update),
+                Err(_) => self.stats.dropped(),
+            }
+        } else if message.is_pong() {
+            let bytes: Bytes = message.into_payload().into();
+            if let Ok(bytes) = bytes.as_ref().try_into() {
+                let micros = u64::from_ne_bytes(bytes);
+                let Some(index) = self.pending_pings.iter().position(|&p| p == micros) else {
+                    // Already counted as dropped.
+                    return Ok(());
+                };
+                // Pongs arrive in order, so earlier pings were lost.
+                for _ in self.pending_pings.drain(..index) {
+                    self.stats.dropped();
+                }
+                self.pending_pings.pop_front();
+                let sent = Duration::from_micros(micros);
+                self.stats.rtt(self.epoch.elapsed().saturating_sub(sent));
+            }
+        } else if message.is_close() {
+            return Err(());
+        }
+        Ok(())
+    }
+
+    fn apply(&mut self, update: CommonUpdate<GU>) {
+        match update {
+            CommonUpdate::Client(ClientUpdate::SessionCreated {
+                server_id,
+                arena_id,
+                player_id,
+                ..
+            }) => {
+                self.state.session = Some(LoadTestSession {
+                    server_id,
+                    arena_id,
+                    player_id,
+                });
+            }
+            CommonUpdate::Client(ClientUpdate::ClearSyncState { game_fence }) => {
+                self.state.game_fence = Some(game_fence);
+            }
+            CommonUpdate::Game(update) => self.policy.receive(&update, &self.state),
+            _ => {}
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum ConnectError {
+    InvalidUrl,
+    Timeout,
+    Other(tokio_websockets::Error),
+}
+
+impl std::error::Error for ConnectError {}
+
+impl Display for ConnectError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidUrl => f.write_str("invalid url"),
+            Self::Timeout => f.write_str("timeout"),
+            Self::Other(e) => Display::fmt(e, f),
+        }
+    }
+}
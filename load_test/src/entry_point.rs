@@ -0,0 +1,92 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+use crate::bitcode::{DecodeOwned, Encode};
+use crate::connection::{Connection, ConnectionConfig};
+use crate::options::Options;
+use crate::policy::LoadTestPolicy;
+use crate::stats::SharedStats;
+use clap::Parser;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::runtime::Builder;
+
+/// Opens [`Options::clients`] connections, each driven by a `P`, and periodically prints
+/// statistics to stdout.
+pub fn entry_point<GR, GU, P>() -> ExitCode
+where
+    GR: Encode + Send + 'static,
+    GU: DecodeOwned + Send + 'static,
+    P: LoadTestPolicy<GR, GU>,
+{
+    let options = Options::parse();
+    let runtime = Builder::new_multi_thread()
+        .enable_io()
+        .enable_time()
+        .build()
+        .expect("could not build tokio runtime");
+
+    runtime.block_on(async move {
+        let start = Instant::now();
+        let deadline = options.duration().map(|duration| start + duration);
+        let stats = Arc::new(SharedStats::default());
+        let config = Arc::new(ConnectionConfig {
+            url: options.url(),
+            origin: options.origin.clone(),
+            update_interval: options.update_interval(),
+            ping_interval: options.ping_interval(),
+        });
+        println!("connecting {} clients to {}", options.clients, config.url);
+
+        {
+            let stats = Arc::clone(&stats);
+            let config = Arc::clone(&config);
+            let clients = options.clients;
+            let ramp_interval = options.ramp_interval();
+            tokio::spawn(async move {
+                for index in 0..clients {
+                    let stats = Arc::clone(&stats);
+                    let config = Arc::clone(&config);
+                    tokio::spawn(async move {
+                        match Connection::<GR, GU, P>::connect(index, &config, Arc::clone(&stats))
+                            .await
+                        {
+                            Ok(connection) => {
+                                stats.connected();
+                                connection.run(&config, deadline).await;
+                            }
+                            Err(e) => {
+                                stats.connect_failed();
+                                eprintln!("client {index} failed to connect: {e}");
+                            }
+                        }
+                    });
+                    tokio::time::sleep(ramp_interval).await;
+                }
+            });
+        }
+
+        let mut report = tokio::time::interval(options.report_interval());
+        // First tick is immediate.
+        report.tick().await;
+        let mut previous = stats.snapshot();
+        let mut previous_time = Instant::now();
+        loop {
+            report.tick().await;
+            let now = Instant::now();
+            let current = stats.snapshot();
+            println!("{}", current.since(&previous, now - previous_time));
+            previous = current;
+            previous_time = now;
+            if deadline.is_some_and(|deadline| now >= deadline) {
+                break;
+            }
+        }
+        if previous.connect_failures > 0 && previous.connect_failures == options.clients as u64 {
+            ExitCode::FAILURE
+        } else {
+            ExitCode::SUCCESS
+        }
+    })
+}
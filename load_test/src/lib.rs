@@ -0,0 +1,24 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Headless clients that connect to a game server over the same WebSocket protocol as the browser
+//! client, for load testing. Each game builds its own binary that calls [`entry_point`] with its
+//! request and update types and a [`LoadTestPolicy`].
+
+#![feature(let_chains)]
+
+mod connection;
+mod entry_point;
+mod options;
+mod policy;
+mod stats;
+mod tests;
+
+// Export `pub` symbols below. Remaining symbols are effectively `pub(crate)`.
+pub use entry_point::entry_point;
+pub use options::Options;
+pub use policy::{LoadTestAction, LoadTestPolicy, LoadTestSession, LoadTestState};
+pub use stats::{LoadTestReport, LoadTestStats};
+
+// Re-export kodiak_common.
+pub use kodiak_common::{self, *};
@@ -0,0 +1,89 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+use crate::{ArenaQuery, NetworkImpairment, SocketQuery};
+use clap::Parser;
+use std::time::Duration;
+
+/// Load test options, to be specified as arguments.
+#[derive(Debug, Parser)]
+pub struct Options {
+    /// Game server, e.g. `ws://localhost:8080` or `wss://1.example.com`.
+    #[clap(long, default_value = "ws://localhost:8080")]
+    pub server: String,
+    /// Sent as the `Origin` header, which the server checks.
+    #[clap(long, default_value = "http://localhost:8080")]
+    pub origin: String,
+    /// Number of concurrent clients.
+    #[clap(long, default_value = "100")]
+    pub clients: usize,
+    /// Milliseconds between opening successive clients.
+    #[clap(long, default_value = "50")]
+    pub ramp_millis: u64,
+    /// Milliseconds between policy updates, i.e. the input rate of each client.
+    #[clap(long, default_value = "100")]
+    pub update_millis: u64,
+    /// Milliseconds between WebSocket pings, used to measure round-trip time.
+    #[clap(long, default_value = "1000")]
+    pub ping_millis: u64,
+    /// Seconds between statistics reports.
+    #[clap(long, default_value = "5")]
+    pub report_seconds: u64,
+    /// Seconds to run for, or forever if omitted.
+    #[clap(long)]
+    pub duration_seconds: Option<u64>,
+    /// Connect as spectators instead of players.
+    #[clap(long)]
+    pub spectate: bool,
+    /// Ask a debug server to simulate network conditions, e.g. `latency=100,loss=0.05`.
+    #[clap(long)]
+    pub impairment: Option<NetworkImpairment>,
+}
+
+impl Options {
+    /// The URL of the `/ws` endpoint, including a [`SocketQuery`].
+    pub(crate) fn url(&self) -> String {
+        let query = SocketQuery {
+            arena_id: ArenaQuery::default(),
+            session_token: None,
+            referrer: None,
+            cohort_id: Default::default(),
+            language_id: Default::default(),
+            date_created: None,
+            timezone_offset: 0,
+            user_agent: None,
+            dns: 0,
+            tcp: 0,
+            tls: 0,
+            http: 0,
+            dom: 0,
+            spectate: self.spectate,
+            impairment: self.impairment,
+        };
+        format!(
+            "{}/ws?{}",
+            self.server.trim_end_matches('/'),
+            serde_urlencoded::to_string(&query).unwrap()
+        )
+    }
+
+    pub(crate) fn ramp_interval(&self) -> Duration {
+        Duration::from_millis(self.ramp_millis)
+    }
+
+    pub(crate) fn update_interval(&self) -> Duration {
+        Duration::from_millis(self.update_millis.max(1))
+    }
+
+    pub(crate) fn ping_interval(&self) -> Duration {
+        Duration::from_millis(self.ping_millis.max(1))
+    }
+
+    pub(crate) fn report_interval(&self) -> Duration {
+        Duration::from_secs(self.report_seconds.max(1))
+    }
+
+    pub(crate) fn duration(&self) -> Option<Duration> {
+        self.duration_seconds.map(Duration::from_secs)
+    }
+}
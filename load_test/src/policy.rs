@@ -0,0 +1,51 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+use crate::{ArenaId, GameFence, PlayerId, ServerId};
+
+/// Drives the inputs of one load test client, like a `Bot` does on the server.
+pub trait LoadTestPolicy<GR, GU>: Default + Send + 'static {
+    /// Called for every game update received from the server.
+    fn receive(&mut self, _update: &GU, _state: &LoadTestState) {}
+
+    /// Called periodically. `Quit` indicates disconnecting.
+    fn update(&mut self, state: &LoadTestState) -> LoadTestAction<GR>;
+}
+
+#[derive(Debug)]
+pub enum LoadTestAction<GR> {
+    Some(GR),
+    None(&'static str),
+    Quit,
+}
+
+impl<GR> Default for LoadTestAction<GR> {
+    fn default() -> Self {
+        Self::None("default")
+    }
+}
+
+// Useful for measuring idle connections.
+impl<GR, GU> LoadTestPolicy<GR, GU> for () {
+    fn update(&mut self, _state: &LoadTestState) -> LoadTestAction<GR> {
+        LoadTestAction::None("idle")
+    }
+}
+
+/// What a load test client knows about its session.
+#[derive(Clone, Debug, Default)]
+pub struct LoadTestState {
+    /// Index of the client, in the order they were opened.
+    pub index: usize,
+    /// `None` until the session is created.
+    pub session: Option<LoadTestSession>,
+    /// Attached to game requests, so the server can discard those meant for a previous game.
+    pub game_fence: Option<GameFence>,
+}
+
+#[derive(Clone, Debug)]
+pub struct LoadTestSession {
+    pub server_id: ServerId,
+    pub arena_id: ArenaId,
+    pub player_id: PlayerId,
+}
@@ -0,0 +1,157 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+use std::fmt::{self, Display, Formatter};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Counters shared by all clients.
+#[derive(Default)]
+pub(crate) struct SharedStats {
+    connected: AtomicU64,
+    connect_failures: AtomicU64,
+    disconnects: AtomicU64,
+    requests: AtomicU64,
+    bytes_sent: AtomicU64,
+    updates: AtomicU64,
+    bytes_received: AtomicU64,
+    dropped: AtomicU64,
+    rtt_samples: AtomicU64,
+    rtt_total_millis: AtomicU64,
+    rtt_max_millis: AtomicU64,
+}
+
+impl SharedStats {
+    pub(crate) fn connected(&self) {
+        self.connected.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn connect_failed(&self) {
+        self.connect_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn disconnected(&self) {
+        self.connected.fetch_sub(1, Ordering::Relaxed);
+        self.disconnects.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn sent(&self, bytes: usize) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.bytes_sent.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn received(&self, bytes: usize) {
+        self.updates.fetch_add(1, Ordering::Relaxed);
+        self.bytes_received
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    /// An update that couldn't be decompressed or decoded, or a ping that was lost or timed out.
+    pub(crate) fn dropped(&self) {
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn rtt(&self, rtt: Duration) {
+        let millis = rtt.as_millis() as u64;
+        self.rtt_samples.fetch_add(1, Ordering::Relaxed);
+        self.rtt_total_millis.fetch_add(millis, Ordering::Relaxed);
+        self.rtt_max_millis.fetch_max(millis, Ordering::Relaxed);
+    }
+
+    /// Reads the counters, resetting the maximum RTT.
+    pub(crate) fn snapshot(&self) -> LoadTestStats {
+        LoadTestStats {
+            connected: self.connected.load(Ordering::Relaxed),
+            connect_failures: self.connect_failures.load(Ordering::Relaxed),
+            disconnects: self.disconnects.load(Ordering::Relaxed),
+            requests: self.requests.load(Ordering::Relaxed),
+            bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+            updates: self.updates.load(Ordering::Relaxed),
+            bytes_received: self.bytes_received.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            rtt_samples: self.rtt_samples.load(Ordering::Relaxed),
+            rtt_total_millis: self.rtt_total_millis.load(Ordering::Relaxed),
+            rtt_max_millis: self.rtt_max_millis.swap(0, Ordering::Relaxed),
+        }
+    }
+}
+
+/// Totals since the start of the load test, except `connected` which is current and
+/// `rtt_max_millis` which is since the previous report.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct LoadTestStats {
+    pub connected: u64,
+    pub connect_failures: u64,
+    pub disconnects: u64,
+    pub requests: u64,
+    /// WebSocket payload bytes, not counting framing.
+    pub bytes_sent: u64,
+    pub updates: u64,
+    /// Compressed WebSocket payload bytes, not counting framing.
+    pub bytes_received: u64,
+    /// Updates that couldn't be decompressed or decoded, plus pings that were lost or timed out.
+    pub dropped: u64,
+    pub rtt_samples: u64,
+    pub rtt_total_millis: u64,
+    pub rtt_max_millis: u64,
+}
+
+impl LoadTestStats {
+    /// Rates between `previous` and `self`, `elapsed` apart.
+    pub fn since(&self, previous: &Self, elapsed: Duration) -> LoadTestReport {
+        let seconds = elapsed.as_secs_f64().max(f64::EPSILON);
+        let rate = |now: u64, then: u64| now.saturating_sub(then) as f64 / seconds;
+        let rtt_samples = self.rtt_samples.saturating_sub(previous.rtt_samples);
+        LoadTestReport {
+            totals: *self,
+            requests_per_second: rate(self.requests, previous.requests),
+            sent_per_second: rate(self.bytes_sent, previous.bytes_sent),
+            updates_per_second: rate(self.updates, previous.updates),
+            received_per_second: rate(self.bytes_received, previous.bytes_received),
+            dropped: self.dropped.saturating_sub(previous.dropped),
+            rtt_average_millis: (rtt_samples > 0).then(|| {
+                self.rtt_total_millis
+                    .saturating_sub(previous.rtt_total_millis)
+                    / rtt_samples
+            }),
+        }
+    }
+}
+
+/// Statistics over one report interval.
+#[derive(Copy, Clone, Debug)]
+pub struct LoadTestReport {
+    pub totals: LoadTestStats,
+    pub requests_per_second: f64,
+    pub sent_per_second: f64,
+    pub updates_per_second: f64,
+    pub received_per_second: f64,
+    /// During the interval.
+    pub dropped: u64,
+    /// `None` if no pongs were received during the interval.
+    pub rtt_average_millis: Option<u64>,
+}
+
+impl Display for LoadTestReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let totals = &self.totals;
+        write!(
+            f,
+            "clients: {} (failed {}, disconnected {}) | up: {:.0} req/s {:.1} KiB/s | down: {:.0} upd/s {:.1} KiB/s | dropped: {} (total {}) | rtt: ",
+            totals.connected,
+            totals.connect_failures,
+            totals.disconnects,
+            self.requests_per_second,
+            self.sent_per_second / 1024.0,
+            self.updates_per_second,
+            self.received_per_second / 1024.0,
+            self.dropped,
+            totals.dropped,
+        )?;
+        if let Some(average) = self.rtt_average_millis {
+            write!(f, "avg {average}ms max {}ms", totals.rtt_max_millis)
+        } else {
+            f.write_str("n/a")
+        }
+    }
+}
@@ -0,0 +1,55 @@
+#[cfg(test)]
+mod stats_tests {
+    use crate::LoadTestStats;
+    use std::time::Duration;
+
+    #[test]
+    fn since() {
+        let previous = LoadTestStats {
+            connected: 10,
+            requests: 100,
+            bytes_sent: 1000,
+            updates: 50,
+            bytes_received: 4096,
+            dropped: 3,
+            rtt_samples: 4,
+            rtt_total_millis: 400,
+            ..Default::default()
+        };
+        let now = LoadTestStats {
+            connected: 9,
+            disconnects: 1,
+            requests: 300,
+            bytes_sent: 3000,
+            updates: 150,
+            bytes_received: 12288,
+            dropped: 5,
+            rtt_samples: 6,
+            rtt_total_millis: 700,
+            rtt_max_millis: 200,
+            ..Default::default()
+        };
+
+        let report = now.since(&previous, Duration::from_secs(2));
+        assert_eq!(report.totals.connected, 9);
+        assert_eq!(report.requests_per_second, 100.0);
+        assert_eq!(report.sent_per_second, 1000.0);
+        assert_eq!(report.updates_per_second, 50.0);
+        assert_eq!(report.received_per_second, 4096.0);
+        assert_eq!(report.dropped, 2);
+        assert_eq!(report.rtt_average_millis, Some(150));
+        assert!(report.to_string().contains("dropped: 2 (total 5)"));
+
+        // No pongs during the interval.
+        let report = previous.since(&previous, Duration::from_secs(2));
+        assert_eq!(report.requests_per_second, 0.0);
+        assert_eq!(report.rtt_average_millis, None);
+        assert!(report.to_string().ends_with("rtt: n/a"));
+
+        // Counters never go backwards, but shouldn't panic if they do.
+        let report = previous.since(&now, Duration::ZERO);
+        assert_eq!(report.requests_per_second, 0.0);
+        assert_eq!(report.dropped, 0);
+        assert!(report.updates_per_second.is_finite());
+    }
+}
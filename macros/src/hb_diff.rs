@@ -0,0 +1,143 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+use proc_macro::TokenStream;
+use proc_macro2::{Ident, Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Attribute, Data, DataEnum, DataStruct, DeriveInput, Field, Fields};
+
+/// Finds differing fields, for diagnosing desyncs.
+pub(crate) fn derive_hb_diff(input: TokenStream) -> TokenStream {
+    let DeriveInput {
+        ident,
+        data,
+        generics,
+        ..
+    } = parse_macro_input!(input);
+
+    fn field_names(fields: &Fields) -> Vec<Ident> {
+        fields
+            .iter()
+            .enumerate()
+            .map(|(i, field)| {
+                field
+                    .ident
+                    .clone()
+                    .unwrap_or_else(|| Ident::new(&format!("f{i}"), Span::mixed_site()))
+            })
+            .collect()
+    }
+
+    fn destructure_fields(fields: &Fields, prefix: &str) -> TokenStream2 {
+        let names = field_names(fields);
+        match fields {
+            Fields::Named(_) => {
+                let renamed = names.iter().map(|name| format_ident!("{}{}", prefix, name));
+                quote! {
+                    {#(#names: #renamed),*}
+                }
+            }
+            Fields::Unnamed(_) => {
+                let renamed = names.iter().map(|name| format_ident!("{}{}", prefix, name));
+                quote! {
+                    (#(#renamed),*)
+                }
+            }
+            Fields::Unit => {
+                quote! {}
+            }
+        }
+    }
+
+    fn has_attr(attrs: &[Attribute], name: &str) -> bool {
+        attrs.iter().any(|attr| {
+            attr.parse_meta()
+                .ok()
+                .map(|meta| meta.path().is_ident(name))
+                .unwrap_or(false)
+        })
+    }
+
+    fn diff_fields(fields: &Fields) -> impl Iterator<Item = TokenStream2> + '_ {
+        fields.iter().zip(field_names(fields)).enumerate().map(
+            |(
+                i,
+                (
+                    Field {
+                        ty, attrs, ident, ..
+                    },
+                    name,
+                ),
+            )| {
+                let segment = if let Some(ident) = ident {
+                    format!(".{ident}")
+                } else {
+                    format!(".{i}")
+                };
+                let a = format_ident!("a_{}", name);
+                let b = format_ident!("b_{}", name);
+                let diff = if has_attr(attrs, "hb_diff") {
+                    quote!(<#ty as kodiak_common::HbDiff>::diff(#a, #b, path, on_diff))
+                } else if has_attr(attrs, "hb_hash") {
+                    quote!(kodiak_common::hb_diff_hb_hash::<#ty>(#a, #b, path, on_diff))
+                } else {
+                    quote!(kodiak_common::hb_diff_hash::<#ty>(#a, #b, path, on_diff))
+                };
+                quote! {
+                    let len = path.len();
+                    path.push_str(#segment);
+                    #diff;
+                    path.truncate(len);
+                }
+            },
+        )
+    }
+
+    let output = match data {
+        Data::Struct(DataStruct { fields, .. }) => {
+            let destructure_a = destructure_fields(&fields, "a_");
+            let destructure_b = destructure_fields(&fields, "b_");
+            let diff_fields = diff_fields(&fields);
+
+            quote! {
+                let Self #destructure_a = self;
+                let Self #destructure_b = other;
+                #(#diff_fields)*
+            }
+        }
+        Data::Enum(DataEnum { variants, .. }) => {
+            let diff_variants = variants.iter().map(|variant| {
+                let ident = &variant.ident;
+                let destructure_a = destructure_fields(&variant.fields, "a_");
+                let destructure_b = destructure_fields(&variant.fields, "b_");
+                let diff_fields = diff_fields(&variant.fields);
+                quote! {
+                    (Self::#ident #destructure_a, Self::#ident #destructure_b) => {
+                        #(#diff_fields)*
+                    }
+                }
+            });
+
+            quote! {
+                #[allow(unreachable_patterns)]
+                match (self, other) {
+                    #(#diff_variants),*
+                    _ => on_diff(path),
+                }
+            }
+        }
+        Data::Union(_) => panic!("unions not supported"),
+    };
+
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics kodiak_common::HbDiff for #ident #ty_generics #where_clause {
+            #[allow(unused_variables)]
+            fn diff(&self, other: &Self, path: &mut String, on_diff: &mut dyn FnMut(&str)) {
+                #output
+            }
+        }
+    }
+    .into()
+}
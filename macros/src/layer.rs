@@ -136,7 +136,7 @@ pub(crate) fn derive_layer(input: LayerInput) -> TokenStream {
         stencil,
     } = input;
 
-    let [mut alphas, mut depths, mut stencils, mut pre_prepares, mut pre_renders] =
+    let [mut alphas, mut depths, mut stencils, mut pre_prepares, mut pre_renders, mut context_restoreds] =
         array::from_fn(|_| Vec::with_capacity(named.len()));
 
     let mut render_inners: HashMap<Option<Type>, _> = HashMap::new();
@@ -170,6 +170,9 @@ pub(crate) fn derive_layer(input: LayerInput) -> TokenStream {
         pre_renders.push(quote! {
             self.#ident.pre_render(renderer);
         });
+        context_restoreds.push(quote! {
+            self.#ident.context_restored(renderer);
+        });
 
         if let Some(renders) = &renders {
             if let Some(renders) = renders {
@@ -261,6 +264,9 @@ pub(crate) fn derive_layer(input: LayerInput) -> TokenStream {
             fn pre_render(&mut self, renderer: &#c::Renderer) {
                 #(#pre_renders)*
             }
+            fn context_restored(&mut self, renderer: &#c::Renderer) {
+                #(#context_restoreds)*
+            }
         }
 
         #(#render_impls)*
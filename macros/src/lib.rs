@@ -9,6 +9,7 @@
 #![feature(box_into_inner)]
 
 mod audio;
+mod hb_diff;
 mod hb_hash;
 mod layer;
 #[cfg(feature = "ply")]
@@ -80,6 +81,11 @@ pub fn derive_hb_hash(input: TokenStream) -> TokenStream {
     hb_hash::derive_hb_hash(input)
 }
 
+#[proc_macro_derive(HbDiff, attributes(hb_diff, hb_hash))]
+pub fn derive_hb_diff(input: TokenStream) -> TokenStream {
+    hb_diff::derive_hb_diff(input)
+}
+
 #[proc_macro_derive(SmolRoutable, attributes(at, not_found))]
 pub fn smol_routable_derive(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as smol_routable::SmolRoutable);
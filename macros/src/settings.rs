@@ -117,6 +117,19 @@ pub(crate) fn derive_settings(input: TokenStream) -> TokenStream {
                                                 }
                                             });
                                         });
+                                    } else if meta.path.is_ident("binding") {
+                                        let label = if let Lit::Str(s) = meta.lit {
+                                            s.value()
+                                        } else {
+                                            panic!("must label as string");
+                                        };
+                                        let (category, label) =
+                                            label.split_once('/').unwrap_or(("Controls", &label));
+
+                                        let category = Ident::new(category, Span::call_site());
+                                        displayers.push(quote! {
+                                            binding(SettingCategory::#category, settings_prerequisites::translate!(t, #label), self.#ident, Self::#setter_name);
+                                        });
                                     } else if meta.path.is_ident("slider") {
                                         let label = if let Lit::Str(s) = meta.lit {
                                             s.value()
@@ -321,8 +334,14 @@ pub(crate) fn derive_settings(input: TokenStream) -> TokenStream {
                             std::ops::RangeInclusive<f32>,
                             fn(&mut Self, f32, &mut BrowserStorages)
                         ),
+                        mut binding: impl FnMut(
+                            SettingCategory,
+                            String,
+                            settings_prerequisites::Binding,
+                            fn(&mut Self, settings_prerequisites::Binding, &mut BrowserStorages)
+                        ),
                     ) {
-                        let _ = (&mut checkbox, &mut dropdown, &mut slider);
+                        let _ = (&mut checkbox, &mut dropdown, &mut slider, &mut binding);
                         #(#displayers)*
                     }
                 }
@@ -0,0 +1,85 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! A stand-in for plasma, Softbear's proprietary backend, so that game servers can run fully
+//! offline (e.g. when self-hosting or in CI). Implements authentication, claims, leaderboards,
+//! team names, files, chat relay, server messages, and topology, with file-backed storage.
+//!
+//! Run `mini_plasma`, then run each game server with `--plasma-url ws://localhost:8081/ws/`.
+
+mod options;
+mod plasma;
+mod socket;
+mod storage;
+
+use crate::options::Options;
+use crate::plasma::MiniPlasma;
+use crate::socket::{ws_request, AppState};
+use crate::storage::Storage;
+use axum::routing::any;
+use axum::Router;
+use clap::Parser;
+use std::process::ExitCode;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How often changes are saved to disk.
+const SAVE_PERIOD: Duration = Duration::from_secs(5);
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let options = Options::parse();
+    let storage = match Storage::load(options.data_dir.clone()) {
+        Ok(storage) => storage,
+        Err(e) => {
+            eprintln!("could not load {:?}: {e}", options.data_dir);
+            return ExitCode::FAILURE;
+        }
+    };
+    let addr = options.socket_addr();
+    let state = AppState {
+        server_token: options.server_token,
+        plasma: Arc::new(Mutex::new(MiniPlasma::new(options, storage))),
+    };
+
+    let plasma = Arc::clone(&state.plasma);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SAVE_PERIOD);
+        loop {
+            interval.tick().await;
+            if let Err(e) = plasma.lock().unwrap().storage_mut().save() {
+                eprintln!("could not save: {e}");
+            }
+        }
+    });
+
+    let app = Router::new()
+        .route("/ws", any(ws_request))
+        .route("/ws/", any(ws_request))
+        .with_state(state.clone());
+    let listener = match tokio::net::TcpListener::bind(addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("could not bind {addr}: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    println!("listening on ws://{addr}/ws/");
+    let result = axum::serve(listener, app)
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await;
+
+    if let Err(e) = state.plasma.lock().unwrap().storage_mut().save() {
+        eprintln!("could not save: {e}");
+        return ExitCode::FAILURE;
+    }
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,43 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+use clap::Parser;
+use plasma_protocol::{RegionId, ServerToken, VisitorId};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+
+/// Mini plasma options, to be specified as arguments.
+#[derive(Debug, Parser)]
+pub struct Options {
+    /// Address to listen on. Game servers should be run with
+    /// `--plasma-url ws://{address}:{port}/ws/`.
+    #[clap(long, default_value_t = IpAddr::V4(Ipv4Addr::LOCALHOST))]
+    pub address: IpAddr,
+    #[clap(long, default_value = "8081")]
+    pub port: u16,
+    /// Directory for sessions, claims, leaderboards, and saved files.
+    #[clap(long, default_value = "./mini_plasma")]
+    pub data_dir: PathBuf,
+    /// If specified, game servers must connect with this token.
+    #[clap(long)]
+    pub server_token: Option<ServerToken>,
+    /// Region reported in topology.
+    #[clap(long, default_value = "NorthAmerica")]
+    pub region_id: RegionId,
+    /// Number of scores kept per leaderboard.
+    #[clap(long, default_value = "10")]
+    pub leaderboard_size: usize,
+    /// Visitors with in-game admin privileges (may be repeated). Visitor IDs are logged when
+    /// players authenticate.
+    #[clap(long = "admin")]
+    pub admins: Vec<VisitorId>,
+    /// Visitors with in-game moderator privileges (may be repeated).
+    #[clap(long = "moderator")]
+    pub moderators: Vec<VisitorId>,
+}
+
+impl Options {
+    pub(crate) fn socket_addr(&self) -> SocketAddr {
+        SocketAddr::new(self.address, self.port)
+    }
+}
@@ -0,0 +1,537 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+use crate::storage::Storage;
+use crate::Options;
+use plasma_protocol::rustrict::CensorStr;
+use plasma_protocol::{
+    ChatId, ChatMessage, ChatRecipient, ClaimUpdateDto, ClientHash, FileLoadedResult,
+    FileNamespace, GameId, NonZeroUnixMillis, PlasmaRequest, PlasmaRequestV1, PlasmaUpdate,
+    PlasmaUpdateV1, RealmHeartbeat, RealmId, RealmUseTopology, SceneId, SceneUseTopology, ServerId,
+    ServerRole, ServerUseTopology, TeamName, TeamToken,
+};
+use rand::{thread_rng, Rng};
+use std::collections::{BTreeMap, HashMap};
+use tokio::sync::mpsc::UnboundedSender;
+
+/// A connected game server.
+struct Server {
+    /// Distinguishes a reconnection from the connection it replaced.
+    connection_id: u64,
+    game_id: GameId,
+    sender: UnboundedSender<PlasmaUpdate>,
+    /// From the latest heartbeat.
+    client_hash: Option<ClientHash>,
+    /// From the latest heartbeat.
+    realms: BTreeMap<RealmId, RealmHeartbeat>,
+}
+
+struct TeamReservation {
+    team_token: TeamToken,
+    expires: NonZeroUnixMillis,
+}
+
+/// Implements the plasma protocol for any number of game servers, without the rest of plasma
+/// (accounts, translations, metrics dashboards, etc.).
+pub(crate) struct MiniPlasma {
+    options: Options,
+    storage: Storage,
+    servers: HashMap<ServerId, Server>,
+    team_names: HashMap<(GameId, RealmId, TeamName), TeamReservation>,
+    next_connection_id: u64,
+}
+
+impl MiniPlasma {
+    pub(crate) fn new(options: Options, storage: Storage) -> Self {
+        Self {
+            options,
+            storage,
+            servers: HashMap::new(),
+            team_names: HashMap::new(),
+            next_connection_id: 0,
+        }
+    }
+
+    pub(crate) fn storage_mut(&mut self) -> &mut Storage {
+        &mut self.storage
+    }
+
+    /// Registers a connection, replacing any previous connection of the same server, and returns
+    /// its `connection_id`.
+    pub(crate) fn connected(
+        &mut self,
+        game_id: GameId,
+        server_id: ServerId,
+        sender: UnboundedSender<PlasmaUpdate>,
+    ) -> u64 {
+        self.next_connection_id += 1;
+        let connection_id = self.next_connection_id;
+        println!("{server_id} ({game_id}) connected");
+        self.servers.insert(
+            server_id,
+            Server {
+                connection_id,
+                game_id,
+                sender,
+                client_hash: None,
+                realms: Default::default(),
+            },
+        );
+        connection_id
+    }
+
+    pub(crate) fn disconnected(&mut self, server_id: ServerId, connection_id: u64) {
+        if self
+            .servers
+            .get(&server_id)
+            .is_some_and(|server| server.connection_id == connection_id)
+        {
+            self.unregister(server_id);
+        }
+    }
+
+    fn unregister(&mut self, server_id: ServerId) {
+        if let Some(server) = self.servers.remove(&server_id) {
+            println!("{server_id} ({}) disconnected", server.game_id);
+            self.send_topology(server.game_id);
+        }
+    }
+
+    fn send(&self, server_id: ServerId, update: PlasmaUpdateV1) {
+        if let Some(server) = self.servers.get(&server_id) {
+            let _ = server
+                .sender
+                .send(PlasmaUpdate::V1(vec![update].into_boxed_slice()));
+        }
+    }
+
+    /// Servers that may exchange players, chat, and messages with `server_id`.
+    fn peers(&self, server_id: ServerId) -> impl Iterator<Item = ServerId> + '_ {
+        let server = self.servers.get(&server_id);
+        self.servers
+            .iter()
+            .filter(move |&(&other_id, other)| {
+                server.is_some_and(|server| {
+                    other.game_id == server.game_id && other_id.kind == server_id.kind
+                })
+            })
+            .map(|(&other_id, _)| other_id)
+    }
+
+    fn topology(&self, server_id: ServerId) -> HashMap<ServerId, ServerUseTopology> {
+        let client_hash = self.servers.get(&server_id).and_then(|s| s.client_hash);
+        self.peers(server_id)
+            .filter_map(|other_id| {
+                let other = &self.servers[&other_id];
+                if other_id != server_id
+                    && client_hash.is_some()
+                    && other.client_hash != client_hash
+                {
+                    // Incompatible.
+                    return None;
+                }
+                let mut realms = other
+                    .realms
+                    .iter()
+                    .map(|(&realm_id, realm)| {
+                        (
+                            realm_id,
+                            RealmUseTopology {
+                                acl: Default::default(),
+                                scenes: realm
+                                    .scenes
+                                    .iter()
+                                    .map(|(&scene_id, scene)| {
+                                        (
+                                            scene_id,
+                                            SceneUseTopology {
+                                                player_count: scene.player_count,
+                                                settings: None,
+                                            },
+                                        )
+                                    })
+                                    .collect(),
+                            },
+                        )
+                    })
+                    .collect::<HashMap<_, _>>();
+                let default_realm = realms.remove(&RealmId::PublicDefault).unwrap_or_else(|| {
+                    // Before the first heartbeat.
+                    RealmUseTopology {
+                        acl: Default::default(),
+                        scenes: [(
+                            SceneId::default(),
+                            SceneUseTopology {
+                                player_count: 0,
+                                settings: None,
+                            },
+                        )]
+                        .into_iter()
+                        .collect(),
+                    }
+                });
+                Some((
+                    other_id,
+                    ServerUseTopology {
+                        datacenter: "local".to_owned(),
+                        default_realm: Some(default_realm),
+                        other_realms: realms,
+                        region_id: self.options.region_id,
+                    },
+                ))
+            })
+            .collect()
+    }
+
+    /// Sends topology to every server of a game.
+    fn send_topology(&self, game_id: GameId) {
+        for (&server_id, server) in &self.servers {
+            if server.game_id == game_id {
+                self.send(
+                    server_id,
+                    PlasmaUpdateV1::Topology {
+                        servers: self.topology(server_id),
+                    },
+                );
+            }
+        }
+    }
+
+    pub(crate) fn handle(&mut self, server_id: ServerId, request: PlasmaRequest) {
+        let Some(game_id) = self.servers.get(&server_id).map(|s| s.game_id) else {
+            return;
+        };
+        let PlasmaRequest::V1(request) = request;
+        match request {
+            PlasmaRequestV1::AuthenticatePlayer {
+                arena_id,
+                arena_token,
+                player_id,
+                session_token,
+            } => {
+                let visitor_id = self.storage.visitor_id(session_token);
+                println!("{server_id}/{arena_id}: {player_id:?} authenticated as {visitor_id}");
+                let admin = self.options.admins.contains(&visitor_id);
+                self.send(
+                    server_id,
+                    PlasmaUpdateV1::Player {
+                        active_heartbeat: false,
+                        admin,
+                        arena_id,
+                        arena_token,
+                        ban: false,
+                        moderator: admin || self.options.moderators.contains(&visitor_id),
+                        nick_name: None,
+                        player_id,
+                        session_token,
+                        user: false,
+                        visitor_id,
+                    },
+                );
+                if let Some(claims) = self.storage.claims(visitor_id) {
+                    let claims = claims.subset(game_id, arena_id.realm_id);
+                    self.send(
+                        server_id,
+                        PlasmaUpdateV1::Claims {
+                            claims: vec![ClaimUpdateDto {
+                                arena_id,
+                                claims,
+                                player_id,
+                                visitor_id,
+                            }]
+                            .into(),
+                        },
+                    );
+                }
+            }
+            PlasmaRequestV1::Heartbeat {
+                claims,
+                client_hash,
+                realms,
+                ..
+            } => {
+                let mut changed_claims = Vec::new();
+                for dto in Vec::from(claims) {
+                    let (subset, _) = self.storage.claims_mut(dto.visitor_id).merge(
+                        &dto.claims,
+                        game_id,
+                        dto.arena_id.realm_id,
+                    );
+                    if let Some(claims) = subset.filter(|subset| !subset.is_empty()) {
+                        changed_claims.push(ClaimUpdateDto { claims, ..dto });
+                    }
+                }
+                if !changed_claims.is_empty() {
+                    self.send(
+                        server_id,
+                        PlasmaUpdateV1::Claims {
+                            claims: changed_claims.into(),
+                        },
+                    );
+                }
+
+                let server = self.servers.get_mut(&server_id).unwrap();
+                let changed = server.client_hash != Some(client_hash) || server.realms != realms;
+                server.client_hash = Some(client_hash);
+                server.realms = realms;
+                self.send(server_id, PlasmaUpdateV1::Heartbeat {});
+                self.send(
+                    server_id,
+                    PlasmaUpdateV1::Role {
+                        role: ServerRole::Public,
+                    },
+                );
+                if changed {
+                    self.send_topology(game_id);
+                } else {
+                    // Self-healing.
+                    self.send(
+                        server_id,
+                        PlasmaUpdateV1::Topology {
+                            servers: self.topology(server_id),
+                        },
+                    );
+                }
+            }
+            PlasmaRequestV1::LoadFile {
+                file_namespace,
+                file_path,
+                accept_content_type,
+                visitor_id,
+                arena_id,
+                player_id,
+            } => {
+                let result =
+                    match (&file_namespace, visitor_id) {
+                        (FileNamespace::RequestVisitorId, Some(visitor_id)) => self
+                            .storage
+                            .load_file(visitor_id, &file_path, accept_content_type.as_deref()),
+                        (FileNamespace::RequestVisitorId, None) => FileLoadedResult::Forbidden,
+                        // There are no accounts, so there are no nick names.
+                        (FileNamespace::NickName(_), _) => FileLoadedResult::NotFound,
+                    };
+                self.send(
+                    server_id,
+                    PlasmaUpdateV1::FileLoaded {
+                        file_namespace,
+                        file_path,
+                        arena_id,
+                        player_id,
+                        visitor_id,
+                        result,
+                    },
+                );
+            }
+            PlasmaRequestV1::ModerateAbuse { chat_id, .. } => {
+                println!("{server_id}: abuse reported in {chat_id} (ignored)");
+            }
+            PlasmaRequestV1::ModerateChat { arena_id, .. } => {
+                println!("{server_id}/{arena_id}: chat moderated (ignored)");
+            }
+            PlasmaRequestV1::RegisterServer { .. } => {
+                self.send(
+                    server_id,
+                    PlasmaUpdateV1::Role {
+                        role: ServerRole::Public,
+                    },
+                );
+                for (realm_id, period_id, scores) in self.storage.leaderboards(game_id) {
+                    self.send(
+                        server_id,
+                        PlasmaUpdateV1::Leaderboard {
+                            period_id,
+                            realm_id,
+                            scores,
+                        },
+                    );
+                }
+                self.send_topology(game_id);
+            }
+            PlasmaRequestV1::ReleaseTeamName {
+                arena_id,
+                team_name,
+                team_token,
+                ..
+            } => {
+                let key = (game_id, arena_id.realm_id, team_name);
+                if self
+                    .team_names
+                    .get(&key)
+                    .is_some_and(|reservation| reservation.team_token == team_token)
+                {
+                    self.team_names.remove(&key);
+                }
+            }
+            PlasmaRequestV1::ReserveTeamName {
+                arena_id,
+                expires,
+                player_id,
+                team_name,
+                team_token,
+            } => {
+                let now = NonZeroUnixMillis::now();
+                let expires = expires.unwrap_or(now.add_millis(60 * 60 * 1000));
+                let key = (game_id, arena_id.realm_id, team_name);
+                if expires <= now {
+                    // Equivalent to release.
+                    if team_token.is_some_and(|team_token| {
+                        self.team_names
+                            .get(&key)
+                            .is_some_and(|reservation| reservation.team_token == team_token)
+                    }) {
+                        self.team_names.remove(&key);
+                    }
+                    return;
+                }
+                let available = match self.team_names.get(&key) {
+                    Some(reservation) => {
+                        reservation.expires <= now || Some(reservation.team_token) == team_token
+                    }
+                    None => true,
+                };
+                if available {
+                    let team_token = team_token.unwrap_or_else(|| TeamToken(thread_rng().gen()));
+                    self.team_names.insert(
+                        key,
+                        TeamReservation {
+                            team_token,
+                            expires,
+                        },
+                    );
+                    self.send(
+                        server_id,
+                        PlasmaUpdateV1::TeamName {
+                            arena_id,
+                            player_id,
+                            team_name,
+                            team_token,
+                        },
+                    );
+                }
+            }
+            PlasmaRequestV1::SaveFile {
+                content_data,
+                content_type,
+                file_path,
+                visitor_id,
+                arena_id,
+                player_id,
+            } => {
+                let error = self
+                    .storage
+                    .save_file(visitor_id, &file_path, content_type, content_data)
+                    .err();
+                self.send(
+                    server_id,
+                    PlasmaUpdateV1::FileSaved {
+                        file_path,
+                        visitor_id,
+                        arena_id,
+                        player_id,
+                        error,
+                    },
+                );
+            }
+            PlasmaRequestV1::SendChat {
+                admin,
+                alias,
+                arena_id,
+                authentic,
+                ip_address,
+                message,
+                player_id,
+                team_name,
+                timestamp,
+                visitor_id,
+                recipient,
+            } => {
+                println!("{server_id}/{arena_id}: {alias}: {message}");
+                let update = PlasmaUpdateV1::Chat {
+                    admin,
+                    alias,
+                    authentic,
+                    chat_id: ChatId {
+                        arena_id,
+                        message_id: timestamp,
+                        server_id,
+                    },
+                    ip_address,
+                    message: ChatMessage::Raw {
+                        message: if admin { message } else { message.censor() },
+                        detected_language_id: Default::default(),
+                        english_translation: None,
+                    },
+                    player_id,
+                    recipient,
+                    team_name,
+                    visitor_id,
+                };
+                match recipient {
+                    ChatRecipient::Broadcast => {
+                        // Servers ignore chat for realms they don't host.
+                        for peer_id in self.peers(server_id) {
+                            self.send(peer_id, update.clone());
+                        }
+                    }
+                    ChatRecipient::Arena | ChatRecipient::Player(_) | ChatRecipient::TeamOf(_) => {
+                        self.send(server_id, update);
+                    }
+                    ChatRecipient::None => {}
+                }
+            }
+            PlasmaRequestV1::SendServerMessage {
+                message,
+                recipients,
+            } => {
+                let peers = self.peers(server_id).collect::<Vec<_>>();
+                for recipient in recipients {
+                    if peers.contains(&recipient) {
+                        self.send(
+                            recipient,
+                            PlasmaUpdateV1::Parley {
+                                message: message.clone(),
+                                sender: server_id,
+                            },
+                        );
+                    }
+                }
+            }
+            PlasmaRequestV1::UnregisterServer => {
+                self.unregister(server_id);
+            }
+            PlasmaRequestV1::UpdateLeaderboards { realm_id, scores } => {
+                let changed = self.storage.update_leaderboards(
+                    game_id,
+                    realm_id,
+                    &scores,
+                    self.options.leaderboard_size,
+                );
+                for (period_id, scores) in changed {
+                    for (&other_id, other) in &self.servers {
+                        if other.game_id == game_id {
+                            self.send(
+                                other_id,
+                                PlasmaUpdateV1::Leaderboard {
+                                    period_id,
+                                    realm_id,
+                                    scores: scores.clone(),
+                                },
+                            );
+                        }
+                    }
+                }
+            }
+            PlasmaRequestV1::UpdateServerLog { server_log } => {
+                for log in Vec::from(server_log) {
+                    println!(
+                        "{server_id} [{:?}] {}: {}",
+                        log.level, log.source, log.message
+                    );
+                }
+            }
+            PlasmaRequestV1::UpdateMetrics { .. } | PlasmaRequestV1::UpdateQuestSamples { .. } => {
+                // Not stored.
+            }
+        }
+    }
+}
@@ -0,0 +1,81 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+use crate::plasma::MiniPlasma;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum_tws::{Message, WebSocket, WebSocketUpgrade};
+use plasma_protocol::{GameId, PlasmaRequest, ServerId, ServerToken, WebsocketConnectQuery};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::unbounded_channel;
+
+#[derive(Clone)]
+pub(crate) struct AppState {
+    pub(crate) plasma: Arc<Mutex<MiniPlasma>>,
+    pub(crate) server_token: Option<ServerToken>,
+}
+
+/// Accepts a game server's connection, like `wss://softbear.com/ws/`.
+pub(crate) async fn ws_request(
+    State(state): State<AppState>,
+    upgrade: WebSocketUpgrade,
+    Query(query): Query<WebsocketConnectQuery>,
+) -> Result<Response, Response> {
+    if state
+        .server_token
+        .is_some_and(|server_token| server_token != query.server_token)
+    {
+        return Err((StatusCode::UNAUTHORIZED, "invalid server token").into_response());
+    }
+    Ok(upgrade.on_upgrade(move |web_socket| {
+        serve(web_socket, query.game_id, query.server_id, state.plasma)
+    }))
+}
+
+async fn serve(
+    mut web_socket: WebSocket,
+    game_id: GameId,
+    server_id: ServerId,
+    plasma: Arc<Mutex<MiniPlasma>>,
+) {
+    let (sender, mut receiver) = unbounded_channel();
+    let connection_id = plasma.lock().unwrap().connected(game_id, server_id, sender);
+    loop {
+        tokio::select! {
+            received = web_socket.recv() => {
+                let Some(Ok(message)) = received else {
+                    break;
+                };
+                if message.is_close() {
+                    break;
+                }
+                let Some(text) = message.as_text() else {
+                    continue;
+                };
+                match serde_json::from_str::<PlasmaRequest>(text) {
+                    Ok(request) => plasma.lock().unwrap().handle(server_id, request),
+                    Err(e) => {
+                        eprintln!("{server_id}: couldn't deserialize {text} due to {e}");
+                        // Like plasma, surface the problem to the game server's log.
+                        let _ = web_socket.send(Message::text(format!("invalid request: {e}"))).await;
+                    }
+                }
+            }
+            update = receiver.recv() => {
+                // Replaced by a newer connection.
+                let Some(update) = update else {
+                    break;
+                };
+                let json = serde_json::to_string(&update).unwrap();
+                if web_socket.send(Message::text(json)).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+    plasma
+        .lock()
+        .unwrap()
+        .disconnected(server_id, connection_id);
+}
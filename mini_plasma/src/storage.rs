@@ -0,0 +1,319 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+use plasma_protocol::{
+    ClaimSet, FileLoadedResult, GameId, LeaderboardScoreDto, NonZeroUnixMillis, PeriodId, RealmId,
+    SessionToken, UnixTime, VisitorId,
+};
+use rand::{thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+/// Everything that outlives a restart, except files.
+#[derive(Default, Serialize, Deserialize)]
+struct State {
+    #[serde(default)]
+    sessions: HashMap<SessionToken, VisitorId>,
+    #[serde(default)]
+    claims: HashMap<VisitorId, ClaimSet>,
+    #[serde(default)]
+    leaderboards: HashMap<GameId, HashMap<RealmId, HashMap<PeriodId, Leaderboard>>>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Leaderboard {
+    /// [`PeriodId::period`], so that stale leaderboards are cleared.
+    period: i64,
+    /// Sorted by descending score.
+    scores: Vec<LeaderboardScoreDto>,
+}
+
+/// A file saved by `SaveFile`.
+#[derive(Serialize, Deserialize)]
+struct StoredFile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    content_type: Option<String>,
+    content_data: Vec<u8>,
+}
+
+/// File-backed storage, in [`Options::data_dir`](crate::Options::data_dir).
+pub(crate) struct Storage {
+    dir: PathBuf,
+    state: State,
+    /// Whether `state` changed since it was last saved.
+    dirty: bool,
+}
+
+impl Storage {
+    const STATE: &'static str = "state.json";
+    const FILES: &'static str = "files";
+
+    pub(crate) fn load(dir: PathBuf) -> io::Result<Self> {
+        std::fs::create_dir_all(dir.join(Self::FILES))?;
+        let state = match std::fs::read_to_string(dir.join(Self::STATE)) {
+            Ok(json) => serde_json::from_str(&json)
+                .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?,
+            Err(e) if e.kind() == ErrorKind::NotFound => State::default(),
+            Err(e) => return Err(e),
+        };
+        Ok(Self {
+            dir,
+            state,
+            dirty: false,
+        })
+    }
+
+    /// Writes the state to disk, if it changed.
+    pub(crate) fn save(&mut self) -> io::Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+        let json = serde_json::to_string(&self.state).map_err(io::Error::from)?;
+        // Write then rename, so a crash doesn't leave a truncated file.
+        let temporary = self.dir.join(format!("{}.tmp", Self::STATE));
+        std::fs::write(&temporary, json)?;
+        std::fs::rename(temporary, self.dir.join(Self::STATE))?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    /// Returns the visitor of a session, creating one if the session is new.
+    pub(crate) fn visitor_id(&mut self, session_token: SessionToken) -> VisitorId {
+        *self.state.sessions.entry(session_token).or_insert_with(|| {
+            self.dirty = true;
+            VisitorId(thread_rng().gen())
+        })
+    }
+
+    pub(crate) fn claims(&self, visitor_id: VisitorId) -> Option<&ClaimSet> {
+        self.state.claims.get(&visitor_id)
+    }
+
+    pub(crate) fn claims_mut(&mut self, visitor_id: VisitorId) -> &mut ClaimSet {
+        // Pessimistic, but callers usually mutate.
+        self.dirty = true;
+        self.state.claims.entry(visitor_id).or_default()
+    }
+
+    /// Returns the current leaderboards of a game.
+    pub(crate) fn leaderboards(
+        &mut self,
+        game_id: GameId,
+    ) -> Vec<(RealmId, PeriodId, Box<[LeaderboardScoreDto]>)> {
+        let Some(realms) = self.state.leaderboards.get_mut(&game_id) else {
+            return Vec::new();
+        };
+        let mut ret = Vec::new();
+        for (&realm_id, leaderboards) in realms.iter_mut() {
+            for (&period_id, leaderboard) in leaderboards.iter_mut() {
+                if expire(period_id, leaderboard) {
+                    self.dirty = true;
+                }
+                ret.push((realm_id, period_id, leaderboard.scores.clone().into()));
+            }
+        }
+        ret
+    }
+
+    /// Merges scores into the leaderboards of a realm, returning those that changed.
+    pub(crate) fn update_leaderboards(
+        &mut self,
+        game_id: GameId,
+        realm_id: RealmId,
+        scores: &[LeaderboardScoreDto],
+        size: usize,
+    ) -> Vec<(PeriodId, Box<[LeaderboardScoreDto]>)> {
+        let leaderboards = self
+            .state
+            .leaderboards
+            .entry(game_id)
+            .or_default()
+            .entry(realm_id)
+            .or_default();
+        let mut ret = Vec::new();
+        for period_id in PeriodId::iter() {
+            let leaderboard = leaderboards.entry(period_id).or_default();
+            let mut changed = expire(period_id, leaderboard);
+            for score in scores {
+                changed |=
+                    LeaderboardScoreDto::insert(&mut leaderboard.scores, score.clone(), size);
+            }
+            if changed {
+                self.dirty = true;
+                ret.push((period_id, leaderboard.scores.clone().into()));
+            }
+        }
+        ret
+    }
+
+    pub(crate) fn save_file(
+        &self,
+        visitor_id: VisitorId,
+        file_path: &str,
+        content_type: Option<String>,
+        content_data: Vec<u8>,
+    ) -> Result<(), String> {
+        let path = self.file_path(visitor_id, file_path)?;
+        let json = serde_json::to_vec(&StoredFile {
+            content_type,
+            content_data,
+        })
+        .map_err(|e| e.to_string())?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        std::fs::write(path, json).map_err(|e| e.to_string())
+    }
+
+    pub(crate) fn load_file(
+        &self,
+        visitor_id: VisitorId,
+        file_path: &str,
+        accept_content_type: Option<&str>,
+    ) -> FileLoadedResult {
+        let path = match self.file_path(visitor_id, file_path) {
+            Ok(path) => path,
+            Err(e) => return FileLoadedResult::Error(e),
+        };
+        let json = match std::fs::read(path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == ErrorKind::NotFound => return FileLoadedResult::NotFound,
+            Err(e) => return FileLoadedResult::Error(e.to_string()),
+        };
+        let file = match serde_json::from_slice::<StoredFile>(&json) {
+            Ok(file) => file,
+            Err(e) => return FileLoadedResult::Error(e.to_string()),
+        };
+        if accept_content_type.is_some_and(|accept| file.content_type.as_deref() != Some(accept)) {
+            return FileLoadedResult::TypeMismatch;
+        }
+        FileLoadedResult::Loaded {
+            content_data: file.content_data,
+            content_type: file.content_type,
+        }
+    }
+
+    /// Maps a visitor's file to a path in the data directory, rejecting any that could escape it.
+    fn file_path(&self, visitor_id: VisitorId, file_path: &str) -> Result<PathBuf, String> {
+        let mut path = self.dir.join(Self::FILES).join(visitor_id.to_string());
+        if file_path.is_empty() || file_path.len() > 256 {
+            return Err("invalid file path length".to_owned());
+        }
+        for component in file_path.split('/') {
+            if component.is_empty()
+                || component.starts_with('.')
+                || !component
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+            {
+                return Err("invalid file path".to_owned());
+            }
+            path.push(Path::new(component));
+        }
+        Ok(path)
+    }
+}
+
+/// Clears a daily or weekly leaderboard if its period is over, returning whether it was cleared.
+fn expire(period_id: PeriodId, leaderboard: &mut Leaderboard) -> bool {
+    let period = period_id.period(NonZeroUnixMillis::now());
+    if leaderboard.period == period {
+        return false;
+    }
+    leaderboard.period = period;
+    let cleared = !leaderboard.scores.is_empty();
+    leaderboard.scores.clear();
+    cleared
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{State, Storage};
+    use plasma_protocol::{GameId, LeaderboardScoreDto, PeriodId, PlayerAlias, RealmId, VisitorId};
+    use std::num::NonZeroU64;
+    use std::path::PathBuf;
+
+    fn storage() -> Storage {
+        Storage {
+            dir: PathBuf::from("data"),
+            state: State::default(),
+            dirty: false,
+        }
+    }
+
+    fn score(alias: &str, score: u32) -> LeaderboardScoreDto {
+        LeaderboardScoreDto {
+            alias: PlayerAlias::new_unsanitized(alias),
+            score,
+        }
+    }
+
+    #[test]
+    fn file_path() {
+        let storage = storage();
+        let visitor_id = VisitorId(NonZeroU64::new(42).unwrap());
+
+        assert_eq!(
+            storage.file_path(visitor_id, "a/b-c_d.json"),
+            Ok(PathBuf::from("data/files/42/a/b-c_d.json"))
+        );
+        for invalid in [
+            "",
+            ".",
+            "..",
+            "../x",
+            "a/../b",
+            "a/..",
+            ".hidden",
+            "a/.hidden",
+            "/a",
+            "a/",
+            "a//b",
+            "a\\..\\b",
+            "a b",
+            "ä",
+            &"a".repeat(257),
+        ] {
+            assert!(
+                storage.file_path(visitor_id, invalid).is_err(),
+                "{invalid:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn update_leaderboards() {
+        let mut storage = storage();
+        let game_id = GameId::Mk48;
+        let realm_id = RealmId::PublicDefault;
+
+        let changed =
+            storage.update_leaderboards(game_id, realm_id, &[score("a", 5), score("b", 7)], 2);
+        assert_eq!(changed.len(), PeriodId::iter().count());
+        assert!(storage.dirty);
+        for (_, scores) in &changed {
+            assert_eq!(&**scores, &[score("b", 7), score("a", 5)]);
+        }
+
+        // Lower score of an existing alias and a score that doesn't make the cut.
+        let changed =
+            storage.update_leaderboards(game_id, realm_id, &[score("b", 6), score("c", 1)], 2);
+        assert!(changed.is_empty());
+
+        // Higher score of an existing alias replaces it rather than adding a duplicate.
+        let changed = storage.update_leaderboards(game_id, realm_id, &[score("a", 9)], 2);
+        assert_eq!(changed.len(), PeriodId::iter().count());
+        for (_, scores) in &changed {
+            assert_eq!(&**scores, &[score("a", 9), score("b", 7)]);
+        }
+
+        let leaderboards = storage.leaderboards(game_id);
+        assert_eq!(leaderboards.len(), PeriodId::iter().count());
+        for (r, _, scores) in leaderboards {
+            assert_eq!(r, realm_id);
+            assert_eq!(&*scores, &[score("a", 9), score("b", 7)]);
+        }
+    }
+}
@@ -72,3 +72,33 @@ impl Ord for LeaderboardScoreDto {
             .then_with(|| self.alias.cmp(&other.alias))
     }
 }
+
+impl AsRef<LeaderboardScoreDto> for LeaderboardScoreDto {
+    fn as_ref(&self) -> &Self {
+        self
+    }
+}
+
+impl LeaderboardScoreDto {
+    /// Adds `score` to a leaderboard that is deduplicated by alias, sorted by descending score,
+    /// and has at most `size` scores. Returns whether the leaderboard changed.
+    pub fn insert<T: AsRef<Self>>(leaderboard: &mut Vec<T>, score: T, size: usize) -> bool {
+        let new = score.as_ref();
+        if let Some(index) = leaderboard
+            .iter()
+            .position(|existing| existing.as_ref().alias == new.alias)
+        {
+            if new.score <= leaderboard[index].as_ref().score {
+                return false;
+            }
+            leaderboard.remove(index);
+        }
+        let index = leaderboard.partition_point(|existing| existing.as_ref() > new);
+        if index >= size {
+            return false;
+        }
+        leaderboard.insert(index, score);
+        leaderboard.truncate(size);
+        true
+    }
+}
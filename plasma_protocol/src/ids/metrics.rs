@@ -1,6 +1,7 @@
 // SPDX-FileCopyrightText: 2024 Softbear, Inc.
 // SPDX-License-Identifier: LGPL-3.0-or-later
 
+use crate::{NonZeroUnixMillis, UnixTime};
 use bitcode::{Decode, Encode};
 use serde::{Deserialize, Serialize};
 use std::fmt::{self, Debug, Display, Formatter};
@@ -81,6 +82,18 @@ impl PeriodId {
     pub fn iter() -> impl Iterator<Item = Self> {
         <Self as IntoEnumIterator>::iter()
     }
+
+    /// Returns an index that changes when a leaderboard of this period rolls over.
+    pub fn period(self, timestamp: NonZeroUnixMillis) -> i64 {
+        const DAY_MILLIS: i64 = 24 * 60 * 60 * 1000;
+        let days = timestamp.to_i64().div_euclid(DAY_MILLIS);
+        match self {
+            Self::AllTime => 0,
+            Self::Daily => days,
+            // The Unix epoch was a Thursday, so weeks start on Monday.
+            Self::Weekly => (days + 3).div_euclid(7),
+        }
+    }
 }
 
 /// Mirrors <https://github.com/finnbear/db_ip>: `Region`.
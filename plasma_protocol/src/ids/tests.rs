@@ -3,7 +3,10 @@
 
 #[cfg(test)]
 mod tests {
-    use crate::{InvitationId, PlayerId, ServerNumber};
+    use crate::{
+        InvitationId, LeaderboardScoreDto, NonZeroUnixMillis, PeriodId, PlayerAlias, PlayerId,
+        ServerNumber, UnixTime,
+    };
     use std::str::FromStr;
 
     /*#[test]
@@ -43,4 +46,101 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn period() {
+        const DAY: i64 = 24 * 60 * 60 * 1000;
+        // Monday, 2024-01-01 00:00 UTC.
+        let monday = 1704067200000;
+        let period = |period_id: PeriodId, millis: i64| {
+            period_id.period(NonZeroUnixMillis::from_i64(millis))
+        };
+
+        for millis in [1, monday - 1, monday, monday + 10 * DAY] {
+            assert_eq!(period(PeriodId::AllTime, millis), 0);
+        }
+        assert_eq!(
+            period(PeriodId::Daily, monday - 1) + 1,
+            period(PeriodId::Daily, monday)
+        );
+        assert_eq!(
+            period(PeriodId::Daily, monday),
+            period(PeriodId::Daily, monday + DAY - 1)
+        );
+        assert_eq!(
+            period(PeriodId::Weekly, monday - 1) + 1,
+            period(PeriodId::Weekly, monday)
+        );
+        assert_eq!(
+            period(PeriodId::Weekly, monday),
+            period(PeriodId::Weekly, monday + 7 * DAY - 1)
+        );
+        assert_eq!(
+            period(PeriodId::Weekly, monday) + 1,
+            period(PeriodId::Weekly, monday + 7 * DAY)
+        );
+    }
+
+    #[test]
+    fn leaderboard_insert() {
+        let score = |alias: &str, score: u32| LeaderboardScoreDto {
+            alias: PlayerAlias::new_unsanitized(alias),
+            score,
+        };
+        let mut leaderboard = Vec::new();
+
+        assert!(LeaderboardScoreDto::insert(
+            &mut leaderboard,
+            score("a", 5),
+            3
+        ));
+        assert!(LeaderboardScoreDto::insert(
+            &mut leaderboard,
+            score("b", 7),
+            3
+        ));
+        assert!(LeaderboardScoreDto::insert(
+            &mut leaderboard,
+            score("c", 6),
+            3
+        ));
+        assert_eq!(leaderboard, [score("b", 7), score("c", 6), score("a", 5)]);
+
+        // Doesn't make the cut.
+        assert!(!LeaderboardScoreDto::insert(
+            &mut leaderboard,
+            score("d", 1),
+            3
+        ));
+        // Not a personal best.
+        assert!(!LeaderboardScoreDto::insert(
+            &mut leaderboard,
+            score("b", 7),
+            3
+        ));
+        assert!(!LeaderboardScoreDto::insert(
+            &mut leaderboard,
+            score("c", 2),
+            3
+        ));
+        assert_eq!(leaderboard, [score("b", 7), score("c", 6), score("a", 5)]);
+
+        // Personal best moves up instead of duplicating.
+        assert!(LeaderboardScoreDto::insert(
+            &mut leaderboard,
+            score("a", 8),
+            3
+        ));
+        assert_eq!(leaderboard, [score("a", 8), score("b", 7), score("c", 6)]);
+
+        // Pushes out the lowest.
+        assert!(LeaderboardScoreDto::insert(
+            &mut leaderboard,
+            score("e", 7),
+            3
+        ));
+        assert_eq!(leaderboard.len(), 3);
+        assert_eq!(leaderboard[0], score("a", 8));
+        assert!(!leaderboard.contains(&score("c", 6)));
+    }
 }
@@ -3,7 +3,7 @@
 
 use super::{
     ContinuousExtremaMetricAccumulator, DiscreteMetricAccumulator, DistinctCountMetricAccumulator,
-    DistinctCountMetricSummary, HistogramMetricAccumulator, RatioMetricAccumulator,
+    DistinctCountMetricSummary, HistogramMetricAccumulator, OpenMetric, RatioMetricAccumulator,
 };
 use crate::{is_default, CohortId, LifecycleId, Referrer, RegionId, UserAgentId};
 use derive_more::Add;
@@ -33,6 +33,7 @@ pub struct MetricsSummaryDto {
     pub bandwidth_tx: <ContinuousExtremaMetricAccumulator as MetricAccumulator>::Summary,
     pub banner_ads: <DiscreteMetricAccumulator as MetricAccumulator>::Summary,
     pub bounce: <RatioMetricAccumulator as MetricAccumulator>::Summary,
+    pub chat_penalties: <DiscreteMetricAccumulator as MetricAccumulator>::Summary,
     pub chats: <DiscreteMetricAccumulator as MetricAccumulator>::Summary,
     pub complain: <RatioMetricAccumulator as MetricAccumulator>::Summary,
     pub concurrent: <ContinuousExtremaMetricAccumulator as MetricAccumulator>::Summary,
@@ -98,6 +99,7 @@ pub struct EngineMetricsDataPointDto {
     pub bandwidth_tx: <ContinuousExtremaMetricAccumulator as MetricAccumulator>::DataPoint,
     pub banner_ads: <DiscreteMetricAccumulator as MetricAccumulator>::DataPoint,
     pub bounce: <RatioMetricAccumulator as MetricAccumulator>::DataPoint,
+    pub chat_penalties: <DiscreteMetricAccumulator as MetricAccumulator>::DataPoint,
     pub chats: <DiscreteMetricAccumulator as MetricAccumulator>::DataPoint,
     pub complain: <RatioMetricAccumulator as MetricAccumulator>::DataPoint,
     pub concurrent: <ContinuousExtremaMetricAccumulator as MetricAccumulator>::DataPoint,
@@ -191,6 +193,9 @@ pub struct EngineMetrics {
     /// Ratio of new players that leave without ever playing.
     #[serde(default, skip_serializing_if = "is_default")]
     pub bounce: RatioMetricAccumulator,
+    /// Number of automated chat moderation penalties (shadow-mutes, mutes, and flags).
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub chat_penalties: DiscreteMetricAccumulator,
     /// Number of chat messages sent.
     #[serde(default, skip_serializing_if = "is_default")]
     pub chats: DiscreteMetricAccumulator,
@@ -383,6 +388,7 @@ impl EngineMetrics {
             bandwidth_tx,
             banner_ads,
             bounce,
+            chat_penalties,
             chats,
             complain,
             concurrent,
@@ -452,6 +458,82 @@ impl EngineMetrics {
             bandwidth_tx,
             banner_ads,
             bounce,
+            chat_penalties,
+            chats,
+            complain,
+            concurrent,
+            connections,
+            connections_per_ip_histogram,
+            conntracks,
+            cpu,
+            cpu_steal,
+            crashes,
+            dns,
+            dom,
+            entities,
+            flop,
+            fps,
+            http,
+            invited,
+            invitations_cached,
+            low_fps,
+            minutes_per_play,
+            minutes_per_play_histogram,
+            minutes_per_visit,
+            minutes_per_visit_histogram,
+            new,
+            no_referrer,
+            peek,
+            players_cached,
+            plays_per_visit,
+            plays_per_visit_histogram,
+            plays_total,
+            ram,
+            renews,
+            retention_days,
+            retention_histogram,
+            rewarded_ads,
+            rtt,
+            score,
+            sessions_cached,
+            spt,
+            tasks,
+            tcp,
+            teamed,
+            tls,
+            toxicity,
+            tps,
+            unauthenticated,
+            unreliable,
+            uptime,
+            user,
+            video_ads,
+            visitors,
+            visits,
+            world_size,
+        }
+    }
+
+    /// Appends every metric in the OpenMetrics text format, named `{prefix}{field}`.
+    pub fn write_open_metrics(&self, prefix: &str, out: &mut String) {
+        macro_rules! write_open_metrics {
+            ($($name: ident,)*) => {
+                $(
+                    self.$name.write_open_metric(&format!("{prefix}{}", stringify!($name)), out);
+                )*
+            }
+        }
+
+        write_open_metrics! {
+            abuse_reports,
+            actives_per_ip_histogram,
+            alt_domain,
+            arenas_cached,
+            bandwidth_rx,
+            bandwidth_tx,
+            banner_ads,
+            bounce,
+            chat_penalties,
             chats,
             complain,
             concurrent,
@@ -10,7 +10,8 @@ const BUCKET_SIZE: usize = 1;
 
 #[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub struct HistogramMetricAccumulator<const BUCKET_COUNT: usize> {
-    /// How many samples have value 0.0-9.99, 10.0-19.99, ... ?
+    /// How many samples have value in [0, 1], (1, 2], ... ? Upper bounds are inclusive, like
+    /// the max bucket's.
     #[serde(rename = "b", with = "BigArray")]
     buckets: [u32; BUCKET_COUNT],
     /// How many samples have value below the min bucket?
@@ -50,11 +51,28 @@ impl<const BUCKET_COUNT: usize> HistogramMetricAccumulator<BUCKET_COUNT> {
         } else if sample > (BUCKET_COUNT * BUCKET_SIZE) as f32 {
             self.overflow = self.overflow.saturating_add(1);
         } else {
-            let bucket = ((sample / BUCKET_SIZE as f32) as usize).min(BUCKET_COUNT - 1);
+            let bucket = ((sample / BUCKET_SIZE as f32).ceil() as usize)
+                .saturating_sub(1)
+                .min(BUCKET_COUNT - 1);
             self.buckets[bucket] = self.buckets[bucket].saturating_add(1);
         }
     }
 
+    /// How many samples are in each bucket.
+    pub fn buckets(&self) -> &[u32; BUCKET_COUNT] {
+        &self.buckets
+    }
+
+    /// How many samples are above the max bucket.
+    pub fn overflow(&self) -> u32 {
+        self.overflow
+    }
+
+    /// How many samples are below the min bucket.
+    pub fn underflow(&self) -> u32 {
+        self.underflow
+    }
+
     pub fn median(&self) -> f32 {
         let sum = self.buckets.iter().map(|b| *b as u64).sum::<u64>();
         let median_partial_sum = sum / 2;
@@ -6,6 +6,7 @@ mod discrete;
 mod engine;
 mod histogram;
 mod navigation;
+mod open_metrics;
 mod ratio;
 
 pub use continuous::{
@@ -20,4 +21,5 @@ pub use engine::{
 };
 pub use histogram::HistogramMetricAccumulator;
 pub use navigation::NavigationMetricsDto;
+pub use open_metrics::{write_open_metrics_gauge, write_open_metrics_labeled_gauge, OpenMetric};
 pub use ratio::{RatioMetricAccumulator, RatioMetricSummary};
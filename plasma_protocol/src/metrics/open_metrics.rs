@@ -0,0 +1,127 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+use super::{
+    ContinuousExtremaMetricAccumulator, DiscreteMetricAccumulator, DistinctCountMetricAccumulator,
+    HistogramMetricAccumulator, MetricAccumulator, RatioMetricAccumulator,
+};
+use hyperloglog::Registers;
+use std::fmt::Write;
+
+/// A metric that can be rendered in the
+/// [OpenMetrics](https://github.com/OpenObservability/OpenMetrics/blob/main/specification/OpenMetrics.md)
+/// text format, e.g. for Prometheus.
+pub trait OpenMetric {
+    /// Appends one or more metric families, whose names start with `name`.
+    fn write_open_metric(&self, name: &str, out: &mut String);
+}
+
+/// Appends a single-sample gauge family.
+pub fn write_open_metrics_gauge(name: &str, value: f64, out: &mut String) {
+    let _ = writeln!(out, "# TYPE {name} gauge\n{name} {value}");
+}
+
+/// Appends a gauge family with one sample per label value, e.g. per arena.
+pub fn write_open_metrics_labeled_gauge<'a>(
+    name: &str,
+    label: &str,
+    samples: impl IntoIterator<Item = (&'a str, f64)>,
+    out: &mut String,
+) {
+    let _ = writeln!(out, "# TYPE {name} gauge");
+    for (value_label, value) in samples {
+        let escaped = value_label
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n");
+        let _ = writeln!(out, "{name}{{{label}=\"{escaped}\"}} {value}");
+    }
+}
+
+/// Gauge, since it resets when the metrics period rolls over.
+impl OpenMetric for DiscreteMetricAccumulator {
+    fn write_open_metric(&self, name: &str, out: &mut String) {
+        write_open_metrics_gauge(name, self.total as f64, out);
+    }
+}
+
+/// Summary (count and sum), plus `_min` and `_max` gauges.
+impl OpenMetric for ContinuousExtremaMetricAccumulator {
+    fn write_open_metric(&self, name: &str, out: &mut String) {
+        let _ = writeln!(
+            out,
+            "# TYPE {name} summary\n{name}_count {}\n{name}_sum {}",
+            self.count, self.total
+        );
+        if self.count > 0 {
+            write_open_metrics_gauge(&format!("{name}_min"), self.min as f64, out);
+            write_open_metrics_gauge(&format!("{name}_max"), self.max as f64, out);
+        }
+    }
+}
+
+/// Gauge of the ratio (from 0 to 1), plus a `_samples` gauge of the population size.
+impl OpenMetric for RatioMetricAccumulator {
+    fn write_open_metric(&self, name: &str, out: &mut String) {
+        let ratio = self.count as f64 / self.total.max(1) as f64;
+        write_open_metrics_gauge(name, ratio, out);
+        write_open_metrics_gauge(&format!("{name}_samples"), self.total as f64, out);
+    }
+}
+
+/// Classic histogram with one bucket per unit, whose inclusive upper bounds match how samples
+/// are bucketed. Samples below zero are counted in the first bucket.
+impl<const BUCKET_COUNT: usize> OpenMetric for HistogramMetricAccumulator<BUCKET_COUNT> {
+    fn write_open_metric(&self, name: &str, out: &mut String) {
+        let _ = writeln!(out, "# TYPE {name} histogram");
+        let mut cumulative = self.underflow() as u64;
+        for (i, &bucket) in self.buckets().iter().enumerate() {
+            cumulative += bucket as u64;
+            let _ = writeln!(out, "{name}_bucket{{le=\"{}.0\"}} {cumulative}", i + 1);
+        }
+        cumulative += self.overflow() as u64;
+        let _ = writeln!(
+            out,
+            "{name}_bucket{{le=\"+Inf\"}} {cumulative}\n{name}_count {cumulative}"
+        );
+    }
+}
+
+/// Gauge of the approximate distinct count.
+impl<R: Registers> OpenMetric for DistinctCountMetricAccumulator<R> {
+    fn write_open_metric(&self, name: &str, out: &mut String) {
+        write_open_metrics_gauge(name, self.data_point().0 as f64, out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OpenMetric;
+    use crate::{ContinuousExtremaMetricAccumulator, HistogramMetricAccumulator};
+
+    #[test]
+    fn histogram() {
+        let mut histogram = HistogramMetricAccumulator::<3>::default();
+        for sample in [-1.0, 0.5, 1.0, 1.5, 1.7, 2.0, 10.0] {
+            histogram.push(sample);
+        }
+        let mut out = String::new();
+        histogram.write_open_metric("h", &mut out);
+        assert_eq!(
+            out,
+            "# TYPE h histogram\n\
+            h_bucket{le=\"1.0\"} 3\n\
+            h_bucket{le=\"2.0\"} 6\n\
+            h_bucket{le=\"3.0\"} 6\n\
+            h_bucket{le=\"+Inf\"} 7\n\
+            h_count 7\n"
+        );
+    }
+
+    #[test]
+    fn summary() {
+        let mut out = String::new();
+        ContinuousExtremaMetricAccumulator::default().write_open_metric("s", &mut out);
+        assert_eq!(out, "# TYPE s summary\ns_count 0\ns_sum 0\n");
+    }
+}
@@ -2,9 +2,11 @@
 // SPDX-License-Identifier: LGPL-3.0-or-later
 
 use crate::actor::ServerActor;
+use crate::ban_list::BanList;
 use crate::service::{ArenaService, Bundle, MetricBundle, MetricRepo, PlayerRepo, Score};
+use crate::socket::BANDWIDTH_PROFILER;
 use crate::{
-    AdminPlayerDto, AdminRequest, AdminUpdate, ClientHash, EngineMetrics, MetricFilter,
+    AdminPlayerDto, AdminRequest, AdminUpdate, BanTarget, ClientHash, EngineMetrics, MetricFilter,
     PlayerAlias, PlayerId, RealmId, RegionId, SceneId, UserAgentId,
 };
 use actix::{fut, ActorFutureExt, Handler, ResponseActFuture, WrapFuture};
@@ -12,11 +14,14 @@ use std::collections::HashMap;
 use std::hash::Hash;
 use std::iter;
 use std::marker::PhantomData;
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
 
 /// Responsible for the admin interface.
 pub struct AdminActlet<G: ArenaService> {
     pub(crate) client_hash: ClientHash,
+    /// Shared with the `/ws` upgrade path.
+    pub(crate) bans: Arc<RwLock<BanList>>,
     #[cfg(unix)]
     cpu_profile: Option<pprof::ProfilerGuard<'static>>,
     heap_profile: Option<dhat::Profiler>,
@@ -24,9 +29,10 @@ pub struct AdminActlet<G: ArenaService> {
 }
 
 impl<G: ArenaService> AdminActlet<G> {
-    pub fn new(client_hash: ClientHash) -> Self {
+    pub fn new(client_hash: ClientHash, bans: Arc<RwLock<BanList>>) -> Self {
         Self {
             client_hash,
+            bans,
             #[cfg(unix)]
             cpu_profile: None,
             heap_profile: None,
@@ -34,6 +40,59 @@ impl<G: ArenaService> AdminActlet<G> {
         }
     }
 
+    /// Bans an IP or visitor, kicking matching players from every arena.
+    fn ban(
+        infrastructure: &mut ServerActor<G>,
+        target: BanTarget,
+        minutes: u32,
+    ) -> Result<AdminUpdate, &'static str> {
+        if minutes == 0 {
+            return Err("invalid duration");
+        }
+        infrastructure
+            .admin
+            .bans
+            .write()
+            .unwrap()
+            .ban(target, minutes);
+        let mut kicked = 0;
+        for (_, scene) in infrastructure.realms.iter_mut() {
+            for (_, player) in scene.arena.arena_context.players.iter_mut() {
+                let Some(client) = player.client_mut() else {
+                    continue;
+                };
+                let matches = match target {
+                    BanTarget::Ip(ip_address) => client.ip_address == ip_address,
+                    BanTarget::Visitor(visitor_id) => client.session.visitor_id == Some(visitor_id),
+                };
+                if matches {
+                    client.kick();
+                    kicked += 1;
+                }
+            }
+        }
+        Ok(AdminUpdate::Banned(kicked))
+    }
+
+    /// Disconnects a given real player, in any arena, removing them from the game.
+    fn kick_player(
+        infrastructure: &mut ServerActor<G>,
+        player_id: PlayerId,
+    ) -> Result<AdminUpdate, &'static str> {
+        let mut result = Err("nonexistent player");
+        for (_, scene) in infrastructure.realms.iter_mut() {
+            let Some(player) = scene.arena.arena_context.players.get_mut(player_id) else {
+                continue;
+            };
+            if let Some(client) = player.client_mut() {
+                client.kick();
+                return Ok(AdminUpdate::PlayerKicked);
+            }
+            result = Err("not a real player");
+        }
+        result
+    }
+
     /// (Temporarily) overrides the alias of a given real player.
     fn override_player_alias(
         &self,
@@ -110,6 +169,11 @@ impl<G: ArenaService> AdminActlet<G> {
         ))
     }
 
+    /// Get list of active bans.
+    fn request_bans(&self) -> Result<AdminUpdate, &'static str> {
+        Ok(AdminUpdate::BansRequested(self.bans.read().unwrap().dtos()))
+    }
+
     /// Get list of games hosted by the server.
     fn request_games(&self) -> Result<AdminUpdate, &'static str> {
         // We only support one game type per server.
@@ -255,6 +319,14 @@ impl<G: ArenaService> AdminActlet<G> {
         }
     }
 
+    fn unban(&self, target: BanTarget) -> Result<AdminUpdate, &'static str> {
+        if self.bans.write().unwrap().unban(target) {
+            Ok(AdminUpdate::Unbanned)
+        } else {
+            Err("not banned")
+        }
+    }
+
     fn finish_heap_profile(&mut self) -> Result<AdminUpdate, &'static str> {
         if let Some(mut profile) = self.heap_profile.take() {
             let output = profile.drop_and_get_memory_output();
@@ -272,6 +344,16 @@ impl<G: ArenaService> Handler<AdminRequest> for ServerActor<G> {
 
     fn handle(&mut self, request: AdminRequest, _ctx: &mut Self::Context) -> Self::Result {
         match request {
+            AdminRequest::Ban { target, minutes } => {
+                Box::pin(fut::ready(AdminActlet::ban(self, target, minutes)))
+            }
+            AdminRequest::Drain { seconds } => {
+                self.drain(Duration::from_secs(seconds as u64));
+                Box::pin(fut::ready(Ok(AdminUpdate::Draining)))
+            }
+            AdminRequest::KickPlayer { player_id } => {
+                Box::pin(fut::ready(AdminActlet::kick_player(self, player_id)))
+            }
             AdminRequest::OverridePlayerAlias { player_id, alias } => {
                 Box::pin(fut::ready(if let Some(tier) = self.realms.main_mut() {
                     self.admin.override_player_alias(
@@ -299,6 +381,10 @@ impl<G: ArenaService> Handler<AdminRequest> for ServerActor<G> {
             AdminRequest::RequestDay { filter } => {
                 Box::pin(fut::ready(AdminActlet::request_day(&self.metrics, filter)))
             }
+            AdminRequest::RequestBandwidthBreakdown => Box::pin(fut::ready(Ok(
+                AdminUpdate::BandwidthBreakdownRequested(BANDWIDTH_PROFILER.lock().unwrap().dtos()),
+            ))),
+            AdminRequest::RequestBans => Box::pin(fut::ready(self.admin.request_bans())),
             AdminRequest::RequestGames => Box::pin(fut::ready(self.admin.request_games())),
             AdminRequest::RequestPlayers => Box::pin(fut::ready(
                 if let Some(realm) = self.realms.realm(RealmId::PublicDefault) {
@@ -352,6 +438,7 @@ impl<G: ArenaService> Handler<AdminRequest> for ServerActor<G> {
             AdminRequest::RequestUserAgents => {
                 Box::pin(fut::ready(self.admin.request_user_agents(&self.metrics)))
             }
+            AdminRequest::Unban { target } => Box::pin(fut::ready(self.admin.unban(target))),
         }
     }
 }
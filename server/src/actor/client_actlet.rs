@@ -11,7 +11,7 @@ use crate::router::AllowedOrigin;
 use crate::service::{
     ArenaService, ChatRepo, ClientChatData, ClientInvitationData, ClientMetricData,
     ClientQuestData, InvitationRepo, LeaderboardRepo, LiveboardRepo, MetricRepo, Player,
-    PlayerInner, PlayerRepo, Realm, SendPlasmaRequest, ShardContextProvider,
+    PlayerInner, PlayerRepo, Realm, RedirectedPlayer, SendPlasmaRequest, ShardContextProvider,
 };
 use crate::{
     AdEvent, ArenaContext, ArenaEntry, ArenaId, ArenaQuery, ArenaSettingsDto, ArenaToken,
@@ -164,6 +164,8 @@ impl<G: ArenaService> ClientActlet<G> {
             warn_if_dropped: true,
         };
         let old_status = std::mem::replace(&mut client.status, new_status);
+        // Spectators never join the game.
+        let spectator = client.spectator;
 
         match old_status {
             ClientStatus::Connected { observer, .. } => {
@@ -177,7 +179,9 @@ impl<G: ArenaService> ClientActlet<G> {
                 metrics.start_visit(client);
 
                 // We weren't in the game, so now we have to join.
-                if player.regulator.join() {
+                if spectator {
+                    info!("player {:?} is spectating", player_id);
+                } else if player.regulator.join() {
                     game.player_joined(player_id, player);
                 } else {
                     debug_assert!(false);
@@ -185,7 +189,7 @@ impl<G: ArenaService> ClientActlet<G> {
             }
             ClientStatus::LeavingLimbo { .. } => {
                 // We previously left the game, so now we have to rejoin.
-                if player.regulator.join() {
+                if !spectator && player.regulator.join() {
                     game.player_joined(player_id, player);
                 }
                 info!("player {:?} restored from leaving limbo", player_id);
@@ -326,7 +330,7 @@ impl<G: ArenaService> ClientActlet<G> {
         let caveat =
             if !(plasma.is_sanctioned(server_id, arena_id) || arena_id.realm_id.is_temporary()) {
                 Some(LeaderboardCaveat::Closing)
-            } else if plasma.role.is_closing() {
+            } else if plasma.is_closing() {
                 Some(LeaderboardCaveat::Closing)
             } else if arena_id.realm_id.is_temporary() {
                 Some(LeaderboardCaveat::Temporary)
@@ -639,6 +643,7 @@ impl<G: ArenaService> ClientActlet<G> {
                     ClientStatus::Limbo { expiry, .. } => {
                         in_limbo += 1;
                         if &now >= expiry {
+                            let spectator = client_data.spectator;
                             client_data.status = ClientStatus::LeavingLimbo {
                                 expiry: now,
                                 ticks: 0,
@@ -647,7 +652,10 @@ impl<G: ArenaService> ClientActlet<G> {
                             if player.regulator.active() {
                                 service.player_quit(player_id, player);
                             }
-                            player.regulator.leave();
+                            // Spectators never joined.
+                            if !spectator {
+                                player.regulator.leave();
+                            }
                         }
                     }
                     ClientStatus::LeavingLimbo {
@@ -740,7 +748,7 @@ impl<G: ArenaService> ClientActlet<G> {
     }
 
     /// Handles [`G::Command`]'s.
-    fn handle_game_command(
+    pub(crate) fn handle_game_command(
         player_id: PlayerId,
         command: G::GameRequest,
         game_fence: Option<GameFence>,
@@ -926,7 +934,27 @@ impl<G: ArenaService> ClientActlet<G> {
         Ok(None)
     }
 
-    fn switch_arena(
+    fn spectate(
+        player_id: PlayerId,
+        follow: Option<PlayerId>,
+        players: &mut PlayerRepo<G>,
+    ) -> Result<Option<ClientUpdate>, &'static str> {
+        if let Some(follow) = follow {
+            let followed = players.get(follow).ok_or("nonexistent followed player")?;
+            if !followed.regulator.active() {
+                return Err("cannot follow inactive player");
+            }
+        }
+        let player = players.get_mut(player_id).ok_or("player doesn't exist")?;
+        let client = player.client_mut().ok_or("only clients can spectate")?;
+        if !client.spectator {
+            return Err("not a spectator");
+        }
+        client.follow = follow;
+        Ok(None)
+    }
+
+    pub(crate) fn switch_arena(
         player_id: PlayerId,
         server_id: ServerId,
         arena_id: ArenaQuery,
@@ -943,6 +971,7 @@ impl<G: ArenaService> ClientActlet<G> {
             return Err("not connected");
         }
         player.regulator.leave();
+        let portable = service.export_player(player_id, player);
         service.player_quit(player_id, player);
         if player.was_alive {
             player.was_alive = false;
@@ -960,6 +989,9 @@ impl<G: ArenaService> ClientActlet<G> {
                     sender_arena_id: context.topology.local_arena_id,
                     arena_id,
                     redirected: base64ed,
+                    portable: portable
+                        .as_ref()
+                        .map(RedirectedPlayer::encode_portable_state::<G>),
                 })
                 .unwrap(),
             }));
@@ -1087,6 +1119,9 @@ impl<G: ArenaService> ClientActlet<G> {
             ClientRequest::AnnouncementPreference(preference) => {
                 Self::announcement_preference(player_id, preference, &mut arena_context.players)
             }
+            ClientRequest::Spectate(follow) => {
+                Self::spectate(player_id, follow, &mut arena_context.players)
+            }
         }
     }
 
@@ -1169,6 +1204,7 @@ impl<G: ArenaService> ClientActlet<G> {
                     arena_id,
                     player_id,
                     request,
+                    server_id,
                     &mut scene.arena,
                     metrics,
                     plasma,
@@ -1278,6 +1314,11 @@ pub struct PlayerClientData<G: ArenaService> {
     pub(crate) chat: ClientChatData,
     /// Players this client has reported.
     pub(crate) reported: HashSet<IpAddr>,
+    /// Watching without playing. Spectators never join the game, so they don't count towards
+    /// [`ArenaService::is_alive`], the liveboard, or the number of real players.
+    pub(crate) spectator: bool,
+    /// The player a spectator's camera follows.
+    pub(crate) follow: Option<PlayerId>,
 }
 
 impl<G: ArenaService> Deref for PlayerClientData<G> {
@@ -1452,6 +1493,8 @@ impl<G: ArenaService> PlayerClientData<G> {
                     detected_language_id: LanguageId::default(),
                     english_translation: None,
                 },
+                player_id: None,
+                recipient: None,
             }),
             None,
         );
@@ -1476,6 +1519,16 @@ impl<G: ArenaService> PlayerClientData<G> {
     pub fn visitor_id(&self) -> Option<VisitorId> {
         self.session.visitor_id
     }
+
+    /// Watching without playing, see [`ArenaService::get_game_update`].
+    pub fn is_spectator(&self) -> bool {
+        self.spectator
+    }
+
+    /// The player a spectator's camera follows, if any.
+    pub fn following(&self) -> Option<PlayerId> {
+        self.follow
+    }
 }
 
 #[derive(Debug)]
@@ -1489,6 +1542,20 @@ pub struct ActiveClientData<G: ArenaService> {
     _permit: Option<ActivePermit>,
 }
 
+impl<G: ArenaService> ActiveClientData<G> {
+    /// For clients that aren't on the network, so don't need an [`ActivePermit`].
+    pub(crate) fn new_local() -> Self {
+        Self {
+            data: G::ClientData::default(),
+            game_fence: random(),
+            game_fence_done: false,
+            activity: Default::default(),
+            prev_claims: Default::default(),
+            _permit: None,
+        }
+    }
+}
+
 // TOOD: this was previously pub(crate)
 pub enum ClientStatus<G: ArenaService> {
     /// Pending: Initial state. Visit not started yet. Can be forgotten after expiry.
@@ -1608,6 +1675,14 @@ impl<G: ArenaService> ClientStatus<G> {
         matches!(self, Self::Redirected { .. })
     }
 
+    pub(crate) fn game_fence(&self) -> Option<GameFence> {
+        if let Self::Connected { active, .. } = self {
+            active.as_ref().map(|a| a.game_fence)
+        } else {
+            None
+        }
+    }
+
     pub fn data(&self) -> Option<&G::ClientData> {
         if let Self::Connected { active, .. } | Self::Redirected { active, .. } = self {
             active.as_ref().map(|a| &a.data)
@@ -1648,7 +1723,12 @@ impl<G: ArenaService> ClientStatus<G> {
 }
 
 impl<G: ArenaService> PlayerClientData<G> {
-    pub(crate) fn new(chat: ClientChatData, metrics: ClientMetricData, ip_address: IpAddr) -> Self {
+    pub(crate) fn new(
+        chat: ClientChatData,
+        metrics: ClientMetricData,
+        ip_address: IpAddr,
+        spectator: bool,
+    ) -> Self {
         Self {
             token: random(),
             status: ClientStatus::Pending {
@@ -1660,6 +1740,8 @@ impl<G: ArenaService> PlayerClientData<G> {
             invitation: Default::default(),
             reported: Default::default(),
             chat,
+            spectator,
+            follow: None,
         }
     }
 
@@ -1716,6 +1798,30 @@ impl<G: ArenaService> PlayerClientData<G> {
         }
     }
 
+    /// Disconnects the client and removes it from the game, without waiting for limbo to expire.
+    pub(crate) fn kick(&mut self) {
+        let now = Instant::now();
+        match &mut self.status {
+            ClientStatus::Connected {
+                observer,
+                supports_unreliable,
+                ..
+            } => {
+                let _ = observer.send(ObserverUpdate::Close);
+                let supports_unreliable = *supports_unreliable;
+                self.push_quest(QuestEvent::Socket {
+                    supports_unreliable,
+                    open: false,
+                });
+                self.status = ClientStatus::Limbo { expiry: now };
+            }
+            ClientStatus::Limbo { expiry } | ClientStatus::Pending { expiry } => {
+                *expiry = now;
+            }
+            ClientStatus::LeavingLimbo { .. } | ClientStatus::Redirected { .. } => {}
+        }
+    }
+
     /// Client sent a message/heartbeat recently.
     pub fn is_active(&self) -> bool {
         if let ClientStatus::Connected { active, .. } = &self.status {
@@ -1861,6 +1967,8 @@ pub struct ClientAuthRequest {
     pub lifecycle: LifecycleId,
     /// To track alterate domain metrics.
     pub alt_domain: Option<DomainName>,
+    /// Watch without playing.
+    pub spectate: bool,
 }
 
 #[derive(Debug, strum::IntoStaticStr)]
@@ -1870,6 +1978,7 @@ pub enum ClientAuthErr {
     UnsanctionedArena,
     UnsanctionedServer,
     TooManyPlayers,
+    Banned,
 }
 
 impl ClientAuthRequest {
@@ -1910,6 +2019,7 @@ impl ClientAuthRequest {
             language_id: query.language_id,
             timezone_offset: query.timezone_offset.clamp(-12 * 60, 14 * 60),
             alt_domain: origin.alternative_domain(),
+            spectate: query.spectate,
         }
     }
 }
@@ -1923,6 +2033,21 @@ impl<G: ArenaService> ServerActor<G> {
             < G::MAX_TEMPORARY_SERVERS
             && !self.plasma.role.is_redirected()
     }
+
+    /// The visitor of a session that plasma already authenticated for a player on this server.
+    fn session_visitor_id(&self, session_token: Option<SessionToken>) -> Option<VisitorId> {
+        let session_token = session_token?;
+        self.realms.iter().find_map(|(_, scene)| {
+            scene
+                .arena
+                .arena_context
+                .players
+                .iter()
+                .filter_map(|(_, player)| player.client())
+                .find(|client| client.session.session_token == Some(session_token))
+                .and_then(|client| client.session.visitor_id)
+        })
+    }
 }
 
 impl<G: ArenaService> ServerActor<G> {
@@ -2041,11 +2166,20 @@ impl<G: ArenaService> Handler<ClientAuthRequest> for ServerActor<G> {
             return Err(ClientAuthErr::TooManyRequests);
         }
 
+        // Visitors whose session is new to this server are checked once plasma authenticates
+        // them, in `PlasmaUpdateV1::Player`.
+        let visitor_id = self.session_visitor_id(msg.session_token);
+        let bans = self.admin.bans.read().unwrap();
+        if bans.is_ip_banned(msg.ip_address) || bans.is_visitor_banned(visitor_id) {
+            return Err(ClientAuthErr::Banned);
+        }
+        drop(bans);
+
         let (arena_id, player_id, accept_invitation_id) = self.resolve(
             msg.arena_id,
             SendPlasmaRequest {
                 web_socket: self.plasma.web_socket.sender.clone(),
-                local: ctx.address().recipient(),
+                local: Some(ctx.address().recipient()),
                 local_server_id: self.server_id,
             },
         );
@@ -2058,6 +2192,9 @@ impl<G: ArenaService> Handler<ClientAuthRequest> for ServerActor<G> {
         let player_id = if let Some(existing) = player_id {
             existing
         } else {
+            if self.plasma.draining {
+                return Err(ClientAuthErr::UnsanctionedServer);
+            }
             // Deliberately allow new players when closing without redirecting, because that
             // seems safer.
             if self.plasma.role.is_redirected()
@@ -2083,6 +2220,15 @@ impl<G: ArenaService> Handler<ClientAuthRequest> for ServerActor<G> {
         let player = match scene.arena.arena_context.players.entry(player_id) {
             ArenaEntry::Occupied(mut occupied) => {
                 if let Some(client) = occupied.get_mut().client_mut() {
+                    if self
+                        .admin
+                        .bans
+                        .read()
+                        .unwrap()
+                        .is_visitor_banned(client.session.visitor_id)
+                    {
+                        return Err(ClientAuthErr::Banned);
+                    }
                     match &mut client.status {
                         ClientStatus::Pending { expiry }
                         | ClientStatus::Limbo { expiry, .. }
@@ -2129,7 +2275,8 @@ impl<G: ArenaService> Handler<ClientAuthRequest> for ServerActor<G> {
                     .chat
                     .initialize_client(self.server_id.number, arena_id);
 
-                let client = PlayerClientData::new(chat, client_metric_data, msg.ip_address);
+                let client =
+                    PlayerClientData::new(chat, client_metric_data, msg.ip_address, msg.spectate);
 
                 let pd = Player::new(PlayerInner::Client(client));
                 vacant.insert(pd)
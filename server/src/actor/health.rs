@@ -78,6 +78,16 @@ impl Health {
         self.system.conntrack_sessions().unwrap_or(0)
     }
 
+    /// Ticks-per-second measurements since they were last taken.
+    pub fn tps(&self) -> &ContinuousExtremaMetricAccumulator {
+        &self.tps
+    }
+
+    /// Seconds-per-tick measurements since they were last taken.
+    pub fn spt(&self) -> &ContinuousExtremaMetricAccumulator {
+        &self.spt
+    }
+
     /// Call to get average TPS over a large interval.
     pub fn take_tps(&mut self) -> ContinuousExtremaMetricAccumulator {
         mem::take(&mut self.tps)
@@ -11,7 +11,8 @@ mod translation_actlet;
 
 pub use self::admin_actlet::AdminActlet;
 pub use self::client_actlet::{
-    ClientActlet, ClientAuthErr, ClientAuthRequest, ClientStatus, PlayerClientData, SessionData,
+    ActiveClientData, ClientActlet, ClientAuthErr, ClientAuthRequest, ClientStatus,
+    PlayerClientData, SessionData,
 };
 pub use self::health::Health;
 pub use self::plasma_actlet::{PlasmaActlet, ServerMessage};
@@ -22,25 +22,28 @@ use bytes::Bytes;
 use kodiak_common::rand::{thread_rng, Rng};
 use kodiak_common::FileLoadedResult;
 use log::{error, info, warn};
+use rustls::server::{ResolvesServerCertUsingSni, ServerConfig};
 use serde_json::Value;
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::num::NonZeroU64;
 use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, LazyLock, Mutex};
 use std::time::{Duration, Instant};
 
 pub struct PlasmaActlet {
     infrastructure: Option<Recipient<PlasmaUpdate>>,
     /// Last outbound heartbeat time.
     last_heartbeat: Option<Instant>,
-    last_acknowledged_heartbeat: Option<Instant>,
+    pub(crate) last_acknowledged_heartbeat: Option<Instant>,
     /// Last server log update.
     last_server_log: Option<Instant>,
     last_quest_samples: Option<Instant>,
     redirect_server_number: &'static AtomicU8,
     pub role: ServerRole,
     pub redirecting_since: Option<Instant>,
+    /// Draining before shutdown, which is treated like a closing [`ServerRole`].
+    pub(crate) draining: bool,
     pub server_token: &'static AtomicU64,
     pub(crate) rustls_config: RustlsConfig,
     pub(crate) cors_alternative_domains: &'static Mutex<Arc<[DomainName]>>,
@@ -50,6 +53,8 @@ pub struct PlasmaActlet {
     pub(crate) health: Health,
     pub(crate) quest_fraction: f32,
     domain_backup: Option<Arc<str>>,
+    /// WebSocket URL, without query.
+    plasma_url: String,
     last_hiccup: Option<Instant>,
     file_client: reqwest::Client,
 }
@@ -61,6 +66,7 @@ impl PlasmaActlet {
         rustls_config: RustlsConfig,
         cors_alternative_domains: &'static Mutex<Arc<[DomainName]>>,
         domain_backup: Option<Arc<str>>,
+        plasma_url: String,
     ) -> Self {
         let mut date_certificate_expires = None;
         if let Some(domain_backup) = &domain_backup {
@@ -82,8 +88,10 @@ impl PlasmaActlet {
             cors_alternative_domains,
             date_certificate_expires,
             domain_backup,
+            plasma_url,
             role: ServerRole::Unlisted,
             redirecting_since: None,
+            draining: false,
             infrastructure: None,
             last_heartbeat: None,
             last_acknowledged_heartbeat: None,
@@ -106,6 +114,27 @@ impl PlasmaActlet {
         }
     }
 
+    /// Never connects to plasma, for [`TestArena`](crate::TestArena).
+    pub(crate) fn new_local<G: ArenaService>() -> Self {
+        static REDIRECT_SERVER_NUMBER: AtomicU8 = AtomicU8::new(0);
+        static SERVER_TOKEN: AtomicU64 = AtomicU64::new(0);
+        static CORS_ALTERNATIVE_DOMAINS: LazyLock<Mutex<Arc<[DomainName]>>> =
+            LazyLock::new(|| Mutex::new(Vec::new().into()));
+
+        // No certificates, since it never serves anything.
+        let config = ServerConfig::builder()
+            .with_no_client_auth()
+            .with_cert_resolver(Arc::new(ResolvesServerCertUsingSni::new()));
+        Self::new::<G>(
+            &REDIRECT_SERVER_NUMBER,
+            &SERVER_TOKEN,
+            RustlsConfig::from_config(Arc::new(config)),
+            &CORS_ALTERNATIVE_DOMAINS,
+            None,
+            String::new(),
+        )
+    }
+
     pub(crate) fn set_infrastructure<G: ArenaService>(
         &mut self,
         game_id: GameId,
@@ -125,12 +154,35 @@ impl PlasmaActlet {
                     ),
                 };
                 let query_string = serde_urlencoded::to_string(query).unwrap();
-                format!("wss://softbear.com/ws/?{query_string}")
+                format!("{}?{query_string}", self.plasma_url)
             },
             infrastructure,
         );
     }
 
+    /// Whether plasma acknowledged a heartbeat recently enough to be relied upon.
+    pub(crate) fn is_connected(&self) -> bool {
+        self.last_acknowledged_heartbeat
+            .is_some_and(|last| last.elapsed() < Duration::from_secs(130))
+    }
+
+    /// Whether the web socket to plasma is currently open. Unlike [`Self::is_connected`], doesn't
+    /// lag behind disconnects, so it decides whether messages must be delivered locally instead.
+    pub(crate) fn is_socket_connected(&self) -> bool {
+        self.web_socket.is_connected()
+    }
+
+    /// Whether the server is closing to new players, either by plasma's decision or because it
+    /// is draining.
+    pub(crate) fn is_closing(&self) -> bool {
+        self.role.is_closing() || self.draining
+    }
+
+    /// Sends a heartbeat, including claims, on the next update.
+    pub(crate) fn heartbeat_soon(&mut self) {
+        self.last_heartbeat = None;
+    }
+
     /// Reports whether a particular arena is provisioned on a particular server.
     pub(crate) fn is_sanctioned(&self, server_id: ServerId, arena_id: ArenaId) -> bool {
         // No longer needed, Plasma is good enough with local server topologies.
@@ -340,7 +392,7 @@ impl PlasmaActlet {
                 )
                 .collect(),
         });
-        let closing = self.role.is_closing();
+        let closing = self.is_closing();
         if closing || !recent_hiccup {
             let max_flush_delay = if closing {
                 Duration::from_secs(110)
@@ -416,7 +468,7 @@ impl PlasmaActlet {
     ) {
         let send_plasma_request = SendPlasmaRequest {
             web_socket: self.web_socket.sender.clone(),
-            local: recipient.clone(),
+            local: Some(recipient.clone()),
             local_server_id: server_id,
         };
         if let Some(server) = self.servers.get(&server_id) {
@@ -557,10 +609,13 @@ impl<G: ArenaService> Handler<PlasmaUpdate> for ServerActor<G> {
                     team_name,
                     visitor_id,
                     chat_id,
-                    //player_id,
+                    player_id: sender_player_id,
                     ..
                 } => {
                     if let Some(realm) = self.realms.realm_mut(chat_id.arena_id.realm_id) {
+                        // Only meaningful (e.g. for replies) if sent from this server.
+                        let local_player_id =
+                            sender_player_id.filter(|_| chat_id.server_id == self.server_id);
                         let message = Arc::new(MessageDto {
                             alias,
                             authentic,
@@ -569,16 +624,19 @@ impl<G: ArenaService> Handler<PlasmaUpdate> for ServerActor<G> {
                             message,
                             visitor_id,
                             whisper: matches!(recipient, ChatRecipient::TeamOf(_)),
+                            player_id: local_player_id,
+                            recipient: None,
                         });
-                        //let player_id = player_id.filter(|_| sender == self.server_id);
+                        let attribution = MessageAttribution {
+                            chat_id,
+                            sender_ip: ip_address,
+                            player_id: local_player_id,
+                        };
                         match recipient {
                             ChatRecipient::Broadcast => {
                                 realm.realm_context.chat.broadcast_message(
                                     Arc::clone(&message),
-                                    Some(MessageAttribution {
-                                        chat_id,
-                                        sender_ip: ip_address,
-                                    }),
+                                    Some(attribution),
                                     realm.scene_repo.iter_mut().map(|(_, t)| &mut t.arena),
                                     None,
                                     true,
@@ -588,10 +646,7 @@ impl<G: ArenaService> Handler<PlasmaUpdate> for ServerActor<G> {
                                 log::warn!("broadcast to scene is deprecated and will erroneously not set recent chat");
                                 realm.realm_context.chat.broadcast_message(
                                     Arc::clone(&message),
-                                    Some(MessageAttribution {
-                                        chat_id,
-                                        sender_ip: ip_address,
-                                    }),
+                                    Some(attribution),
                                     realm
                                         .scene_repo
                                         .get_mut(chat_id.arena_id.scene_id)
@@ -606,15 +661,19 @@ impl<G: ArenaService> Handler<PlasmaUpdate> for ServerActor<G> {
                                     && let Some(player) =
                                         scene.arena.arena_context.players.get_mut(player_id)
                                     && player.regulator.active()
-                                    && let Some(client) = player.client_mut()
                                 {
-                                    client.chat.receive(
-                                        &message,
-                                        Some(MessageAttribution {
-                                            chat_id,
-                                            sender_ip: ip_address,
-                                        }),
-                                    );
+                                    let message = if sender_player_id.is_some() {
+                                        // From another player, as opposed to plasma itself.
+                                        Arc::new(MessageDto {
+                                            recipient: Some(player.alias),
+                                            ..MessageDto::clone(&message)
+                                        })
+                                    } else {
+                                        message
+                                    };
+                                    if let Some(client) = player.client_mut() {
+                                        client.chat.receive(&message, Some(attribution));
+                                    }
                                 }
                             }
                             ChatRecipient::TeamOf(player_id) => {
@@ -631,13 +690,7 @@ impl<G: ArenaService> Handler<PlasmaUpdate> for ServerActor<G> {
                                             scene.arena.arena_context.players.get_mut(member)
                                         {
                                             if let Some(client) = player.client_mut() {
-                                                client.chat.receive(
-                                                    &message,
-                                                    Some(MessageAttribution {
-                                                        chat_id,
-                                                        sender_ip: ip_address,
-                                                    }),
-                                                );
+                                                client.chat.receive(&message, Some(attribution));
                                             }
                                         } else {
                                             debug_assert!(
@@ -730,13 +783,14 @@ impl<G: ArenaService> Handler<PlasmaUpdate> for ServerActor<G> {
                                 sender_arena_id,
                                 arena_id,
                                 redirected,
+                                portable,
                             } => {
                                 use base64::prelude::*;
                                 let (arena_id, returning, accept_invitation_id) = self.resolve(
                                     arena_id,
                                     SendPlasmaRequest {
                                         web_socket: self.plasma.web_socket.sender.clone(),
-                                        local: ctx.address().recipient(),
+                                        local: Some(ctx.address().recipient()),
                                         local_server_id: self.server_id,
                                     },
                                 );
@@ -746,14 +800,18 @@ impl<G: ArenaService> Handler<PlasmaUpdate> for ServerActor<G> {
                                     && let Ok(redirected) =
                                         decode_buffer::<RedirectedPlayer>(&base64ed)
                                 {
+                                    let portable = portable
+                                        .as_deref()
+                                        .and_then(RedirectedPlayer::decode_portable_state::<G>);
                                     let player_id = scene.arena.arena_context.receive_player(
                                         sender,
                                         sender_arena_id,
                                         redirected,
                                     );
-                                    scene.arena.arena_service.player_joined(
+                                    scene.arena.arena_service.player_migrated(
                                         player_id,
                                         &mut scene.arena.arena_context.players[player_id],
+                                        portable,
                                     );
                                     if accept_invitation_id.is_some() {
                                         let _ = self.invitations.accept(
@@ -850,6 +908,16 @@ impl<G: ArenaService> Handler<PlasmaUpdate> for ServerActor<G> {
                                     info!(
                                         "set moderator status of {session_token:?} to {moderator}"
                                     );
+                                    if self
+                                        .admin
+                                        .bans
+                                        .read()
+                                        .unwrap()
+                                        .is_visitor_banned(Some(visitor_id))
+                                    {
+                                        info!("kicking banned visitor {visitor_id}");
+                                        client.kick();
+                                    }
                                 } else {
                                     warn!("user_id/session_id didn't match");
                                 }
@@ -1092,6 +1160,10 @@ pub enum ServerMessage {
         sender_arena_id: ArenaId,
         arena_id: ArenaQuery,
         redirected: String,
+        /// Base64ed bitcode of [`ArenaService::PortableState`], if any. Separate from
+        /// `redirected` so servers that predate it remain compatible.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        portable: Option<String>,
     },
     Ack {
         old_arena_id: ArenaId,
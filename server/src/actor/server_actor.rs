@@ -2,17 +2,21 @@
 // SPDX-License-Identifier: LGPL-3.0-or-later
 
 use crate::actor::{AdminActlet, ClientActlet, PlasmaActlet, SystemActlet, TranslationActlet};
+use crate::ban_list::BanList;
 use crate::rate_limiter::RateLimiterProps;
 use crate::service::{
-    ArenaService, InvitationRepo, LeaderboardRepo, MetricRepo, RealmRepo, ShardContextProvider,
+    ArenaService, ChatRepo, InvitationRepo, LeaderboardRepo, LocalLeaderboards, MetricRepo,
+    RealmRepo, ShardContextProvider,
 };
+use crate::shutdown::Draining;
 use crate::{ArenaId, ClientHash, PlasmaRequestV1, Referrer, RegionId, ServerId};
-use actix::{Actor, AsyncContext, Context as ActorContext};
+use actix::{Actor, ActorContext as _, AsyncContext, Context as ActorContext};
 use axum_server::tls_rustls::RustlsConfig;
 use bytes::Bytes;
 use kodiak_common::DomainName;
 use log::{error, info, warn};
 use std::collections::HashMap;
+use std::path::Path;
 use std::sync::atomic::{AtomicU64, AtomicU8};
 use std::sync::{Arc, Mutex, RwLock};
 use std::time::{Duration, Instant};
@@ -39,13 +43,17 @@ pub struct ServerActor<G: ArenaService> {
     pub(crate) admin: AdminActlet<G>,
     /// Shared metrics.
     pub(crate) metrics: MetricRepo<G>,
+    /// Leaderboards for when plasma is unavailable, `None` if the store couldn't be opened.
+    pub(crate) local_leaderboards: Option<LocalLeaderboards>,
+    /// `Some` while gracefully shutting down.
+    pub(crate) draining: Option<Draining>,
 
     /// Drop missed updates.
     last_update: Instant,
     last_tick_end: Instant,
 
-    /// Misc.
-    stop_tx: Option<oneshot::Sender<()>>,
+    /// Misc. Sends whether the server stopped gracefully, after draining finished.
+    stop_tx: Option<oneshot::Sender<bool>>,
 }
 
 impl<G: ArenaService> Actor for ServerActor<G> {
@@ -89,7 +97,9 @@ impl<G: ArenaService> Actor for ServerActor<G> {
             system.stop();
         }
         */
-        let _ = self.stop_tx.take().unwrap().send(());
+        // An interrupted drain (e.g. Ctrl+C) isn't graceful.
+        let drained = self.draining.as_ref().is_some_and(|d| d.finished);
+        let _ = self.stop_tx.take().unwrap().send(drained);
     }
 }
 
@@ -103,12 +113,15 @@ impl<G: ArenaService> ServerActor<G> {
         region_id: RegionId,
         bots: Option<u16>,
         ads_txt: Arc<RwLock<HashMap<Option<Referrer>, Bytes>>>,
+        bans: Arc<RwLock<BanList>>,
         server_token: &'static AtomicU64,
         rustls_config: RustlsConfig,
         cors_alternative_domains: &'static Mutex<Arc<[DomainName]>>,
         domain_backup: Option<Arc<str>>,
+        plasma_url: String,
+        leaderboard_path: Option<&Path>,
         client_authenticate: RateLimiterProps,
-        stop_tx: oneshot::Sender<()>,
+        stop_tx: oneshot::Sender<bool>,
     ) -> Self {
         let now = Instant::now();
         Self {
@@ -122,12 +135,18 @@ impl<G: ArenaService> ServerActor<G> {
                 rustls_config,
                 cors_alternative_domains,
                 domain_backup,
+                plasma_url,
             ),
             system: SystemActlet::new(),
-            admin: AdminActlet::new(client_hash),
+            admin: AdminActlet::new(client_hash, bans),
             realms: RealmRepo::new(bots),
             invitations: InvitationRepo::default(),
             metrics: MetricRepo::new(),
+            local_leaderboards: LocalLeaderboards::open::<G::LeaderboardStore>(
+                leaderboard_path,
+                G::LEADERBOARD_SIZE,
+            ),
+            draining: None,
             last_update: now,
             last_tick_end: now,
             stop_tx: Some(stop_tx),
@@ -145,6 +164,18 @@ impl<G: ArenaService> ServerActor<G> {
         self.last_update = now;
         let server_delta = self.system.delta();
         let temporaries_available = self.temporaries_available();
+
+        // Moderators should learn of flags regardless of which realm they're in.
+        let flags = self
+            .realms
+            .realms_mut()
+            .flat_map(|(_, context_realm)| context_realm.realm_context.chat.take_flags())
+            .collect::<Vec<_>>();
+        ChatRepo::deliver_flags(
+            &flags,
+            self.realms.iter_mut().map(|(_, scene)| &mut scene.arena),
+        );
+
         for (realm_id, context_realm) in self.realms.realms_mut() {
             let mut players_online = 0;
             for (_, scene) in context_realm.scene_repo.iter_mut() {
@@ -163,6 +194,13 @@ impl<G: ArenaService> ServerActor<G> {
                 }
             }
 
+            context_realm.realm_context.chat.deliver_local_broadcasts(
+                context_realm
+                    .scene_repo
+                    .iter_mut()
+                    .map(|(_, scene)| &mut scene.arena),
+            );
+
             for (scene_id, scene) in context_realm.scene_repo.iter_mut() {
                 let shard_context = <G::Shard as ShardContextProvider<G>>::shard_context_mut(
                     &mut context_realm.realm_context.per_realm,
@@ -238,8 +276,10 @@ impl<G: ArenaService> ServerActor<G> {
         self.metrics.health.record_tick::<G>(tick_end, elapsed);
         self.plasma.health.record_tick::<G>(tick_end, elapsed);
 
+        let drained = self.update_drain();
+
         // These are all rate-limited internally.
-        LeaderboardRepo::update_to_plasma(self);
+        LeaderboardRepo::update_to_plasma(self, false);
         MetricRepo::update_to_plasma(self, ctx);
         self.plasma.update(
             self.server_id,
@@ -252,5 +292,9 @@ impl<G: ArenaService> ServerActor<G> {
         );
         self.realms
             .collect_arenas(self.server_id, &mut self.invitations, &self.plasma);
+
+        if drained {
+            ctx.stop();
+        }
     }
 }
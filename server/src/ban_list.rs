@@ -0,0 +1,162 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+use crate::{AdminBanDto, BanTarget, NonZeroUnixMillis, UnixTime, VisitorId};
+use log::{error, warn};
+use std::collections::HashMap;
+use std::io;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+/// IPs and visitors banned by admins. Shared by the server actor and the `/ws` upgrade path, and
+/// persisted to `--ban-path` so bans survive restarts.
+pub struct BanList {
+    path: PathBuf,
+    bans: HashMap<BanTarget, NonZeroUnixMillis>,
+}
+
+impl BanList {
+    pub fn open(path: &Path) -> Self {
+        let mut bans = HashMap::new();
+        match std::fs::read_to_string(path) {
+            Ok(contents) => match serde_json::from_str::<Vec<AdminBanDto>>(&contents) {
+                Ok(dtos) => {
+                    bans.extend(dtos.into_iter().map(|dto| (dto.target, dto.expiry)));
+                }
+                Err(e) => error!("could not parse ban list {path:?}: {e}"),
+            },
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => error!("could not read ban list {path:?}: {e}"),
+        }
+        Self {
+            path: path.to_owned(),
+            bans,
+        }
+    }
+
+    pub fn is_banned(&self, target: BanTarget) -> bool {
+        self.bans
+            .get(&target)
+            .is_some_and(|&expiry| expiry > NonZeroUnixMillis::now())
+    }
+
+    pub fn is_ip_banned(&self, ip_address: IpAddr) -> bool {
+        self.is_banned(BanTarget::Ip(ip_address))
+    }
+
+    pub fn is_visitor_banned(&self, visitor_id: Option<VisitorId>) -> bool {
+        visitor_id.is_some_and(|visitor_id| self.is_banned(BanTarget::Visitor(visitor_id)))
+    }
+
+    /// Replaces any existing ban of the same target.
+    pub fn ban(&mut self, target: BanTarget, minutes: u32) {
+        let expiry = NonZeroUnixMillis::now().add_millis(minutes as i64 * 60 * 1000);
+        self.bans.insert(target, expiry);
+        self.save();
+    }
+
+    /// Returns `false` if the target wasn't banned.
+    pub fn unban(&mut self, target: BanTarget) -> bool {
+        let removed = self.bans.remove(&target).is_some();
+        if removed {
+            self.save();
+        }
+        removed
+    }
+
+    /// Active bans, soonest to expire first.
+    pub fn dtos(&self) -> Box<[AdminBanDto]> {
+        let now = NonZeroUnixMillis::now();
+        let mut dtos = self
+            .bans
+            .iter()
+            .filter(|(_, &expiry)| expiry > now)
+            .map(|(&target, &expiry)| AdminBanDto { target, expiry })
+            .collect::<Vec<_>>();
+        dtos.sort_by_key(|dto| dto.expiry);
+        dtos.into_boxed_slice()
+    }
+
+    /// Writes active bans, forgetting expired ones.
+    fn save(&mut self) {
+        let now = NonZeroUnixMillis::now();
+        self.bans.retain(|_, expiry| *expiry > now);
+        let json = serde_json::to_string(&self.dtos()).unwrap();
+        let temporary = self.path.with_extension("tmp");
+        if let Err(e) =
+            std::fs::write(&temporary, json).and_then(|_| std::fs::rename(&temporary, &self.path))
+        {
+            warn!("could not save ban list {:?}: {e}", self.path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BanList;
+    use crate::{BanTarget, VisitorId};
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::num::NonZeroU64;
+    use std::path::PathBuf;
+
+    fn temporary_path(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("ban_list_{name}_{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn ban_and_unban() {
+        let path = temporary_path("ban_and_unban");
+        let ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let visitor_id = VisitorId(NonZeroU64::new(42).unwrap());
+
+        let mut bans = BanList::open(&path);
+        assert!(bans.dtos().is_empty());
+        bans.ban(BanTarget::Ip(ip), 10);
+        bans.ban(BanTarget::Visitor(visitor_id), 5);
+        assert!(bans.is_ip_banned(ip));
+        assert!(!bans.is_ip_banned(IpAddr::V4(Ipv4Addr::LOCALHOST)));
+        assert!(bans.is_visitor_banned(Some(visitor_id)));
+        assert!(!bans.is_visitor_banned(None));
+        assert_eq!(
+            bans.dtos().iter().map(|dto| dto.target).collect::<Vec<_>>(),
+            [BanTarget::Visitor(visitor_id), BanTarget::Ip(ip)]
+        );
+
+        // Bans persist.
+        let mut bans = BanList::open(&path);
+        assert!(bans.is_ip_banned(ip));
+        assert!(bans.is_visitor_banned(Some(visitor_id)));
+
+        assert!(bans.unban(BanTarget::Ip(ip)));
+        assert!(!bans.unban(BanTarget::Ip(ip)));
+        assert!(!bans.is_ip_banned(ip));
+        let bans = BanList::open(&path);
+        assert!(!bans.is_ip_banned(ip));
+        assert!(bans.is_visitor_banned(Some(visitor_id)));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn expiry() {
+        let path = temporary_path("expiry");
+        let ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+
+        let mut bans = BanList::open(&path);
+        bans.ban(BanTarget::Ip(ip), 0);
+        assert!(!bans.is_ip_banned(ip));
+        assert!(bans.dtos().is_empty());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn corrupt() {
+        let path = temporary_path("corrupt");
+        std::fs::write(&path, "not json").unwrap();
+        let bans = BanList::open(&path);
+        assert!(bans.dtos().is_empty());
+        let _ = std::fs::remove_file(&path);
+    }
+}
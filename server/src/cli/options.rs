@@ -1,7 +1,7 @@
 // SPDX-FileCopyrightText: 2024 Softbear, Inc.
 // SPDX-License-Identifier: LGPL-3.0-or-later
 
-use crate::{RegionId, ServerId, ServerKind, ServerToken};
+use crate::{NetworkImpairment, RegionId, ServerId, ServerKind, ServerToken};
 use clap::Parser;
 use log::LevelFilter;
 use std::net::{Ipv4Addr, Ipv6Addr};
@@ -29,8 +29,22 @@ pub struct Options {
     #[cfg_attr(debug_assertions, clap(long, default_value = "info"))]
     #[cfg_attr(not(debug_assertions), clap(long, default_value = "warn"))]
     pub debug_plasma: LevelFilter,
+    /// Where to persist IP and visitor bans.
+    #[clap(long, default_value = "./bans.json")]
+    pub ban_path: String,
     #[clap(long, default_value = "./domain_backup.json")]
     pub domain_backup: String,
+    /// How long to wait for players to be moved to other servers after SIGTERM (in seconds).
+    #[clap(long, default_value = "120")]
+    pub drain_timeout: u64,
+    /// Where to keep leaderboard scores while plasma is unavailable, if the game's
+    /// `LeaderboardStore` persists them (e.g. `./leaderboard.jsonl`).
+    #[clap(long)]
+    pub leaderboard_path: Option<String>,
+    /// Simulate poor network conditions for all clients, for example
+    /// `latency=100,jitter=20,loss=0.05,reorder=0.01,kbps=1000`.
+    #[clap(long)]
+    pub network_impairment: Option<NetworkImpairment>,
     /// Server ID.
     #[clap(long)]
     server_id: Option<ServerId>,
@@ -40,6 +54,10 @@ pub struct Options {
     /// Initial secret key unique to this server.
     #[clap(long)]
     pub server_token: Option<ServerToken>,
+    /// Override the plasma WebSocket URL (for example, `ws://localhost:8081/ws/` to use
+    /// `mini_plasma`).
+    #[clap(long, default_value = "wss://softbear.com/ws/")]
+    pub plasma_url: String,
     #[clap(long)]
     /// Override the server ipv4.
     pub ipv4_address: Option<Ipv4Addr>,
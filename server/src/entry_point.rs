@@ -5,6 +5,7 @@
 //! via web_socket.
 
 use crate::actor::ServerActor;
+use crate::ban_list::BanList;
 use crate::cli::Options;
 use crate::files::{set_open_file_limit, static_size_and_hash};
 use crate::net::{get_own_public_ip, ip_to_region_id, load_domains, CustomAcceptor, IpRateLimiter};
@@ -12,7 +13,10 @@ use crate::rate_limiter::RateLimiterProps;
 use crate::router::new_router;
 use crate::service::ArenaService;
 use crate::socket::web_transport;
-use crate::{AdminRequest, AdminUpdate, DomainDto, RealmId, ServerId, ServerKind, ServerNumber};
+use crate::{
+    AdminRequest, AdminUpdate, DomainDto, NetworkImpairment, RealmId, ServerId, ServerKind,
+    ServerNumber,
+};
 use actix::Actor;
 use axum::extract::FromRequestParts;
 use axum::http::StatusCode;
@@ -32,9 +36,10 @@ use std::borrow::Cow;
 use std::fs::File;
 use std::io::{Read, Write};
 use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
 use std::process::ExitCode;
 use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
-use std::sync::{Arc, LazyLock, Mutex, RwLock};
+use std::sync::{Arc, LazyLock, Mutex, OnceLock, RwLock};
 use std::time::Duration;
 use tokio::net::TcpStream;
 use tokio::runtime::Builder;
@@ -49,6 +54,9 @@ pub static SERVER_TOKEN: AtomicU64 = AtomicU64::new(0);
 pub static HTTP_RATE_LIMITER: LazyLock<Mutex<IpRateLimiter>> =
     LazyLock::new(|| Mutex::new(IpRateLimiter::new_bandwidth_limiter(1, 0)));
 
+/// Simulated network conditions for all clients, if any.
+pub static NETWORK_IMPAIRMENT: OnceLock<NetworkImpairment> = OnceLock::new();
+
 pub static CORS_ALTERNATIVE_DOMAINS: LazyLock<Mutex<Arc<[DomainName]>>> =
     LazyLock::new(|| Mutex::new(Vec::new().into()));
 
@@ -233,6 +241,10 @@ where
 
         *HTTP_RATE_LIMITER.lock().unwrap() =
             IpRateLimiter::new_bandwidth_limiter(options.http_bandwidth_limit, bandwidth_burst);
+        if let Some(impairment) = options.network_impairment {
+            warn!("impairing network: {impairment}");
+            let _ = NETWORK_IMPAIRMENT.set(impairment);
+        }
         SERVER_TOKEN.store(
             if let Some(token) = options.server_token {
                 token.0.get()
@@ -276,6 +288,8 @@ where
 
         let game_client = Arc::new(RwLock::new(game_client));
         let ads_txt = Arc::default();
+        let bans = Arc::new(RwLock::new(BanList::open(Path::new(&options.ban_path))));
+        let drain_timeout = Duration::from_secs(options.drain_timeout);
         #[allow(deprecated)]
         let (certificate, private_key) = options
             .certificate_private_key_paths()
@@ -299,7 +313,7 @@ where
             .0,
         );
         // Awaiting https://github.com/actix/actix-net/issues/588
-        let (stop_tx, stop_rx) = tokio::sync::oneshot::channel::<()>();
+        let (stop_tx, stop_rx) = tokio::sync::oneshot::channel::<bool>();
 
         // For debug.
         /*
@@ -323,10 +337,13 @@ where
                 region_id,
                 options.bots,
                 Arc::clone(&ads_txt),
+                Arc::clone(&bans),
                 &SERVER_TOKEN,
                 rustls_config.clone(),
                 &*CORS_ALTERNATIVE_DOMAINS,
                 Some(options.domain_backup.into()),
+                options.plasma_url,
+                options.leaderboard_path.as_deref().map(Path::new),
                 RateLimiterProps::new(
                     Duration::from_secs(options.client_authenticate_rate_limit),
                     options.client_authenticate_burst,
@@ -371,7 +388,7 @@ where
             });
         }
 
-        let app = new_router(server_id, srv.clone(), game_client, ads_txt);
+        let app = new_router(server_id, srv.clone(), game_client, ads_txt, bans);
 
         #[cfg(not(debug_assertions))]
         let http_app = axum::Router::new().fallback_service(axum::routing::get(
@@ -455,28 +472,56 @@ where
         }
         .into();
 
+        #[cfg(unix)]
+        let mut terminate =
+            tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()).unwrap();
+        let mut draining = false;
+        let mut stop_rx = stop_rx;
+        tokio::pin!(http_server, https_server, wt_server);
+
         let mut exit_code = ExitCode::FAILURE;
-        tokio::select! {
-            result = http_server => {
-                error!("http server stopped: {result:?}");
-            }
-            result = https_server => {
-                error!("https server stopped: {result:?}");
-            }
-            result = wt_server, if G::GAME_CONSTANTS.udp_enabled => {
-                error!("wt server stopped: {result:?}");
-            }
-            result = stop_rx => {
-                if result.is_ok () {
-                    error!("server actor stopped");
-                } else {
-                    error!("server actor dropped");
+        loop {
+            tokio::select! {
+                result = &mut http_server => {
+                    error!("http server stopped: {result:?}");
+                }
+                result = &mut https_server => {
+                    error!("https server stopped: {result:?}");
+                }
+                result = &mut wt_server, if G::GAME_CONSTANTS.udp_enabled => {
+                    error!("wt server stopped: {result:?}");
+                }
+                result = &mut stop_rx => {
+                    match result {
+                        Ok(true) => {
+                            // Drained, whether due to SIGTERM or an admin.
+                            info!("server actor stopped after draining");
+                            exit_code = ExitCode::SUCCESS;
+                        }
+                        Ok(false) => error!("server actor stopped"),
+                        Err(_) => error!("server actor dropped"),
+                    }
+                }
+                _ = tokio::signal::ctrl_c() => {
+                    error!("received Ctrl+C / SIGINT");
+                    exit_code = ExitCode::SUCCESS;
+                }
+                _ = async {
+                    #[cfg(unix)]
+                    terminate.recv().await;
+                    #[cfg(not(unix))]
+                    std::future::pending::<()>().await;
+                }, if !draining => {
+                    // Keep serving existing players until the server actor stops itself.
+                    error!("received SIGTERM, draining");
+                    draining = true;
+                    srv.do_send(crate::shutdown::Drain {
+                        timeout: drain_timeout,
+                    });
+                    continue;
                 }
             }
-            _ = tokio::signal::ctrl_c() => {
-                error!("received Ctrl+C / SIGINT");
-                exit_code = ExitCode::SUCCESS;
-            }
+            break;
         }
 
         srv.do_send(crate::shutdown::Shutdown);
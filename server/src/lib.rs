@@ -24,6 +24,7 @@
 #![feature(if_let_guard)]
 
 mod actor;
+mod ban_list;
 mod entry_point;
 mod files;
 mod service;
@@ -32,6 +33,7 @@ mod util;
 mod cli;
 mod net;
 mod observer;
+mod open_metrics;
 mod rate_limiter;
 mod router;
 mod shutdown;
@@ -45,7 +47,10 @@ static ALLOC: dhat::Alloc = dhat::Alloc;
 pub use entry_point::entry_point;
 pub use service::{
     random_bot_name, random_emoji_bot_name, ArenaContext, ArenaService, Bot, BotAction, BotOptions,
-    Player, RedirectedPlayer, Score, ShardPerRealm, ShardPerTier,
+    ChatArgument, ChatArgumentKind, ChatArgumentValue, ChatArguments, ChatCommand,
+    ChatCommandContext, ChatModerationSettings, ChatPermission, FileLeaderboardStore,
+    LeaderboardRecord, LeaderboardStore, Player, RedirectedPlayer, Score, ShardPerRealm,
+    ShardPerTier, TestArena,
 };
 pub use util::{base64_decode, base64_encode, diff_large_n, diff_small_n};
 
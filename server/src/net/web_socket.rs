@@ -11,12 +11,12 @@ use hyper::Uri;
 use log::{info, warn};
 use std::error::Error;
 use std::fmt::{self, Display, Formatter};
-use std::io::{self, ErrorKind};
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::net::TcpStream;
 use tokio::sync::mpsc::{channel, Sender};
-use tokio_rustls::client::TlsStream;
 use tokio_websockets::resolver::{self, Resolver};
 use tokio_websockets::{
     ClientBuilder, Connector, Limits, MaybeTlsStream, Message, WebSocketStream,
@@ -25,11 +25,22 @@ use tokio_websockets::{
 // TODO: was pub(crate)
 pub struct WebSocket {
     pub(crate) sender: Option<Sender<PlasmaRequest>>,
+    /// Whether the connection is currently established.
+    pub(crate) connected: Arc<AtomicBool>,
 }
 
 impl WebSocket {
     pub fn new() -> Self {
-        Self { sender: None }
+        Self {
+            sender: None,
+            connected: Arc::default(),
+        }
+    }
+
+    /// Whether the connection is currently established, so sent messages are likely to be
+    /// delivered. Reacts to disconnects immediately, unlike heartbeats.
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Relaxed)
     }
 
     pub fn do_send(&self, message: PlasmaRequest) {
@@ -42,12 +53,14 @@ impl WebSocket {
         info!("connecting to {url:?}");
         let (sender, mut receiver) = channel(16);
         self.sender = Some(sender);
+        let connected = Arc::clone(&self.connected);
         tokio::spawn(async move {
-            let mut connection: Option<WebSocketStream<TlsStream<TcpStream>>> = None;
+            let mut connection: Option<WebSocketStream<MaybeTlsStream<TcpStream>>> = None;
             const TIMEOUT: Duration = Duration::from_secs(100);
             let mut timeout = std::pin::pin!(tokio::time::sleep(TIMEOUT));
             let mut tries = 0;
             loop {
+                connected.store(connection.is_some(), Ordering::Relaxed);
                 if let Some(websocket) = connection.as_mut() {
                     tokio::select! {
                         to_send = receiver.recv() => {
@@ -124,34 +137,42 @@ impl WebSocket {
                     }
                 }
             }
+            connected.store(false, Ordering::Relaxed);
         });
     }
 
-    async fn connect(url: String) -> Result<WebSocketStream<TlsStream<TcpStream>>, ConnectError> {
+    async fn connect(
+        url: String,
+    ) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, ConnectError> {
         let uri = Uri::from_str(&url).map_err(ConnectError::InvalidUri)?;
 
+        let tls = match uri.scheme_str() {
+            Some("wss") => true,
+            // For a plasma on localhost, e.g. `mini_plasma`.
+            Some("ws") => false,
+            _ => return Err(ConnectError::UnsupportedScheme),
+        };
         let host = uri.host().ok_or(ConnectError::Other(
             tokio_websockets::Error::CannotResolveHost,
         ))?;
+        let port = uri.port_u16().unwrap_or(if tls { 443 } else { 80 });
         let addr = resolver::Gai
-            .resolve(host, 443)
+            .resolve(host, port)
             .await
             .map_err(ConnectError::Other)?;
         let stream = TcpStream::connect(&addr)
             .await
             .map_err(|e| ConnectError::Other(tokio_websockets::Error::Io(e)))?;
         nodelay_keepalive(&stream, 10, 4);
-        let connector = Connector::new().map_err(ConnectError::Other)?;
+        let connector = if tls {
+            Connector::new().map_err(ConnectError::Other)?
+        } else {
+            Connector::Plain
+        };
         let stream = connector
             .wrap(host, stream)
             .await
             .map_err(ConnectError::Other)?;
-        let MaybeTlsStream::Rustls(stream) = stream else {
-            debug_assert!(false);
-            return Err(ConnectError::Other(tokio_websockets::Error::Io(
-                io::Error::new(ErrorKind::Other, "not client rustls"),
-            )));
-        };
         let result = tokio::time::timeout(
             Duration::from_secs(12),
             ClientBuilder::from_uri(uri)
@@ -177,6 +198,7 @@ impl WebSocket {
 #[derive(Debug)]
 enum ConnectError {
     InvalidUri(InvalidUri),
+    UnsupportedScheme,
     Timeout,
     Other(tokio_websockets::Error),
 }
@@ -187,6 +209,7 @@ impl Display for ConnectError {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             Self::InvalidUri(e) => Display::fmt(e, f),
+            Self::UnsupportedScheme => f.write_str("unsupported scheme"),
             Self::Timeout => f.write_str("timeout"),
             Self::Other(e) => Display::fmt(e, f),
         }
@@ -0,0 +1,97 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+use super::actor::ServerActor;
+use super::entry_point::HTTP_RATE_LIMITER;
+use super::service::{ArenaService, MetricRepo};
+use crate::{write_open_metrics_gauge, write_open_metrics_labeled_gauge, OpenMetric};
+use actix::{Handler, Message};
+
+/// Prefix of every metric name.
+const PREFIX: &str = "kodiak_";
+
+/// Asks the server to render its current metrics in the OpenMetrics text format, for `/metrics`.
+#[derive(Message)]
+#[rtype(result = "String")]
+pub struct RequestOpenMetrics;
+
+impl<G: ArenaService> Handler<RequestOpenMetrics> for ServerActor<G> {
+    type Result = String;
+
+    fn handle(&mut self, _request: RequestOpenMetrics, _ctx: &mut Self::Context) -> Self::Result {
+        let mut out = String::new();
+
+        // Accumulated since the start of the current metrics period (hour).
+        MetricRepo::get_metrics(self, None).write_open_metrics(PREFIX, &mut out);
+
+        let health = &mut self.metrics.health;
+        write_open_metrics_gauge("kodiak_health_cpu", health.cpu() as f64, &mut out);
+        write_open_metrics_gauge(
+            "kodiak_health_cpu_steal",
+            health.cpu_steal() as f64,
+            &mut out,
+        );
+        write_open_metrics_gauge("kodiak_health_ram", health.ram() as f64, &mut out);
+        write_open_metrics_gauge(
+            "kodiak_health_missed_ticks",
+            health.missed_ticks() as f64,
+            &mut out,
+        );
+        // Since the last minutely metrics update.
+        health
+            .tps()
+            .write_open_metric("kodiak_health_tps", &mut out);
+        health
+            .spt()
+            .write_open_metric("kodiak_health_spt", &mut out);
+
+        let (mut ips, mut connections, mut actives) = (0u64, 0u64, 0u64);
+        for (c, a) in HTTP_RATE_LIMITER
+            .lock()
+            .unwrap()
+            .connections_actives_per_ip()
+        {
+            ips += 1;
+            connections += c as u64;
+            actives += a as u64;
+        }
+        write_open_metrics_gauge("kodiak_ips", ips as f64, &mut out);
+        write_open_metrics_gauge("kodiak_ip_connections", connections as f64, &mut out);
+        write_open_metrics_gauge("kodiak_ip_actives", actives as f64, &mut out);
+
+        let arenas = self
+            .realms
+            .iter()
+            .map(|(arena_id, scene)| {
+                let context = &scene.arena.arena_context;
+                (
+                    arena_id.to_string(),
+                    context.players.real_players,
+                    context.players.real_players_live,
+                    context.bots.count,
+                )
+            })
+            .collect::<Vec<_>>();
+        write_open_metrics_labeled_gauge(
+            "kodiak_arena_players",
+            "arena",
+            arenas.iter().map(|(a, p, _, _)| (a.as_str(), *p as f64)),
+            &mut out,
+        );
+        write_open_metrics_labeled_gauge(
+            "kodiak_arena_players_live",
+            "arena",
+            arenas.iter().map(|(a, _, l, _)| (a.as_str(), *l as f64)),
+            &mut out,
+        );
+        write_open_metrics_labeled_gauge(
+            "kodiak_arena_bots",
+            "arena",
+            arenas.iter().map(|(a, _, _, b)| (a.as_str(), *b as f64)),
+            &mut out,
+        );
+
+        out.push_str("# EOF\n");
+        out
+    }
+}
@@ -2,8 +2,10 @@
 // SPDX-License-Identifier: LGPL-3.0-or-later
 
 use super::actor::ServerActor;
+use super::ban_list::BanList;
 use super::entry_point::{Authenticated, CORS_ALTERNATIVE_DOMAINS, REDIRECT_TO_SERVER_ID};
 use super::net::{limit_content_length, IpRateLimiter, KillSwitch};
+use super::open_metrics::RequestOpenMetrics;
 use super::rate_limiter::{RateLimiterProps, RateLimiterState};
 use super::service::ArenaService;
 use super::socket::ws_request;
@@ -22,7 +24,7 @@ use axum::response::{IntoResponse, Redirect, Response};
 use axum::routing::{any, get, post};
 use axum::{Json, Router};
 use bytes::Bytes;
-use hyper::header::{CACHE_CONTROL, CONNECTION, CONTENT_LENGTH};
+use hyper::header::{CACHE_CONTROL, CONNECTION, CONTENT_LENGTH, CONTENT_TYPE};
 use kodiak_common::DomainName;
 use minicdn::MiniCdn;
 use std::collections::HashMap;
@@ -48,11 +50,29 @@ pub async fn admin_request<G: ArenaService>(
     }
 }
 
+/// Prometheus scrape endpoint.
+pub async fn open_metrics_request<G: ArenaService>(
+    State(state): State<AppState<G>>,
+    _: Authenticated,
+) -> impl IntoResponse {
+    match state.server.send(RequestOpenMetrics).await {
+        Ok(text) => Ok((
+            [(
+                CONTENT_TYPE,
+                "application/openmetrics-text; version=1.0.0; charset=utf-8",
+            )],
+            text,
+        )),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
 pub fn new_router<G: ArenaService>(
     server_id: ServerId,
     infrastructure: Addr<ServerActor<G>>,
     game_client: Arc<RwLock<MiniCdn>>,
     ads_txt: Arc<RwLock<HashMap<Option<Referrer>, Bytes>>>,
+    bans: Arc<RwLock<BanList>>,
 ) -> Router {
     let cors_layer = CorsLayer::new()
         .allow_origin(tower_http::cors::AllowOrigin::predicate(
@@ -66,7 +86,7 @@ pub fn new_router<G: ArenaService>(
         .allow_headers(tower_http::cors::Any)
         .allow_methods([Method::GET, Method::HEAD, Method::POST, Method::OPTIONS]);
 
-    let state = AppState::<G>::new(server_id, infrastructure, ads_txt);
+    let state = AppState::<G>::new(server_id, infrastructure, ads_txt, bans);
     Router::new()
         .fallback_service(get(StaticFilesHandler {
             cdn: game_client,
@@ -88,6 +108,7 @@ pub fn new_router<G: ArenaService>(
         // Need both, see https://github.com/tokio-rs/axum/issues/1607#issuecomment-1335025399
         .route("/admin/", post(admin_request))
         .route("/admin/{*path}", post(admin_request))
+        .route("/metrics", get(open_metrics_request))
         .route("/ads.txt", get(ads_txt_file))
         .route("/robots.txt", get(robots_txt_file::<G>))
         .route("/sitemap.txt", get(sitemap_txt_file::<G>))
@@ -17,7 +17,7 @@ use crate::{
 use actix::Recipient;
 use kodiak_common::rand::random;
 use kodiak_common::{FileNamespace, VisitorId};
-use log::info;
+use log::{info, warn};
 use std::net::IpAddr;
 use std::time::{Duration, Instant};
 use tokio::sync::mpsc::Sender;
@@ -42,7 +42,8 @@ pub struct ArenaContext<G: ArenaService> {
 #[derive(Clone)]
 pub struct SendPlasmaRequest {
     pub(crate) web_socket: Option<Sender<PlasmaRequest>>,
-    pub(crate) local: Recipient<PlasmaUpdate>,
+    /// `None` if not running in an actor (e.g. [`TestArena`](crate::TestArena)).
+    pub(crate) local: Option<Recipient<PlasmaUpdate>>,
     pub(crate) local_server_id: ServerId,
 }
 
@@ -52,11 +53,12 @@ impl SendPlasmaRequest {
             PlasmaRequest::V1(PlasmaRequestV1::SendServerMessage {
                 recipients,
                 message,
-            }) if recipients.len() == 1
+            }) if let Some(local) = &self.local
+                && recipients.len() == 1
                 && *recipients.iter().next().unwrap() == self.local_server_id =>
             {
                 info!("sent {message:?} efficiently");
-                let _ = self.local.do_send(PlasmaUpdate::V1(
+                let _ = local.do_send(PlasmaUpdate::V1(
                     vec![PlasmaUpdateV1::Parley {
                         sender: self.local_server_id,
                         message,
@@ -84,6 +86,27 @@ pub struct RedirectedPlayer {
     metrics: ClientMetricData,
 }
 
+impl RedirectedPlayer {
+    /// Encodes game-specific state, to be sent alongside (not inside, which would break
+    /// compatibility with other servers) a redirected player.
+    pub(crate) fn encode_portable_state<G: ArenaService>(state: &G::PortableState) -> String {
+        use base64::prelude::*;
+        BASE64_STANDARD_NO_PAD.encode(bitcode::encode(state))
+    }
+
+    /// Returns `None` if the state couldn't be decoded, e.g. if the other server runs a different
+    /// version of the game.
+    pub(crate) fn decode_portable_state<G: ArenaService>(
+        encoded: &str,
+    ) -> Option<G::PortableState> {
+        use base64::prelude::*;
+        let bitcoded = BASE64_STANDARD_NO_PAD.decode(encoded).ok()?;
+        bitcode::decode(&bitcoded)
+            .inspect_err(|e| warn!("couldn't decode portable state: {e}"))
+            .ok()
+    }
+}
+
 impl<G: ArenaService> ArenaContext<G> {
     pub(crate) fn new(
         server_id: ServerId,
@@ -177,7 +200,9 @@ impl<G: ArenaService> ArenaContext<G> {
     /// Sends player at `player_id` to server at `server_id`.
     ///
     /// Game should remove and forget player as if `player_quit`
-    /// and `player_left` were both called.
+    /// and `player_left` were both called. Unlike engine-initiated redirects, which use
+    /// [`ArenaService::export_player`], game may include its own state in the message that carries
+    /// the [`RedirectedPlayer`].
     ///
     /// **Panics**
     ///
@@ -310,6 +335,8 @@ impl<G: ArenaService> ArenaContext<G> {
             redirected_player.chat,
             redirected_player.metrics,
             redirected_player.ip_address,
+            // Only players that joined the game are redirected.
+            false,
         );
         client.session = redirected_player.session;
         client.status = ClientStatus::Limbo {
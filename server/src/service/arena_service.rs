@@ -2,7 +2,7 @@
 // SPDX-License-Identifier: LGPL-3.0-or-later
 
 use super::shard_context::ShardContextProvider;
-use super::{BotOptions, ShardPerRealm};
+use super::{BotOptions, ChatCommand, ChatModerationSettings, LeaderboardStore, ShardPerRealm};
 use crate::bitcode::*;
 use crate::service::{ArenaContext, Player, Score};
 use crate::{
@@ -33,12 +33,24 @@ pub trait ArenaService: 'static + Unpin + Sized + Send + Sync {
     const LIVEBOARD_LEADERBOARD_TEAM_REPRESENTATION: bool = false;
     const GAME_CONSTANTS: &'static GameConstants;
     const MAX_TEMPORARY_SERVERS: usize = 16;
+    /// Chat commands, in addition to the engine's (e.g. `/help`).
+    const CHAT_COMMANDS: &'static [ChatCommand<Self>] = &[];
+    /// Thresholds for automated chat spam moderation, e.g.
+    /// [`ChatModerationSettings::RECOMMENDED`]. Disabled by default.
+    const CHAT_MODERATION: ChatModerationSettings = ChatModerationSettings::DISABLED;
 
     type Bot: 'static + Bot<Self> + Debug = ();
     type ClientData: 'static + Default + Debug + Unpin + Send + Sync = ();
+    /// Game-specific state (e.g. score or team membership) that follows a player when they're
+    /// redirected to another server. See [`Self::export_player`] and [`Self::player_migrated`].
+    type PortableState: 'static + Encode + DecodeOwned = ();
     type GameUpdate: 'static + Sync + Send + Encode + DecodeOwned;
     type GameRequest: 'static + Debug + DecodeOwned + Send + Unpin;
     type Shard: ShardContextProvider<Self> = ShardPerRealm;
+    /// Where leaderboard scores are kept while plasma is unavailable. By default, they are only
+    /// kept in memory. Use [`FileLeaderboardStore`](super::FileLeaderboardStore) along with `--leaderboard-path` to keep them
+    /// across restarts.
+    type LeaderboardStore: LeaderboardStore = ();
     type ArenaSettings: 'static
         + Sync
         + Send
@@ -87,11 +99,43 @@ pub trait ArenaService: 'static + Unpin + Sized + Send + Sync {
         false
     }
 
+    /// Game may label a game update (e.g. by variant), to break down bandwidth usage reported by
+    /// [`AdminRequest::RequestBandwidthBreakdown`](crate::AdminRequest::RequestBandwidthBreakdown).
+    fn label_game_update(update: &Self::GameUpdate) -> Option<&'static str> {
+        let _ = update;
+        None
+    }
+
     /// Game should add the player.
     fn player_joined(&mut self, player_id: PlayerId, _player: &mut Player<Self>) {
         let _ = player_id;
     }
 
+    /// Game may return state that should follow the player to another server. Called just before
+    /// `player_quit` when the engine redirects the player, e.g. when they switch arenas or the
+    /// server is draining. Not called by [`ArenaContext::send_player`], since the game already
+    /// controls what it sends.
+    fn export_player(
+        &mut self,
+        player_id: PlayerId,
+        _player: &Player<Self>,
+    ) -> Option<Self::PortableState> {
+        let _ = player_id;
+        None
+    }
+
+    /// Game should add the player, who was redirected from another server, restoring `state`
+    /// if any. Called instead of `player_joined`.
+    fn player_migrated(
+        &mut self,
+        player_id: PlayerId,
+        player: &mut Player<Self>,
+        state: Option<Self::PortableState>,
+    ) {
+        let _ = state;
+        self.player_joined(player_id, player);
+    }
+
     /// Game should idempotently kill the player.
     fn player_quit(&mut self, player_id: PlayerId, _player: &mut Player<Self>) {
         let _ = player_id;
@@ -110,7 +154,9 @@ pub trait ArenaService: 'static + Unpin + Sized + Send + Sync {
         _player: &mut Player<Self>,
     ) -> Option<Self::GameUpdate>;
 
-    /// Game should interpret player chat command.
+    /// Game should interpret player chat command, which isn't one of [`Self::CHAT_COMMANDS`].
+    ///
+    /// Prefer registering typed commands in [`Self::CHAT_COMMANDS`].
     fn chat_command(
         &mut self,
         command: &str,
@@ -162,6 +208,10 @@ pub trait ArenaService: 'static + Unpin + Sized + Send + Sync {
     /// May return one update to be send reliably and/or use
     /// `player.client().unwrap().send_with_reliable()` for
     /// more flexibility.
+    ///
+    /// Also called for spectators, which never join (so `player_joined` isn't called for them and
+    /// their commands are rejected). Send them e.g. `LockstepServer::spectator_update`, or
+    /// `World::get_spectator_update` with the knowledge of `player.client().unwrap().following()`.
     fn get_game_update(
         &self,
         player_id: PlayerId,
@@ -234,7 +284,8 @@ impl<G: ArenaService> Bot<G> for () {
 mod tests {
     use crate::service::ArenaService;
     use crate::{
-        ArenaContext, Bot, BotAction, GameId, Player, PlayerAlias, PlayerId, Score, TierNumber,
+        ArenaContext, ArenaSettingsDto, Bot, BotAction, DefaultedGameConstants, GameConstants,
+        NoGameArenaSettings, Player, PlayerAlias, PlayerId, Score,
     };
 
     pub struct MockGame;
@@ -243,7 +294,12 @@ mod tests {
     pub struct MockGameBot;
 
     impl Bot<MockGame> for MockGameBot {
-        fn update(_: &MockGame, _: PlayerId, _: &mut Player<MockGame>) -> BotAction<()> {
+        fn update(
+            _: &MockGame,
+            _: PlayerId,
+            _: &mut Player<MockGame>,
+            _: &ArenaSettingsDto<NoGameArenaSettings>,
+        ) -> BotAction<()> {
             Default::default()
         }
     }
@@ -254,10 +310,18 @@ mod tests {
         type GameRequest = ();
         type GameUpdate = ();
 
-        const GAME_ID: GameId = GameId::Redacted;
+        const GAME_CONSTANTS: &'static GameConstants = &GameConstants {
+            domain: "example.com",
+            game_id: "Mock",
+            geodns_enabled: false,
+            name: "Mock",
+            trademark: "Mock",
+            server_names: &["Mock"],
+            defaulted: DefaultedGameConstants::new(),
+        };
         const TICK_PERIOD_SECS: f32 = 0.5;
 
-        fn new(_: Option<TierNumber>, _: usize) -> Self {
+        fn new(_: &mut ArenaContext<Self>) -> Self {
             Self
         }
 
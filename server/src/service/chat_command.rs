@@ -0,0 +1,358 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+use super::{ArenaContext, ArenaService, ChatRepo, Player, PlayerRepo};
+use crate::actor::PlasmaActlet;
+use crate::{ArenaId, ChatCommandDto, PlayerId};
+use std::fmt::Write;
+
+/// A typed chat command, like `/slow 5m`. The engine registers its own, and games may register
+/// more via [`ArenaService::CHAT_COMMANDS`].
+pub struct ChatCommand<G: ArenaService> {
+    /// Invoked as `/{name}`.
+    pub name: &'static str,
+    /// Parsed, in order, from the words after the name.
+    pub arguments: &'static [ChatArgument],
+    pub permission: ChatPermission,
+    /// Shown by `/help`.
+    pub description: &'static str,
+    /// Returns the response to show the sender.
+    pub execute: fn(&mut ChatCommandContext<'_, G>, &ChatArguments) -> Result<String, &'static str>,
+}
+
+// Can't derive, since that would require `G: Copy`.
+impl<G: ArenaService> Clone for ChatCommand<G> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<G: ArenaService> Copy for ChatCommand<G> {}
+
+/// One argument of a [`ChatCommand`].
+#[derive(Copy, Clone, Debug)]
+pub struct ChatArgument {
+    pub name: &'static str,
+    /// The first kind that parses is used.
+    pub kinds: &'static [ChatArgumentKind],
+    /// Whether the argument may be omitted, in which case it and all following arguments are
+    /// `None`.
+    pub optional: bool,
+}
+
+impl ChatArgument {
+    pub const fn required(name: &'static str, kinds: &'static [ChatArgumentKind]) -> Self {
+        Self {
+            name,
+            kinds,
+            optional: false,
+        }
+    }
+
+    pub const fn optional(name: &'static str, kinds: &'static [ChatArgumentKind]) -> Self {
+        Self {
+            name,
+            kinds,
+            optional: true,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum ChatArgumentKind {
+    /// A duration like `30`, `30m`, `2h`, or `off`.
+    Minutes,
+    /// A [`PlayerId`] or (optionally `@`-prefixed) alias of a player in the same arena.
+    Player,
+    /// A number in the inclusive range.
+    Number { min: f64, max: f64 },
+    /// One of the (lowercase) variants.
+    Enum(&'static [&'static str]),
+    /// Any single word.
+    Word,
+}
+
+/// A parsed [`ChatArgument`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChatArgumentValue {
+    Minutes(u32),
+    Player(PlayerId),
+    Number(f64),
+    Enum(&'static str),
+    Word(String),
+}
+
+/// Parsed arguments, indexed in the same order as [`ChatCommand::arguments`].
+#[derive(Debug, Default)]
+pub struct ChatArguments(Vec<Option<ChatArgumentValue>>);
+
+impl ChatArguments {
+    /// Returns `None` if the argument was omitted.
+    pub fn get(&self, index: usize) -> Option<&ChatArgumentValue> {
+        self.0.get(index).and_then(Option::as_ref)
+    }
+
+    pub fn minutes(&self, index: usize) -> Option<u32> {
+        match self.get(index) {
+            Some(&ChatArgumentValue::Minutes(minutes)) => Some(minutes),
+            _ => None,
+        }
+    }
+
+    pub fn player(&self, index: usize) -> Option<PlayerId> {
+        match self.get(index) {
+            Some(&ChatArgumentValue::Player(player_id)) => Some(player_id),
+            _ => None,
+        }
+    }
+
+    pub fn number(&self, index: usize) -> Option<f64> {
+        match self.get(index) {
+            Some(&ChatArgumentValue::Number(number)) => Some(number),
+            _ => None,
+        }
+    }
+
+    pub fn variant(&self, index: usize) -> Option<&'static str> {
+        match self.get(index) {
+            Some(&ChatArgumentValue::Enum(variant)) => Some(variant),
+            _ => None,
+        }
+    }
+
+    pub fn word(&self, index: usize) -> Option<&str> {
+        match self.get(index) {
+            Some(ChatArgumentValue::Word(word)) => Some(word),
+            _ => None,
+        }
+    }
+}
+
+/// Who may use a [`ChatCommand`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ChatPermission {
+    Player,
+    /// Moderators and admins.
+    Moderator,
+    /// Admins, or anyone in debug builds.
+    Admin,
+    /// Anyone, but only in debug builds.
+    Debug,
+}
+
+impl ChatPermission {
+    pub fn allows<G: ArenaService>(self, player: &Player<G>) -> bool {
+        let Some(client) = player.client() else {
+            return false;
+        };
+        match self {
+            Self::Player => true,
+            Self::Moderator => client.moderator() || client.admin(),
+            Self::Admin => cfg!(debug_assertions) || client.admin(),
+            Self::Debug => cfg!(debug_assertions),
+        }
+    }
+}
+
+/// What a [`ChatCommand`] may act upon.
+pub struct ChatCommandContext<'a, G: ArenaService> {
+    /// The sender.
+    pub player_id: PlayerId,
+    pub arena_id: ArenaId,
+    pub service: &'a mut G,
+    pub context: &'a mut ArenaContext<G>,
+    pub(crate) chat: &'a mut ChatRepo<G>,
+    pub(crate) plasma: &'a PlasmaActlet,
+}
+
+impl<G: ArenaService> ChatCommandContext<'_, G> {
+    /// The sender.
+    pub fn player(&mut self) -> &mut Player<G> {
+        self.context
+            .players
+            .get_mut(self.player_id)
+            .expect("sender must exist")
+    }
+}
+
+impl<G: ArenaService> ChatCommand<G> {
+    /// E.g. `/bots [count|default]`.
+    pub fn usage(&self) -> String {
+        let mut usage = format!("/{}", self.name);
+        for argument in self.arguments {
+            let (open, close) = if argument.optional {
+                ('[', ']')
+            } else {
+                ('<', '>')
+            };
+            let _ = write!(usage, " {open}");
+            for (i, kind) in argument.kinds.iter().enumerate() {
+                if i > 0 {
+                    usage.push('|');
+                }
+                match kind {
+                    ChatArgumentKind::Enum(variants) => usage.push_str(&variants.join("|")),
+                    _ => usage.push_str(argument.name),
+                }
+            }
+            usage.push(close);
+        }
+        usage
+    }
+
+    pub fn dto(&self) -> ChatCommandDto {
+        ChatCommandDto {
+            name: self.name.to_owned(),
+            usage: self.usage(),
+            description: self.description.to_owned(),
+        }
+    }
+
+    /// Parses the words following the command name.
+    pub(crate) fn parse<'a>(
+        &self,
+        mut words: impl Iterator<Item = &'a str>,
+        players: &PlayerRepo<G>,
+    ) -> Result<ChatArguments, String> {
+        let mut values = Vec::with_capacity(self.arguments.len());
+        for argument in self.arguments {
+            let Some(word) = words.next() else {
+                if argument.optional {
+                    values.push(None);
+                    continue;
+                }
+                return Err(format!(
+                    "missing {}, usage: {}",
+                    argument.name,
+                    self.usage()
+                ));
+            };
+            let value = argument
+                .kinds
+                .iter()
+                .find_map(|kind| kind.parse(word, players))
+                .ok_or_else(|| format!("invalid {}, usage: {}", argument.name, self.usage()))?;
+            values.push(Some(value));
+        }
+        if words.next().is_some() {
+            return Err(format!("too many arguments, usage: {}", self.usage()));
+        }
+        Ok(ChatArguments(values))
+    }
+}
+
+impl ChatArgumentKind {
+    fn parse<G: ArenaService>(
+        &self,
+        word: &str,
+        players: &PlayerRepo<G>,
+    ) -> Option<ChatArgumentValue> {
+        match *self {
+            Self::Minutes => parse_minutes(word).map(ChatArgumentValue::Minutes),
+            Self::Player => {
+                if let Ok(player_id) = word.parse::<PlayerId>()
+                    && players.get(player_id).is_some()
+                {
+                    return Some(ChatArgumentValue::Player(player_id));
+                }
+                let alias = word.strip_prefix('@').unwrap_or(word);
+                players
+                    .iter()
+                    .find(|(_, player)| player.alias.as_str().eq_ignore_ascii_case(alias))
+                    .map(|(player_id, _)| ChatArgumentValue::Player(player_id))
+            }
+            Self::Number { min, max } => word
+                .parse::<f64>()
+                .ok()
+                .filter(|n| (min..=max).contains(n))
+                .map(ChatArgumentValue::Number),
+            Self::Enum(variants) => variants
+                .iter()
+                .find(|v| v.eq_ignore_ascii_case(word))
+                .map(|v| ChatArgumentValue::Enum(v)),
+            Self::Word => Some(ChatArgumentValue::Word(word.to_owned())),
+        }
+    }
+}
+
+fn parse_minutes(arg: &str) -> Option<u32> {
+    if matches!(arg, "none" | "off") {
+        Some(0)
+    } else {
+        arg.parse::<u32>()
+            .ok()
+            .or_else(|| arg.strip_suffix('m').and_then(|s| s.parse().ok()))
+            .or_else(|| {
+                arg.strip_suffix('h')
+                    .and_then(|s| s.parse::<u32>().ok())
+                    .and_then(|n| n.checked_mul(60))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_minutes;
+    use crate::service::test_arena::tests::Echo;
+    use crate::service::{ArenaService, ChatCommand, TestArena};
+    use crate::PlayerAlias;
+
+    fn echo() -> &'static ChatCommand<Echo> {
+        &Echo::CHAT_COMMANDS[0]
+    }
+
+    #[test]
+    fn minutes() {
+        assert_eq!(parse_minutes("off"), Some(0));
+        assert_eq!(parse_minutes("5"), Some(5));
+        assert_eq!(parse_minutes("5m"), Some(5));
+        assert_eq!(parse_minutes("2h"), Some(120));
+        assert_eq!(parse_minutes("2d"), None);
+        assert_eq!(parse_minutes("-1"), None);
+    }
+
+    #[test]
+    fn usage() {
+        assert_eq!(echo().usage(), "/echo <target|all|none> <times> [word]");
+        assert_eq!(echo().dto().usage, echo().usage());
+    }
+
+    #[test]
+    fn parse() {
+        let mut arena = TestArena::<Echo>::new();
+        let a = arena.add_client();
+        arena.context_mut().players.get_mut(a).unwrap().alias =
+            PlayerAlias::new_unsanitized("Alice");
+        let players = &arena.context().players;
+        let parse = |text: &str| echo().parse(text.split_ascii_whitespace(), players);
+
+        // Player by id, or case-insensitive alias with optional `@`.
+        for target in [a.to_string().as_str(), "alice", "@Alice"] {
+            let arguments = parse(&format!("{target} 2")).unwrap();
+            assert_eq!(arguments.player(0), Some(a));
+            assert_eq!(arguments.number(1), Some(2.0));
+            // Optional and omitted.
+            assert_eq!(arguments.word(2), None);
+        }
+
+        // Falls back to the next kind.
+        let arguments = parse("ALL 3 hi").unwrap();
+        assert_eq!(arguments.player(0), None);
+        assert_eq!(arguments.variant(0), Some("all"));
+        assert_eq!(arguments.number(1), Some(3.0));
+        assert_eq!(arguments.word(2), Some("hi"));
+
+        assert_eq!(
+            parse("").unwrap_err(),
+            "missing target, usage: /echo <target|all|none> <times> [word]"
+        );
+        assert!(parse("alice").unwrap_err().starts_with("missing times"));
+        assert!(parse("bob 1").unwrap_err().starts_with("invalid target"));
+        // Out of range.
+        assert!(parse("all 4").unwrap_err().starts_with("invalid times"));
+        assert!(parse("all x").unwrap_err().starts_with("invalid times"));
+        assert!(parse("all 1 hi there")
+            .unwrap_err()
+            .starts_with("too many arguments"));
+    }
+}
@@ -0,0 +1,393 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+use crate::{ArenaId, PlayerId};
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+/// Thresholds for automated chat moderation, which is disabled by default. See
+/// [`ArenaService::CHAT_MODERATION`](crate::ArenaService::CHAT_MODERATION).
+#[derive(Copy, Clone, Debug)]
+pub struct ChatModerationSettings {
+    pub enabled: bool,
+    /// At most this many messages...
+    pub rate_limit: usize,
+    /// ...may be sent per this period, per IP and per player. Penalties only apply to the player.
+    pub rate_period: Duration,
+    /// How many recent messages new ones are compared against.
+    pub duplicate_history: usize,
+    /// Messages at least this similar (from 0 to 1) to a recent one are duplicates.
+    pub duplicate_similarity: f32,
+    /// Messages with at least this many letters...
+    pub caps_min_letters: usize,
+    /// ...of which at least this fraction are uppercase are a caps flood.
+    pub caps_fraction: f32,
+    /// Messages with at least this many emoji are an emoji flood.
+    pub emoji_limit: usize,
+    /// First penalty: messages are only shown to the sender.
+    pub shadow_mute: Duration,
+    /// Second penalty: messages are rejected. Further offenses also flag the sender to admins.
+    pub mute: Duration,
+    /// Offenses are forgotten after this long without another.
+    pub forgive: Duration,
+}
+
+impl ChatModerationSettings {
+    /// Reasonable thresholds, enabled.
+    pub const RECOMMENDED: Self = Self {
+        enabled: true,
+        rate_limit: 5,
+        rate_period: Duration::from_secs(10),
+        duplicate_history: 4,
+        duplicate_similarity: 0.85,
+        caps_min_letters: 12,
+        caps_fraction: 0.7,
+        emoji_limit: 8,
+        shadow_mute: Duration::from_secs(60),
+        mute: Duration::from_secs(5 * 60),
+        forgive: Duration::from_secs(30 * 60),
+    };
+
+    pub const DISABLED: Self = Self {
+        enabled: false,
+        ..Self::RECOMMENDED
+    };
+}
+
+impl Default for ChatModerationSettings {
+    fn default() -> Self {
+        Self::DISABLED
+    }
+}
+
+/// What to do with a message.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum ChatVerdict {
+    Allow,
+    /// Only show the message to the sender.
+    ShadowMute,
+    /// Reject the message.
+    Mute,
+    /// Reject the message, without penalizing the sender, since others on their IP are sending too
+    /// many messages.
+    RateLimited,
+}
+
+/// Why a message was flagged.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum ChatViolation {
+    Rate,
+    Duplicate,
+    Caps,
+    Emoji,
+}
+
+impl ChatViolation {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Rate => "sending too many messages",
+            Self::Duplicate => "repeating messages",
+            Self::Caps => "excessive caps",
+            Self::Emoji => "excessive emoji",
+        }
+    }
+}
+
+/// Escalates with each offense.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum ChatPenalty {
+    ShadowMute,
+    Mute,
+    /// Mute and flag to admins.
+    Flag,
+}
+
+/// Tracks recent messages per player, to detect spam. Also rate limits per IP, so that players
+/// sharing an IP (e.g. at a school) aren't penalized for each other's messages.
+#[derive(Default)]
+pub(crate) struct ChatModerator {
+    /// When recent messages were sent, oldest first.
+    ips: HashMap<IpAddr, VecDeque<Instant>>,
+    players: HashMap<(ArenaId, PlayerId), SenderHistory>,
+    last_prune: Option<Instant>,
+}
+
+#[derive(Default)]
+struct SenderHistory {
+    /// Normalized, oldest first.
+    recent: VecDeque<(Instant, String)>,
+    offenses: u8,
+    last_offense: Option<Instant>,
+    shadow_muted_until: Option<Instant>,
+    muted_until: Option<Instant>,
+}
+
+impl SenderHistory {
+    fn is_idle(&self, settings: &ChatModerationSettings, now: Instant) -> bool {
+        self.recent
+            .back()
+            .map_or(true, |&(time, _)| now - time > settings.rate_period)
+            && self
+                .last_offense
+                .map_or(true, |time| now - time > settings.forgive)
+            && self.muted_until.map_or(true, |until| until <= now)
+            && self.shadow_muted_until.map_or(true, |until| until <= now)
+    }
+}
+
+impl ChatModerator {
+    /// Judges a message, recording it and applying a penalty if it is spam.
+    pub(crate) fn moderate(
+        &mut self,
+        settings: &ChatModerationSettings,
+        ip: IpAddr,
+        arena_id: ArenaId,
+        player_id: PlayerId,
+        message: &str,
+    ) -> (ChatVerdict, Option<(ChatPenalty, ChatViolation)>) {
+        if !settings.enabled {
+            return (ChatVerdict::Allow, None);
+        }
+        let now = Instant::now();
+        if self
+            .last_prune
+            .map_or(true, |last| now - last > Duration::from_secs(60))
+        {
+            self.last_prune = Some(now);
+            self.ips.retain(|_, recent| {
+                recent
+                    .back()
+                    .is_some_and(|&time| now - time <= settings.rate_period)
+            });
+            self.players
+                .retain(|_, history| !history.is_idle(settings, now));
+        }
+
+        let ip_recent = self.ips.entry(ip).or_default();
+        let history = self.players.entry((arena_id, player_id)).or_default();
+        let active = |until: Option<Instant>| until.is_some_and(|until| until > now);
+        if active(history.muted_until) {
+            return (ChatVerdict::Mute, None);
+        }
+        let rate = |times: &mut dyn Iterator<Item = Instant>| {
+            times
+                .filter(|&time| now - time < settings.rate_period)
+                .count()
+        };
+
+        let normalized = normalize(message);
+        let violation = if rate(&mut history.recent.iter().map(|&(time, _)| time))
+            >= settings.rate_limit
+        {
+            Some(ChatViolation::Rate)
+        } else if rate(&mut ip_recent.iter().copied()) >= settings.rate_limit {
+            return (ChatVerdict::RateLimited, None);
+        } else if normalized.chars().count() >= 4
+            && history
+                .recent
+                .iter()
+                .rev()
+                .take(settings.duplicate_history)
+                .any(|(_, recent)| similarity(recent, &normalized) >= settings.duplicate_similarity)
+        {
+            Some(ChatViolation::Duplicate)
+        } else if is_caps_flood(settings, message) {
+            Some(ChatViolation::Caps)
+        } else if message.chars().filter(|&c| is_emoji(c)).count() >= settings.emoji_limit {
+            Some(ChatViolation::Emoji)
+        } else {
+            None
+        };
+
+        if ip_recent.len() >= settings.rate_limit {
+            ip_recent.pop_front();
+        }
+        ip_recent.push_back(now);
+        if history.recent.len() >= settings.rate_limit.max(settings.duplicate_history) {
+            history.recent.pop_front();
+        }
+        history.recent.push_back((now, normalized));
+
+        let penalty = violation.map(|violation| {
+            if history
+                .last_offense
+                .is_some_and(|time| now - time > settings.forgive)
+            {
+                history.offenses = 0;
+            }
+            history.offenses = history.offenses.saturating_add(1);
+            history.last_offense = Some(now);
+            let penalty = match history.offenses {
+                1 => ChatPenalty::ShadowMute,
+                2 => ChatPenalty::Mute,
+                _ => ChatPenalty::Flag,
+            };
+            if penalty == ChatPenalty::ShadowMute {
+                history.shadow_muted_until = Some(now + settings.shadow_mute);
+            } else {
+                history.muted_until = Some(now + settings.mute);
+            }
+            (penalty, violation)
+        });
+
+        let verdict = if active(history.muted_until) {
+            ChatVerdict::Mute
+        } else if active(history.shadow_muted_until) {
+            ChatVerdict::ShadowMute
+        } else {
+            ChatVerdict::Allow
+        };
+        (verdict, penalty)
+    }
+}
+
+/// Lowercase alphanumerics, without repeated characters (e.g. "Heyyy!!" becomes "hey").
+fn normalize(message: &str) -> String {
+    let mut ret = String::new();
+    for c in message
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .take(128)
+    {
+        if !ret.ends_with(c) {
+            ret.push(c);
+        }
+    }
+    ret
+}
+
+/// From 0 (completely different) to 1 (identical), based on edit distance.
+fn similarity(a: &str, b: &str) -> f32 {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+    let max = a.len().max(b.len());
+    if max == 0 {
+        return 1.0;
+    }
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + (ca != cb) as usize;
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    1.0 - previous[b.len()] as f32 / max as f32
+}
+
+fn is_caps_flood(settings: &ChatModerationSettings, message: &str) -> bool {
+    let (mut letters, mut uppercase) = (0usize, 0usize);
+    for c in message.chars().filter(|c| c.is_alphabetic()) {
+        letters += 1;
+        uppercase += c.is_uppercase() as usize;
+    }
+    letters >= settings.caps_min_letters
+        && uppercase as f32 >= letters as f32 * settings.caps_fraction
+}
+
+fn is_emoji(c: char) -> bool {
+    matches!(c as u32, 0x1F000..=0x1FAFF | 0x2600..=0x27BF | 0x2B00..=0x2BFF)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{normalize, similarity, ChatModerationSettings, ChatModerator, ChatPenalty};
+    use super::{ChatVerdict, ChatViolation};
+    use crate::{ArenaId, PlayerId};
+    use std::net::{IpAddr, Ipv4Addr};
+
+    #[test]
+    fn near_duplicates() {
+        assert_eq!(normalize("Heyyy!! You"), "heyou");
+        assert_eq!(similarity("", ""), 1.0);
+        assert_eq!(similarity("abc", "abc"), 1.0);
+        assert!(similarity("join my team now", "join my team now!") > 0.9);
+        assert!(similarity("hello there", "good game") < 0.5);
+    }
+
+    #[test]
+    fn escalation() {
+        let settings = ChatModerationSettings::RECOMMENDED;
+        let mut moderator = ChatModerator::default();
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let player_id = PlayerId::nth_client(0).unwrap();
+        let mut moderate = |message: &str| {
+            moderator.moderate(&settings, ip, ArenaId::default(), player_id, message)
+        };
+
+        assert_eq!(moderate("hello everyone"), (ChatVerdict::Allow, None));
+        assert_eq!(
+            moderate("hello everyone!"),
+            (
+                ChatVerdict::ShadowMute,
+                Some((ChatPenalty::ShadowMute, ChatViolation::Duplicate))
+            )
+        );
+        assert_eq!(
+            moderate("THIS IS VERY LOUD TEXT"),
+            (
+                ChatVerdict::Mute,
+                Some((ChatPenalty::Mute, ChatViolation::Caps))
+            )
+        );
+        assert_eq!(moderate("sorry"), (ChatVerdict::Mute, None));
+    }
+
+    #[test]
+    fn shared_ip() {
+        let settings = ChatModerationSettings::RECOMMENDED;
+        let mut moderator = ChatModerator::default();
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let spammer = PlayerId::nth_client(0).unwrap();
+        let bystander = PlayerId::nth_client(1).unwrap();
+        let arena_id = ArenaId::default();
+
+        for message in ["apples", "bananas", "cherries", "dates", "elderberries"] {
+            assert_eq!(
+                moderator.moderate(&settings, ip, arena_id, spammer, message),
+                (ChatVerdict::Allow, None)
+            );
+        }
+        assert_eq!(
+            moderator.moderate(&settings, ip, arena_id, spammer, "one more"),
+            (
+                ChatVerdict::ShadowMute,
+                Some((ChatPenalty::ShadowMute, ChatViolation::Rate))
+            )
+        );
+
+        // Rate limited, since the IP is the same, but not penalized.
+        assert_eq!(
+            moderator.moderate(&settings, ip, arena_id, bystander, "hello"),
+            (ChatVerdict::RateLimited, None)
+        );
+        let other_ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        assert_eq!(
+            moderator.moderate(&settings, other_ip, arena_id, bystander, "hello"),
+            (ChatVerdict::Allow, None)
+        );
+    }
+
+    #[test]
+    fn disabled_by_default() {
+        let mut moderator = ChatModerator::default();
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let player_id = PlayerId::nth_client(0).unwrap();
+        for _ in 0..10 {
+            assert_eq!(
+                moderator.moderate(
+                    &ChatModerationSettings::default(),
+                    ip,
+                    ArenaId::default(),
+                    player_id,
+                    "SPAM SPAM SPAM SPAM"
+                ),
+                (ChatVerdict::Allow, None)
+            );
+        }
+    }
+}
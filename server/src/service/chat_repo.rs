@@ -1,23 +1,29 @@
 // SPDX-FileCopyrightText: 2024 Softbear, Inc.
 // SPDX-License-Identifier: LGPL-3.0-or-later
 
-use super::{Arena, ChatInbox};
-use crate::actor::PlasmaActlet;
+use super::chat_moderator::{ChatModerator, ChatPenalty, ChatVerdict};
+use super::{
+    Arena, ChatArgument, ChatArgumentKind, ChatArguments, ChatCommand, ChatCommandContext,
+    ChatInbox, ChatPermission,
+};
+use crate::actor::{PlasmaActlet, PlayerClientData};
 use crate::bitcode::{self, *};
-use crate::service::{ArenaService, BotRepo, MetricRepo, Player, PlayerRepo};
+use crate::rustrict::{Censor, Type};
+use crate::service::{ArenaService, MetricRepo, Player, PlayerRepo};
 use crate::{
-    ArenaId, ArenaSettingsDto, ChatId, ChatMessage, ChatRecipient, ChatRequest, ChatUpdate,
+    ArenaId, ChatId, ChatMessage, ChatRecipient, ChatRequest, ChatUpdate, DirectRecipient,
     MessageDto, MessageNumber, NonZeroUnixMillis, PlasmaRequestV1, PlayerAlias, PlayerId,
-    QuestEvent, RealmId, SceneId, ServerNumber, UnixTime,
+    QuestEvent, RealmId, SceneId, ServerId, ServerNumber, UnixTime,
 };
 use kodiak_common::arrayvec::ArrayString;
 use kodiak_common::heapless::HistoryBuffer;
 use kodiak_common::slice_up_to_array_string;
+use log::warn;
 use std::collections::HashSet;
 use std::marker::PhantomData;
 use std::net::IpAddr;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 /// Component of [`Context`] dedicated to chat.
 pub struct ChatRepo<G> {
@@ -25,6 +31,15 @@ pub struct ChatRepo<G> {
     recent: HistoryBuffer<(Arc<MessageDto>, Option<MessageAttribution>), 16>,
     /// For uniqueness.
     last_timestamp: NonZeroUnixMillis,
+    /// Broadcasts sent while plasma was unavailable, to be delivered to every arena in the realm.
+    local_broadcasts: Vec<(Arc<MessageDto>, MessageAttribution)>,
+    /// Safe and slow mode are enforced by plasma, except while it is unavailable.
+    safe_mode_until: Option<Instant>,
+    slow_mode_until: Option<Instant>,
+    /// Automated spam moderation, which applies regardless of plasma.
+    moderator: ChatModerator,
+    /// Flagged senders, to be delivered to moderators in every arena on the server.
+    flags: Vec<Arc<MessageDto>>,
     _spooky: PhantomData<G>,
 }
 
@@ -32,6 +47,8 @@ pub struct ChatRepo<G> {
 pub struct MessageAttribution {
     pub(crate) chat_id: ChatId,
     pub(crate) sender_ip: IpAddr,
+    /// The sender, if they were on this server, for replying with a direct message.
+    pub(crate) player_id: Option<PlayerId>,
 }
 
 /// Component of client data encompassing chat information.
@@ -43,6 +60,8 @@ pub struct ClientChatData {
     pub(crate) inbox: ChatInbox,
     /// `None` if not yet announced. Cleared if traveling between different realms.
     pub(crate) join_announced: Option<SceneId>,
+    /// When the client last sent a message delivered locally, for slow mode.
+    last_sent_locally: Option<NonZeroUnixMillis>,
 }
 
 impl ClientChatData {
@@ -66,6 +85,11 @@ impl<G: ArenaService> Default for ChatRepo<G> {
         Self {
             recent: HistoryBuffer::new(),
             last_timestamp: NonZeroUnixMillis::MIN,
+            local_broadcasts: Vec::new(),
+            safe_mode_until: None,
+            slow_mode_until: None,
+            moderator: ChatModerator::default(),
+            flags: Vec::new(),
             _spooky: PhantomData,
         }
     }
@@ -193,6 +217,7 @@ impl<G: ArenaService> ChatRepo<G> {
                 slow_mode: None,
             });
         }
+        self.safe_mode_until = Self::mode_until(minutes);
         Ok(ChatUpdate::SafeModeSet(minutes))
     }
 
@@ -217,14 +242,27 @@ impl<G: ArenaService> ChatRepo<G> {
                 slow_mode: Some(minutes),
             });
         }
+        self.slow_mode_until = Self::mode_until(minutes);
         Ok(ChatUpdate::SlowModeSet(minutes))
     }
 
+    fn mode_until(minutes: u32) -> Option<Instant> {
+        (minutes != 0).then(|| Instant::now() + Duration::from_secs(minutes as u64 * 60))
+    }
+
+    fn mode_active(until: Option<Instant>) -> bool {
+        until.is_some_and(|until| until > Instant::now())
+    }
+
     /// Send a chat to all players, or one's team (whisper).
+    ///
+    /// Normally, plasma relays the message back to us (and other servers in the realm). While
+    /// plasma is unavailable, the message is delivered locally instead.
     #[allow(clippy::too_many_arguments)]
     fn send_chat(
         &mut self,
         req_arena_id: ArenaId,
+        server_id: ServerId,
         req_player_id: PlayerId,
         message: String,
         whisper: bool,
@@ -252,16 +290,14 @@ impl<G: ArenaService> ChatRepo<G> {
         let whisper = whisper || req_tier.arena_service.force_whisper(req_player_id);
         let team_name = req_tier.arena_service.get_team_name(req_player_id);
 
-        if let Some(text) = self.try_execute_command(
-            req_arena_id.realm_id,
-            req_player_id,
-            &message,
-            &mut req_tier.arena_service,
-            req_player,
-            &req_tier.arena_context.bots,
-            &mut req_tier.arena_context.settings,
-            plasma,
-        ) {
+        if let Some(text) =
+            self.try_execute_command(req_arena_id, req_player_id, &message, req_tier, plasma)
+        {
+            let req_player = req_tier
+                .arena_context
+                .players
+                .get_mut(req_player_id)
+                .ok_or("nonexistent player")?;
             let alias = req_player.alias;
             if let Some(req_client) = req_player.inner.client_mut() {
                 let timestamp = NonZeroUnixMillis::now().max(self.last_timestamp.add_millis(1));
@@ -295,6 +331,8 @@ impl<G: ArenaService> ChatRepo<G> {
                         english_translation: None,
                     },
                     whisper,
+                    player_id: None,
+                    recipient: None,
                 };
                 req_client.chat.receive(&Arc::new(message), None);
             } else {
@@ -303,6 +341,14 @@ impl<G: ArenaService> ChatRepo<G> {
             return Ok(ChatUpdate::Sent);
         }
 
+        let shadow_muted =
+            self.moderate_automatically(req_arena_id, req_player_id, &message, req_tier, metrics)?;
+
+        let req_player = req_tier
+            .arena_context
+            .players
+            .get_mut(req_player_id)
+            .ok_or("nonexistent player")?;
         let team = req_tier.arena_service.get_team_members(req_player_id);
 
         /*
@@ -390,26 +436,354 @@ impl<G: ArenaService> ChatRepo<G> {
 
         let timestamp = NonZeroUnixMillis::now().max(self.last_timestamp.add_millis(1));
         self.last_timestamp = timestamp;
-        plasma.do_request(PlasmaRequestV1::SendChat {
-            admin: false,
+        if shadow_muted {
+            let message = MessageDto {
+                alias: req_player.alias,
+                visitor_id: req_client.session.visitor_id,
+                team_name,
+                authority: false,
+                authentic,
+                message: ChatMessage::Raw {
+                    message,
+                    detected_language_id: Default::default(),
+                    english_translation: None,
+                },
+                whisper,
+                player_id: Some(req_player_id),
+                recipient: None,
+            };
+            req_client.chat.receive(&Arc::new(message), None);
+            return Ok(ChatUpdate::Sent);
+        }
+        if plasma.is_socket_connected() {
+            plasma.do_request(PlasmaRequestV1::SendChat {
+                admin: false,
+                alias: req_player.alias,
+                authentic,
+                ip_address: req_client.ip_address,
+                message,
+                arena_id: req_arena_id,
+                team_name,
+                player_id: Some(req_player_id),
+                timestamp,
+                visitor_id: req_client.session.visitor_id,
+                recipient: if whisper {
+                    ChatRecipient::TeamOf(req_player_id)
+                } else {
+                    ChatRecipient::Broadcast
+                },
+            });
+            return Ok(ChatUpdate::Sent);
+        }
+
+        let censored = self.moderate_locally(req_client, timestamp, &message)?;
+
+        let message = Arc::new(MessageDto {
             alias: req_player.alias,
-            authentic,
-            ip_address: req_client.ip_address,
-            message,
-            arena_id: req_arena_id,
+            visitor_id: req_client.session.visitor_id,
             team_name,
+            authority: false,
+            authentic,
+            message: ChatMessage::Raw {
+                message: censored,
+                detected_language_id: Default::default(),
+                english_translation: None,
+            },
+            whisper,
+            player_id: Some(req_player_id),
+            recipient: None,
+        });
+        let attribution = MessageAttribution {
+            chat_id: ChatId {
+                arena_id: req_arena_id,
+                message_id: timestamp,
+                server_id,
+            },
+            sender_ip: req_client.ip_address,
             player_id: Some(req_player_id),
-            timestamp,
+        };
+        if let Some(team) = team.filter(|_| whisper) {
+            for member in team {
+                if let Some(player) = req_tier.arena_context.players.get_mut(member)
+                    && player.regulator.active()
+                    && let Some(client) = player.client_mut()
+                {
+                    client.chat.receive(
+                        &message,
+                        Some(attribution).filter(|_| member != req_player_id),
+                    );
+                }
+            }
+        } else {
+            self.local_broadcasts.push((message, attribution));
+        }
+        Ok(ChatUpdate::Sent)
+    }
+
+    /// Send a private message to one player in the same arena.
+    ///
+    /// Like [`Self::send_chat`], plasma relays the message to the recipient, unless it is
+    /// unavailable.
+    #[allow(clippy::too_many_arguments)]
+    fn send_direct(
+        &mut self,
+        req_arena_id: ArenaId,
+        server_id: ServerId,
+        req_player_id: PlayerId,
+        recipient: DirectRecipient,
+        message: String,
+        req_tier: &mut Arena<G>,
+        metrics: &mut MetricRepo<G>,
+        plasma: &PlasmaActlet,
+    ) -> Result<ChatUpdate, &'static str> {
+        let players = &mut req_tier.arena_context.players;
+        let recipient_id = match recipient {
+            DirectRecipient::Player(player_id) => player_id,
+            DirectRecipient::Reply(message_number) => {
+                Self::sender_attribution(req_player_id, message_number, players)
+                    .filter(|attribution| {
+                        attribution.chat_id.server_id == server_id
+                            && attribution.chat_id.arena_id == req_arena_id
+                    })
+                    .and_then(|attribution| attribution.player_id)
+                    .ok_or("sender unavailable")?
+            }
+        };
+        if recipient_id == req_player_id {
+            return Err("cannot message self");
+        }
+        let recipient_alias = players
+            .get(recipient_id)
+            .filter(|player| player.client().is_some() && player.regulator.active())
+            .ok_or("recipient unavailable")?
+            .alias;
+        let shadow_muted =
+            self.moderate_automatically(req_arena_id, req_player_id, &message, req_tier, metrics)?;
+
+        let players = &mut req_tier.arena_context.players;
+        let req_player = players.get_mut(req_player_id).ok_or("nonexistent player")?;
+        if !req_player.regulator.active() {
+            return Err("inactive");
+        }
+        let alias = req_player.alias;
+        let Some(req_client) = req_player.client_mut() else {
+            return Err("not a client");
+        };
+        req_client.push_quest(QuestEvent::Chat { whisper: true });
+        metrics.mutate_with(
+            |metrics| {
+                metrics.chats.increment();
+            },
+            &req_client.metrics,
+        );
+        let authentic = req_client
+            .nick_name()
+            .is_some_and(|n| n.as_str() == alias.as_str());
+        let team_name = req_tier.arena_service.get_team_name(req_player_id);
+
+        let timestamp = NonZeroUnixMillis::now().max(self.last_timestamp.add_millis(1));
+        self.last_timestamp = timestamp;
+        let connected = plasma.is_socket_connected();
+        let text = if shadow_muted {
+            message
+        } else if connected {
+            plasma.do_request(PlasmaRequestV1::SendChat {
+                admin: false,
+                alias,
+                authentic,
+                ip_address: req_client.ip_address,
+                message: message.clone(),
+                arena_id: req_arena_id,
+                team_name,
+                player_id: Some(req_player_id),
+                timestamp,
+                visitor_id: req_client.session.visitor_id,
+                recipient: ChatRecipient::Player(recipient_id),
+            });
+            // Plasma only relays to the recipient, so the sender should see what it would.
+            Censor::from_str(&message).censor()
+        } else {
+            self.moderate_locally(req_client, timestamp, &message)?
+        };
+
+        let message = Arc::new(MessageDto {
+            alias,
             visitor_id: req_client.session.visitor_id,
-            recipient: if whisper {
-                ChatRecipient::TeamOf(req_player_id)
-            } else {
-                ChatRecipient::Broadcast
+            team_name,
+            authority: false,
+            authentic,
+            message: ChatMessage::Raw {
+                message: text,
+                detected_language_id: Default::default(),
+                english_translation: None,
             },
+            whisper: false,
+            player_id: Some(req_player_id),
+            recipient: Some(recipient_alias),
         });
+        let attribution = MessageAttribution {
+            chat_id: ChatId {
+                arena_id: req_arena_id,
+                message_id: timestamp,
+                server_id,
+            },
+            sender_ip: req_client.ip_address,
+            player_id: Some(req_player_id),
+        };
+        // Plasma only relays to the recipient.
+        req_client.chat.receive(&message, None);
+        if !connected
+            && !shadow_muted
+            && let Some(recipient) = players.get_mut(recipient_id)
+            && let Some(client) = recipient.client_mut()
+        {
+            // Respects mutes.
+            client.chat.receive(&message, Some(attribution));
+        }
         Ok(ChatUpdate::Sent)
     }
 
+    /// Enforces slow and safe mode in lieu of plasma, returning the censored message.
+    fn moderate_locally(
+        &self,
+        req_client: &mut PlayerClientData<G>,
+        timestamp: NonZeroUnixMillis,
+        message: &str,
+    ) -> Result<String, &'static str> {
+        if Self::mode_active(self.slow_mode_until)
+            && !req_client.moderator()
+            && req_client
+                .chat
+                .last_sent_locally
+                .is_some_and(|last| timestamp < last.add_millis(10_000))
+        {
+            return Err("slow mode");
+        }
+        let (censored, analysis) = Censor::from_str(message).censor_and_analyze();
+        if Self::mode_active(self.safe_mode_until)
+            && !req_client.moderator()
+            && analysis.is(Type::INAPPROPRIATE & Type::MILD_OR_HIGHER)
+        {
+            return Err("safe mode");
+        }
+        req_client.chat.last_sent_locally = Some(timestamp);
+        Ok(censored)
+    }
+
+    /// Detects spam and abuse, applying escalating penalties. Returns whether the message should
+    /// only be shown to the sender.
+    fn moderate_automatically(
+        &mut self,
+        req_arena_id: ArenaId,
+        req_player_id: PlayerId,
+        message: &str,
+        req_tier: &Arena<G>,
+        metrics: &mut MetricRepo<G>,
+    ) -> Result<bool, &'static str> {
+        let players = &req_tier.arena_context.players;
+        let req_player = players.get(req_player_id).ok_or("nonexistent player")?;
+        let alias = req_player.alias;
+        let Some(req_client) = req_player.client() else {
+            return Err("not a client");
+        };
+        if req_client.moderator() || req_client.admin() {
+            return Ok(false);
+        }
+        let ip_address = req_client.ip_address;
+        let (verdict, penalty) = self.moderator.moderate(
+            &G::CHAT_MODERATION,
+            ip_address,
+            req_arena_id,
+            req_player_id,
+            message,
+        );
+        if let Some((penalty, violation)) = penalty {
+            metrics.mutate_with(
+                |metrics| {
+                    metrics.chat_penalties.increment();
+                },
+                &req_client.metrics,
+            );
+            if penalty == ChatPenalty::Flag {
+                let violation = violation.as_str();
+                warn!("flagged {alias} ({ip_address}) in {req_arena_id} for {violation}");
+                self.flags.push(Arc::new(MessageDto {
+                    alias: PlayerAlias::authority(),
+                    visitor_id: None,
+                    team_name: None,
+                    authority: true,
+                    authentic: true,
+                    message: ChatMessage::Raw {
+                        message: format!("Flagged {alias} ({req_player_id}) for {violation}"),
+                        detected_language_id: Default::default(),
+                        english_translation: None,
+                    },
+                    whisper: true,
+                    player_id: None,
+                    recipient: None,
+                }));
+            }
+        }
+        match verdict {
+            ChatVerdict::Allow => Ok(false),
+            ChatVerdict::ShadowMute => Ok(true),
+            ChatVerdict::Mute => Err("muted"),
+            ChatVerdict::RateLimited => Err("too many messages from your network"),
+        }
+    }
+
+    /// Takes flags that haven't been delivered yet. See [`Self::deliver_flags`].
+    pub(crate) fn take_flags(&mut self) -> Vec<Arc<MessageDto>> {
+        std::mem::take(&mut self.flags)
+    }
+
+    /// Delivers flags to moderators and admins. `tiers` should be every arena on the server, not
+    /// just those in the flagged sender's realm.
+    pub(crate) fn deliver_flags<'a>(
+        flags: &[Arc<MessageDto>],
+        tiers: impl IntoIterator<Item = &'a mut Arena<G>>,
+    ) {
+        if flags.is_empty() {
+            return;
+        }
+        for tier in tiers {
+            for (_, player) in tier.arena_context.players.iter_mut() {
+                if let Some(client) = player.client_mut()
+                    && (client.moderator() || client.admin())
+                {
+                    for flag in flags {
+                        client.chat.receive(flag, None);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Delivers broadcasts that were sent while plasma was unavailable to every arena in the realm.
+    pub(crate) fn deliver_local_broadcasts<'a>(
+        &mut self,
+        tiers: impl IntoIterator<Item = &'a mut Arena<G>>,
+    ) {
+        if self.local_broadcasts.is_empty() {
+            return;
+        }
+        let local_broadcasts = std::mem::take(&mut self.local_broadcasts);
+        for tier in tiers {
+            for (message, attribution) in &local_broadcasts {
+                self.broadcast_message(
+                    Arc::clone(message),
+                    Some(*attribution),
+                    std::iter::once(&mut *tier),
+                    None,
+                    false,
+                );
+            }
+        }
+        for (message, attribution) in local_broadcasts {
+            self.recent.write((message, Some(attribution)));
+        }
+    }
+
     /// Broadcasts a message to all players (including queuing it for those who haven't joined yet).
     pub(crate) fn broadcast_message<'a>(
         &mut self,
@@ -441,6 +815,7 @@ impl<G: ArenaService> ChatRepo<G> {
         req_arena_id: ArenaId,
         req_player_id: PlayerId,
         request: ChatRequest,
+        server_id: ServerId,
         req_tier: &mut Arena<G>,
         metrics: &mut MetricRepo<G>,
         plasma: &PlasmaActlet,
@@ -455,6 +830,7 @@ impl<G: ArenaService> ChatRepo<G> {
             }
             ChatRequest::Send { message, whisper } => self.send_chat(
                 req_arena_id,
+                server_id,
                 req_player_id,
                 message,
                 whisper,
@@ -462,6 +838,16 @@ impl<G: ArenaService> ChatRepo<G> {
                 metrics,
                 plasma,
             ),
+            ChatRequest::SendDirect { recipient, message } => self.send_direct(
+                req_arena_id,
+                server_id,
+                req_player_id,
+                recipient,
+                message,
+                req_tier,
+                metrics,
+                plasma,
+            ),
             ChatRequest::SetSafeMode(minutes) => {
                 let req_player = context
                     .players
@@ -476,6 +862,7 @@ impl<G: ArenaService> ChatRepo<G> {
                     .ok_or("nonexistent player")?;
                 self.set_slow_mode(minutes, req_player, req_arena_id.realm_id, plasma)
             }
+            ChatRequest::Commands => Self::list_commands(req_player_id, &context.players),
             ChatRequest::Report(message_number) => self.report(
                 req_player_id,
                 message_number,
@@ -507,6 +894,8 @@ impl<G: ArenaService> ChatRepo<G> {
             authority: true,
             authentic: false,
             whisper: false,
+            player_id: None,
+            recipient: None,
         });
         chat.receive(&intro, None);
         chat
@@ -521,106 +910,227 @@ impl<G: ArenaService> ChatRepo<G> {
         }
     }
 
+    /// Commands provided by the engine, in addition to [`ArenaService::CHAT_COMMANDS`].
+    const ENGINE_COMMANDS: &'static [ChatCommand<G>] = &[
+        ChatCommand {
+            name: "help",
+            arguments: &[ChatArgument::optional("command", &[ChatArgumentKind::Word])],
+            permission: ChatPermission::Player,
+            description: "Lists commands, or describes one",
+            execute: Self::help_command,
+        },
+        ChatCommand {
+            name: "slow",
+            arguments: &[ChatArgument::required(
+                "minutes",
+                &[ChatArgumentKind::Minutes],
+            )],
+            permission: ChatPermission::Moderator,
+            description: "Limits how often players may chat, for a number of minutes",
+            execute: Self::slow_command,
+        },
+        ChatCommand {
+            name: "safe",
+            arguments: &[ChatArgument::required(
+                "minutes",
+                &[ChatArgumentKind::Minutes],
+            )],
+            permission: ChatPermission::Moderator,
+            description: "Filters chat more strictly, for a number of minutes",
+            execute: Self::safe_command,
+        },
+        ChatCommand {
+            name: "bots",
+            arguments: &[ChatArgument::optional(
+                "count",
+                &[
+                    ChatArgumentKind::Number {
+                        min: 0.0,
+                        max: if cfg!(debug_assertions) { 64.0 } else { 1024.0 },
+                    },
+                    ChatArgumentKind::Enum(&["default"]),
+                ],
+            )],
+            permission: ChatPermission::Admin,
+            description: "Shows or sets the number of bots",
+            execute: Self::bots_command,
+        },
+        ChatCommand {
+            name: "bot_aggression",
+            arguments: &[ChatArgument::optional(
+                "aggression",
+                &[
+                    ChatArgumentKind::Number {
+                        min: 0.0,
+                        max: 10.0,
+                    },
+                    ChatArgumentKind::Enum(&["default"]),
+                ],
+            )],
+            permission: ChatPermission::Admin,
+            description: "Shows or sets how aggressive bots are",
+            execute: Self::bot_aggression_command,
+        },
+    ];
+
+    /// Every registered command, engine commands first. A game command replaces any engine command
+    /// of the same name.
+    pub(crate) fn commands() -> impl Iterator<Item = &'static ChatCommand<G>> {
+        Self::ENGINE_COMMANDS
+            .iter()
+            .filter(|engine| {
+                !G::CHAT_COMMANDS
+                    .iter()
+                    .any(|game| game.name.eq_ignore_ascii_case(engine.name))
+            })
+            .chain(G::CHAT_COMMANDS)
+    }
+
+    /// Commands the player may use, for autocomplete.
+    fn list_commands(
+        req_player_id: PlayerId,
+        players: &PlayerRepo<G>,
+    ) -> Result<ChatUpdate, &'static str> {
+        let req_player = players.get(req_player_id).ok_or("nonexistent player")?;
+        Ok(ChatUpdate::Commands(
+            Self::commands()
+                .filter(|command| command.permission.allows(req_player))
+                .map(ChatCommand::dto)
+                .collect(),
+        ))
+    }
+
+    /// Returns the response to a message starting with `/`, or `None` if it isn't a command.
     fn try_execute_command(
         &mut self,
-        req_realm_id: RealmId,
+        req_arena_id: ArenaId,
         req_player_id: PlayerId,
         message: &str,
-        service: &mut G,
-        player: &mut Player<G>,
-        bots: &BotRepo<G>,
-        settings: &mut ArenaSettingsDto<G::ArenaSettings>,
+        req_tier: &mut Arena<G>,
         plasma: &PlasmaActlet,
     ) -> Option<String> {
-        fn parse_minutes(arg: &str) -> Option<u32> {
-            if matches!(arg, "none" | "off") {
-                Some(0)
-            } else {
-                arg.parse::<u32>()
-                    .ok()
-                    .or_else(|| arg.strip_suffix('m').and_then(|s| s.parse().ok()))
-                    .or_else(|| {
-                        arg.strip_suffix('h')
-                            .and_then(|s| s.parse::<u32>().ok())
-                            .and_then(|n| n.checked_mul(60))
-                    })
-            }
-        }
-
         let command = message.strip_prefix('/')?;
         let mut words = command.split_ascii_whitespace();
-        let first = words.next()?;
-
-        macro_rules! until {
-            ($name: literal, $setter: ident) => {{
-                match words.next() {
-                    None => String::from("missing number of minutes"),
-                    Some(arg) => {
-                        if let Some(minutes) = parse_minutes(arg) {
-                            self.$setter(minutes, player, req_realm_id, plasma)
-                                .map(|_| "done".to_owned())
-                                .unwrap_or_else(|e| String::from(e))
-                        } else {
-                            String::from("failed to parse argument as minutes")
-                        }
-                    }
-                }
-            }};
+        let name = words.next()?;
+        let Arena {
+            arena_context,
+            arena_service,
+        } = req_tier;
+
+        let Some(registered) = Self::commands().find(|c| c.name.eq_ignore_ascii_case(name)) else {
+            let player = arena_context.players.get_mut(req_player_id)?;
+            return Some(
+                arena_service
+                    .chat_command(command, req_player_id, player)
+                    .unwrap_or_else(|| format!("unrecognized command /{name}, try /help")),
+            );
+        };
+        if !registered
+            .permission
+            .allows(arena_context.players.get(req_player_id)?)
+        {
+            return Some(String::from("permission denied"));
         }
+        let arguments = match registered.parse(words, &arena_context.players) {
+            Ok(arguments) => arguments,
+            Err(e) => return Some(e),
+        };
+        let mut context = ChatCommandContext {
+            player_id: req_player_id,
+            arena_id: req_arena_id,
+            service: arena_service,
+            context: arena_context,
+            chat: self,
+            plasma,
+        };
+        Some((registered.execute)(&mut context, &arguments).unwrap_or_else(String::from))
+    }
 
-        Some(match first {
-            "slow" => until!("slow mode", set_slow_mode),
-            "safe" => until!("safe mode", set_safe_mode),
-            "bots" => {
-                if let Some(client) = player.client()
-                    && (cfg!(debug_assertions) || client.admin())
-                {
-                    let hard_max = if cfg!(debug_assertions) { 64 } else { 1024 };
-                    if let Some(count) = words.next() {
-                        if let Some(count) = count.parse::<u16>().ok()
-                            && count <= hard_max
-                        {
-                            settings.engine.bots = Some(count);
-                            "OK".to_owned()
-                        } else if count == "default" {
-                            settings.engine.bots = None;
-                            "OK".to_owned()
-                        } else {
-                            "error".to_owned()
-                        }
-                    } else {
-                        bots.count.to_string()
-                    }
-                } else {
-                    "permission denied".to_owned()
-                }
-            }
-            "bot_aggression" => {
-                if let Some(client) = player.client()
-                    && (cfg!(debug_assertions) || client.admin())
-                {
-                    if let Some(aggression) = words.next() {
-                        if let Some(aggression) = aggression.parse::<f32>().ok()
-                            && (0.0..=10.0).contains(&aggression)
-                        {
-                            settings.engine.bot_aggression = Some(aggression);
-                            "OK".to_owned()
-                        } else if aggression == "default" {
-                            settings.engine.bot_aggression = None;
-                            "OK".to_owned()
-                        } else {
-                            "error".to_owned()
-                        }
-                    } else {
-                        settings.engine.bot_aggression().to_string()
-                    }
-                } else {
-                    "permission denied".to_owned()
-                }
+    fn help_command(
+        context: &mut ChatCommandContext<'_, G>,
+        arguments: &ChatArguments,
+    ) -> Result<String, &'static str> {
+        let player = &*context.player();
+        if let Some(name) = arguments.word(0) {
+            let name = name.strip_prefix('/').unwrap_or(name);
+            Self::commands()
+                .find(|c| c.name.eq_ignore_ascii_case(name) && c.permission.allows(player))
+                .map(|c| format!("{}: {}", c.usage(), c.description))
+                .ok_or("unrecognized command")
+        } else {
+            let names = Self::commands()
+                .filter(|c| c.permission.allows(player))
+                .map(|c| format!("/{}", c.name))
+                .collect::<Vec<_>>();
+            Ok(format!(
+                "commands: {} (try /help <command>)",
+                names.join(", ")
+            ))
+        }
+    }
+
+    fn slow_command(
+        context: &mut ChatCommandContext<'_, G>,
+        arguments: &ChatArguments,
+    ) -> Result<String, &'static str> {
+        let minutes = arguments.minutes(0).ok_or("missing minutes")?;
+        let player = context
+            .context
+            .players
+            .get(context.player_id)
+            .ok_or("nonexistent player")?;
+        context
+            .chat
+            .set_slow_mode(minutes, player, context.arena_id.realm_id, context.plasma)
+            .map(|_| "done".to_owned())
+    }
+
+    fn safe_command(
+        context: &mut ChatCommandContext<'_, G>,
+        arguments: &ChatArguments,
+    ) -> Result<String, &'static str> {
+        let minutes = arguments.minutes(0).ok_or("missing minutes")?;
+        let player = context
+            .context
+            .players
+            .get(context.player_id)
+            .ok_or("nonexistent player")?;
+        context
+            .chat
+            .set_safe_mode(minutes, player, context.arena_id.realm_id, context.plasma)
+            .map(|_| "done".to_owned())
+    }
+
+    fn bots_command(
+        context: &mut ChatCommandContext<'_, G>,
+        arguments: &ChatArguments,
+    ) -> Result<String, &'static str> {
+        let engine = &mut context.context.settings.engine;
+        if let Some(count) = arguments.number(0) {
+            if count.fract() != 0.0 {
+                return Err("count must be a whole number");
             }
-            _ => service
-                .chat_command(command, req_player_id, player)
-                .unwrap_or_else(|| String::from("unrecognized command")),
-        })
+            engine.bots = Some(count as u16);
+        } else if arguments.variant(0).is_some() {
+            engine.bots = None;
+        } else {
+            return Ok(context.context.bots.count.to_string());
+        }
+        Ok("OK".to_owned())
+    }
+
+    fn bot_aggression_command(
+        context: &mut ChatCommandContext<'_, G>,
+        arguments: &ChatArguments,
+    ) -> Result<String, &'static str> {
+        let engine = &mut context.context.settings.engine;
+        if let Some(aggression) = arguments.number(0) {
+            engine.bot_aggression = Some(aggression as f32);
+        } else if arguments.variant(0).is_some() {
+            engine.bot_aggression = None;
+        } else {
+            return Ok(engine.bot_aggression().to_string());
+        }
+        Ok("OK".to_owned())
     }
 }
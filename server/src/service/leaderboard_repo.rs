@@ -86,7 +86,9 @@ impl<G: ArenaService> LeaderboardRepo<G> {
         debug_assert!(liveboard_items.is_sorted_by_key(|dto| u32::MAX - dto.score));
 
         if (cfg!(not(debug_assertions)) && liveboard.player_count < G::LEADERBOARD_MIN_PLAYERS)
-            || (plasma.role.is_unlisted() || plasma.role.is_closing())
+            || plasma.is_closing()
+            // Role is meaningless without plasma, and scores can be stored locally.
+            || (plasma.is_connected() && plasma.role.is_unlisted())
         {
             return;
         }
@@ -110,9 +112,10 @@ impl<G: ArenaService> LeaderboardRepo<G> {
         }
     }
 
-    /// Returns scores pending database commit, draining them in the process. Rate limited.
-    pub fn take_pending(&mut self) -> Option<Box<[LeaderboardScoreDto]>> {
-        if self.pending.is_empty() || self.take_pending_rate_limit.should_limit_rate() {
+    /// Returns scores pending database commit, draining them in the process. Rate limited, unless
+    /// `flush`.
+    pub fn take_pending(&mut self, flush: bool) -> Option<Box<[LeaderboardScoreDto]>> {
+        if self.pending.is_empty() || (!flush && self.take_pending_rate_limit.should_limit_rate()) {
             None
         } else {
             Some(
@@ -124,12 +127,33 @@ impl<G: ArenaService> LeaderboardRepo<G> {
         }
     }
 
-    pub fn update_to_plasma(infrastructure: &mut ServerActor<G>) {
+    /// Sends pending scores to plasma, if connected, or else the local leaderboards, if any, which
+    /// are displayed while plasma is unavailable.
+    ///
+    /// If `flush`, sends pending scores regardless of rate limit, e.g. before shutting down.
+    pub fn update_to_plasma(infrastructure: &mut ServerActor<G>, flush: bool) {
+        let connected = infrastructure.plasma.is_connected();
         for (realm_id, context_realm) in infrastructure.realms.realms_mut() {
-            if let Some(scores) = context_realm.realm_context.leaderboard.take_pending() {
-                infrastructure
-                    .plasma
-                    .do_request(PlasmaRequestV1::UpdateLeaderboards { realm_id, scores });
+            let leaderboard = &mut context_realm.realm_context.leaderboard;
+            if let Some(scores) = leaderboard.take_pending(flush) {
+                if connected {
+                    infrastructure
+                        .plasma
+                        .do_request(PlasmaRequestV1::UpdateLeaderboards { realm_id, scores });
+                } else if let Some(local) = &mut infrastructure.local_leaderboards {
+                    local.record(realm_id, &scores);
+                }
+            }
+            if !connected && let Some(local) = &mut infrastructure.local_leaderboards {
+                for period_id in PeriodId::iter() {
+                    if !local
+                        .get(realm_id, period_id)
+                        .eq(leaderboard.get(period_id).iter().cloned())
+                    {
+                        leaderboard
+                            .put_leaderboard(period_id, local.get(realm_id, period_id).collect());
+                    }
+                }
             }
         }
     }
@@ -0,0 +1,385 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+use crate::{LeaderboardScoreDto, NonZeroUnixMillis, PeriodId, RealmId, UnixTime};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Sender};
+use std::thread::JoinHandle;
+
+/// A score that made it onto a leaderboard at a certain time.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LeaderboardRecord {
+    #[serde(default)]
+    pub realm_id: RealmId,
+    #[serde(flatten)]
+    pub score: LeaderboardScoreDto,
+    pub timestamp: NonZeroUnixMillis,
+}
+
+impl AsRef<LeaderboardScoreDto> for LeaderboardRecord {
+    fn as_ref(&self) -> &LeaderboardScoreDto {
+        &self.score
+    }
+}
+
+/// Persists leaderboard scores locally, so leaderboards survive restarts when plasma is
+/// unavailable. See [`ArenaService::LeaderboardStore`](crate::ArenaService::LeaderboardStore).
+///
+/// Methods other than [`Self::open`] and [`Self::load`] are called on a dedicated thread, so they
+/// may block.
+pub trait LeaderboardStore: Send + Sized + 'static {
+    /// Opens the store at `path`, which is `--leaderboard-path`, if any.
+    fn open(path: Option<&Path>) -> io::Result<Self>;
+
+    /// Returns all records, in any order. Called once, after [`Self::open`].
+    fn load(&mut self) -> io::Result<Vec<LeaderboardRecord>>;
+
+    /// Durably adds records.
+    fn append(&mut self, records: &[LeaderboardRecord]) -> io::Result<()>;
+
+    /// Optionally discards all records except those given, which are the only ones that are still
+    /// relevant. Called after [`Self::load`] and then every so often.
+    fn compact(&mut self, _records: &[LeaderboardRecord]) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Doesn't store anything, so local leaderboards are lost on restart.
+impl LeaderboardStore for () {
+    fn open(_path: Option<&Path>) -> io::Result<Self> {
+        Ok(())
+    }
+
+    fn load(&mut self) -> io::Result<Vec<LeaderboardRecord>> {
+        Ok(Vec::new())
+    }
+
+    fn append(&mut self, _records: &[LeaderboardRecord]) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Stores one JSON record per line in an append-only file. Requires `--leaderboard-path`.
+pub struct FileLeaderboardStore {
+    path: PathBuf,
+    file: File,
+}
+
+impl LeaderboardStore for FileLeaderboardStore {
+    fn open(path: Option<&Path>) -> io::Result<Self> {
+        let path = path.ok_or_else(|| {
+            io::Error::new(ErrorKind::InvalidInput, "--leaderboard-path is required")
+        })?;
+        Ok(Self {
+            path: path.to_owned(),
+            file: OpenOptions::new().create(true).append(true).open(path)?,
+        })
+    }
+
+    fn load(&mut self) -> io::Result<Vec<LeaderboardRecord>> {
+        let mut records = Vec::new();
+        for line in BufReader::new(File::open(&self.path)?).lines() {
+            let line = line?;
+            match serde_json::from_str(&line) {
+                Ok(record) => records.push(record),
+                // Most likely a line that was partially written during a crash.
+                Err(e) => warn!("skipping leaderboard record {line:?}: {e}"),
+            }
+        }
+        Ok(records)
+    }
+
+    fn append(&mut self, records: &[LeaderboardRecord]) -> io::Result<()> {
+        let mut buf = Vec::new();
+        for record in records {
+            serde_json::to_writer(&mut buf, record)?;
+            buf.push(b'\n');
+        }
+        self.file.write_all(&buf)?;
+        self.file.sync_data()
+    }
+
+    fn compact(&mut self, records: &[LeaderboardRecord]) -> io::Result<()> {
+        let temporary = self.path.with_extension("tmp");
+        let mut file = File::create(&temporary)?;
+        for record in records {
+            serde_json::to_writer(&mut file, record)?;
+            file.write_all(b"\n")?;
+        }
+        file.sync_all()?;
+        std::fs::rename(&temporary, &self.path)?;
+        self.file = OpenOptions::new().append(true).open(&self.path)?;
+        Ok(())
+    }
+}
+
+/// A blocking operation on a [`LeaderboardStore`].
+enum StoreRequest {
+    Append(Vec<LeaderboardRecord>),
+    Compact(Vec<LeaderboardRecord>),
+}
+
+/// Leaderboards computed from a [`LeaderboardStore`], which are displayed while plasma is
+/// unavailable.
+pub(crate) struct LocalLeaderboards {
+    /// Requests for the thread that owns the store, which handles them in order. `None` once
+    /// dropped.
+    store: Option<Sender<StoreRequest>>,
+    worker: Option<JoinHandle<()>>,
+    /// Deduplicated by alias and sorted by descending score.
+    realms: HashMap<RealmId, [Vec<LeaderboardRecord>; PeriodId::VARIANT_COUNT]>,
+    size: usize,
+    /// Records appended since the store was last compacted.
+    appended: usize,
+}
+
+impl LocalLeaderboards {
+    /// Compact after appending this many records.
+    const COMPACT_THRESHOLD: usize = 1000;
+
+    pub(crate) fn open<S: LeaderboardStore>(path: Option<&Path>, size: usize) -> Option<Self> {
+        let result = S::open(path).and_then(|mut store| {
+            let records = store.load()?;
+            Ok((store, records))
+        });
+        let (mut store, records) = match result {
+            Ok(ok) => ok,
+            Err(e) => {
+                error!("could not open leaderboard store {path:?}: {e}");
+                return None;
+            }
+        };
+        let (sender, receiver) = channel::<StoreRequest>();
+        let worker = std::thread::Builder::new()
+            .name(String::from("leaderboard_store"))
+            .spawn(move || {
+                for request in receiver {
+                    match request {
+                        StoreRequest::Append(records) => {
+                            if let Err(e) = store.append(&records) {
+                                error!("could not append to leaderboard store: {e}");
+                            }
+                        }
+                        StoreRequest::Compact(records) => {
+                            if let Err(e) = store.compact(&records) {
+                                warn!("could not compact leaderboard store: {e}");
+                            }
+                        }
+                    }
+                }
+            });
+        let worker = match worker {
+            Ok(worker) => worker,
+            Err(e) => {
+                error!("could not spawn leaderboard store thread: {e}");
+                return None;
+            }
+        };
+        let loaded = records.len();
+        let mut ret = Self {
+            store: Some(sender),
+            worker: Some(worker),
+            realms: HashMap::new(),
+            size,
+            appended: 0,
+        };
+        ret.insert(records);
+        let retained = ret.records();
+        info!(
+            "loaded {loaded} leaderboard records, retained {}",
+            retained.len()
+        );
+        if retained.len() < loaded {
+            ret.request(StoreRequest::Compact(retained));
+        }
+        Some(ret)
+    }
+
+    /// Records scores, which are assumed to have passed `LEADERBOARD_MIN_PLAYERS`. Doesn't block.
+    pub(crate) fn record(&mut self, realm_id: RealmId, scores: &[LeaderboardScoreDto]) {
+        let timestamp = NonZeroUnixMillis::now();
+        let records = scores
+            .iter()
+            .map(|dto| LeaderboardRecord {
+                realm_id,
+                score: dto.clone(),
+                timestamp,
+            })
+            .collect::<Vec<_>>();
+        self.appended += records.len();
+        self.insert(records.iter().cloned());
+        self.request(StoreRequest::Append(records));
+        if self.appended >= Self::COMPACT_THRESHOLD {
+            // Records that fell off the leaderboards or rolled over would otherwise accumulate.
+            self.appended = 0;
+            let retained = self.records();
+            self.request(StoreRequest::Compact(retained));
+        }
+    }
+
+    /// Returns the current leaderboard, excluding scores from past days or weeks.
+    pub(crate) fn get(
+        &mut self,
+        realm_id: RealmId,
+        period_id: PeriodId,
+    ) -> impl Iterator<Item = LeaderboardScoreDto> + '_ {
+        let current = period_id.period(NonZeroUnixMillis::now());
+        self.realms
+            .get_mut(&realm_id)
+            .map(|periods| {
+                let records = &mut periods[period_id as usize];
+                records.retain(|record| period_id.period(record.timestamp) == current);
+                &*records
+            })
+            .into_iter()
+            .flatten()
+            .map(|record| record.score.clone())
+    }
+
+    fn insert(&mut self, records: impl IntoIterator<Item = LeaderboardRecord>) {
+        let now = NonZeroUnixMillis::now();
+        for record in records {
+            let periods = self.realms.entry(record.realm_id).or_default();
+            for period_id in PeriodId::iter() {
+                if period_id.period(record.timestamp) != period_id.period(now) {
+                    // Rolled over.
+                    continue;
+                }
+                LeaderboardScoreDto::insert(
+                    &mut periods[period_id as usize],
+                    record.clone(),
+                    self.size,
+                );
+            }
+        }
+    }
+
+    /// Every record that is on at least one current leaderboard.
+    fn records(&self) -> Vec<LeaderboardRecord> {
+        let now = NonZeroUnixMillis::now();
+        let mut ret = Vec::<LeaderboardRecord>::new();
+        for periods in self.realms.values() {
+            for (period_id, records) in PeriodId::iter().zip(periods) {
+                for record in records {
+                    if period_id.period(record.timestamp) == period_id.period(now)
+                        && !ret.contains(record)
+                    {
+                        ret.push(record.clone());
+                    }
+                }
+            }
+        }
+        ret
+    }
+
+    fn request(&self, request: StoreRequest) {
+        if let Some(store) = &self.store
+            && store.send(request).is_err()
+        {
+            error!("leaderboard store thread stopped");
+        }
+    }
+}
+
+impl Drop for LocalLeaderboards {
+    /// Waits for pending records to be stored.
+    fn drop(&mut self) {
+        self.store = None;
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FileLeaderboardStore, LeaderboardRecord, LeaderboardStore, LocalLeaderboards};
+    use crate::{LeaderboardScoreDto, NonZeroUnixMillis, PeriodId, PlayerAlias, RealmId, UnixTime};
+    use std::path::PathBuf;
+
+    fn score(alias: &str, score: u32) -> LeaderboardScoreDto {
+        LeaderboardScoreDto {
+            alias: PlayerAlias::new_unsanitized(alias),
+            score,
+        }
+    }
+
+    fn temporary_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "leaderboard_store_{name}_{}.jsonl",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn file_store() {
+        assert!(FileLeaderboardStore::open(None).is_err());
+
+        let path = temporary_path("file_store");
+        let record = |alias: &str, s: u32| LeaderboardRecord {
+            realm_id: RealmId::PublicDefault,
+            score: score(alias, s),
+            timestamp: NonZeroUnixMillis::now(),
+        };
+        let (a, b, c) = (record("a", 1), record("b", 2), record("c", 3));
+
+        let mut store = FileLeaderboardStore::open(Some(&path)).unwrap();
+        assert_eq!(store.load().unwrap(), []);
+        store.append(&[a.clone(), b.clone()]).unwrap();
+        store.append(&[c.clone()]).unwrap();
+        assert_eq!(store.load().unwrap(), [a.clone(), b.clone(), c.clone()]);
+
+        store.compact(&[c.clone()]).unwrap();
+        store.append(&[a.clone()]).unwrap();
+        drop(store);
+
+        let mut store = FileLeaderboardStore::open(Some(&path)).unwrap();
+        assert_eq!(store.load().unwrap(), [c, a]);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn local_leaderboards() {
+        let realm_id = RealmId::PublicDefault;
+        let mut local = LocalLeaderboards::open::<()>(None, 2).unwrap();
+        local.record(realm_id, &[score("a", 5), score("b", 7)]);
+        local.record(realm_id, &[score("a", 9), score("b", 6), score("c", 1)]);
+        for period_id in PeriodId::iter() {
+            assert_eq!(
+                local.get(realm_id, period_id).collect::<Vec<_>>(),
+                [score("a", 9), score("b", 7)]
+            );
+        }
+    }
+
+    #[test]
+    fn local_leaderboards_persist() {
+        let path = temporary_path("persist");
+        let realm_id = RealmId::PublicDefault;
+
+        let mut local = LocalLeaderboards::open::<FileLeaderboardStore>(Some(&path), 2).unwrap();
+        local.record(realm_id, &[score("a", 5), score("b", 7)]);
+        local.record(realm_id, &[score("a", 9), score("c", 1)]);
+        // Waits for the store thread.
+        drop(local);
+
+        let mut local = LocalLeaderboards::open::<FileLeaderboardStore>(Some(&path), 2).unwrap();
+        assert_eq!(
+            local.get(realm_id, PeriodId::AllTime).collect::<Vec<_>>(),
+            [score("a", 9), score("b", 7)]
+        );
+        drop(local);
+
+        // Compacted on open, since some records didn't make the cut.
+        let mut store = FileLeaderboardStore::open(Some(&path)).unwrap();
+        assert_eq!(store.load().unwrap().len(), 2);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -4,10 +4,13 @@
 mod arena_context;
 mod arena_service;
 mod bot_repo;
+mod chat_command;
 mod chat_inbox;
+mod chat_moderator;
 mod chat_repo;
 mod invitation_repo;
 mod leaderboard_repo;
+mod leaderboard_store;
 mod liveboard_repo;
 mod metric_repo;
 mod player_repo;
@@ -16,6 +19,7 @@ mod realm_repo;
 mod regulator;
 mod scene_repo;
 mod shard_context;
+pub(crate) mod test_arena;
 mod topology;
 
 pub use self::arena_context::{ArenaContext, RedirectedPlayer, SendPlasmaRequest};
@@ -25,10 +29,17 @@ pub use self::bot_repo::random_bot_team_name;
 pub use self::bot_repo::{
     random_bot_name, random_emoji_bot_name, BotOptions, BotRepo, PlayerBotData,
 };
+pub use self::chat_command::{
+    ChatArgument, ChatArgumentKind, ChatArgumentValue, ChatArguments, ChatCommand,
+    ChatCommandContext, ChatPermission,
+};
 pub use self::chat_inbox::ChatInbox;
+pub use self::chat_moderator::ChatModerationSettings;
 pub use self::chat_repo::{ChatRepo, ClientChatData, MessageAttribution};
 pub use self::invitation_repo::{ClientInvitationData, InvitationRepo};
 pub use self::leaderboard_repo::{LeaderboardRepo, PlayerLeaderboardData};
+pub(crate) use self::leaderboard_store::LocalLeaderboards;
+pub use self::leaderboard_store::{FileLeaderboardStore, LeaderboardRecord, LeaderboardStore};
 pub use self::liveboard_repo::{LiveboardRepo, PlayerLiveboardData, Score};
 pub use self::metric_repo::{Bundle, ClientMetricData, MetricBundle, MetricRepo};
 pub use self::player_repo::{Player, PlayerInner, PlayerRepo};
@@ -37,4 +48,5 @@ pub use self::realm_repo::{Realm, RealmRepo};
 pub use self::regulator::Regulator;
 pub use self::scene_repo::{Arena, SceneRepo};
 pub use self::shard_context::{ShardContextProvider, ShardPerRealm, ShardPerTier};
+pub use self::test_arena::TestArena;
 pub use self::topology::Topology;
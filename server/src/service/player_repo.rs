@@ -147,6 +147,8 @@ impl<G: ArenaService> PlayerRepo<G> {
                             authority: true,
                             authentic: false,
                             whisper: false,
+                            player_id: None,
+                            recipient: None,
                         }));
                     }
                 } else {
@@ -179,7 +181,7 @@ impl<G: ArenaService> PlayerRepo<G> {
         let player_dtos = self
             .iter()
             .filter_map(|(player_id, p)| {
-                if !p.is_bot() {
+                if !p.is_bot() && !p.is_spectator() {
                     real_players += 1;
                 }
 
@@ -444,6 +446,11 @@ impl<G: ArenaService> Player<G> {
         self.inner.is_bot()
     }
 
+    /// Returns true iff player is a client watching without playing.
+    pub fn is_spectator(&self) -> bool {
+        self.client().is_some_and(|c| c.is_spectator())
+    }
+
     /// Returns true iff the player 1) never played yet 2) stopped playing over half a minute ago.
     pub fn is_out_of_game(&self) -> bool {
         !self.was_ever_alive
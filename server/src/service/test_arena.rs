@@ -0,0 +1,806 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+use super::arena_context::SendPlasmaRequest;
+use super::{
+    Arena, ArenaContext, ArenaService, ChatRepo, ClientMetricData, InvitationRepo, MetricRepo,
+    Player, PlayerInner,
+};
+use crate::actor::{ActiveClientData, ClientActlet, ClientStatus, PlasmaActlet, PlayerClientData};
+use crate::observer::ObserverUpdate;
+use crate::{
+    ArenaId, ArenaSettingsDto, ChatRequest, CommonUpdate, LifecycleId, NonZeroUnixMillis,
+    PlasmaRequest, PlayerId, PlayerUpdate, ServerId, ServerKind, ServerNumber,
+};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr};
+use std::num::NonZeroU8;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::mpsc::{channel, unbounded_channel, Receiver, UnboundedReceiver};
+
+type Observer<G> = UnboundedReceiver<ObserverUpdate<CommonUpdate<<G as ArenaService>::GameUpdate>>>;
+
+/// Runs an [`ArenaService`] in-process, without sockets, TLS, plasma, or actors, so that game logic
+/// can be covered by `cargo test`.
+///
+/// Each [`Self::tick`] does what the server does once per [`ArenaService::TICK_PERIOD_SECS`], minus
+/// the leaderboard, liveboard, and metrics. What each client would have been sent is buffered until
+/// read with [`Self::updates`] or [`Self::game_updates`]. Chat goes through the same [`ChatRepo`] as
+/// on a server that can't reach plasma.
+pub struct TestArena<G: ArenaService> {
+    arena: Arena<G>,
+    server_id: ServerId,
+    arena_id: ArenaId,
+    metrics: MetricRepo<G>,
+    invitations: InvitationRepo<G>,
+    chat: ChatRepo<G>,
+    /// Never connected, so chat is delivered locally.
+    plasma: PlasmaActlet,
+    observers: HashMap<PlayerId, Observer<G>>,
+    /// Requests that would have been sent to plasma.
+    plasma_requests: Receiver<PlasmaRequest>,
+    /// Clients that were removed, and will be forgotten after leaving.
+    removed: Vec<PlayerId>,
+    /// Each client gets a unique IP address, for the purpose of muting.
+    next_ip: u32,
+}
+
+impl<G: ArenaService> Default for TestArena<G> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<G: ArenaService> TestArena<G> {
+    /// Creates an arena with no bots.
+    pub fn new() -> Self {
+        let server_id = ServerId {
+            kind: ServerKind::Local,
+            number: ServerNumber(NonZeroU8::MIN),
+        };
+        let arena_id = ArenaId::default();
+        let (sender, plasma_requests) = channel(1024);
+        let mut plasma = PlasmaActlet::new_local::<G>();
+        plasma.web_socket.sender = Some(sender.clone());
+        let mut arena = Arena::new(
+            server_id,
+            arena_id,
+            SendPlasmaRequest {
+                web_socket: Some(sender),
+                local: None,
+                local_server_id: server_id,
+            },
+        );
+        let mut settings = ArenaSettingsDto::default();
+        settings.engine.bots = Some(0);
+        arena.arena_context.set_settings(settings);
+        Self {
+            arena,
+            server_id,
+            arena_id,
+            metrics: MetricRepo::new(),
+            invitations: Default::default(),
+            chat: Default::default(),
+            plasma,
+            observers: Default::default(),
+            plasma_requests,
+            removed: Vec::new(),
+            next_ip: 0,
+        }
+    }
+
+    pub fn service(&self) -> &G {
+        &self.arena.arena_service
+    }
+
+    pub fn service_mut(&mut self) -> &mut G {
+        &mut self.arena.arena_service
+    }
+
+    pub fn context(&self) -> &ArenaContext<G> {
+        &self.arena.arena_context
+    }
+
+    pub fn context_mut(&mut self) -> &mut ArenaContext<G> {
+        &mut self.arena.arena_context
+    }
+
+    /// Sets the number of bots, which will join over the next few ticks.
+    pub fn set_bots(&mut self, bots: u16) {
+        let mut settings = self.arena.arena_context.settings.clone();
+        settings.engine.bots = Some(bots);
+        self.arena.arena_context.set_settings(settings);
+    }
+
+    /// Connects a client, which immediately joins the game.
+    ///
+    /// **Panics**
+    ///
+    /// If the arena runs out of [`PlayerId`]s.
+    pub fn add_client(&mut self) -> PlayerId {
+        let players = &mut self.arena.arena_context.players;
+        let player_id = (0..)
+            .map_while(PlayerId::nth_client)
+            .find(|&player_id| !players.contains(player_id))
+            .expect("ran out of PlayerIds");
+
+        self.next_ip += 1;
+        let ip_address = IpAddr::V4(Ipv4Addr::from(
+            u32::from(Ipv4Addr::LOCALHOST) + self.next_ip,
+        ));
+        let metrics = ClientMetricData::new(
+            0.0,
+            self.server_id,
+            self.arena_id,
+            &mut NonZeroUnixMillis::MIN,
+            Default::default(),
+            LifecycleId::New,
+        );
+        let chat = self
+            .chat
+            .initialize_client(self.server_id.number, self.arena_id);
+        let mut client = PlayerClientData::new(chat, metrics, ip_address, false);
+        let (observer, receiver) = unbounded_channel();
+        client.status = ClientStatus::Connected {
+            observer,
+            supports_unreliable: false,
+            warn_if_dropped: false,
+            last_activity: Instant::now(),
+            active: Some(ActiveClientData::new_local()),
+        };
+        self.observers.insert(player_id, receiver);
+
+        let mut player = Player::new(PlayerInner::Client(client));
+        assert!(player.regulator.join());
+        self.arena
+            .arena_service
+            .player_joined(player_id, &mut player);
+        players.insert(player_id, player);
+        player_id
+    }
+
+    /// Disconnects a client, which quits immediately and is forgotten after it leaves.
+    pub fn remove_client(&mut self, player_id: PlayerId) {
+        let Some(player) = self.arena.arena_context.players.get_mut(player_id) else {
+            return;
+        };
+        let Some(client) = player.client_mut() else {
+            return;
+        };
+        if self.observers.remove(&player_id).is_none() {
+            // Already removed.
+            return;
+        }
+        client.status = ClientStatus::LeavingLimbo {
+            expiry: Instant::now(),
+            ticks: 0,
+            warn_if_unforgettable: false,
+        };
+        if player.regulator.active() {
+            self.arena.arena_service.player_quit(player_id, player);
+        }
+        player.regulator.leave();
+        self.removed.push(player_id);
+    }
+
+    /// Issues a game command on behalf of a client, as if received from its socket. A direct
+    /// response, if any, is buffered like any other update.
+    pub fn command(
+        &mut self,
+        player_id: PlayerId,
+        request: G::GameRequest,
+    ) -> Result<(), &'static str> {
+        let context = &mut self.arena.arena_context;
+        let game_fence = context
+            .players
+            .get(player_id)
+            .and_then(|p| p.client())
+            .and_then(|c| c.status.game_fence());
+        let update = ClientActlet::<G>::handle_game_command(
+            player_id,
+            request,
+            game_fence,
+            &mut self.arena.arena_service,
+            &mut context.players,
+        )?;
+        if let Some(update) = update {
+            context.players[player_id]
+                .client()
+                .ok_or("not a client")?
+                .send(update);
+        }
+        Ok(())
+    }
+
+    /// Sends a chat message on behalf of a client, see [`Self::chat_request`].
+    pub fn chat(
+        &mut self,
+        player_id: PlayerId,
+        message: &str,
+        whisper: bool,
+    ) -> Result<(), &'static str> {
+        self.chat_request(
+            player_id,
+            ChatRequest::Send {
+                message: message.to_owned(),
+                whisper,
+            },
+        )
+    }
+
+    /// Issues a chat request on behalf of a client, as if received from its socket, including
+    /// commands, moderation, and censoring. The response is buffered like any other update, and
+    /// broadcasts are delivered on the next [`Self::tick`].
+    pub fn chat_request(
+        &mut self,
+        player_id: PlayerId,
+        request: ChatRequest,
+    ) -> Result<(), &'static str> {
+        let update = self.chat.handle_chat_request(
+            self.arena_id,
+            player_id,
+            request,
+            self.server_id,
+            &mut self.arena,
+            &mut self.metrics,
+            &self.plasma,
+        )?;
+        let client = self
+            .arena
+            .arena_context
+            .players
+            .get(player_id)
+            .and_then(|p| p.client())
+            .ok_or("not a client")?;
+        if let ClientStatus::Connected { observer, .. } = &client.status {
+            let _ = observer.send(ObserverUpdate::Send {
+                message: CommonUpdate::Chat(update),
+                reliable: true,
+            });
+        }
+        Ok(())
+    }
+
+    /// Advances the game by one tick, including bots, and buffers updates for clients.
+    pub fn tick(&mut self) {
+        let flags = self.chat.take_flags();
+        ChatRepo::deliver_flags(&flags, std::iter::once(&mut self.arena));
+        self.chat
+            .deliver_local_broadcasts(std::iter::once(&mut self.arena));
+        let Arena {
+            arena_context: context,
+            arena_service: service,
+        } = &mut self.arena;
+
+        context
+            .bots
+            .update_count(service, &mut context.players, &context.settings.engine);
+
+        service.tick(context);
+        let announcements = context.players.update_is_alive_and_team_id(
+            service,
+            &mut self.metrics,
+            self.server_id,
+            self.arena_id,
+        );
+
+        let player_update = context.players.delta();
+        for (player_id, player) in context.players.iter_mut() {
+            let Some(client) = player.client_mut() else {
+                continue;
+            };
+            if !client.status.is_connected() {
+                continue;
+            }
+            for announcement in &announcements {
+                client.chat.receive(announcement, None);
+            }
+            let chat_update = ChatRepo::<G>::player_delta(&mut client.chat);
+            let update = service.get_game_update(player_id, player);
+            let client = player.client().unwrap();
+            if let Some(update) = update {
+                client.send(update);
+            }
+            let ClientStatus::Connected { observer, .. } = &client.status else {
+                unreachable!();
+            };
+            if let Some((added, removed)) = player_update.as_ref() {
+                let _ = observer.send(ObserverUpdate::Send {
+                    message: CommonUpdate::Player(PlayerUpdate::Updated {
+                        added: Arc::clone(added),
+                        removed: Arc::clone(removed),
+                    }),
+                    reliable: true,
+                });
+            }
+            if let Some(chat_update) = chat_update {
+                let _ = observer.send(ObserverUpdate::Send {
+                    message: CommonUpdate::Chat(chat_update),
+                    reliable: true,
+                });
+            }
+        }
+
+        context
+            .bots
+            .update(service, &mut context.players, &context.settings);
+        service.post_update(context);
+        context.bots.post_update(service, &mut context.players);
+
+        // Forget players that finished leaving.
+        let players = &mut context.players;
+        let to_forget = players
+            .iter()
+            .filter(|(player_id, player)| {
+                player.regulator.can_forget()
+                    && (player.is_bot() || self.removed.contains(player_id))
+            })
+            .map(|(player_id, _)| player_id)
+            .collect::<Vec<_>>();
+        for player_id in to_forget {
+            players.forget(player_id, &mut self.invitations);
+            self.removed.retain(|&removed| removed != player_id);
+        }
+    }
+
+    /// Calls [`Self::tick`] `n` times.
+    pub fn ticks(&mut self, n: usize) {
+        for _ in 0..n {
+            self.tick();
+        }
+    }
+
+    /// Returns all the updates a client was sent since the last call.
+    pub fn updates(&mut self, player_id: PlayerId) -> Vec<CommonUpdate<G::GameUpdate>> {
+        let Some(observer) = self.observers.get_mut(&player_id) else {
+            return Vec::new();
+        };
+        std::iter::from_fn(|| observer.try_recv().ok())
+            .filter_map(|update| match update {
+                ObserverUpdate::Send { message, .. } => Some(message),
+                ObserverUpdate::Close => None,
+            })
+            .collect()
+    }
+
+    /// Returns the game updates a client was sent since the last call, discarding other updates.
+    pub fn game_updates(&mut self, player_id: PlayerId) -> Vec<G::GameUpdate> {
+        self.updates(player_id)
+            .into_iter()
+            .filter_map(|update| {
+                if let CommonUpdate::Game(update) = update {
+                    Some(update)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Returns requests that would have been sent to plasma since the last call.
+    pub fn plasma_requests(&mut self) -> Vec<PlasmaRequest> {
+        std::iter::from_fn(|| self.plasma_requests.try_recv().ok()).collect()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::TestArena;
+    use crate::actor::{ClientActlet, ServerMessage};
+    use crate::service::{
+        ArenaContext, ArenaService, ChatArgument, ChatArgumentKind, ChatArguments, ChatCommand,
+        ChatCommandContext, ChatPermission, ChatRepo, Player, RedirectedPlayer, Score,
+    };
+    use crate::{
+        ArenaId, ArenaQuery, ChatMessage, ChatRecipient, ChatRequest, ChatUpdate, CommonUpdate,
+        DefaultedGameConstants, DirectRecipient, GameConstants, MessageDto, MessageNumber,
+        PlasmaRequest, PlasmaRequestV1, PlayerAlias, PlayerId, ServerId, ServerKind, ServerNumber,
+    };
+    use std::num::NonZeroU8;
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+    use std::time::Instant;
+
+    /// Echoes commands, and sends the tick count every tick.
+    #[derive(Default)]
+    pub(crate) struct Echo {
+        ticks: u32,
+    }
+
+    impl Echo {
+        fn echo_command(
+            _: &mut ChatCommandContext<'_, Self>,
+            arguments: &ChatArguments,
+        ) -> Result<String, &'static str> {
+            Ok(format!("{arguments:?}"))
+        }
+
+        fn slow_command(
+            _: &mut ChatCommandContext<'_, Self>,
+            _: &ChatArguments,
+        ) -> Result<String, &'static str> {
+            Ok(String::from("echo slow"))
+        }
+    }
+
+    impl ArenaService for Echo {
+        const GAME_CONSTANTS: &'static GameConstants = &GameConstants {
+            domain: "example.com",
+            game_id: "Echo",
+            geodns_enabled: false,
+            name: "Echo",
+            trademark: "Echo",
+            server_names: &["Echo"],
+            defaulted: DefaultedGameConstants::new(),
+        };
+        const TICK_PERIOD_SECS: f32 = 0.1;
+        const CHAT_COMMANDS: &'static [ChatCommand<Self>] = &[
+            ChatCommand {
+                name: "echo",
+                arguments: &[
+                    ChatArgument::required(
+                        "target",
+                        &[
+                            ChatArgumentKind::Player,
+                            ChatArgumentKind::Enum(&["all", "none"]),
+                        ],
+                    ),
+                    ChatArgument::required(
+                        "times",
+                        &[ChatArgumentKind::Number { min: 1.0, max: 3.0 }],
+                    ),
+                    ChatArgument::optional("word", &[ChatArgumentKind::Word]),
+                ],
+                permission: ChatPermission::Player,
+                description: "Echoes its arguments",
+                execute: Self::echo_command,
+            },
+            // Shadows the engine's moderator-only command.
+            ChatCommand {
+                name: "slow",
+                arguments: &[],
+                permission: ChatPermission::Player,
+                description: "Echoes slowly",
+                execute: Self::slow_command,
+            },
+        ];
+
+        type GameRequest = u32;
+        type GameUpdate = u32;
+        type PortableState = u32;
+
+        fn new(_: &mut ArenaContext<Self>) -> Self {
+            Self::default()
+        }
+
+        fn is_alive(&self, _: PlayerId) -> bool {
+            true
+        }
+
+        fn get_score(&self, _: PlayerId) -> Score {
+            Score::None
+        }
+
+        fn get_alias(&self, _: PlayerId) -> PlayerAlias {
+            PlayerAlias::default()
+        }
+
+        fn player_command(
+            &mut self,
+            request: u32,
+            _: PlayerId,
+            _: &mut Player<Self>,
+        ) -> Option<u32> {
+            Some(request)
+        }
+
+        fn get_game_update(&self, _: PlayerId, _: &mut Player<Self>) -> Option<u32> {
+            Some(self.ticks)
+        }
+
+        fn label_game_update(update: &u32) -> Option<&'static str> {
+            (update % 2 == 0).then_some("Even")
+        }
+
+        fn export_player(&mut self, _: PlayerId, _: &Player<Self>) -> Option<u32> {
+            Some(self.ticks)
+        }
+
+        fn tick(&mut self, _: &mut ArenaContext<Self>) {
+            self.ticks += 1;
+        }
+
+        fn entities(&self) -> usize {
+            0
+        }
+
+        fn world_size(&self) -> f32 {
+            0.0
+        }
+    }
+
+    /// Chat messages a client was sent since the last call, discarding other updates.
+    fn received(
+        arena: &mut TestArena<Echo>,
+        player_id: PlayerId,
+    ) -> Vec<(MessageNumber, Arc<MessageDto>)> {
+        arena
+            .updates(player_id)
+            .into_iter()
+            .filter_map(|update| match update {
+                CommonUpdate::Chat(ChatUpdate::Received(messages)) => Some(messages.into_vec()),
+                _ => None,
+            })
+            .flatten()
+            .collect()
+    }
+
+    fn raw(message: &MessageDto) -> Option<&str> {
+        match &message.message {
+            ChatMessage::Raw { message, .. } => Some(message),
+            _ => None,
+        }
+    }
+
+    /// Raw chat messages a client was sent since the last call, discarding other updates.
+    fn chat(arena: &mut TestArena<Echo>, player_id: PlayerId) -> Vec<String> {
+        received(arena, player_id)
+            .iter()
+            .filter_map(|(_, message)| raw(message).map(String::from))
+            .collect()
+    }
+
+    #[test]
+    fn commands() {
+        let mut arena = TestArena::<Echo>::new();
+        let a = arena.add_client();
+        let b = arena.add_client();
+        assert_ne!(a, b);
+
+        arena.command(a, 42).unwrap();
+        arena.tick();
+        assert_eq!(arena.game_updates(a), [42, 1]);
+        assert_eq!(arena.game_updates(b), [1]);
+
+        arena.remove_client(b);
+        arena.ticks(2);
+        assert_eq!(arena.game_updates(a), [2, 3]);
+        assert!(arena.game_updates(b).is_empty());
+        assert!(arena.command(b, 1).is_err());
+    }
+
+    #[test]
+    fn chat_goes_through_chat_repo() {
+        let mut arena = TestArena::<Echo>::new();
+        let a = arena.add_client();
+        let b = arena.add_client();
+        arena.tick();
+        chat(&mut arena, a);
+        chat(&mut arena, b);
+
+        // Broadcasts are delivered on the next tick, like on a server without plasma.
+        arena.chat(a, "hello", false).unwrap();
+        assert!(chat(&mut arena, b).is_empty());
+        arena.tick();
+        assert!(chat(&mut arena, b).contains(&String::from("hello")));
+        assert!(chat(&mut arena, a).contains(&String::from("hello")));
+
+        // Censored.
+        arena.chat(a, "fuck", false).unwrap();
+        arena.tick();
+        let received = chat(&mut arena, b);
+        assert!(!received.contains(&String::from("fuck")), "{received:?}");
+
+        // Registered commands are only shown to the sender.
+        arena.chat(a, "/help", false).unwrap();
+        arena.tick();
+        assert!(chat(&mut arena, a)
+            .iter()
+            .any(|message| message.starts_with("commands: /help")),);
+        assert!(chat(&mut arena, b).is_empty());
+
+        // No team to whisper to.
+        assert!(arena.chat(a, "psst", true).is_err());
+    }
+
+    #[test]
+    fn game_commands_take_precedence() {
+        let mut arena = TestArena::<Echo>::new();
+        let a = arena.add_client();
+        arena.tick();
+        chat(&mut arena, a);
+
+        arena.chat(a, "/slow", false).unwrap();
+        arena.tick();
+        assert_eq!(chat(&mut arena, a), ["echo slow"]);
+
+        arena.chat(a, "/help", false).unwrap();
+        arena.tick();
+        let help = chat(&mut arena, a);
+        assert_eq!(help.len(), 1);
+        assert_eq!(help[0].matches("/slow").count(), 1, "{help:?}");
+        assert!(help[0].contains("/echo"), "{help:?}");
+
+        assert!(ChatRepo::<Echo>::commands()
+            .filter(|command| command.name == "slow")
+            .all(|command| command.description == "Echoes slowly"));
+    }
+
+    #[test]
+    fn chat_follows_plasma_socket() {
+        let mut arena = TestArena::<Echo>::new();
+        let a = arena.add_client();
+        let b = arena.add_client();
+        arena.tick();
+        chat(&mut arena, b);
+        let sent_chats = |arena: &mut TestArena<Echo>| {
+            arena
+                .plasma_requests()
+                .into_iter()
+                .filter_map(|request| match request {
+                    PlasmaRequest::V1(PlasmaRequestV1::SendChat { message, .. }) => Some(message),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // A recent heartbeat doesn't help if the socket has since closed.
+        arena.plasma.last_acknowledged_heartbeat = Some(Instant::now());
+        arena.chat(a, "hello", false).unwrap();
+        arena.tick();
+        assert!(chat(&mut arena, b).contains(&String::from("hello")));
+        assert!(sent_chats(&mut arena).is_empty());
+
+        // Plasma delivers it instead.
+        arena
+            .plasma
+            .web_socket
+            .connected
+            .store(true, Ordering::Relaxed);
+        arena.chat(a, "world", false).unwrap();
+        arena.tick();
+        assert!(!chat(&mut arena, b).contains(&String::from("world")));
+        assert_eq!(sent_chats(&mut arena), ["world"]);
+    }
+
+    #[test]
+    fn send_direct() {
+        let mut arena = TestArena::<Echo>::new();
+        let a = arena.add_client();
+        let b = arena.add_client();
+        let c = arena.add_client();
+        arena.tick();
+        for player_id in [a, b, c] {
+            received(&mut arena, player_id);
+        }
+        let send_direct = |arena: &mut TestArena<Echo>, from, recipient, message: &str| {
+            arena.chat_request(
+                from,
+                ChatRequest::SendDirect {
+                    recipient,
+                    message: message.to_owned(),
+                },
+            )
+        };
+
+        assert_eq!(
+            send_direct(&mut arena, a, DirectRecipient::Player(a), "me"),
+            Err("cannot message self")
+        );
+
+        send_direct(&mut arena, a, DirectRecipient::Player(b), "hi b").unwrap();
+        arena.tick();
+        let b_alias = arena.context().players.get(b).unwrap().alias;
+        for player_id in [a, b] {
+            let received = received(&mut arena, player_id);
+            assert_eq!(received.len(), 1, "{received:?}");
+            let message = &received[0].1;
+            assert_eq!(raw(message), Some("hi b"));
+            // Identifies the sender, even though aliases aren't unique.
+            assert_eq!(message.player_id, Some(a));
+            assert_eq!(message.recipient, Some(b_alias));
+        }
+        assert!(received(&mut arena, c).is_empty());
+
+        // Reply to the sender of a received message.
+        send_direct(&mut arena, c, DirectRecipient::Player(b), "hi from c").unwrap();
+        arena.tick();
+        received(&mut arena, c);
+        let from_c = received(&mut arena, b)[0].0;
+        send_direct(&mut arena, b, DirectRecipient::Reply(from_c), "hi c").unwrap();
+        arena.tick();
+        assert_eq!(chat(&mut arena, c), ["hi c"]);
+        assert!(chat(&mut arena, a).is_empty());
+        assert_eq!(
+            send_direct(
+                &mut arena,
+                b,
+                DirectRecipient::Reply(from_c.wrapping_add(100)),
+                "?"
+            ),
+            Err("sender unavailable")
+        );
+
+        // Plasma relays to the recipient, but the sender still sees it censored.
+        arena
+            .plasma
+            .web_socket
+            .connected
+            .store(true, Ordering::Relaxed);
+        send_direct(&mut arena, a, DirectRecipient::Player(b), "fuck").unwrap();
+        arena.tick();
+        let echo = chat(&mut arena, a);
+        assert_eq!(echo.len(), 1);
+        assert_ne!(echo[0], "fuck");
+        assert!(chat(&mut arena, b).is_empty());
+        assert!(arena.plasma_requests().iter().any(|request| matches!(
+            request,
+            PlasmaRequest::V1(PlasmaRequestV1::SendChat {
+                message,
+                recipient: ChatRecipient::Player(recipient),
+                ..
+            }) if message == "fuck" && *recipient == b
+        )));
+    }
+
+    #[test]
+    fn switch_arena_carries_portable_state() {
+        let mut arena = TestArena::<Echo>::new();
+        let a = arena.add_client();
+        arena.ticks(2);
+        arena.plasma_requests();
+
+        let server_id = ServerId {
+            kind: ServerKind::Local,
+            number: ServerNumber(NonZeroU8::new(2).unwrap()),
+        };
+        ClientActlet::switch_arena(
+            a,
+            server_id,
+            ArenaQuery::default(),
+            &mut arena.arena.arena_service,
+            &mut arena.arena.arena_context,
+            &mut arena.metrics,
+        )
+        .unwrap();
+        let messages = arena
+            .plasma_requests()
+            .into_iter()
+            .filter_map(|request| match request {
+                PlasmaRequest::V1(PlasmaRequestV1::SendServerMessage { message, .. }) => {
+                    serde_json::from_value::<ServerMessage>(message).ok()
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let [ServerMessage::Engine {
+            redirected,
+            portable,
+            ..
+        }] = messages.as_slice()
+        else {
+            panic!("{messages:?}");
+        };
+        assert_eq!(
+            portable
+                .as_deref()
+                .and_then(RedirectedPlayer::decode_portable_state::<Echo>),
+            Some(2)
+        );
+
+        // Servers that predate portable state neither send nor expect it.
+        let old = serde_json::json!({
+            "Engine": {
+                "sender_arena_id": ArenaId::default(),
+                "arena_id": ArenaQuery::default(),
+                "redirected": redirected,
+            }
+        });
+        assert!(matches!(
+            serde_json::from_value::<ServerMessage>(old),
+            Ok(ServerMessage::Engine { portable: None, .. })
+        ));
+        assert!(RedirectedPlayer::decode_portable_state::<Echo>("not base64!").is_none());
+    }
+}
@@ -1,9 +1,12 @@
 // SPDX-FileCopyrightText: 2024 Softbear, Inc.
 // SPDX-License-Identifier: LGPL-3.0-or-later
 
-use super::actor::ServerActor;
-use super::service::ArenaService;
+use super::actor::{ClientActlet, PlasmaActlet, ServerActor};
+use super::service::{ArenaService, LeaderboardRepo};
+use crate::{ArenaQuery, PlayerId, ServerId};
 use actix::{ActorContext, Handler, Message};
+use log::{info, warn};
+use std::time::{Duration, Instant};
 
 /// Asks the server to stop itself.
 #[derive(Message)]
@@ -17,3 +20,221 @@ impl<G: ArenaService> Handler<Shutdown> for ServerActor<G> {
         ctx.stop();
     }
 }
+
+/// Asks the server to stop itself gracefully: close to new players, move existing players to
+/// other servers, and stop once empty or after `timeout`.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct Drain {
+    pub timeout: Duration,
+}
+
+/// State of a [`Drain`] in progress.
+pub(crate) struct Draining {
+    deadline: Instant,
+    /// For spreading players across available servers.
+    next_server: usize,
+    /// When the final heartbeat, which flushes claims, was requested.
+    flushing_since: Option<Instant>,
+    /// Whether every player was migrated or timed out, and claims were flushed (or gave up on).
+    pub(crate) finished: bool,
+}
+
+/// How long to wait for plasma to acknowledge the final heartbeat.
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(10);
+
+impl Draining {
+    fn new(deadline: Instant) -> Self {
+        Self {
+            deadline,
+            next_server: 0,
+            flushing_since: None,
+            finished: false,
+        }
+    }
+
+    /// Decides whether it's time to stop, given how many clients remain. Once none remain or the
+    /// deadline passes, requests a final heartbeat and waits for plasma to acknowledge it.
+    fn should_stop(&mut self, clients: usize, plasma: &mut PlasmaActlet, now: Instant) -> bool {
+        if let Some(flushing_since) = self.flushing_since {
+            return if plasma
+                .last_acknowledged_heartbeat
+                .is_some_and(|ack| ack > flushing_since)
+            {
+                info!("flushed");
+                true
+            } else if !plasma.is_socket_connected() {
+                warn!("couldn't flush, since plasma is unavailable");
+                true
+            } else if now.saturating_duration_since(flushing_since) >= FLUSH_TIMEOUT {
+                warn!("flush timed out");
+                true
+            } else {
+                false
+            };
+        }
+
+        if clients == 0 {
+            info!("drained");
+        } else if now >= self.deadline {
+            warn!("drain timed out with {clients} client(s) remaining");
+        } else {
+            return false;
+        }
+        self.flushing_since = Some(now);
+        // Flush claims.
+        plasma.heartbeat_soon();
+        false
+    }
+}
+
+impl<G: ArenaService> Handler<Drain> for ServerActor<G> {
+    type Result = ();
+
+    fn handle(&mut self, request: Drain, _ctx: &mut Self::Context) -> Self::Result {
+        self.drain(request.timeout);
+    }
+}
+
+impl<G: ArenaService> ServerActor<G> {
+    /// Starts draining, or shortens the deadline if already draining.
+    pub(crate) fn drain(&mut self, timeout: Duration) {
+        let deadline = Instant::now() + timeout;
+        if let Some(draining) = &mut self.draining {
+            draining.deadline = draining.deadline.min(deadline);
+            return;
+        }
+        warn!("draining for up to {timeout:?}");
+        self.draining = Some(Draining::new(deadline));
+        self.plasma.draining = true;
+        LeaderboardRepo::update_to_plasma(self, true);
+        self.plasma.heartbeat_soon();
+    }
+
+    /// Redirects some players to other servers, spreading them over the first half of the
+    /// remaining time. Returns `true` when it's time to stop, after claims are flushed.
+    pub(crate) fn update_drain(&mut self) -> bool {
+        let Some(draining) = &mut self.draining else {
+            return false;
+        };
+        let now = Instant::now();
+        let remaining = draining.deadline.saturating_duration_since(now);
+        let servers = self
+            .system
+            .available_servers
+            .iter()
+            .copied()
+            .filter(|&server_id| server_id != self.server_id)
+            .collect::<Vec<ServerId>>();
+        // Redirection is coordinated via plasma.
+        let can_redirect =
+            draining.flushing_since.is_none() && !servers.is_empty() && self.plasma.is_connected();
+        let ticks = (remaining.as_secs_f32() * 0.5 / G::TICK_PERIOD_SECS).max(1.0);
+
+        let mut clients = 0;
+        for (arena_id, scene) in self.realms.iter_mut() {
+            let context = &mut scene.arena.arena_context;
+            let connected = context
+                .players
+                .iter()
+                .filter(|(_, player)| {
+                    player.regulator.active()
+                        && player.client().is_some_and(|c| c.status.is_connected())
+                })
+                .map(|(player_id, _)| player_id)
+                .collect::<Vec<PlayerId>>();
+            clients += context
+                .players
+                .iter()
+                .filter(|(_, player)| player.client().is_some())
+                .count();
+            if !can_redirect {
+                continue;
+            }
+            let budget = (connected.len() as f32 / ticks).ceil() as usize;
+            // Temporary realms only exist on this server.
+            let arena_query = if arena_id.realm_id.is_temporary() {
+                ArenaQuery::default()
+            } else {
+                ArenaQuery::AnyInstance(arena_id.realm_id, arena_id.scene_id.tier_number)
+            };
+            for player_id in connected.into_iter().take(budget) {
+                let server_id = servers[draining.next_server % servers.len()];
+                draining.next_server = draining.next_server.wrapping_add(1);
+                if let Err(e) = ClientActlet::switch_arena(
+                    player_id,
+                    server_id,
+                    arena_query,
+                    &mut scene.arena.arena_service,
+                    &mut scene.arena.arena_context,
+                    &mut self.metrics,
+                ) {
+                    warn!("couldn't redirect {player_id:?} while draining: {e}");
+                }
+            }
+        }
+
+        draining.finished = draining.should_stop(clients, &mut self.plasma, now);
+        draining.finished
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Draining, FLUSH_TIMEOUT};
+    use crate::actor::PlasmaActlet;
+    use crate::service::test_arena::tests::Echo;
+    use std::sync::atomic::Ordering;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn waits_for_flush() {
+        let mut plasma = PlasmaActlet::new_local::<Echo>();
+        plasma.web_socket.connected.store(true, Ordering::Relaxed);
+        let start = Instant::now();
+        let mut draining = Draining::new(start + Duration::from_secs(60));
+
+        assert!(!draining.should_stop(2, &mut plasma, start));
+        assert!(draining.flushing_since.is_none());
+
+        // Drained, but claims haven't been flushed yet.
+        let drained = start + Duration::from_secs(1);
+        assert!(!draining.should_stop(0, &mut plasma, drained));
+        assert_eq!(draining.flushing_since, Some(drained));
+        assert!(!draining.should_stop(0, &mut plasma, drained));
+
+        // An older acknowledgement doesn't count.
+        plasma.last_acknowledged_heartbeat = Some(start);
+        assert!(!draining.should_stop(0, &mut plasma, drained));
+        plasma.last_acknowledged_heartbeat = Some(drained + Duration::from_millis(100));
+        assert!(draining.should_stop(0, &mut plasma, drained + Duration::from_millis(100)));
+    }
+
+    #[test]
+    fn deadline() {
+        let mut plasma = PlasmaActlet::new_local::<Echo>();
+        plasma.web_socket.connected.store(true, Ordering::Relaxed);
+        let start = Instant::now();
+        let deadline = start + Duration::from_secs(60);
+        let mut draining = Draining::new(deadline);
+
+        // Players remain, but time is up.
+        assert!(!draining.should_stop(3, &mut plasma, deadline));
+        assert_eq!(draining.flushing_since, Some(deadline));
+
+        // Plasma never acknowledges.
+        assert!(!draining.should_stop(3, &mut plasma, deadline + FLUSH_TIMEOUT / 2));
+        assert!(draining.should_stop(3, &mut plasma, deadline + FLUSH_TIMEOUT));
+    }
+
+    #[test]
+    fn plasma_unavailable() {
+        let mut plasma = PlasmaActlet::new_local::<Echo>();
+        let start = Instant::now();
+        let mut draining = Draining::new(start + Duration::from_secs(60));
+
+        // No point waiting for a heartbeat that can't be sent.
+        assert!(!draining.should_stop(0, &mut plasma, start));
+        assert!(draining.should_stop(0, &mut plasma, start));
+    }
+}
@@ -0,0 +1,192 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+use super::Sent;
+use crate::{AdminBandwidthDto, ArenaService, CommonUpdate};
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+/// Bytes sent since startup, per kind of update. Sockets record locally and periodically merge
+/// into this, to avoid contention.
+pub(crate) static BANDWIDTH_PROFILER: LazyLock<Mutex<BandwidthProfile>> =
+    LazyLock::new(Default::default);
+
+/// How often sockets merge into [`BANDWIDTH_PROFILER`].
+pub(crate) const BANDWIDTH_PROFILE_FLUSH: Duration = Duration::from_secs(5);
+
+/// Bytes sent per [`CommonUpdate`] variant (and optionally game update sub-variant) and
+/// transport.
+#[derive(Default)]
+pub(crate) struct BandwidthProfile {
+    counters: HashMap<BandwidthKey, BandwidthCounter>,
+}
+
+/// Kind of update.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub(crate) struct BandwidthLabel {
+    variant: &'static str,
+    /// See [`ArenaService::label_game_update`].
+    sub_variant: Option<&'static str>,
+}
+
+impl BandwidthLabel {
+    pub(crate) fn new<G: ArenaService>(update: &CommonUpdate<G::GameUpdate>) -> Self {
+        let (variant, sub_variant) = match update {
+            CommonUpdate::Chat(_) => ("Chat", None),
+            CommonUpdate::Client(_) => ("Client", None),
+            CommonUpdate::Game(update) => ("Game", G::label_game_update(update)),
+            CommonUpdate::Invitation(_) => ("Invitation", None),
+            CommonUpdate::Leaderboard(_) => ("Leaderboard", None),
+            CommonUpdate::Liveboard(_) => ("Liveboard", None),
+            CommonUpdate::Player(_) => ("Player", None),
+            CommonUpdate::System(_) => ("System", None),
+        };
+        Self {
+            variant,
+            sub_variant,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+struct BandwidthKey {
+    label: BandwidthLabel,
+    /// See [`Sent::reliable`].
+    reliable: bool,
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+struct BandwidthCounter {
+    messages: u64,
+    /// Before compression.
+    encoded_bytes: u64,
+    /// On the wire, excluding transport overhead.
+    compressed_bytes: u64,
+}
+
+impl BandwidthProfile {
+    pub(crate) fn record(&mut self, label: BandwidthLabel, sent: Sent, encoded_bytes: usize) {
+        let counter = self
+            .counters
+            .entry(BandwidthKey {
+                label,
+                reliable: sent.reliable,
+            })
+            .or_default();
+        counter.messages += 1;
+        counter.encoded_bytes += encoded_bytes as u64;
+        counter.compressed_bytes += sent.bytes as u64;
+    }
+
+    /// Adds `other` to `self`.
+    pub(crate) fn merge(&mut self, other: Self) {
+        for (key, other) in other.counters {
+            let counter = self.counters.entry(key).or_default();
+            counter.messages += other.messages;
+            counter.encoded_bytes += other.encoded_bytes;
+            counter.compressed_bytes += other.compressed_bytes;
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.counters.is_empty()
+    }
+
+    /// Most compressed bytes first.
+    pub(crate) fn dtos(&self) -> Box<[AdminBandwidthDto]> {
+        let mut dtos = self
+            .counters
+            .iter()
+            .map(|(key, counter)| AdminBandwidthDto {
+                update: if let Some(sub_variant) = key.label.sub_variant {
+                    format!("{}::{sub_variant}", key.label.variant)
+                } else {
+                    key.label.variant.to_owned()
+                },
+                reliable: key.reliable,
+                messages: counter.messages,
+                encoded_bytes: counter.encoded_bytes,
+                compressed_bytes: counter.compressed_bytes,
+            })
+            .collect::<Vec<_>>();
+        dtos.sort_by(|a, b| b.compressed_bytes.cmp(&a.compressed_bytes));
+        dtos.into_boxed_slice()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BandwidthLabel, BandwidthProfile};
+    use crate::service::test_arena::tests::Echo;
+    use crate::socket::Sent;
+    use crate::{AdminBandwidthDto, ChatUpdate, CommonUpdate};
+
+    fn dto(
+        update: &str,
+        reliable: bool,
+        messages: u64,
+        encoded: u64,
+        compressed: u64,
+    ) -> AdminBandwidthDto {
+        AdminBandwidthDto {
+            update: update.to_owned(),
+            reliable,
+            messages,
+            encoded_bytes: encoded,
+            compressed_bytes: compressed,
+        }
+    }
+
+    #[test]
+    fn merge() {
+        let datagram = Sent {
+            bytes: 10,
+            reliable: false,
+        };
+        let stream = Sent {
+            bytes: 40,
+            reliable: true,
+        };
+
+        let mut total = BandwidthProfile::default();
+        assert!(total.is_empty());
+        total.record(
+            BandwidthLabel::new::<Echo>(&CommonUpdate::Game(1)),
+            datagram,
+            20,
+        );
+        // An unreliable update that fell back to the stream, and was labeled by the game.
+        total.record(
+            BandwidthLabel::new::<Echo>(&CommonUpdate::Game(2)),
+            stream,
+            60,
+        );
+
+        let mut socket = BandwidthProfile::default();
+        socket.record(
+            BandwidthLabel::new::<Echo>(&CommonUpdate::Game(3)),
+            datagram,
+            30,
+        );
+        socket.record(
+            BandwidthLabel::new::<Echo>(&CommonUpdate::Chat(ChatUpdate::Sent)),
+            Sent {
+                bytes: 35,
+                reliable: true,
+            },
+            50,
+        );
+        total.merge(socket);
+        assert!(!total.is_empty());
+
+        assert_eq!(
+            total.dtos().into_vec(),
+            [
+                dto("Game::Even", true, 1, 60, 40),
+                dto("Chat", true, 1, 50, 35),
+                dto("Game", false, 2, 50, 20),
+            ]
+        );
+    }
+}
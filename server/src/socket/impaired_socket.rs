@@ -0,0 +1,123 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+use crate::entry_point::NETWORK_IMPAIRMENT;
+use crate::socket::{Sending, Sent, Socket, SocketMessage};
+use crate::{ImpairedQueue, NetworkImpairment};
+use bytes::Bytes;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+
+/// Holds back outbound messages to simulate poor network conditions. Passes through if there is
+/// no impairment.
+#[pin_project::pin_project]
+pub(crate) struct ImpairedSocket<S> {
+    #[pin]
+    inner: S,
+    /// Messages, whether they are reliable, and their ids (see [`Sending::Held`]).
+    queue: Option<ImpairedQueue<(Bytes, bool, u64)>>,
+    next_id: u64,
+    epoch: Instant,
+}
+
+impl<S: Socket> ImpairedSocket<S> {
+    /// `requested` by the client is only honored by debug builds. Otherwise, falls back to
+    /// [`NETWORK_IMPAIRMENT`].
+    pub(crate) fn new(inner: S, requested: Option<NetworkImpairment>) -> Self {
+        let impairment = requested
+            .filter(|_| cfg!(debug_assertions))
+            .or_else(|| NETWORK_IMPAIRMENT.get().copied());
+        Self {
+            inner,
+            queue: impairment.map(ImpairedQueue::new),
+            next_id: 0,
+            epoch: Instant::now(),
+        }
+    }
+}
+
+fn socket_message(bytes: Bytes, reliable: bool) -> SocketMessage {
+    if reliable {
+        SocketMessage::Reliable(bytes)
+    } else {
+        SocketMessage::Unreliable(bytes)
+    }
+}
+
+impl<S: Socket> Socket for ImpairedSocket<S> {
+    type RecvErr = S::RecvErr;
+    type SendErr = S::SendErr;
+
+    const SUPPORTS_UNRELIABLE: bool = S::SUPPORTS_UNRELIABLE;
+
+    /// Messages are held back, and their compressed size is only known once [`Self::flush`] sends
+    /// them.
+    async fn send(self: Pin<&mut Self>, message: SocketMessage) -> Result<Sending, Self::SendErr> {
+        let mut this = self.project();
+        let Some(queue) = this.queue else {
+            return this.inner.send(message).await;
+        };
+        let now = this.epoch.elapsed().as_secs_f64();
+        let (bytes, reliable) = match message {
+            SocketMessage::Reliable(bytes) => (bytes, true),
+            SocketMessage::Unreliable(bytes) => (bytes, false),
+            SocketMessage::Close { error } => {
+                for (bytes, reliable, _) in queue.drain().collect::<Vec<_>>() {
+                    this.inner
+                        .as_mut()
+                        .send(socket_message(bytes, reliable))
+                        .await?;
+                }
+                return this.inner.send(SocketMessage::Close { error }).await;
+            }
+        };
+        let id = *this.next_id;
+        *this.next_id += 1;
+        Ok(
+            if queue.push(now, bytes.len(), reliable, (bytes, reliable, id)) {
+                Sending::Held(id)
+            } else {
+                Sending::Dropped
+            },
+        )
+    }
+
+    async fn recv(self: Pin<&mut Self>) -> Result<SocketMessage, Self::RecvErr> {
+        self.project().inner.recv().await
+    }
+
+    fn addr(&self) -> SocketAddr {
+        self.inner.addr()
+    }
+
+    fn rtt(&self) -> Option<Duration> {
+        self.inner.rtt()
+    }
+
+    fn next_flush(&self) -> Option<Instant> {
+        let due = self.queue.as_ref()?.next_due()?;
+        Some(self.epoch + Duration::from_secs_f64(due))
+    }
+
+    async fn flush(self: Pin<&mut Self>) -> Result<Vec<(u64, Sent)>, Self::SendErr> {
+        let mut this = self.project();
+        let Some(queue) = this.queue else {
+            return Ok(Vec::new());
+        };
+        let now = this.epoch.elapsed().as_secs_f64();
+        let mut flushed = Vec::new();
+        while let Some((bytes, reliable, id)) = queue.pop_due(now) {
+            // Inner sockets don't hold back messages.
+            if let Sending::Sent(sent) = this
+                .inner
+                .as_mut()
+                .send(socket_message(bytes, reliable))
+                .await?
+            {
+                flushed.push((id, sent));
+            }
+        }
+        Ok(flushed)
+    }
+}
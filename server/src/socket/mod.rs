@@ -1,12 +1,16 @@
 // SPDX-FileCopyrightText: 2024 Softbear, Inc.
 // SPDX-License-Identifier: LGPL-3.0-or-later
 
+mod bandwidth_profiler;
+mod impaired_socket;
 mod socket;
 mod web_socket;
 mod web_transport;
 
+pub(crate) use self::bandwidth_profiler::BANDWIDTH_PROFILER;
 pub use self::socket::{
-    Socket, SocketMessage, INBOUND_HARD_LIMIT, KEEPALIVE_HARD_TIMEOUT, KEEPALIVE_INTERVAL,
+    Sending, Sent, Socket, SocketMessage, INBOUND_HARD_LIMIT, KEEPALIVE_HARD_TIMEOUT,
+    KEEPALIVE_INTERVAL,
 };
 pub use self::web_socket::ws_request;
 pub use self::web_transport::web_transport;
@@ -1,6 +1,9 @@
 // SPDX-FileCopyrightText: 2024 Softbear, Inc.
 // SPDX-License-Identifier: LGPL-3.0-or-later
 
+use super::bandwidth_profiler::{
+    BandwidthLabel, BandwidthProfile, BANDWIDTH_PROFILER, BANDWIDTH_PROFILE_FLUSH,
+};
 use crate::actor::{ClientAuthRequest, ServerActor};
 use crate::observer::{ObserverMessage, ObserverMessageBody, ObserverUpdate};
 use crate::rate_limiter::{RateLimiterProps, RateLimiterState};
@@ -12,6 +15,7 @@ use crate::{
 use actix::Addr;
 use bytes::Bytes;
 use log::{info, warn};
+use std::collections::HashMap;
 use std::error::Error;
 use std::net::SocketAddr;
 use std::pin::Pin;
@@ -42,15 +46,43 @@ pub enum SocketMessage {
     Close { error: bool },
 }
 
+/// What [`Socket::send`] did with a message.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Sending {
+    Sent(Sent),
+    /// Held back, to be sent by a [`Socket::flush`] that reports this id.
+    Held(u64),
+    /// Never to be sent (e.g. simulated loss).
+    Dropped,
+}
+
+/// What was put on the wire for a message.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Sent {
+    /// Compressed, excluding transport overhead.
+    pub bytes: usize,
+    /// Whether the reliable transport was used, which may differ from the [`SocketMessage`].
+    pub reliable: bool,
+}
+
 pub trait Socket: Sized {
     const SUPPORTS_UNRELIABLE: bool;
     type SendErr: Error;
     type RecvErr: Error;
 
-    async fn send(self: Pin<&mut Self>, message: SocketMessage) -> Result<(), Self::SendErr>;
+    async fn send(self: Pin<&mut Self>, message: SocketMessage) -> Result<Sending, Self::SendErr>;
     async fn recv(self: Pin<&mut Self>) -> Result<SocketMessage, Self::RecvErr>;
     fn addr(&self) -> SocketAddr;
     fn rtt(&self) -> Option<Duration>;
+    /// When [`Socket::flush`] should next be called, if ever.
+    fn next_flush(&self) -> Option<Instant> {
+        None
+    }
+    /// Sends messages that were held back, returning their ids (see [`Sending::Held`]) and what
+    /// was sent.
+    async fn flush(self: Pin<&mut Self>) -> Result<Vec<(u64, Sent)>, Self::SendErr> {
+        Ok(Vec::new())
+    }
     async fn serve<G: ArenaService>(
         self: Pin<&mut Self>,
         origin: AllowedOrigin,
@@ -88,6 +120,11 @@ pub trait Socket: Sized {
 
         let mut warnings_left = 5u8;
 
+        let mut bandwidth = BandwidthProfile::default();
+        let mut bandwidth_flushed = Instant::now();
+        // Labels and encoded sizes of messages that were held back.
+        let mut held = HashMap::<u64, (BandwidthLabel, usize)>::new();
+
         const RTT_RATE_LIMIT_PROPS: RateLimiterProps =
             RateLimiterProps::const_new(Duration::from_secs(60), 0);
 
@@ -96,6 +133,7 @@ pub trait Socket: Sized {
         const CLOSE_SILENT: Option<bool> = None;
 
         let result = loop {
+            let next_flush = this.as_ref().next_flush();
             tokio::select! {
                 result = this.as_mut().recv() => {
                     let Ok(message) = result else {
@@ -242,9 +280,25 @@ pub trait Socket: Sized {
                             } else {
                                 SocketMessage::Unreliable(bytes)
                             };
-                            if let Err(e) = this.as_mut().send(socket_message).await {
-                                warn!("closing after failed to send {size} bytes: {e}");
-                                break CLOSE_ERROR;
+                            let label = BandwidthLabel::new::<G>(&message);
+                            match this.as_mut().send(socket_message).await {
+                                Ok(sending) => {
+                                    match sending {
+                                        Sending::Sent(sent) => bandwidth.record(label, sent, size),
+                                        Sending::Held(id) => {
+                                            held.insert(id, (label, size));
+                                        }
+                                        Sending::Dropped => {}
+                                    }
+                                    if bandwidth_flushed.elapsed() >= BANDWIDTH_PROFILE_FLUSH {
+                                        bandwidth_flushed = Instant::now();
+                                        BANDWIDTH_PROFILER.lock().unwrap().merge(std::mem::take(&mut bandwidth));
+                                    }
+                                }
+                                Err(e) => {
+                                    warn!("closing after failed to send {size} bytes: {e}");
+                                    break CLOSE_ERROR;
+                                }
                             }
                         }
                         ObserverUpdate::Close => {
@@ -253,6 +307,21 @@ pub trait Socket: Sized {
                         }
                     }
                 },
+                _ = tokio::time::sleep_until(next_flush.unwrap_or_else(Instant::now).into()), if next_flush.is_some() => {
+                    match this.as_mut().flush().await {
+                        Ok(flushed) => {
+                            for (id, sent) in flushed {
+                                if let Some((label, size)) = held.remove(&id) {
+                                    bandwidth.record(label, sent, size);
+                                }
+                            }
+                        }
+                        Err(e) => {
+                            warn!("closing after failed to flush: {e}");
+                            break CLOSE_ERROR;
+                        }
+                    }
+                },
             }
         };
 
@@ -268,6 +337,9 @@ pub trait Socket: Sized {
                 },
             },
         );
+        if !bandwidth.is_empty() {
+            BANDWIDTH_PROFILER.lock().unwrap().merge(bandwidth);
+        }
         if let Some(error) = result {
             let _ = this.as_mut().send(SocketMessage::Close { error }).await;
         }
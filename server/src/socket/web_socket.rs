@@ -1,11 +1,12 @@
 // SPDX-FileCopyrightText: 2024 Softbear, Inc.
 // SPDX-License-Identifier: LGPL-3.0-or-later
 
+use super::impaired_socket::ImpairedSocket;
 use super::{INBOUND_HARD_LIMIT, KEEPALIVE_INTERVAL};
 use crate::actor::{ClientAuthErr, ClientAuthRequest};
 use crate::router::check_origin;
 use crate::service::ArenaService;
-use crate::socket::{Socket, SocketMessage, KEEPALIVE_HARD_TIMEOUT};
+use crate::socket::{Sending, Sent, Socket, SocketMessage, KEEPALIVE_HARD_TIMEOUT};
 use crate::state::AppState;
 use crate::{Compression, CompressionImpl, Compressor, NonZeroUnixMillis, SocketQuery, UnixTime};
 use axum::body::Body;
@@ -47,11 +48,16 @@ pub async fn ws_request<G: ArenaService>(
             .unwrap());
     };
 
+    if state.bans.read().unwrap().is_ip_banned(addr.ip()) {
+        return Err((StatusCode::FORBIDDEN, "banned").into_response());
+    }
+
     let user_agent_id = user_agent
         .as_ref()
         .map(|h| h.as_str())
         .or(query.user_agent.as_deref())
         .and_then(|h| crate::net::user_agent_into_id(h));
+    let impairment = query.impairment;
     let client_auth_request =
         ClientAuthRequest::new::<G>(query, addr.ip(), origin.clone(), user_agent_id);
 
@@ -66,6 +72,7 @@ pub async fn ws_request<G: ArenaService>(
             {
                 match e {
                     ClientAuthErr::TooManyRequests => StatusCode::TOO_MANY_REQUESTS,
+                    ClientAuthErr::Banned => StatusCode::FORBIDDEN,
                     _ => StatusCode::SERVICE_UNAVAILABLE,
                 }
             },
@@ -92,7 +99,7 @@ pub async fn ws_request<G: ArenaService>(
                 compressor: Default::default(),
             };
             async move {
-                std::pin::pin!(web_socket)
+                std::pin::pin!(ImpairedSocket::new(web_socket, impairment))
                     .as_mut()
                     .serve(origin, user_agent_id, arena_id, player_id, state.server)
                     .await;
@@ -140,11 +147,15 @@ impl Socket for TokioWebSocket {
 
     const SUPPORTS_UNRELIABLE: bool = false;
 
-    async fn send(self: Pin<&mut Self>, message: SocketMessage) -> Result<(), Self::SendErr> {
+    /// Unreliable messages are sent reliably.
+    async fn send(self: Pin<&mut Self>, message: SocketMessage) -> Result<Sending, Self::SendErr> {
         let this = self.project();
+        let mut size = 0;
         let ws_message = match message {
             SocketMessage::Reliable(message) | SocketMessage::Unreliable(message) => {
-                Message::binary(this.compressor.compress(&message))
+                let compressed = this.compressor.compress(&message);
+                size = compressed.len();
+                Message::binary(compressed)
             }
             SocketMessage::Close { error } => {
                 let code = if error {
@@ -159,6 +170,12 @@ impl Socket for TokioWebSocket {
         this.inner
             .send(ws_message)
             .await
+            .map(|_| {
+                Sending::Sent(Sent {
+                    bytes: size,
+                    reliable: true,
+                })
+            })
             .map_err(TokioWebSocketError::Internal)
     }
 
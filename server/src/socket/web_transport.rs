@@ -1,12 +1,13 @@
 // SPDX-FileCopyrightText: 2024 Softbear, Inc.
 // SPDX-License-Identifier: LGPL-3.0-or-later
 
+use super::impaired_socket::ImpairedSocket;
 use super::{KEEPALIVE_HARD_TIMEOUT, KEEPALIVE_INTERVAL};
 use crate::actor::{ClientAuthErr, ClientAuthRequest, ServerActor};
 use crate::net::ConnectionPermit;
 use crate::rate_limiter::RateLimiter;
 use crate::router::check_origin;
-use crate::socket::{Socket, SocketMessage, INBOUND_HARD_LIMIT};
+use crate::socket::{Sending, Sent, Socket, SocketMessage, INBOUND_HARD_LIMIT};
 use crate::ArenaService;
 use actix::Addr;
 use axum_server::tls_rustls::RustlsConfig;
@@ -71,7 +72,10 @@ impl Socket for WebTransportSocket {
     const SUPPORTS_UNRELIABLE: bool = true;
 
     /// If `reliable` is `false`, the receiver is allowed to miss it or get it out of order.
-    async fn send(mut self: Pin<&mut Self>, message: SocketMessage) -> Result<(), Self::SendErr> {
+    async fn send(
+        mut self: Pin<&mut Self>,
+        message: SocketMessage,
+    ) -> Result<Sending, Self::SendErr> {
         match message {
             SocketMessage::Unreliable(message)
                 if self
@@ -81,11 +85,20 @@ impl Socket for WebTransportSocket {
                     .map(|max| message.len() <= max)
                     .unwrap_or(false) =>
             {
+                let message = CompressionImpl::compress(&message);
+                let size = message.len();
                 self.as_mut()
                     .connection
-                    .send_datagram(CompressionImpl::compress(&message))
+                    .send_datagram(message)
+                    .map(|_| {
+                        Sending::Sent(Sent {
+                            bytes: size,
+                            reliable: false,
+                        })
+                    })
                     .map_err(SendError::Datagram)
             }
+            // Unreliable messages too large for a datagram fall back to the stream.
             SocketMessage::Reliable(message) | SocketMessage::Unreliable(message) => {
                 let mut message = self.compressor.compress(&message);
                 message.splice(..0, (message.len() as Size).to_be_bytes());
@@ -93,6 +106,12 @@ impl Socket for WebTransportSocket {
                     .send
                     .write_all(&message)
                     .await
+                    .map(|_| {
+                        Sending::Sent(Sent {
+                            bytes: message.len(),
+                            reliable: true,
+                        })
+                    })
                     .map_err(SendError::Stream)
             }
             SocketMessage::Close { error } => {
@@ -105,7 +124,7 @@ impl Socket for WebTransportSocket {
                 self.as_mut()
                     .connection
                     .close(VarInt::from_u32(error as u32), &[]);
-                Ok(())
+                Ok(Sending::Sent(Sent::default()))
             }
         }
     }
@@ -239,6 +258,7 @@ pub async fn web_transport<G: ArenaService>(
             let user_agent_id = user_agent
                 .or(query.user_agent.as_deref())
                 .and_then(|h| crate::net::user_agent_into_id(h));
+            let impairment = query.impairment;
             let client_auth_request =
                 ClientAuthRequest::new::<G>(query, ip, origin.clone(), user_agent_id);
             let result = server
@@ -269,13 +289,16 @@ pub async fn web_transport<G: ArenaService>(
                 .await
                 .map_err(|e| io::Error::new(ErrorKind::Other, e.to_string()))?;
 
-            let socket = std::pin::pin!(WebTransportSocket {
-                connection,
-                send,
-                recv,
-                recv_buffer: Default::default(),
-                compressor: Default::default(),
-            });
+            let socket = std::pin::pin!(ImpairedSocket::new(
+                WebTransportSocket {
+                    connection,
+                    send,
+                    recv,
+                    recv_buffer: Default::default(),
+                    compressor: Default::default(),
+                },
+                impairment
+            ));
             socket
                 .serve(origin, user_agent_id, arena_id, player_id, server)
                 .await;
@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: LGPL-3.0-or-later
 
 use super::actor::ServerActor;
+use super::ban_list::BanList;
 use super::service::ArenaService;
 use crate::{Referrer, ServerId};
 use actix::Addr;
@@ -13,6 +14,7 @@ pub struct AppState<G: ArenaService> {
     pub server_id: ServerId,
     pub server: Addr<ServerActor<G>>,
     pub ads_txt: Arc<RwLock<HashMap<Option<Referrer>, Bytes>>>,
+    pub bans: Arc<RwLock<BanList>>,
 }
 
 impl<G: ArenaService> AppState<G> {
@@ -20,11 +22,13 @@ impl<G: ArenaService> AppState<G> {
         server_id: ServerId,
         server: Addr<ServerActor<G>>,
         ads_txt: Arc<RwLock<HashMap<Option<Referrer>, Bytes>>>,
+        bans: Arc<RwLock<BanList>>,
     ) -> Self {
         Self {
             server_id,
             server,
             ads_txt,
+            bans,
         }
     }
 }
@@ -35,6 +39,7 @@ impl<G: ArenaService> Clone for AppState<G> {
             server_id: self.server_id,
             server: self.server.clone(),
             ads_txt: self.ads_txt.clone(),
+            bans: self.bans.clone(),
         }
     }
 }